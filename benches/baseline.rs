@@ -48,6 +48,8 @@ fn owner_namespace() -> NamespaceConfig {
             RelationConfig {
                 name: relation,
                 userset_rewrite: None,
+                allowed_subject_types: None,
+                wildcard_subject_types: Vec::new(),
             },
         )]),
     }
@@ -288,6 +290,7 @@ fn bench_lookup_resources(c: &mut Criterion) {
             subject: User::UserId("lookup-user".to_string()),
             permission: Relation("viewer".to_string()),
             resource_type: "doc".to_string(),
+            cursor: None,
         };
         let name = format!("lookup_resources_{count}_candidates");
 
@@ -352,11 +352,13 @@ fn build_org_scenario(rules: usize) -> OrgScenario {
             subject: User::UserId(TARGET_USER_ID.to_string()),
             permission: relation("can_view"),
             resource_type: "doc".to_string(),
+            cursor: None,
         },
         lookup_subjects_request: LookupSubjectsRequest {
             resource: object("doc", "direct_doc"),
             permission: relation("can_view"),
             subject_type: "user".to_string(),
+            cursor: None,
         },
     };
 
@@ -685,6 +687,11 @@ fn subject_filter(subject: &SubjectRef) -> SubjectFilter {
             create_subject_id(object.object_id().as_str()),
             Some(relation.clone()),
         ),
+        SubjectRef::Wildcard(object_type) => SubjectFilter::exact(
+            create_subject_type(object_type.as_str()),
+            SubjectId::wildcard(),
+            None,
+        ),
     }
 }
 
@@ -20,7 +20,7 @@ use simple_zanzibar::{
     },
     relationship::RelationshipMutation,
     revision::Consistency,
-    schema::SchemaSource,
+    schema::{SchemaSource, SchemaSyntax},
 };
 
 const DATASET_RULES: usize = 100_000;
@@ -69,6 +69,7 @@ fn bench_schema_apis(criterion: &mut Criterion, filters: &[String]) {
                         engine.apply_schema(SchemaSource {
                             name: Some("org"),
                             text: org_schema(),
+                            syntax: SchemaSyntax::Legacy,
                         }),
                         "apply_schema failed",
                     ))
@@ -88,6 +89,7 @@ fn bench_schema_apis(criterion: &mut Criterion, filters: &[String]) {
                         engine.apply_schema(SchemaSource {
                             name: Some("initial"),
                             text: unused_schema(),
+                            syntax: SchemaSyntax::Legacy,
                         }),
                         "initial apply_schema failed",
                     );
@@ -98,6 +100,7 @@ fn bench_schema_apis(criterion: &mut Criterion, filters: &[String]) {
                         engine.replace_schema(SchemaSource {
                             name: Some("org"),
                             text: org_schema(),
+                            syntax: SchemaSyntax::Legacy,
                         }),
                         "replace_schema failed",
                     ))
@@ -180,6 +183,7 @@ fn bench_read_apis(criterion: &mut Criterion, filters: &[String], engine: &Zanzi
             subject: target_user.clone(),
             permission: can_view.clone(),
             resource_type: "doc".to_string(),
+            cursor: None,
         };
         criterion.bench_function(lookup_resources_name, |bencher| {
             bencher.iter(|| {
@@ -197,6 +201,7 @@ fn bench_read_apis(criterion: &mut Criterion, filters: &[String], engine: &Zanzi
             resource: direct_doc.clone(),
             permission: can_view.clone(),
             subject_type: "user".to_string(),
+            cursor: None,
         };
         criterion.bench_function(lookup_subjects_name, |bencher| {
             bencher.iter(|| {
@@ -231,6 +236,7 @@ fn bench_read_apis(criterion: &mut Criterion, filters: &[String], engine: &Zanzi
             resource: direct_doc,
             subject_type: "user".to_string(),
             consistency: Consistency::Latest,
+            cursor: None,
         };
         criterion.bench_function(object_permissions_name, |bencher| {
             bencher.iter(|| {
@@ -561,6 +567,7 @@ fn unused_policy_engine() -> ZanzibarEngine {
         engine.apply_schema(SchemaSource {
             name: Some("unused"),
             text: unused_schema(),
+            syntax: SchemaSyntax::Legacy,
         }),
         "unused schema failed",
     );
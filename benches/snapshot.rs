@@ -284,6 +284,7 @@ fn bench_loaded_query_set(
         subject: target_user.clone(),
         permission: can_view.clone(),
         resource_type: "doc".to_string(),
+        cursor: None,
     };
 
     if should_benchmark(names.direct, filters) {
@@ -19,7 +19,7 @@ use simple_zanzibar::{
     },
     relationship::RelationshipMutation,
     revision::{Consistency, ConsistencyToken},
-    schema::SchemaSource,
+    schema::{SchemaSource, SchemaSyntax},
 };
 
 const SCHEMA: &str = r#"
@@ -123,6 +123,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     engine.apply_schema(SchemaSource {
         name: Some("collaboration"),
         text: SCHEMA,
+        syntax: SchemaSyntax::Legacy,
     })?;
 
     let initial_token = grant_initial_relationships(&engine)?;
@@ -388,6 +389,7 @@ fn user_name(user: &User) -> String {
         User::Userset(object, relation) => {
             format!("{}:{}#{}", object.namespace, object.id, relation.0)
         }
+        User::Wildcard => "user:*".to_string(),
     }
 }
 
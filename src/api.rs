@@ -2,17 +2,18 @@
 
 use std::{
     borrow::Borrow,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt,
-    num::NonZeroUsize,
+    num::{NonZeroU32, NonZeroUsize},
     path::Path,
     str::FromStr,
     sync::{
         Arc, Mutex,
         atomic::{AtomicBool, Ordering},
-        mpsc::{self, SyncSender},
+        mpsc::{self, RecvTimeoutError, Sender, SyncSender},
     },
     thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use arc_swap::{ArcSwap, ArcSwapOption};
@@ -20,25 +21,43 @@ use thiserror::Error;
 
 use crate::{
     WriterState,
-    domain::{DomainError, ObjectType, RelationName},
+    caveat::CaveatContext,
+    delta::{self, SnapshotChain},
+    diagnostic::Diagnostics,
+    domain::{DomainError, ObjectRef, ObjectType, RelationName, SubjectRef},
     error::ZanzibarError,
     eval::{self, EvaluationError, EvaluationLimits},
+    impact::{AccessGrant, AccessImpact, AccessImpactRequest, AccessImpactScope},
     model::{
-        CheckRequest, CheckResponse, ExpandRequest, ExpandResponse, ExpandedUserset,
-        LookupObjectPermissions, LookupObjectPermissionsRequest, LookupPermissions,
-        LookupPermissionsRequest, LookupResources, LookupResourcesRequest, LookupSubjects,
-        LookupSubjectsRequest, NamespaceConfig, Object, PermissionSubjects, Relation,
-        RelationTuple, User,
+        CheckRequest, CheckResponse, DeleteRelationships, ExpandRequest, ExpandResponse,
+        ExpandedUserset, LookupObjectPermissions, LookupObjectPermissionsRequest,
+        LookupPermissions, LookupPermissionsRequest, LookupResources, LookupResourcesRequest,
+        LookupSubjects, LookupSubjectsRequest, NamespaceConfig, Object, PermissionSubjects,
+        ReadRelationships, Relation, RelationTuple, User,
     },
     policy::{self, PolicyIoError, PolicyText},
-    relationship::{Precondition, RelationshipMutation, StoreError},
-    revision::{Consistency, ConsistencyError, ConsistencyToken, default_retained_snapshots},
+    relationship::{
+        Precondition, QueryLimit, RelationshipFilter, RelationshipMutation, RelationshipRef,
+        StoreError, SubjectFilter,
+    },
+    reload::SnapshotDirectoryWatcher,
+    revision::{
+        Consistency, ConsistencyError, ConsistencyToken, CursorKey, DatastoreId, LookupCursor,
+        PublishedSnapshot, default_retained_snapshots, lookup_request_hash,
+    },
     runtime::{EngineState, SharedEngineState},
-    schema::{SchemaError, SchemaSource},
+    schema::{
+        self, MigrationStep, SchemaDiffReport, SchemaError, SchemaMigration, SchemaSource,
+        SchemaSyntax,
+    },
     snapshot::{IndexProfile, SnapshotIoError, SnapshotLoadOptions, SnapshotSaveOptions},
+    trace::CheckTraceResponse,
+    wal::WalError,
+    watch::{WatchEvent, WatchStream},
 };
 
 const DEFAULT_WRITER_QUEUE_CAPACITY: usize = 1024;
+const DEFAULT_EXPIRATION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
 const MAX_TENANT_ID_BYTES: usize = 128;
 
 macro_rules! enter_api_span {
@@ -57,6 +76,7 @@ macro_rules! enter_api_span {
 pub struct ZanzibarEngine {
     state: SharedEngineState,
     writer: WriterActor,
+    cursor_key: CursorKey,
 }
 
 impl ZanzibarEngine {
@@ -75,23 +95,158 @@ impl ZanzibarEngine {
     pub fn check(&self, request: CheckRequest) -> Result<CheckResponse, EngineError> {
         enter_api_span!("check");
         request.validate()?;
-        let (snapshot, limits) = self.snapshot_for_consistency(request.consistency)?;
+        let SelectedSnapshot {
+            snapshot,
+            token,
+            limits,
+        } = self.snapshot_for_consistency(request.consistency)?;
         let object_type = ObjectType::try_from(request.object.namespace.as_str())?;
         let relation_name = RelationName::try_from(request.relation.0.as_str())?;
         let relation_definition = snapshot
             .schema()
             .resolver()
             .relation(&object_type, &relation_name)?;
-        let allowed = eval::check_prepared_with_snapshot(
+        let (membership, missing_context) = eval::check_prepared_with_snapshot(
             &snapshot,
             &request.object,
             &request.relation,
             &request.user,
             relation_definition,
+            &request.context,
             limits,
-        )?
-        .is_allowed();
-        Ok(CheckResponse { allowed })
+        )?;
+        Ok(CheckResponse {
+            allowed: membership.is_allowed(),
+            permissionship: membership.into(),
+            missing_context,
+            token: Some(token),
+        })
+    }
+
+    /// Checks many relations or permissions in one call.
+    ///
+    /// Each distinct consistency selector is resolved to one snapshot for the whole batch, so
+    /// items with different caveat contexts still read the same revision. Items that also share
+    /// a caveat context share one request memo, so sub-checks repeated across rows (a shared
+    /// folder or group, say) are evaluated once. Results are returned in request order; an item
+    /// that fails validation or evaluation reports its own error without failing the batch.
+    #[must_use]
+    pub fn check_bulk(
+        &self,
+        requests: Vec<CheckRequest>,
+    ) -> Vec<Result<CheckResponse, EngineError>> {
+        enter_api_span!("check_bulk");
+        let mut results = Vec::with_capacity(requests.len());
+        let mut groups = Vec::<CheckBulkGroup>::new();
+        for (index, request) in requests.into_iter().enumerate() {
+            if let Err(error) = request.validate() {
+                results.push((index, Err(error.into())));
+                continue;
+            }
+            match groups.iter_mut().find(|group| {
+                group.consistency == request.consistency && group.context == request.context
+            }) {
+                Some(group) => group.items.push((index, request)),
+                None => groups.push(CheckBulkGroup {
+                    consistency: request.consistency.clone(),
+                    context: request.context.clone(),
+                    items: vec![(index, request)],
+                }),
+            }
+        }
+
+        // Every group with the same consistency reads one snapshot, resolved from one load of
+        // the published state, however many caveat contexts the batch carries.
+        let state = self.current_state();
+        let mut selected_snapshots =
+            Vec::<(Consistency, Result<SelectedSnapshot, EngineError>)>::new();
+        for group in groups {
+            let selected = match selected_snapshots
+                .iter()
+                .find(|(consistency, _)| *consistency == group.consistency)
+            {
+                Some((_, selected)) => selected.clone(),
+                None => {
+                    let selected = state.clone().and_then(|state| {
+                        let snapshot = state.snapshot_for_consistency(group.consistency.clone())?;
+                        Ok(SelectedSnapshot {
+                            token: state.token_for(&snapshot),
+                            limits: state.evaluation_limits(),
+                            snapshot,
+                        })
+                    });
+                    selected_snapshots.push((group.consistency, selected.clone()));
+                    selected
+                }
+            };
+            let selected = match selected {
+                Ok(selected) => selected,
+                Err(error) => {
+                    results.extend(
+                        group
+                            .items
+                            .into_iter()
+                            .map(|(index, _)| (index, Err(error.clone()))),
+                    );
+                    continue;
+                }
+            };
+            let mut context =
+                eval::EvaluationContext::new_with_request_memo(&selected.snapshot, selected.limits)
+                    .with_caveat_context(group.context);
+            for (index, request) in group.items {
+                context.reset_for_reuse();
+                results.push((
+                    index,
+                    Self::check_in_context(&mut context, &selected, &request),
+                ));
+            }
+        }
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Checks a relation or permission and returns the evaluated tree alongside the result.
+    ///
+    /// The trace records every sub-check and userset expression node, the relationships each one
+    /// read, request-memo hits, cycle denials, and the node where a depth or fanout limit was hit.
+    /// Hitting a limit yields a trace with no response instead of an error. Tracing allocates per
+    /// node, so use [`Self::check`] on hot paths.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError`] when request validation, consistency, store access, or caveat
+    /// evaluation fails.
+    pub fn check_with_trace(
+        &self,
+        request: CheckRequest,
+    ) -> Result<CheckTraceResponse, EngineError> {
+        enter_api_span!("check_with_trace");
+        request.validate()?;
+        let SelectedSnapshot {
+            snapshot,
+            token,
+            limits,
+        } = self.snapshot_for_consistency(request.consistency)?;
+        let object_type = ObjectType::try_from(request.object.namespace.as_str())?;
+        let relation_name = RelationName::try_from(request.relation.0.as_str())?;
+        let relation_definition = snapshot
+            .schema()
+            .resolver()
+            .relation(&object_type, &relation_name)?;
+        let mut trace = eval::check_prepared_with_trace(
+            &snapshot,
+            &request.object,
+            &request.relation,
+            &request.user,
+            relation_definition,
+            &request.context,
+            limits,
+        )?;
+        if let Some(response) = &mut trace.response {
+            response.token = Some(token);
+        }
+        Ok(trace)
     }
 
     /// Checks a relation or permission using latest consistency.
@@ -159,7 +314,11 @@ impl ZanzibarEngine {
     pub fn expand(&self, request: ExpandRequest) -> Result<ExpandResponse, EngineError> {
         enter_api_span!("expand");
         request.validate()?;
-        let (snapshot, limits) = self.snapshot_for_consistency(request.consistency)?;
+        let SelectedSnapshot {
+            snapshot,
+            token,
+            limits,
+        } = self.snapshot_for_consistency(request.consistency)?;
         let object_type = ObjectType::try_from(request.object.namespace.as_str())?;
         let relation_name = RelationName::try_from(request.relation.0.as_str())?;
         snapshot
@@ -168,7 +327,10 @@ impl ZanzibarEngine {
             .relation(&object_type, &relation_name)?;
         let expanded =
             eval::expand_with_snapshot(&snapshot, &request.object, &request.relation, limits)?;
-        Ok(ExpandResponse { expanded })
+        Ok(ExpandResponse {
+            expanded,
+            token: Some(token),
+        })
     }
 
     /// Expands a relation or permission using latest consistency.
@@ -205,23 +367,44 @@ impl ZanzibarEngine {
 
     /// Looks up resources of a type at the requested consistency.
     ///
+    /// Results are paged by `max_lookup_results`. A request carrying a cursor resumes at the
+    /// cursor's snapshot and ignores `consistency`.
+    ///
     /// # Errors
     ///
-    /// Returns [`EngineError`] when request validation, consistency, store access, or evaluation
-    /// fails.
+    /// Returns [`EngineError`] when request validation, consistency, cursor binding, store access,
+    /// or evaluation fails.
     pub fn lookup_resources_with_consistency(
         &self,
         request: impl Borrow<LookupResourcesRequest>,
         consistency: Consistency,
     ) -> Result<LookupResources, EngineError> {
         enter_api_span!("lookup_resources");
-        request.borrow().validate()?;
-        let (snapshot, limits) = self.snapshot_for_consistency(consistency)?;
-        Self::ensure_subject_reverse_lookup_supported(&snapshot, "lookup_resources")?;
         let request = request.borrow();
-        Ok(eval::lookup_resources_with_snapshot(
-            &snapshot, request, limits,
-        )?)
+        request.validate()?;
+        let request_hash = lookup_request_hash(
+            "lookup_resources",
+            &[
+                &SubjectRef::try_from(&request.subject)?.to_string(),
+                &request.permission.0,
+                &request.resource_type,
+            ],
+        );
+        let page = self.lookup_page(request.cursor.as_ref(), request_hash, consistency)?;
+        Self::ensure_subject_reverse_lookup_supported(&page.snapshot, "lookup_resources")?;
+        let resources = eval::lookup_resources_with_snapshot(
+            &page.snapshot,
+            request,
+            page.window_limits(page.offset, page.size()),
+        )?
+        .resources;
+        let (resources, has_more) = take_lookup_page(resources, page.offset, page.size());
+        Ok(LookupResources {
+            resources,
+            cursor: has_more
+                .then(|| page.cursor(0, page.offset.saturating_add(page.size().into()))),
+            token: Some(page.token),
+        })
     }
 
     /// Looks up subjects of a type that can access a resource at latest consistency.
@@ -238,22 +421,43 @@ impl ZanzibarEngine {
 
     /// Looks up subjects of a type at the requested consistency.
     ///
+    /// Results are paged by `max_lookup_results`. A request carrying a cursor resumes at the
+    /// cursor's snapshot and ignores `consistency`.
+    ///
     /// # Errors
     ///
-    /// Returns [`EngineError`] when request validation, consistency, store access, or evaluation
-    /// fails.
+    /// Returns [`EngineError`] when request validation, consistency, cursor binding, store access,
+    /// or evaluation fails.
     pub fn lookup_subjects_with_consistency(
         &self,
         request: impl Borrow<LookupSubjectsRequest>,
         consistency: Consistency,
     ) -> Result<LookupSubjects, EngineError> {
         enter_api_span!("lookup_subjects");
-        request.borrow().validate()?;
-        let (snapshot, limits) = self.snapshot_for_consistency(consistency)?;
         let request = request.borrow();
-        Ok(eval::lookup_subjects_with_snapshot(
-            &snapshot, request, limits,
-        )?)
+        request.validate()?;
+        let request_hash = lookup_request_hash(
+            "lookup_subjects",
+            &[
+                &ObjectRef::try_from(&request.resource)?.to_string(),
+                &request.permission.0,
+                &request.subject_type,
+            ],
+        );
+        let page = self.lookup_page(request.cursor.as_ref(), request_hash, consistency)?;
+        let subjects = eval::lookup_subjects_with_snapshot(
+            &page.snapshot,
+            request,
+            page.window_limits(page.offset, page.size()),
+        )?
+        .subjects;
+        let (subjects, has_more) = take_lookup_page(subjects, page.offset, page.size());
+        Ok(LookupSubjects {
+            subjects,
+            cursor: has_more
+                .then(|| page.cursor(0, page.offset.saturating_add(page.size().into()))),
+            token: Some(page.token),
+        })
     }
 
     /// Returns benchmark-only delta stats for the selected snapshot.
@@ -266,7 +470,7 @@ impl ZanzibarEngine {
         &self,
         consistency: Consistency,
     ) -> Result<crate::relationship::StoreViewDeltaStats, EngineError> {
-        let (snapshot, _) = self.snapshot_for_consistency(consistency)?;
+        let snapshot = self.snapshot_for_consistency(consistency)?.snapshot;
         Ok(snapshot.relationships().delta_stats())
     }
 
@@ -280,7 +484,7 @@ impl ZanzibarEngine {
         &self,
         consistency: Consistency,
     ) -> Result<crate::relationship::StorePostingHistograms, EngineError> {
-        let (snapshot, _) = self.snapshot_for_consistency(consistency)?;
+        let snapshot = self.snapshot_for_consistency(consistency)?.snapshot;
         Ok(snapshot.relationships().posting_histograms())
     }
 
@@ -420,6 +624,40 @@ impl ZanzibarEngine {
         })
     }
 
+    /// Deletes relationships matching `filter` atomically in one new revision.
+    ///
+    /// Matches are resolved inside the writer actor against the current store view, after
+    /// `preconditions` pass. At most `limit` relationships are removed per call, further capped at
+    /// one mutation batch; when the response reports `truncated`, call again to continue.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError`] when no schema is loaded, the filter or a precondition is invalid,
+    /// a precondition fails, or the writer actor is unavailable.
+    pub fn delete_relationships(
+        &self,
+        filter: RelationshipFilter,
+        preconditions: impl IntoIterator<Item = Precondition>,
+        limit: NonZeroU32,
+    ) -> Result<DeleteRelationships, EngineError> {
+        enter_api_span!("delete_relationships");
+        let operation = "delete_relationships";
+        let (sender, receiver) = mpsc::sync_channel(1);
+        self.writer.send(
+            WriterCommand::DeleteRelationships {
+                filter,
+                preconditions: preconditions.into_iter().collect(),
+                limit,
+                response: sender,
+            },
+            operation,
+        )?;
+        receiver
+            .recv()
+            .map_err(|_| EngineError::WriterUnavailable { operation })?
+            .map_err(EngineError::from)
+    }
+
     /// Applies a schema document and publishes a new revision.
     ///
     /// # Errors
@@ -428,7 +666,9 @@ impl ZanzibarEngine {
     pub fn apply_schema(&self, source: SchemaSource<'_>) -> Result<ConsistencyToken, EngineError> {
         enter_api_span!("apply_schema");
         self.submit_write("apply_schema", |response| WriterCommand::ApplySchema {
+            name: source.name.map(str::to_string),
             text: source.text.to_string(),
+            syntax: source.syntax,
             response,
         })
     }
@@ -448,10 +688,7 @@ impl ZanzibarEngine {
     ///
     /// Returns [`EngineError`] when the DSL cannot be parsed or validated.
     pub fn add_dsl_with_token(&self, dsl: &str) -> Result<ConsistencyToken, EngineError> {
-        self.apply_schema(SchemaSource {
-            name: Some("dsl"),
-            text: dsl,
-        })
+        self.apply_schema(SchemaSource::legacy(dsl).with_name("dsl"))
     }
 
     /// Applies one structured namespace config and publishes a new revision.
@@ -505,11 +742,58 @@ impl ZanzibarEngine {
     ) -> Result<ConsistencyToken, EngineError> {
         enter_api_span!("replace_schema");
         self.submit_write("replace_schema", |response| WriterCommand::ReplaceSchema {
+            name: source.name.map(str::to_string),
             text: source.text.to_string(),
+            syntax: source.syntax,
             response,
         })
     }
 
+    /// Applies a replacement schema and rewrites stored relationships in one revision.
+    ///
+    /// See [`SchemaMigration`] and [`MigrationStep`] for the supported rewrites. Readers see
+    /// either the old schema with the old relationships or the new schema with the migrated ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError`] when the schema cannot be parsed or a migrated relationship does
+    /// not validate against it; nothing is published in that case.
+    pub fn migrate_schema(
+        &self,
+        migration: SchemaMigration<'_>,
+    ) -> Result<ConsistencyToken, EngineError> {
+        enter_api_span!("migrate_schema");
+        self.submit_write("migrate_schema", |response| WriterCommand::MigrateSchema {
+            name: migration.schema.name.map(str::to_string),
+            text: migration.schema.text.to_string(),
+            syntax: migration.schema.syntax,
+            steps: migration.steps,
+            response,
+        })
+    }
+
+    /// Diffs `source` against the latest schema and counts the stored relationships each change
+    /// would invalidate.
+    ///
+    /// Nothing is published. Unlike [`replace_schema`](Self::replace_schema), which stops at the
+    /// first relationship that fails revalidation, the report covers every breaking change.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError`] when no schema is loaded or `source` cannot be parsed or compiled.
+    pub fn diff_schema(&self, source: SchemaSource<'_>) -> Result<SchemaDiffReport, EngineError> {
+        enter_api_span!("diff_schema");
+        let state = self.current_state()?;
+        let snapshot = state.latest_snapshot();
+        let (_, candidate) = crate::compile_replacement_schema(source)?;
+        let changes = schema::diff(snapshot.schema(), &candidate)
+            .impact(&candidate, snapshot.relationships().rows());
+        Ok(SchemaDiffReport {
+            changes,
+            token: state.token_for(&snapshot),
+        })
+    }
+
     /// Deletes one namespace definition.
     ///
     /// # Errors
@@ -572,6 +856,56 @@ impl ZanzibarEngine {
         })
     }
 
+    /// Deletes expired relationships now instead of waiting for the periodic writer sweep.
+    ///
+    /// Returns the token of the sweep revision, or `None` when no relationship has expired.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError`] when the writer actor is unavailable or the sweep cannot publish.
+    pub fn sweep_expired_relationships(&self) -> Result<Option<ConsistencyToken>, EngineError> {
+        enter_api_span!("sweep_expired_relationships");
+        let operation = "sweep_expired_relationships";
+        let (sender, receiver) = mpsc::sync_channel(1);
+        self.writer
+            .send(WriterCommand::SweepExpired { response: sender }, operation)?;
+        receiver
+            .recv()
+            .map_err(|_| EngineError::WriterUnavailable { operation })?
+            .map_err(EngineError::from)
+    }
+
+    /// Subscribes to the ordered stream of revisions published after `from`.
+    ///
+    /// [`Consistency::Latest`] streams revisions published after the call. An exact token first
+    /// replays every retained revision newer than the token, then continues with live revisions,
+    /// so consumers can resume from the last token they processed without gaps.
+    /// [`Consistency::AtLeastAsFresh`] checks its token against this engine and then streams like
+    /// `Latest`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError`] when a token is invalid for this engine, an exact token is no longer
+    /// retained, or the writer actor is unavailable.
+    pub fn watch(&self, from: Consistency) -> Result<WatchStream, EngineError> {
+        enter_api_span!("watch");
+        let operation = "watch";
+        let (events, stream) = mpsc::channel();
+        let (sender, receiver) = mpsc::sync_channel(1);
+        self.writer.send(
+            WriterCommand::Watch {
+                from,
+                events,
+                response: sender,
+            },
+            operation,
+        )?;
+        receiver
+            .recv()
+            .map_err(|_| EngineError::WriterUnavailable { operation })??;
+        Ok(WatchStream::new(stream))
+    }
+
     /// Saves a snapshot built from policy text without keeping an engine.
     ///
     /// # Errors
@@ -595,6 +929,7 @@ impl ZanzibarEngine {
         let snapshot = self.latest_snapshot()?;
         Ok(policy::export_policy_text(
             snapshot.configs(),
+            snapshot.schema().caveats(),
             snapshot.relationships().rows(),
         ))
     }
@@ -611,8 +946,11 @@ impl ZanzibarEngine {
             .map_err(|_| PolicyIoError::Zanzibar {
                 source: ZanzibarError::SchemaRequired,
             })?;
-        let policy =
-            policy::export_policy_text(snapshot.configs(), snapshot.relationships().rows());
+        let policy = policy::export_policy_text(
+            snapshot.configs(),
+            snapshot.schema().caveats(),
+            snapshot.relationships().rows(),
+        );
         policy::write_policy_files(directory.as_ref(), &policy)
     }
 
@@ -628,7 +966,11 @@ impl ZanzibarEngine {
         enter_api_span!("lookup_permissions");
         let request = request.borrow();
         request.validate()?;
-        let (snapshot, limits) = self.snapshot_for_consistency(request.consistency.clone())?;
+        let SelectedSnapshot {
+            snapshot,
+            token,
+            limits,
+        } = self.snapshot_for_consistency(request.consistency.clone())?;
         let object_type = ObjectType::try_from(request.resource.namespace.as_str())?;
         snapshot.schema().resolver().namespace(&object_type)?;
         let mut permissions = Vec::new();
@@ -652,14 +994,21 @@ impl ZanzibarEngine {
                 permissions.push(relation);
             }
         }
-        Ok(LookupPermissions { permissions })
+        Ok(LookupPermissions {
+            permissions,
+            token: Some(token),
+        })
     }
 
     /// Looks up subjects grouped by every relation or permission they have on one resource.
     ///
+    /// One page holds at most `max_lookup_results` subjects across all groups; a group split by
+    /// a page boundary continues at the start of the next page.
+    ///
     /// # Errors
     ///
-    /// Returns [`EngineError`] when request validation, store access, or evaluation fails.
+    /// Returns [`EngineError`] when request validation, cursor binding, store access, or
+    /// evaluation fails.
     pub fn lookup_object_permissions(
         &self,
         request: impl Borrow<LookupObjectPermissionsRequest>,
@@ -667,7 +1016,19 @@ impl ZanzibarEngine {
         enter_api_span!("lookup_object_permissions");
         let request = request.borrow();
         request.validate()?;
-        let (snapshot, limits) = self.snapshot_for_consistency(request.consistency.clone())?;
+        let request_hash = lookup_request_hash(
+            "lookup_object_permissions",
+            &[
+                &ObjectRef::try_from(&request.resource)?.to_string(),
+                &request.subject_type,
+            ],
+        );
+        let page = self.lookup_page(
+            request.cursor.as_ref(),
+            request_hash,
+            request.consistency.clone(),
+        )?;
+        let snapshot = &page.snapshot;
         let object_type = ObjectType::try_from(request.resource.namespace.as_str())?;
         snapshot.schema().resolver().namespace(&object_type)?;
         let subject_type = crate::domain::SubjectType::try_from(request.subject_type.as_str())?;
@@ -680,30 +1041,112 @@ impl ZanzibarEngine {
         }
 
         let mut permissions = Vec::new();
-        for relation_definition in snapshot
+        let mut cursor = None;
+        let mut remaining = page.size();
+        let mut offset = page.offset;
+        let first_group = usize::try_from(page.group).unwrap_or(usize::MAX);
+        for (group, relation_definition) in snapshot
             .schema()
             .resolver()
             .sorted_relations(&object_type)?
+            .enumerate()
+            .skip(first_group)
         {
             let permission = Relation(relation_definition.name().as_str().to_string());
             let subjects = eval::lookup_subjects_with_snapshot(
-                &snapshot,
-                &LookupSubjectsRequest {
-                    resource: request.resource.clone(),
-                    permission: permission.clone(),
-                    subject_type: request.subject_type.clone(),
-                },
-                limits,
+                snapshot,
+                &LookupSubjectsRequest::new(
+                    request.resource.clone(),
+                    permission.clone(),
+                    request.subject_type.clone(),
+                ),
+                page.window_limits(offset, remaining),
             )?
             .subjects;
+            let (subjects, has_more) = take_lookup_page(subjects, offset, remaining);
+            let taken = u32::try_from(subjects.len()).unwrap_or(u32::MAX);
             if !subjects.is_empty() {
                 permissions.push(PermissionSubjects {
                     permission,
                     subjects,
                 });
             }
+            if has_more {
+                let group = u32::try_from(group).unwrap_or(u32::MAX);
+                cursor = Some(page.cursor(group, offset.saturating_add(taken.into())));
+                break;
+            }
+            remaining = remaining.saturating_sub(taken);
+            offset = 0;
         }
-        Ok(LookupObjectPermissions { permissions })
+        Ok(LookupObjectPermissions {
+            permissions,
+            cursor,
+            token: Some(page.token),
+        })
+    }
+
+    /// Reads stored relationships matching `filter`, one page at a time.
+    ///
+    /// Only direct relationships are returned; no userset or permission is evaluated. A filter
+    /// without a resource type reads through the subject index, or scans every relationship
+    /// when it has no subject filter either. `limit` sets the page size and replaces the
+    /// filter's own query limit. Pass the cursor from the previous page to continue at that
+    /// page's snapshot and expiration cutoff; `consistency` is ignored when a cursor is given.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError`] when the filter names an unknown namespace or relation, or when
+    /// consistency, cursor binding, or store access fails.
+    pub fn read_relationships(
+        &self,
+        filter: &RelationshipFilter,
+        consistency: Consistency,
+        cursor: Option<&LookupCursor>,
+        limit: NonZeroU32,
+    ) -> Result<ReadRelationships, EngineError> {
+        enter_api_span!("read_relationships");
+        let subject = filter.optional_subject();
+        let request_hash = lookup_request_hash(
+            "read_relationships",
+            &[
+                filter
+                    .optional_resource_type()
+                    .map_or("", |resource_type| resource_type.as_str()),
+                filter.optional_resource_id().map_or("", |id| id.as_str()),
+                filter
+                    .optional_relation()
+                    .map_or("", |relation| relation.as_str()),
+                subject.map_or("", |subject| subject.subject_type().as_str()),
+                subject
+                    .and_then(SubjectFilter::optional_subject_id)
+                    .map_or("", |id| id.as_str()),
+                subject
+                    .and_then(SubjectFilter::optional_relation)
+                    .map_or("", |relation| relation.as_str()),
+            ],
+        );
+        let page = self.lookup_page(cursor, request_hash, consistency)?;
+        crate::validate_relationship_filter(page.snapshot.schema(), filter)?;
+        let offset = usize::try_from(page.offset).unwrap_or(usize::MAX);
+        let size = usize::try_from(limit.get()).unwrap_or(usize::MAX);
+        let mut matches = page
+            .snapshot
+            .relationships()
+            .query_compact_relationships(&filter.unbounded())
+            .skip(offset);
+        let relationships = matches
+            .by_ref()
+            .take(size)
+            .map(RelationshipRef::to_relationship)
+            .collect::<Result<Vec<_>, _>>()?;
+        let has_more = relationships.len() == size && matches.next().is_some();
+        Ok(ReadRelationships {
+            cursor: has_more
+                .then(|| page.cursor(0, page.offset.saturating_add(limit.get().into()))),
+            relationships,
+            token: page.token,
+        })
     }
 
     /// Saves the latest published snapshot to a versioned `.szsnap` artifact.
@@ -718,7 +1161,7 @@ impl ZanzibarEngine {
         options: SnapshotSaveOptions,
     ) -> Result<(), SnapshotIoError> {
         enter_api_span!("save_snapshot");
-        let snapshot = self.latest_snapshot().map_err(|error| match error {
+        let state = self.current_state().map_err(|error| match error {
             EngineError::SchemaRequired => SnapshotIoError::Format {
                 reason: "schema snapshot is required before saving",
             },
@@ -726,11 +1169,85 @@ impl ZanzibarEngine {
                 reason: "engine state unavailable during snapshot save",
             },
         })?;
-        crate::snapshot::save_snapshot_file(path.as_ref(), &snapshot, options)
+        crate::snapshot::save_snapshot_file(
+            path.as_ref(),
+            &state.latest_snapshot(),
+            state.datastore_id(),
+            options,
+        )
+    }
+
+    /// Saves what changed since the end of `chain` as a delta artifact at `path`.
+    ///
+    /// The chain is loaded with full validation and diffed against this engine's latest
+    /// revision, so only removed and added rows, identifiers the base lacks, and a changed schema
+    /// are written. Append the delta to the chain to produce the next one. Compression is taken
+    /// from `options`; index options do not apply because deltas carry no indexes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SnapshotIoError`] when no schema is loaded, the chain cannot be loaded, this
+    /// engine is older than the end of the chain, or the delta cannot be written.
+    pub fn save_snapshot_delta(
+        &self,
+        chain: &SnapshotChain,
+        path: impl AsRef<Path>,
+        options: SnapshotSaveOptions,
+    ) -> Result<(), SnapshotIoError> {
+        enter_api_span!("save_snapshot_delta");
+        let state = self.current_state().map_err(|error| match error {
+            EngineError::SchemaRequired => SnapshotIoError::Format {
+                reason: "schema snapshot is required before saving",
+            },
+            _ => SnapshotIoError::Format {
+                reason: "engine state unavailable during snapshot save",
+            },
+        })?;
+        delta::save_delta_file(
+            chain,
+            path.as_ref(),
+            &state.latest_snapshot(),
+            state.datastore_id(),
+            options,
+        )
+    }
+
+    /// Loads a base snapshot and applies the chain's deltas on top of it into a new engine.
+    ///
+    /// Deltas are layered over the base checkpoint as a relationship overlay rather than
+    /// rebuilding its indexes. The engine resumes at the last delta's revision and, unless
+    /// `options.identity` forks, the datastore id of the engine that saved it. Compression is
+    /// detected per file; every other option applies to the base and each delta alike.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SnapshotIoError`] when an artifact cannot be read or fails validation, or with
+    /// [`SnapshotIoError::DeltaMismatch`] when a delta was saved against another base or does not
+    /// start where the previous artifact ended.
+    pub fn load_snapshot_chain(
+        chain: &SnapshotChain,
+        options: SnapshotLoadOptions,
+    ) -> Result<Self, SnapshotIoError> {
+        enter_api_span!("load_snapshot_chain");
+        let state = Arc::new(ArcSwapOption::empty());
+        let writer_state =
+            WriterState::load_snapshot_chain_with_publisher(chain, options, Arc::clone(&state))?;
+        Ok(Self {
+            state,
+            writer: WriterActor::start(
+                writer_state,
+                default_writer_queue_capacity(),
+                DEFAULT_EXPIRATION_SWEEP_INTERVAL,
+            ),
+            cursor_key: CursorKey::new_random(),
+        })
     }
 
     /// Loads a versioned `.szsnap` artifact into a new engine.
     ///
+    /// The engine resumes at the saved revision and, unless `options.identity` forks, under the
+    /// saved datastore id, so tokens issued by the saving engine stay valid.
+    ///
     /// # Errors
     ///
     /// Returns [`SnapshotIoError`] when the artifact cannot be read or fails validation.
@@ -744,10 +1261,266 @@ impl ZanzibarEngine {
             WriterState::load_snapshot_with_publisher(path, options, Arc::clone(&state))?;
         Ok(Self {
             state,
-            writer: WriterActor::start(writer_state, default_writer_queue_capacity()),
+            writer: WriterActor::start(
+                writer_state,
+                default_writer_queue_capacity(),
+                DEFAULT_EXPIRATION_SWEEP_INTERVAL,
+            ),
+            cursor_key: CursorKey::new_random(),
         })
     }
 
+    /// Forks a sandbox engine from the snapshot selected by `consistency`.
+    ///
+    /// The fork starts with the parent's schema, relationships, and evaluation limits at that
+    /// revision and shares its relationship store copy-on-write, so forking is cheap regardless
+    /// of store size. Writes and schema changes applied to the fork are visible only to the fork;
+    /// the parent never observes them. Forks are in-memory, use default writer settings, and get
+    /// their own datastore id, so tokens issued by the parent and the fork are not interchangeable.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError::SchemaRequired`] when the parent has no schema, or a consistency
+    /// error when `consistency` selects a snapshot that is unavailable.
+    pub fn fork(&self, consistency: Consistency) -> Result<Self, EngineError> {
+        enter_api_span!("fork");
+        let state = self.current_state()?;
+        let snapshot = state.snapshot_for_consistency(consistency)?;
+        Ok(Self::fork_snapshot(snapshot, state.evaluation_limits()))
+    }
+
+    /// Reports which grants a pending change would add or remove.
+    ///
+    /// The change is applied to a private [`fork`](Self::fork) of the selected snapshot, and for
+    /// each scope every resource of that type is looked up before and after. The cost therefore
+    /// grows with the number of resources in each scope, and the live engine is never modified.
+    /// A scope whose permission does not exist on one side of the change contributes no grants
+    /// on that side.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError`] when the snapshot cannot be selected, the candidate schema or
+    /// mutations are rejected, or a scope names an invalid identifier or subject type.
+    pub fn analyze_access_impact(
+        &self,
+        request: &AccessImpactRequest<'_>,
+    ) -> Result<AccessImpact, EngineError> {
+        enter_api_span!("analyze_access_impact");
+        let state = self.current_state()?;
+        let snapshot = state.snapshot_for_consistency(request.consistency.clone())?;
+        let token = state.token_for(&snapshot);
+        let candidate = Self::fork_snapshot(snapshot, state.evaluation_limits());
+        if let Some(schema) = request.schema {
+            candidate.replace_schema(schema)?;
+        }
+        if !request.mutations.is_empty() {
+            candidate.write_relationships(request.mutations.iter().cloned())?;
+        }
+
+        let mut impact = AccessImpact::new(token.clone());
+        for scope in &request.scopes {
+            let before = self.scope_grants(scope, Consistency::Exact(token.clone()))?;
+            let after = candidate.scope_grants(scope, Consistency::Latest)?;
+            impact.record(before, after);
+        }
+        Ok(impact)
+    }
+
+    /// Replaces this engine's data with a `.szsnap` artifact without rebuilding the engine.
+    ///
+    /// The artifact is read and validated on the calling thread; the writer then publishes it in
+    /// one atomic swap, so readers already holding the previous state finish on it while every
+    /// shared handle to this engine observes the reloaded data. The engine keeps its retention and
+    /// evaluation limits, and watch subscribers receive one event deleting the previous
+    /// relationships and touching the reloaded ones.
+    ///
+    /// Returns the token of the reloaded snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SnapshotIoError`] when the artifact cannot be read or fails validation, when the
+    /// engine is durable, or with [`SnapshotIoError::StaleRevision`] when an artifact from this
+    /// engine's datastore is older than its latest revision.
+    pub fn reload_snapshot(
+        &self,
+        path: impl AsRef<Path>,
+        options: SnapshotLoadOptions,
+    ) -> Result<ConsistencyToken, SnapshotIoError> {
+        enter_api_span!("reload_snapshot");
+        let candidate = WriterState::load_snapshot_with_publisher(
+            path,
+            options,
+            Arc::new(ArcSwapOption::empty()),
+        )?;
+        self.install_reloaded_snapshot(candidate)
+    }
+
+    /// Replaces this engine's data with a base snapshot plus deltas, as
+    /// [`reload_snapshot`](Self::reload_snapshot) does for one artifact.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`load_snapshot_chain`](Self::load_snapshot_chain) and
+    /// [`reload_snapshot`](Self::reload_snapshot).
+    pub fn reload_snapshot_chain(
+        &self,
+        chain: &SnapshotChain,
+        options: SnapshotLoadOptions,
+    ) -> Result<ConsistencyToken, SnapshotIoError> {
+        enter_api_span!("reload_snapshot_chain");
+        let candidate = WriterState::load_snapshot_chain_with_publisher(
+            chain,
+            options,
+            Arc::new(ArcSwapOption::empty()),
+        )?;
+        self.install_reloaded_snapshot(candidate)
+    }
+
+    fn install_reloaded_snapshot(
+        &self,
+        candidate: WriterState,
+    ) -> Result<ConsistencyToken, SnapshotIoError> {
+        let writer_unavailable = || SnapshotIoError::Format {
+            reason: "engine writer unavailable during snapshot reload",
+        };
+        let (sender, receiver) = mpsc::sync_channel(1);
+        self.writer
+            .send(
+                WriterCommand::ReloadSnapshot {
+                    candidate: Box::new(candidate),
+                    response: sender,
+                },
+                "reload_snapshot",
+            )
+            .map_err(|_| writer_unavailable())?;
+        receiver.recv().map_err(|_| writer_unavailable())?
+    }
+
+    /// Starts a background watcher that reloads this engine from `directory`.
+    ///
+    /// See [`SnapshotDirectoryWatcher`] for how artifacts are picked.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SnapshotIoError::Io`] when the directory cannot be read or the watcher thread
+    /// cannot be spawned.
+    pub fn watch_snapshot_directory(
+        self: &Arc<Self>,
+        directory: impl AsRef<Path>,
+        options: SnapshotLoadOptions,
+        poll_interval: Duration,
+    ) -> Result<SnapshotDirectoryWatcher, SnapshotIoError> {
+        SnapshotDirectoryWatcher::start(self, directory.as_ref(), options, poll_interval)
+    }
+
+    /// Opens a durable engine backed by the write-ahead log in `directory` with default settings.
+    ///
+    /// See [`ZanzibarEngineBuilder::open`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WalError`] when the directory, checkpoint, or log cannot be read or replayed.
+    pub fn open(directory: impl AsRef<Path>) -> Result<Self, WalError> {
+        Self::builder().open(directory)
+    }
+
+    /// Saves the latest revision as a durable checkpoint and truncates the write-ahead log.
+    ///
+    /// Returns the token of the checkpointed revision.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WalError::NotDurable`] for engines not opened from a directory, or when the
+    /// checkpoint cannot be written.
+    pub fn checkpoint(&self) -> Result<ConsistencyToken, WalError> {
+        enter_api_span!("checkpoint");
+        let operation = "checkpoint";
+        let (sender, receiver) = mpsc::sync_channel(1);
+        if self
+            .writer
+            .send(WriterCommand::Checkpoint { response: sender }, operation)
+            .is_err()
+        {
+            return Err(WalError::WriterUnavailable { operation });
+        }
+        receiver
+            .recv()
+            .map_err(|_| WalError::WriterUnavailable { operation })?
+    }
+
+    fn fork_snapshot(snapshot: Arc<PublishedSnapshot>, limits: EvaluationLimits) -> Self {
+        let state = Arc::new(ArcSwapOption::empty());
+        let writer_state = WriterState::fork_with_publisher(snapshot, limits, Arc::clone(&state));
+        Self {
+            state,
+            writer: WriterActor::start(
+                writer_state,
+                default_writer_queue_capacity(),
+                DEFAULT_EXPIRATION_SWEEP_INTERVAL,
+            ),
+            cursor_key: CursorKey::new_random(),
+        }
+    }
+
+    /// Lists every grant in `scope` at one snapshot, paging through resources and subjects.
+    fn scope_grants(
+        &self,
+        scope: &AccessImpactScope,
+        consistency: Consistency,
+    ) -> Result<Vec<AccessGrant>, EngineError> {
+        let selected = self.snapshot_for_consistency(consistency)?;
+        if !scope.resolves_in(selected.snapshot.schema())? {
+            return Ok(Vec::new());
+        }
+        let pinned = Consistency::Exact(selected.token);
+        let filter = RelationshipFilter::new(
+            ObjectType::try_from(scope.resource_type.as_str())?,
+            None,
+            None,
+            None,
+            QueryLimit::default(),
+        );
+        let mut resources = Vec::new();
+        let mut seen = HashSet::new();
+        let mut cursor = None;
+        loop {
+            let page =
+                self.read_relationships(&filter, pinned.clone(), cursor.as_ref(), NonZeroU32::MAX)?;
+            for relationship in page.relationships {
+                let id = relationship.resource().object_id().as_str().to_string();
+                if seen.insert(id.clone()) {
+                    resources.push(Object::new(scope.resource_type.clone(), id));
+                }
+            }
+            cursor = page.cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        let mut grants = Vec::new();
+        for resource in resources {
+            let mut request = LookupSubjectsRequest::new(
+                resource.clone(),
+                scope.permission.clone(),
+                scope.subject_type.clone(),
+            );
+            loop {
+                let page = self.lookup_subjects_with_consistency(&request, pinned.clone())?;
+                grants.extend(page.subjects.into_iter().map(|subject| AccessGrant {
+                    resource: resource.clone(),
+                    permission: scope.permission.clone(),
+                    subject,
+                }));
+                match page.cursor {
+                    Some(cursor) => request = request.with_cursor(cursor),
+                    None => break,
+                }
+            }
+        }
+        Ok(grants)
+    }
+
     fn submit_write(
         &self,
         operation: &'static str,
@@ -772,11 +1545,91 @@ impl ZanzibarEngine {
     fn snapshot_for_consistency(
         &self,
         consistency: Consistency,
-    ) -> Result<(Arc<crate::revision::PublishedSnapshot>, EvaluationLimits), EngineError> {
+    ) -> Result<SelectedSnapshot, EngineError> {
         let state = self.current_state()?;
-        let limits = state.evaluation_limits();
         let snapshot = state.snapshot_for_consistency(consistency)?;
-        Ok((snapshot, limits))
+        Ok(SelectedSnapshot {
+            token: state.token_for(&snapshot),
+            limits: state.evaluation_limits(),
+            snapshot,
+        })
+    }
+
+    fn lookup_page(
+        &self,
+        cursor: Option<&LookupCursor>,
+        request_hash: [u8; 16],
+        consistency: Consistency,
+    ) -> Result<LookupPage, EngineError> {
+        let state = self.current_state()?;
+        let (consistency, expiration_cutoff, group, offset) = match cursor {
+            Some(cursor) => {
+                cursor.ensure_sealed(self.cursor_key.seal(
+                    request_hash,
+                    cursor.token(),
+                    cursor.expiration_cutoff(),
+                ))?;
+                (
+                    Consistency::Exact(cursor.token().clone()),
+                    cursor.expiration_cutoff(),
+                    cursor.group(),
+                    cursor.offset(),
+                )
+            }
+            None => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs());
+                (consistency, now, 0, 0)
+            }
+        };
+        let snapshot = state.snapshot_for_consistency(consistency)?;
+        // Relationship expirations have whole-second precision, so hiding rows as of the start of
+        // `expiration_cutoff` matches the wall clock and keeps offsets stable across pages.
+        let pinned =
+            snapshot.with_expiration_cutoff(UNIX_EPOCH + Duration::from_secs(expiration_cutoff));
+        let token = state.token_for(&snapshot);
+        Ok(LookupPage {
+            seal: self
+                .cursor_key
+                .seal(request_hash, &token, expiration_cutoff),
+            token,
+            snapshot: Arc::new(pinned),
+            limits: state.evaluation_limits(),
+            expiration_cutoff,
+            group,
+            offset,
+        })
+    }
+
+    fn check_in_context(
+        context: &mut eval::EvaluationContext<'_>,
+        selected: &SelectedSnapshot,
+        request: &CheckRequest,
+    ) -> Result<CheckResponse, EngineError> {
+        let object_type = ObjectType::try_from(request.object.namespace.as_str())?;
+        let relation_name = RelationName::try_from(request.relation.0.as_str())?;
+        let relation_definition = selected
+            .snapshot
+            .schema()
+            .resolver()
+            .relation(&object_type, &relation_name)?;
+        let membership = context.check_prepared(
+            &request.object,
+            &request.relation,
+            &request.user,
+            relation_definition,
+        )?;
+        Ok(CheckResponse {
+            allowed: membership.is_allowed(),
+            permissionship: membership.into(),
+            missing_context: if membership == eval::Membership::Conditional {
+                context.missing_caveat_parameters()
+            } else {
+                Vec::new()
+            },
+            token: Some(selected.token.clone()),
+        })
     }
 
     fn ensure_subject_reverse_lookup_supported(
@@ -797,12 +1650,77 @@ impl Default for ZanzibarEngine {
     }
 }
 
+/// Bulk check items that can share one snapshot and request memo.
+struct CheckBulkGroup {
+    consistency: Consistency,
+    context: CaveatContext,
+    items: Vec<(usize, CheckRequest)>,
+}
+
+/// Snapshot resolved for one read, with the token that names it.
+#[derive(Clone)]
+struct SelectedSnapshot {
+    snapshot: Arc<PublishedSnapshot>,
+    token: ConsistencyToken,
+    limits: EvaluationLimits,
+}
+
+/// Snapshot and position resolved for one page of a cursor-paged lookup.
+struct LookupPage {
+    snapshot: Arc<PublishedSnapshot>,
+    token: ConsistencyToken,
+    limits: EvaluationLimits,
+    seal: [u8; 16],
+    expiration_cutoff: u64,
+    group: u32,
+    offset: u64,
+}
+
+impl LookupPage {
+    const fn size(&self) -> u32 {
+        self.limits.max_lookup_results.get()
+    }
+
+    /// Limits that let the evaluator reach `offset + wanted` results plus one lookahead result.
+    fn window_limits(&self, offset: u64, wanted: u32) -> EvaluationLimits {
+        let window = offset.saturating_add(u64::from(wanted)).saturating_add(1);
+        EvaluationLimits {
+            max_lookup_results: u32::try_from(window)
+                .ok()
+                .and_then(NonZeroU32::new)
+                .unwrap_or(NonZeroU32::MAX),
+            ..self.limits
+        }
+    }
+
+    fn cursor(&self, group: u32, offset: u64) -> LookupCursor {
+        LookupCursor::new(
+            self.token.clone(),
+            self.seal,
+            self.expiration_cutoff,
+            group,
+            offset,
+        )
+    }
+}
+
+fn take_lookup_page<T>(results: Vec<T>, offset: u64, size: u32) -> (Vec<T>, bool) {
+    let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+    let size = usize::try_from(size).unwrap_or(usize::MAX);
+    let mut page = results.into_iter().skip(offset).collect::<Vec<_>>();
+    let has_more = page.len() > size;
+    page.truncate(size);
+    (page, has_more)
+}
+
 /// Builder for [`ZanzibarEngine`].
 #[derive(Debug, Clone, Copy)]
 pub struct ZanzibarEngineBuilder {
     retained_snapshots: NonZeroUsize,
     evaluation_limits: EvaluationLimits,
     writer_queue_capacity: NonZeroUsize,
+    expiration_sweep_interval: Duration,
+    datastore_id: Option<DatastoreId>,
 }
 
 impl ZanzibarEngineBuilder {
@@ -813,6 +1731,8 @@ impl ZanzibarEngineBuilder {
             retained_snapshots: default_retained_snapshots(),
             evaluation_limits: EvaluationLimits::default(),
             writer_queue_capacity: default_writer_queue_capacity(),
+            expiration_sweep_interval: DEFAULT_EXPIRATION_SWEEP_INTERVAL,
+            datastore_id: None,
         }
     }
 
@@ -837,20 +1757,78 @@ impl ZanzibarEngineBuilder {
         self
     }
 
+    /// Sets how often the writer deletes expired relationships into a new revision.
+    ///
+    /// Expired relationships are hidden from reads immediately; the sweep only reclaims storage.
+    #[must_use]
+    pub fn expiration_sweep_interval(mut self, expiration_sweep_interval: Duration) -> Self {
+        self.expiration_sweep_interval = expiration_sweep_interval;
+        self
+    }
+
+    /// Pins the datastore id stamped into consistency tokens and saved snapshots.
+    ///
+    /// By default every built engine gets a new unique id. A pinned id makes saved snapshot bytes
+    /// reproducible across builds of the same data. Durable engines opened with [`Self::open`]
+    /// keep the id recorded in their write-ahead log instead.
+    #[must_use]
+    pub const fn datastore_id(mut self, datastore_id: DatastoreId) -> Self {
+        self.datastore_id = Some(datastore_id);
+        self
+    }
+
     /// Builds the engine.
     #[must_use]
     pub fn build(self) -> ZanzibarEngine {
         let state = Arc::new(ArcSwapOption::empty());
-        let writer_state = WriterState::with_snapshot_retention_and_publisher(
+        let mut writer_state = WriterState::with_snapshot_retention_and_publisher(
             self.retained_snapshots,
             Arc::clone(&state),
         )
         .with_evaluation_limits(self.evaluation_limits);
+        if let Some(datastore_id) = self.datastore_id {
+            writer_state = writer_state.with_datastore_id(datastore_id);
+        }
         ZanzibarEngine {
             state,
-            writer: WriterActor::start(writer_state, self.writer_queue_capacity),
+            writer: WriterActor::start(
+                writer_state,
+                self.writer_queue_capacity,
+                self.expiration_sweep_interval,
+            ),
+            cursor_key: CursorKey::new_random(),
         }
     }
+
+    /// Opens a durable engine backed by `directory`.
+    ///
+    /// The engine loads the newest `.szsnap` checkpoint in the directory, replays the write-ahead
+    /// log tail recorded after it, and keeps the datastore id and revision sequence of the
+    /// previous process. Every later write is appended and fsynced to the log before it is
+    /// acknowledged. A missing directory is created as an empty datastore.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WalError`] when the directory, checkpoint, or log cannot be read or replayed.
+    pub fn open(self, directory: impl AsRef<Path>) -> Result<ZanzibarEngine, WalError> {
+        enter_api_span!("open");
+        let state = Arc::new(ArcSwapOption::empty());
+        let writer_state = WriterState::open_durable(
+            directory.as_ref(),
+            self.retained_snapshots,
+            self.evaluation_limits,
+            Arc::clone(&state),
+        )?;
+        Ok(ZanzibarEngine {
+            state,
+            writer: WriterActor::start(
+                writer_state,
+                self.writer_queue_capacity,
+                self.expiration_sweep_interval,
+            ),
+            cursor_key: CursorKey::new_random(),
+        })
+    }
 }
 
 impl Default for ZanzibarEngineBuilder {
@@ -991,7 +1969,7 @@ impl Default for ZanzibarTenantShards {
 }
 
 /// Error returned by public [`ZanzibarEngine`] methods.
-#[derive(Debug, Error, PartialEq)]
+#[derive(Debug, Clone, Error, PartialEq)]
 pub enum EngineError {
     /// Domain primitive validation failed.
     #[error(transparent)]
@@ -1029,6 +2007,10 @@ pub enum EngineError {
         relation: String,
     },
 
+    /// Schema or relationship text had one or more located errors.
+    #[error(transparent)]
+    Diagnostics(Diagnostics),
+
     /// Schema source parsing failed.
     #[error("schema parse error: {message}")]
     ParseError {
@@ -1080,6 +2062,7 @@ impl From<ZanzibarError> for EngineError {
             ZanzibarError::Store(error) => Self::Store(error),
             ZanzibarError::Consistency(error) => Self::Consistency(error),
             ZanzibarError::Evaluation(error) => Self::Evaluation(error),
+            ZanzibarError::Diagnostics(diagnostics) => Self::Diagnostics(diagnostics),
         }
     }
 }
@@ -1100,6 +2083,7 @@ impl From<EngineError> for ZanzibarError {
             EngineError::Store(error) => Self::Store(error),
             EngineError::Consistency(error) => Self::Consistency(error),
             EngineError::Evaluation(error) => Self::Evaluation(error),
+            EngineError::Diagnostics(diagnostics) => Self::Diagnostics(diagnostics),
             EngineError::WriterUnavailable { operation } => Self::StorageError(format!(
                 "engine writer actor unavailable during {operation}",
             )),
@@ -1148,8 +2132,16 @@ enum WriterCommand {
         preconditions: Vec<Precondition>,
         response: WriteResponseSender,
     },
+    DeleteRelationships {
+        filter: RelationshipFilter,
+        preconditions: Vec<Precondition>,
+        limit: NonZeroU32,
+        response: SyncSender<Result<DeleteRelationships, ZanzibarError>>,
+    },
     ApplySchema {
+        name: Option<String>,
         text: String,
+        syntax: SchemaSyntax,
         response: WriteResponseSender,
     },
     ApplyNamespaceConfigs {
@@ -1157,7 +2149,9 @@ enum WriterCommand {
         response: WriteResponseSender,
     },
     ReplaceSchema {
+        name: Option<String>,
         text: String,
+        syntax: SchemaSyntax,
         response: WriteResponseSender,
     },
     DeleteNamespace {
@@ -1173,6 +2167,28 @@ enum WriterCommand {
         policy: PolicyText,
         response: WriteResponseSender,
     },
+    MigrateSchema {
+        name: Option<String>,
+        text: String,
+        syntax: SchemaSyntax,
+        steps: Vec<MigrationStep>,
+        response: WriteResponseSender,
+    },
+    SweepExpired {
+        response: SyncSender<Result<Option<ConsistencyToken>, ZanzibarError>>,
+    },
+    Watch {
+        from: Consistency,
+        events: Sender<WatchEvent>,
+        response: SyncSender<Result<(), ZanzibarError>>,
+    },
+    Checkpoint {
+        response: SyncSender<Result<ConsistencyToken, WalError>>,
+    },
+    ReloadSnapshot {
+        candidate: Box<WriterState>,
+        response: SyncSender<Result<ConsistencyToken, SnapshotIoError>>,
+    },
     Shutdown,
 }
 
@@ -1183,10 +2199,34 @@ struct WriterActor {
 }
 
 impl WriterActor {
-    fn start(mut state: WriterState, queue_capacity: NonZeroUsize) -> Self {
+    fn start(
+        mut state: WriterState,
+        queue_capacity: NonZeroUsize,
+        sweep_interval: Duration,
+    ) -> Self {
         let (sender, receiver) = mpsc::sync_channel(queue_capacity.get());
         let handle = thread::spawn(move || {
-            while let Ok(command) = receiver.recv() {
+            let mut next_sweep = Instant::now().checked_add(sweep_interval);
+            loop {
+                let received = match next_sweep {
+                    Some(deadline) => {
+                        receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    }
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                let command = match received {
+                    Ok(command) => Some(command),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                if next_sweep.is_some_and(|deadline| Instant::now() >= deadline) {
+                    // Background sweeps are best effort; reads already hide expired rows.
+                    drop(state.sweep_expired_relationships(SystemTime::now()));
+                    next_sweep = Instant::now().checked_add(sweep_interval);
+                }
+                let Some(command) = command else {
+                    continue;
+                };
                 match command {
                     WriterCommand::WriteRelationships {
                         mutations,
@@ -1198,14 +2238,44 @@ impl WriterActor {
                                 .send(state.apply_relationship_mutations(mutations, preconditions)),
                         );
                     }
-                    WriterCommand::ApplySchema { text, response } => {
-                        drop(response.send(state.add_dsl_with_token(&text)));
+                    WriterCommand::DeleteRelationships {
+                        filter,
+                        preconditions,
+                        limit,
+                        response,
+                    } => {
+                        drop(response.send(state.delete_relationships(
+                            &filter,
+                            preconditions,
+                            limit,
+                        )));
+                    }
+                    WriterCommand::ApplySchema {
+                        name,
+                        text,
+                        syntax,
+                        response,
+                    } => {
+                        drop(response.send(state.add_schema_with_token(SchemaSource {
+                            name: name.as_deref(),
+                            text: &text,
+                            syntax,
+                        })));
                     }
                     WriterCommand::ApplyNamespaceConfigs { configs, response } => {
                         drop(response.send(state.apply_namespace_configs(configs)));
                     }
-                    WriterCommand::ReplaceSchema { text, response } => {
-                        drop(response.send(state.replace_dsl_with_token(&text)));
+                    WriterCommand::ReplaceSchema {
+                        name,
+                        text,
+                        syntax,
+                        response,
+                    } => {
+                        drop(response.send(state.replace_schema_with_token(SchemaSource {
+                            name: name.as_deref(),
+                            text: &text,
+                            syntax,
+                        })));
                     }
                     WriterCommand::DeleteNamespace {
                         namespace,
@@ -1223,6 +2293,41 @@ impl WriterActor {
                     WriterCommand::ApplyPolicyText { policy, response } => {
                         drop(response.send(state.apply_policy_text(&policy)));
                     }
+                    WriterCommand::MigrateSchema {
+                        name,
+                        text,
+                        syntax,
+                        steps,
+                        response,
+                    } => {
+                        drop(response.send(state.migrate_schema_with_token(
+                            SchemaSource {
+                                name: name.as_deref(),
+                                text: &text,
+                                syntax,
+                            },
+                            &steps,
+                        )));
+                    }
+                    WriterCommand::SweepExpired { response } => {
+                        drop(response.send(state.sweep_expired_relationships(SystemTime::now())));
+                    }
+                    WriterCommand::Watch {
+                        from,
+                        events,
+                        response,
+                    } => {
+                        drop(response.send(state.subscribe(from, events)));
+                    }
+                    WriterCommand::Checkpoint { response } => {
+                        drop(response.send(state.checkpoint()));
+                    }
+                    WriterCommand::ReloadSnapshot {
+                        candidate,
+                        response,
+                    } => {
+                        drop(response.send(state.install_reloaded_snapshot(*candidate)));
+                    }
                     WriterCommand::Shutdown => break,
                 }
             }
@@ -0,0 +1,741 @@
+//! `zanzibar`: query and check policy directories and `.szsnap` artifacts from the shell.
+//!
+//! Every query command takes a source: a policy directory written by `export` (a `schema.zed`
+//! plus `relationships/*.zedtuples`) or a snapshot file, raw or zstd-compressed. Pass `--json`
+//! before the command for machine-readable output.
+
+use std::{
+    error::Error,
+    fmt::Write as _,
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use simple_zanzibar::{
+    PolicyText, SnapshotCompression, SnapshotLoadOptions, SnapshotSaveOptions, ZanzibarEngine,
+    caveat::CaveatContext,
+    domain::{ObjectRef, RelationName, Relationship, SubjectRef},
+    model::{
+        CheckRequest, ExpandedUserset, LookupPermissionsRequest, LookupResourcesRequest,
+        LookupSubjectsRequest, Object, Permissionship, Relation, User,
+    },
+    revision::Consistency,
+    snapshot::{self, SnapshotCheckKind, SnapshotCheckOutcome, SnapshotInspection},
+    trace::{CheckTraceKind, CheckTraceNode, CheckTraceNote},
+    validation::{ValidationFile, ValidationOutcome},
+};
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Exit status when `validate` or `snapshot verify` finds failing checks, as opposed to errors
+/// reading the inputs.
+const EXIT_CHECKS_FAILED: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+
+type CliResult<T = ()> = Result<T, Box<dyn Error>>;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "zanzibar",
+    version,
+    about = "Query and verify Zanzibar policies and snapshots"
+)]
+struct Cli {
+    /// Print JSON instead of human-readable text.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Checks `resource#permission@subject`, e.g. `doc:readme#view@user:alice`.
+    Check {
+        /// Policy directory or snapshot file.
+        source: PathBuf,
+        /// Check to evaluate.
+        check: String,
+        /// Print the evaluation tree showing which path granted or denied access.
+        #[arg(long)]
+        explain: bool,
+        /// Caveat context as a JSON object, e.g. `{"amount":5}`.
+        #[arg(long, value_name = "JSON")]
+        context: Option<CaveatContext>,
+    },
+    /// Expands `resource#permission` into its userset tree.
+    Expand {
+        /// Policy directory or snapshot file.
+        source: PathBuf,
+        /// Resource and permission, e.g. `doc:readme#view`.
+        permission: String,
+    },
+    /// Lists resources of one type a subject can access through a permission.
+    LookupResources {
+        /// Policy directory or snapshot file.
+        source: PathBuf,
+        /// Subject, e.g. `user:alice` or `group:eng#member`.
+        subject: String,
+        /// Permission to check on each resource.
+        permission: String,
+        /// Resource type to list.
+        resource_type: String,
+    },
+    /// Lists subjects of one type that can access `resource#permission`.
+    LookupSubjects {
+        /// Policy directory or snapshot file.
+        source: PathBuf,
+        /// Resource and permission, e.g. `doc:readme#view`.
+        permission: String,
+        /// Subject type to list.
+        #[arg(default_value = "user")]
+        subject_type: String,
+    },
+    /// Lists every relation and permission a subject has on a resource.
+    LookupPermissions {
+        /// Policy directory or snapshot file.
+        source: PathBuf,
+        /// Resource, e.g. `doc:readme`.
+        resource: String,
+        /// Subject, e.g. `user:alice`.
+        subject: String,
+    },
+    /// Validates a policy directory, or runs a validation file's assertions.
+    Validate {
+        /// Policy directory, or a `.yaml` validation file.
+        path: PathBuf,
+    },
+    /// Builds, inspects, and verifies snapshot artifacts.
+    #[command(subcommand)]
+    Snapshot(SnapshotCommand),
+    /// Prints the policy text of a source, or writes it as a policy directory.
+    Export {
+        /// Policy directory or snapshot file.
+        source: PathBuf,
+        /// Directory to write `schema.zed` and relationship files into.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum SnapshotCommand {
+    /// Builds a snapshot from a policy directory.
+    Build {
+        /// Policy directory.
+        policy: PathBuf,
+        /// Snapshot file to write.
+        output: PathBuf,
+        /// Wrap the snapshot in zstd.
+        #[arg(long)]
+        zstd: bool,
+    },
+    /// Prints a snapshot's header, section sizes, and verification results.
+    Inspect {
+        /// Snapshot file.
+        snapshot: PathBuf,
+    },
+    /// Verifies a snapshot as a full load would, without building an engine.
+    Verify {
+        /// Snapshot file.
+        snapshot: PathBuf,
+        /// Also fail when any section is larger than this many bytes.
+        #[arg(long)]
+        max_section_bytes: Option<u64>,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("error: {}", error_chain(error.as_ref()));
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+fn run(cli: &Cli) -> CliResult<ExitCode> {
+    match &cli.command {
+        Command::Check {
+            source,
+            check,
+            explain,
+            context,
+        } => run_check(cli.json, source, check, *explain, context.clone())?,
+        Command::Expand { source, permission } => {
+            let engine = load_source(source)?;
+            let (object, relation) = parse_permission(permission)?;
+            let expanded = engine.expand_relation(&object, &relation)?;
+            if cli.json {
+                print_json(&expanded)?;
+            } else {
+                let mut output = String::new();
+                write_expanded(&mut output, &expanded, 0);
+                emit(&output)?;
+            }
+        }
+        Command::LookupResources {
+            source,
+            subject,
+            permission,
+            resource_type,
+        } => {
+            let engine = load_source(source)?;
+            let mut request = LookupResourcesRequest::new(
+                parse_subject(subject)?,
+                Relation::new(RelationName::try_from(permission.as_str())?.as_str()),
+                resource_type.as_str(),
+            );
+            let mut resources = Vec::new();
+            loop {
+                let page = engine.lookup_resources(&request)?;
+                resources.extend(page.resources.iter().map(object_text));
+                let Some(cursor) = page.cursor else { break };
+                request = request.with_cursor(cursor);
+            }
+            print_list(cli.json, "resources", &resources)?;
+        }
+        Command::LookupSubjects {
+            source,
+            permission,
+            subject_type,
+        } => {
+            let engine = load_source(source)?;
+            let (object, relation) = parse_permission(permission)?;
+            let mut request = LookupSubjectsRequest::new(object, relation, subject_type.as_str());
+            let mut subjects = Vec::new();
+            loop {
+                let page = engine.lookup_subjects(&request)?;
+                for subject in &page.subjects {
+                    subjects.push(SubjectRef::try_from(subject)?.to_string());
+                }
+                let Some(cursor) = page.cursor else { break };
+                request = request.with_cursor(cursor);
+            }
+            print_list(cli.json, "subjects", &subjects)?;
+        }
+        Command::LookupPermissions {
+            source,
+            resource,
+            subject,
+        } => {
+            let engine = load_source(source)?;
+            let resource = resource.parse::<ObjectRef>()?;
+            let permissions = engine.lookup_permissions(LookupPermissionsRequest::new(
+                parse_subject(subject)?,
+                model_object(&resource),
+                Consistency::Latest,
+            ))?;
+            let permissions = permissions
+                .permissions
+                .into_iter()
+                .map(|permission| permission.0)
+                .collect::<Vec<_>>();
+            print_list(cli.json, "permissions", &permissions)?;
+        }
+        Command::Validate { path } => return run_validate(cli.json, path),
+        Command::Snapshot(command) => return run_snapshot(cli.json, command),
+        Command::Export { source, output } => {
+            let engine = load_source(source)?;
+            if let Some(output) = output {
+                engine.export_policy_files(output)?;
+            } else if cli.json {
+                print_json(&engine.export_policy_text()?)?;
+            } else {
+                let policy = engine.export_policy_text()?;
+                emit("# schema.zed\n")?;
+                emit(&policy.schema)?;
+                for file in &policy.relationship_files {
+                    emit(&format!("# {}\n", file.path))?;
+                    emit(&file.contents)?;
+                }
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_check(
+    json: bool,
+    source: &Path,
+    check: &str,
+    explain: bool,
+    context: Option<CaveatContext>,
+) -> CliResult {
+    let engine = load_source(source)?;
+    let relationship = check.parse::<Relationship>()?;
+    if relationship.caveat().is_some() || relationship.expiration().is_some() {
+        return Err("checks cannot carry a caveat or expiration".into());
+    }
+    let request = CheckRequest::new(
+        model_object(relationship.resource()),
+        Relation::new(relationship.relation().as_str()),
+        User::try_from(relationship.subject())?,
+        Consistency::Latest,
+    )
+    .with_context(context.unwrap_or_default());
+    if !explain {
+        let response = engine.check(request)?;
+        if json {
+            print_json(&response)?;
+        } else {
+            emit(&format!(
+                "{}\n",
+                permissionship_text(response.permissionship, &response.missing_context)
+            ))?;
+        }
+        return Ok(());
+    }
+
+    let trace = engine.check_with_trace(request)?;
+    if json {
+        return print_json(&trace);
+    }
+    let mut output = String::new();
+    match &trace.response {
+        Some(response) => {
+            let _ = writeln!(
+                output,
+                "{}",
+                permissionship_text(response.permissionship, &response.missing_context)
+            );
+        }
+        None => output.push_str("aborted: evaluation limit reached\n"),
+    }
+    write_trace(&mut output, &trace.root, 1);
+    let stats = trace.stats;
+    let _ = writeln!(
+        output,
+        "{} checks, {} memo hits, {} cycle denials, {} relationships read",
+        stats.checks, stats.memo_hits, stats.cycle_denials, stats.relationships_read
+    );
+    emit(&output)?;
+    Ok(())
+}
+
+fn run_validate(json: bool, path: &Path) -> CliResult<ExitCode> {
+    if path.is_dir() {
+        let policy = PolicyText::read_directory(path)?;
+        ZanzibarEngine::from_policy_text(&policy)?;
+        if json {
+            print_json(&json!({ "valid": true }))?;
+        } else {
+            emit(&format!("ok: {}\n", path.display()))?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let text = std::fs::read_to_string(path)?;
+    let name = path.display().to_string();
+    let report = ValidationFile::parse(Some(&name), &text)?.run()?;
+    if json {
+        let results = report
+            .results
+            .iter()
+            .map(|result| {
+                let mut value = json!({
+                    "check": result.check.to_string(),
+                    "text": result.text,
+                    "line": result.line,
+                    "passed": result.passed(),
+                });
+                match &result.outcome {
+                    ValidationOutcome::Passed | ValidationOutcome::AssertionFailed => {}
+                    ValidationOutcome::RelationsDiffer {
+                        missing,
+                        unexpected,
+                    } => {
+                        value["missing"] = json!(missing);
+                        value["unexpected"] = json!(unexpected);
+                    }
+                    ValidationOutcome::Error(error) => {
+                        value["error"] = json!(error_chain(error));
+                    }
+                }
+                value
+            })
+            .collect::<Vec<_>>();
+        print_json(&json!({ "valid": report.is_success(), "results": results }))?;
+    } else if report.is_success() {
+        emit(&format!("ok: {} checks passed\n", report.results.len()))?;
+    } else {
+        emit(&format!("{report}\n"))?;
+    }
+    Ok(if report.is_success() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(EXIT_CHECKS_FAILED)
+    })
+}
+
+fn run_snapshot(json: bool, command: &SnapshotCommand) -> CliResult<ExitCode> {
+    match command {
+        SnapshotCommand::Build {
+            policy,
+            output,
+            zstd,
+        } => {
+            let options = if *zstd {
+                SnapshotSaveOptions::zstd()
+            } else {
+                SnapshotSaveOptions::uncompressed()
+            };
+            ZanzibarEngine::save_snapshot_from_policy_text(
+                output,
+                &PolicyText::read_directory(policy)?,
+                options,
+            )?;
+            let bytes = std::fs::metadata(output)?.len();
+            if json {
+                print_json(&json!({ "path": output, "bytes": bytes }))?;
+            } else {
+                emit(&format!("wrote {} ({bytes} bytes)\n", output.display()))?;
+            }
+        }
+        SnapshotCommand::Inspect { snapshot } => {
+            let inspection = snapshot::inspect(snapshot)?;
+            if json {
+                print_json(&inspection_json(snapshot, &inspection, None))?;
+            } else {
+                emit(&inspection_text(snapshot, &inspection))?;
+            }
+        }
+        SnapshotCommand::Verify {
+            snapshot,
+            max_section_bytes,
+        } => {
+            let inspection = snapshot::inspect(snapshot)?;
+            let oversized = inspection
+                .sections
+                .iter()
+                .filter(|section| max_section_bytes.is_some_and(|limit| section.bytes > limit))
+                .collect::<Vec<_>>();
+            let valid = inspection.is_valid() && oversized.is_empty();
+            if json {
+                print_json(&inspection_json(snapshot, &inspection, *max_section_bytes))?;
+            } else if valid {
+                emit(&format!("ok: {}\n", snapshot.display()))?;
+            } else {
+                let mut output = String::new();
+                for check in inspection.failures() {
+                    let _ = writeln!(
+                        output,
+                        "{}: {}: {}",
+                        snapshot.display(),
+                        check_name(check.kind),
+                        check_outcome_text(&check.outcome)
+                    );
+                }
+                for section in oversized {
+                    let _ = writeln!(
+                        output,
+                        "{}: section {} is {} bytes, over the {} byte limit",
+                        snapshot.display(),
+                        section.kind,
+                        section.bytes,
+                        max_section_bytes.unwrap_or_default()
+                    );
+                }
+                emit(&output)?;
+            }
+            if !valid {
+                return Ok(ExitCode::from(EXIT_CHECKS_FAILED));
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn inspection_text(path: &Path, inspection: &SnapshotInspection) -> String {
+    let mut output = format!("{}\n", path.display());
+    let _ = writeln!(
+        output,
+        "  compression:   {} ({} bytes, {} bytes uncompressed)",
+        compression_name(inspection.compression),
+        inspection.file_bytes,
+        inspection.payload_bytes
+    );
+    let _ = writeln!(
+        output,
+        "  format:        v{}, {:?} indexes",
+        inspection.format_version, inspection.index_profile
+    );
+    let _ = writeln!(output, "  revision:      {}", inspection.revision);
+    let _ = writeln!(output, "  schema hash:   {}", inspection.schema_hash);
+    if let Some(datastore_id) = inspection.datastore_id {
+        let _ = writeln!(output, "  datastore id:  {datastore_id}");
+    }
+    let _ = writeln!(output, "  symbols:       {}", inspection.symbol_count);
+    let _ = writeln!(output, "  relationships: {}", inspection.relationship_count);
+    output.push_str("  sections:\n");
+    for section in &inspection.sections {
+        let _ = writeln!(
+            output,
+            "    {:<26}{:>12} bytes{:>12} rows",
+            section.kind.name(),
+            section.bytes,
+            section.row_count
+        );
+    }
+    output.push_str("  checks:\n");
+    for check in &inspection.checks {
+        let _ = writeln!(
+            output,
+            "    {:<26}{}",
+            check_name(check.kind),
+            check_outcome_text(&check.outcome)
+        );
+    }
+    output
+}
+
+fn inspection_json(
+    path: &Path,
+    inspection: &SnapshotInspection,
+    max_section_bytes: Option<u64>,
+) -> serde_json::Value {
+    let sections = inspection
+        .sections
+        .iter()
+        .map(|section| {
+            json!({
+                "kind": section.kind.name(),
+                "offset": section.offset,
+                "bytes": section.bytes,
+                "rows": section.row_count,
+                "overLimit": max_section_bytes.is_some_and(|limit| section.bytes > limit),
+            })
+        })
+        .collect::<Vec<_>>();
+    let checks = inspection
+        .checks
+        .iter()
+        .map(|check| {
+            json!({
+                "check": check_name(check.kind),
+                "passed": matches!(check.outcome, SnapshotCheckOutcome::Passed),
+                "outcome": check_outcome_text(&check.outcome),
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "path": path,
+        "valid": inspection.is_valid()
+            && max_section_bytes.is_none_or(|limit| {
+                inspection.sections.iter().all(|section| section.bytes <= limit)
+            }),
+        "compression": compression_name(inspection.compression),
+        "fileBytes": inspection.file_bytes,
+        "payloadBytes": inspection.payload_bytes,
+        "formatVersion": inspection.format_version,
+        "indexProfile": format!("{:?}", inspection.index_profile),
+        "revision": inspection.revision.get(),
+        "schemaHash": inspection.schema_hash.to_string(),
+        "datastoreId": inspection.datastore_id.map(|id| id.to_string()),
+        "symbols": inspection.symbol_count,
+        "relationships": inspection.relationship_count,
+        "sections": sections,
+        "checks": checks,
+    })
+}
+
+const fn compression_name(compression: SnapshotCompression) -> &'static str {
+    match compression {
+        SnapshotCompression::None => "none",
+        SnapshotCompression::Zstd => "zstd",
+    }
+}
+
+const fn check_name(kind: SnapshotCheckKind) -> &'static str {
+    match kind {
+        SnapshotCheckKind::Checksum => "checksum",
+        SnapshotCheckKind::Schema => "schema",
+        SnapshotCheckKind::Relationships => "relationships",
+        SnapshotCheckKind::Caveats => "caveats",
+        SnapshotCheckKind::DatastoreIdentity => "datastore_identity",
+    }
+}
+
+fn check_outcome_text(outcome: &SnapshotCheckOutcome) -> String {
+    match outcome {
+        SnapshotCheckOutcome::Passed => "passed".to_string(),
+        SnapshotCheckOutcome::Failed(error) => format!("failed: {}", error_chain(error)),
+        SnapshotCheckOutcome::Skipped => "skipped".to_string(),
+    }
+}
+
+/// Loads a policy directory or a raw or zstd snapshot, detected from its leading bytes.
+fn load_source(path: &Path) -> CliResult<ZanzibarEngine> {
+    if path.is_dir() {
+        let policy = PolicyText::read_directory(path)?;
+        return Ok(ZanzibarEngine::from_policy_text(&policy)?);
+    }
+    load_snapshot(path)
+}
+
+fn load_snapshot(path: &Path) -> CliResult<ZanzibarEngine> {
+    let options = if is_zstd(path)? {
+        SnapshotLoadOptions::zstd()
+    } else {
+        SnapshotLoadOptions::uncompressed()
+    };
+    Ok(ZanzibarEngine::load_snapshot(path, options)?)
+}
+
+fn is_zstd(path: &Path) -> CliResult<bool> {
+    let mut magic = [0; ZSTD_MAGIC.len()];
+    let read = File::open(path)?.read(&mut magic)?;
+    Ok(read == magic.len() && magic == ZSTD_MAGIC)
+}
+
+fn parse_permission(text: &str) -> CliResult<(Object, Relation)> {
+    let (resource, relation) = text
+        .split_once('#')
+        .ok_or("expected `resource#permission`, e.g. `doc:readme#view`")?;
+    Ok((
+        model_object(&resource.parse()?),
+        Relation::new(RelationName::try_from(relation)?.as_str()),
+    ))
+}
+
+fn parse_subject(text: &str) -> CliResult<User> {
+    Ok(User::try_from(&text.parse::<SubjectRef>()?)?)
+}
+
+fn model_object(object: &ObjectRef) -> Object {
+    Object::new(object.object_type().as_str(), object.object_id().as_str())
+}
+
+fn object_text(object: &Object) -> String {
+    format!("{}:{}", object.namespace, object.id)
+}
+
+fn permissionship_text(permissionship: Permissionship, missing_context: &[String]) -> String {
+    match permissionship {
+        Permissionship::HasPermission => "allowed".to_string(),
+        Permissionship::NoPermission => "denied".to_string(),
+        Permissionship::ConditionalPermission => {
+            format!("conditional: missing {}", missing_context.join(", "))
+        }
+    }
+}
+
+fn write_expanded(output: &mut String, expanded: &ExpandedUserset, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let children = match expanded {
+        ExpandedUserset::User(id) => {
+            let _ = writeln!(output, "{indent}user:{id}");
+            return;
+        }
+        ExpandedUserset::Wildcard => {
+            let _ = writeln!(output, "{indent}user:*");
+            return;
+        }
+        ExpandedUserset::Userset(object, relation) => {
+            let _ = writeln!(output, "{indent}{}#{}", object_text(object), relation.0);
+            return;
+        }
+        ExpandedUserset::Union(children) => {
+            let _ = writeln!(output, "{indent}union");
+            children.iter().collect::<Vec<_>>()
+        }
+        ExpandedUserset::Intersection(children) => {
+            let _ = writeln!(output, "{indent}intersection");
+            children.iter().collect()
+        }
+        ExpandedUserset::Exclusion { base, exclude } => {
+            let _ = writeln!(output, "{indent}exclusion");
+            vec![base.as_ref(), exclude.as_ref()]
+        }
+    };
+    for child in children {
+        write_expanded(output, child, depth + 1);
+    }
+}
+
+fn write_trace(output: &mut String, node: &CheckTraceNode, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let label = match &node.kind {
+        CheckTraceKind::Check {
+            object,
+            relation,
+            subject,
+        } => {
+            let subject = SubjectRef::try_from(subject)
+                .map_or_else(|_| format!("{subject:?}"), |subject| subject.to_string());
+            format!("check {}#{}@{subject}", object_text(object), relation.0)
+        }
+        CheckTraceKind::This => "this".to_string(),
+        CheckTraceKind::ComputedUserset { relation } => format!("computed_userset {}", relation.0),
+        CheckTraceKind::TupleToUserset {
+            tupleset,
+            computed_userset,
+        } => format!("tuple_to_userset {}->{}", tupleset.0, computed_userset.0),
+        CheckTraceKind::Union => "union".to_string(),
+        CheckTraceKind::Intersection => "intersection".to_string(),
+        CheckTraceKind::Exclusion => "exclusion".to_string(),
+    };
+    let result = match node.result {
+        Some(Permissionship::HasPermission) => "allowed",
+        Some(Permissionship::NoPermission) => "denied",
+        Some(Permissionship::ConditionalPermission) => "conditional",
+        None => "aborted",
+    };
+    let _ = write!(output, "{indent}{label}: {result}");
+    match node.note {
+        Some(CheckTraceNote::MemoHit) => output.push_str(" (memoized)"),
+        Some(CheckTraceNote::CycleDenied) => output.push_str(" (cycle)"),
+        Some(CheckTraceNote::DepthExceeded) => output.push_str(" (depth limit)"),
+        Some(CheckTraceNote::FanoutExceeded { limit }) => {
+            let _ = write!(output, " (fanout limit {limit})");
+        }
+        None => {}
+    }
+    output.push('\n');
+    for relationship in &node.relationships {
+        let _ = writeln!(output, "{indent}  read {relationship}");
+    }
+    for child in &node.children {
+        write_trace(output, child, depth + 1);
+    }
+}
+
+fn print_list(json: bool, key: &str, items: &[String]) -> CliResult {
+    if json {
+        return print_json(&json!({ key: items }));
+    }
+    for item in items {
+        emit(&format!("{item}\n"))?;
+    }
+    Ok(())
+}
+
+fn print_json(value: &impl serde::Serialize) -> CliResult {
+    emit(&format!("{}\n", serde_json::to_string_pretty(value)?))?;
+    Ok(())
+}
+
+/// Writes to stdout, treating a closed pipe such as `zanzibar export ... | head` as success.
+fn emit(text: &str) -> CliResult {
+    match io::stdout().lock().write_all(text.as_bytes()) {
+        Err(error) if error.kind() != io::ErrorKind::BrokenPipe => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Joins an error with its sources, since library errors keep details in `source()`.
+fn error_chain(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        let _ = write!(message, ": {error}");
+        source = error.source();
+    }
+    message
+}
@@ -0,0 +1,1153 @@
+//! Caveat definitions, bound caveat context, and conditional caveat evaluation.
+//!
+//! A caveat is a named boolean expression over typed parameters. Relationships may reference a
+//! caveat and bind some of its parameters; the remaining parameters are supplied by the check
+//! request. Evaluation uses three-valued logic so a check can report which parameters were missing
+//! instead of silently denying access.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    net::IpAddr,
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::domain::{CaveatName, DomainError};
+
+const MAX_CONTEXT_DEPTH: usize = 8;
+
+/// Errors produced while defining, binding, or evaluating caveats.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CaveatError {
+    /// A caveat or parameter name is not a valid identifier.
+    #[error(transparent)]
+    Domain(#[from] DomainError),
+
+    /// A caveat declares the same parameter twice.
+    #[error("caveat '{caveat}' declares parameter '{parameter}' more than once")]
+    DuplicateParameter {
+        /// Caveat name.
+        caveat: String,
+        /// Duplicate parameter name.
+        parameter: String,
+    },
+
+    /// A caveat expression or bound context references an undeclared parameter.
+    #[error("caveat '{caveat}' has no parameter named '{parameter}'")]
+    UnknownParameter {
+        /// Caveat name.
+        caveat: String,
+        /// Undeclared parameter name.
+        parameter: String,
+    },
+
+    /// A caveat expression does not type-check.
+    #[error("caveat '{caveat}' expression is ill-typed: {reason}")]
+    TypeMismatch {
+        /// Caveat name.
+        caveat: String,
+        /// Human-readable type error.
+        reason: String,
+    },
+
+    /// A context value does not match the declared parameter type.
+    #[error("caveat '{caveat}' parameter '{parameter}' expects a value of type {expected}")]
+    ParameterTypeMismatch {
+        /// Caveat name.
+        caveat: String,
+        /// Parameter name.
+        parameter: String,
+        /// Declared parameter type.
+        expected: CaveatParameterType,
+    },
+
+    /// A caveat parameter type name is not supported.
+    #[error("unknown caveat parameter type '{value}'")]
+    UnknownParameterType {
+        /// Rejected type text.
+        value: String,
+    },
+
+    /// Caveat context text is not a supported JSON object.
+    #[error("caveat context is malformed: {reason}")]
+    MalformedContext {
+        /// Static parse failure reason.
+        reason: &'static str,
+    },
+
+    /// A CIDR network operand is malformed.
+    #[error("'{value}' is not a valid CIDR network")]
+    InvalidNetwork {
+        /// Rejected network text.
+        value: String,
+    },
+}
+
+/// A typed caveat context value.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CaveatValue {
+    /// Boolean value.
+    Bool(bool),
+    /// Signed integer value.
+    Int(i64),
+    /// String value. IP address parameters are also carried as strings.
+    String(String),
+    /// Homogeneous list value.
+    List(Vec<CaveatValue>),
+}
+
+impl From<bool> for CaveatValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for CaveatValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<&str> for CaveatValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for CaveatValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl fmt::Display for CaveatValue {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(formatter, "{value}"),
+            Self::Int(value) => write!(formatter, "{value}"),
+            Self::String(value) => write_json_string(formatter, value),
+            Self::List(values) => {
+                formatter.write_str("[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        formatter.write_str(",")?;
+                    }
+                    write!(formatter, "{value}")?;
+                }
+                formatter.write_str("]")
+            }
+        }
+    }
+}
+
+/// Named caveat parameter values, either bound on a relationship or supplied by a request.
+///
+/// The text form is a JSON object whose values are booleans, integers, strings, or arrays of those.
+/// [`fmt::Display`] renders keys in sorted order so equal contexts always produce equal text.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CaveatContext(BTreeMap<String, CaveatValue>);
+
+impl CaveatContext {
+    /// Creates an empty context.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns this context with one additional parameter value.
+    #[must_use]
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<CaveatValue>) -> Self {
+        self.insert(name, value);
+        self
+    }
+
+    /// Inserts or replaces one parameter value.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<CaveatValue>) {
+        self.0.insert(name.into(), value.into());
+    }
+
+    /// Returns one parameter value.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&CaveatValue> {
+        self.0.get(name)
+    }
+
+    /// Returns true when no parameters are present.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of parameters present.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Iterates parameters in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &CaveatValue)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), value))
+    }
+}
+
+impl fmt::Display for CaveatContext {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("{")?;
+        for (index, (name, value)) in self.0.iter().enumerate() {
+            if index > 0 {
+                formatter.write_str(",")?;
+            }
+            write_json_string(formatter, name)?;
+            write!(formatter, ":{value}")?;
+        }
+        formatter.write_str("}")
+    }
+}
+
+impl FromStr for CaveatContext {
+    type Err = CaveatError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parser = JsonParser::new(value);
+        let context = parser.parse_object()?;
+        parser.skip_whitespace();
+        if !parser.is_done() {
+            return Err(malformed("trailing characters after context object"));
+        }
+        Ok(context)
+    }
+}
+
+impl FromIterator<(String, CaveatValue)> for CaveatContext {
+    fn from_iter<T: IntoIterator<Item = (String, CaveatValue)>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// A caveat reference stored on a relationship.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelationshipCaveat {
+    name: CaveatName,
+    context: CaveatContext,
+}
+
+impl RelationshipCaveat {
+    /// Creates a caveat reference with relationship-bound parameter values.
+    #[must_use]
+    pub fn new(name: CaveatName, context: CaveatContext) -> Self {
+        Self { name, context }
+    }
+
+    /// Returns the referenced caveat name.
+    #[must_use]
+    pub fn name(&self) -> &CaveatName {
+        &self.name
+    }
+
+    /// Returns the relationship-bound parameter values.
+    #[must_use]
+    pub fn context(&self) -> &CaveatContext {
+        &self.context
+    }
+}
+
+impl fmt::Display for RelationshipCaveat {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.context.is_empty() {
+            write!(formatter, "{}", self.name)
+        } else {
+            write!(formatter, "{}:{}", self.name, self.context)
+        }
+    }
+}
+
+impl FromStr for RelationshipCaveat {
+    type Err = CaveatError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once(':') {
+            Some((name, context)) => Ok(Self::new(CaveatName::new(name)?, context.parse()?)),
+            None => Ok(Self::new(CaveatName::new(value)?, CaveatContext::new())),
+        }
+    }
+}
+
+/// Declared type of a caveat parameter or expression.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CaveatParameterType {
+    /// Boolean.
+    Bool,
+    /// Signed 64-bit integer.
+    Int,
+    /// UTF-8 string.
+    String,
+    /// IPv4 or IPv6 address carried as a string.
+    IpAddress,
+    /// Homogeneous list.
+    List(Box<CaveatParameterType>),
+}
+
+impl CaveatParameterType {
+    fn accepts(&self, value: &CaveatValue) -> bool {
+        match (self, value) {
+            (Self::Bool, CaveatValue::Bool(_))
+            | (Self::Int, CaveatValue::Int(_))
+            | (Self::String, CaveatValue::String(_)) => true,
+            (Self::IpAddress, CaveatValue::String(value)) => value.parse::<IpAddr>().is_ok(),
+            (Self::List(element), CaveatValue::List(values)) => {
+                values.iter().all(|value| element.accepts(value))
+            }
+            _ => false,
+        }
+    }
+
+    fn is_comparable_with(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::IpAddress | Self::String, Self::IpAddress | Self::String) => true,
+            (Self::List(left), Self::List(right)) => left.is_comparable_with(right),
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for CaveatParameterType {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool => formatter.write_str("bool"),
+            Self::Int => formatter.write_str("int"),
+            Self::String => formatter.write_str("string"),
+            Self::IpAddress => formatter.write_str("ipaddress"),
+            Self::List(element) => write!(formatter, "list<{element}>"),
+        }
+    }
+}
+
+impl FromStr for CaveatParameterType {
+    type Err = CaveatError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        match value {
+            "bool" => Ok(Self::Bool),
+            "int" => Ok(Self::Int),
+            "string" => Ok(Self::String),
+            "ipaddress" => Ok(Self::IpAddress),
+            _ => value
+                .strip_prefix("list<")
+                .and_then(|rest| rest.strip_suffix('>'))
+                .map(|element| element.parse().map(|element| Self::List(Box::new(element))))
+                .unwrap_or_else(|| {
+                    Err(CaveatError::UnknownParameterType {
+                        value: value.to_string(),
+                    })
+                }),
+        }
+    }
+}
+
+/// Comparison operator in a caveat expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaveatComparison {
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+    /// `in`, list membership.
+    In,
+}
+
+impl CaveatComparison {
+    /// Returns the source symbol for this operator.
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::In => "in",
+        }
+    }
+}
+
+/// Caveat expression AST.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CaveatExpression {
+    /// Boolean literal.
+    Bool(bool),
+    /// Integer literal.
+    Int(i64),
+    /// String literal.
+    String(String),
+    /// Parameter reference.
+    Parameter(String),
+    /// List literal.
+    List(Vec<CaveatExpression>),
+    /// Logical negation.
+    Not(Box<CaveatExpression>),
+    /// Logical conjunction.
+    And(Box<CaveatExpression>, Box<CaveatExpression>),
+    /// Logical disjunction.
+    Or(Box<CaveatExpression>, Box<CaveatExpression>),
+    /// Binary comparison.
+    Compare {
+        /// Comparison operator.
+        operator: CaveatComparison,
+        /// Left operand.
+        left: Box<CaveatExpression>,
+        /// Right operand.
+        right: Box<CaveatExpression>,
+    },
+    /// `in_cidr(address, network)` IP network membership.
+    InCidr {
+        /// IP address operand.
+        address: Box<CaveatExpression>,
+        /// CIDR network operand such as `10.0.0.0/8`.
+        network: Box<CaveatExpression>,
+    },
+}
+
+impl fmt::Display for CaveatExpression {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(formatter, "{value}"),
+            Self::Int(value) => write!(formatter, "{value}"),
+            Self::String(value) => write!(formatter, "\"{value}\""),
+            Self::Parameter(name) => formatter.write_str(name),
+            Self::List(values) => {
+                formatter.write_str("[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        formatter.write_str(", ")?;
+                    }
+                    write!(formatter, "{value}")?;
+                }
+                formatter.write_str("]")
+            }
+            Self::Not(inner) => write!(formatter, "!{inner}"),
+            Self::And(left, right) => write!(formatter, "({left} && {right})"),
+            Self::Or(left, right) => write!(formatter, "({left} || {right})"),
+            Self::Compare {
+                operator,
+                left,
+                right,
+            } => write!(formatter, "({left} {} {right})", operator.symbol()),
+            Self::InCidr { address, network } => write!(formatter, "in_cidr({address}, {network})"),
+        }
+    }
+}
+
+/// Result of evaluating a caveat against bound and request context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaveatOutcome {
+    /// The caveat expression evaluated to true.
+    Satisfied,
+    /// The caveat expression evaluated to false.
+    Unsatisfied,
+    /// The result depends on parameters that neither the relationship nor the request supplied.
+    MissingContext(Vec<String>),
+}
+
+/// A type-checked caveat definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaveatDefinition {
+    name: CaveatName,
+    parameters: Vec<(String, CaveatParameterType)>,
+    expression: CaveatExpression,
+}
+
+impl CaveatDefinition {
+    /// Creates and type-checks a caveat definition.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CaveatError`] when a parameter name is invalid or duplicated, the expression
+    /// references an undeclared parameter, or the expression is not a well-typed boolean.
+    pub fn new(
+        name: CaveatName,
+        parameters: impl IntoIterator<Item = (String, CaveatParameterType)>,
+        expression: CaveatExpression,
+    ) -> Result<Self, CaveatError> {
+        let parameters = parameters.into_iter().collect::<Vec<_>>();
+        let mut names = BTreeSet::new();
+        for (parameter, _) in &parameters {
+            CaveatName::new(parameter.as_str())?;
+            if !names.insert(parameter.as_str()) {
+                return Err(CaveatError::DuplicateParameter {
+                    caveat: name.to_string(),
+                    parameter: parameter.clone(),
+                });
+            }
+        }
+        let definition = Self {
+            name,
+            parameters,
+            expression,
+        };
+        let root = definition.infer_type(&definition.expression)?;
+        if root != CaveatParameterType::Bool {
+            return Err(definition.type_error(format!("expression must be bool, found {root}")));
+        }
+        Ok(definition)
+    }
+
+    /// Returns the caveat name.
+    #[must_use]
+    pub fn name(&self) -> &CaveatName {
+        &self.name
+    }
+
+    /// Returns declared parameters in source order.
+    #[must_use]
+    pub fn parameters(&self) -> &[(String, CaveatParameterType)] {
+        &self.parameters
+    }
+
+    /// Returns the declared type of one parameter.
+    #[must_use]
+    pub fn parameter_type(&self, name: &str) -> Option<&CaveatParameterType> {
+        self.parameters
+            .iter()
+            .find(|(parameter, _)| parameter == name)
+            .map(|(_, parameter_type)| parameter_type)
+    }
+
+    /// Returns the caveat expression.
+    #[must_use]
+    pub fn expression(&self) -> &CaveatExpression {
+        &self.expression
+    }
+
+    /// Validates relationship-bound context against the declared parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CaveatError::UnknownParameter`] or [`CaveatError::ParameterTypeMismatch`] when a
+    /// bound value does not match the declaration.
+    pub fn validate_context(&self, context: &CaveatContext) -> Result<(), CaveatError> {
+        for (name, value) in context.iter() {
+            let expected =
+                self.parameter_type(name)
+                    .ok_or_else(|| CaveatError::UnknownParameter {
+                        caveat: self.name.to_string(),
+                        parameter: name.to_string(),
+                    })?;
+            if !expected.accepts(value) {
+                return Err(self.parameter_type_error(name, expected));
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluates this caveat.
+    ///
+    /// Relationship-bound values take precedence over request values. Request keys that are not
+    /// declared by this caveat are ignored because one request context is shared by every caveat
+    /// reached during a check.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CaveatError`] when a supplied value does not match its declared type or a CIDR
+    /// operand is malformed.
+    pub fn evaluate(
+        &self,
+        bound: &CaveatContext,
+        request: &CaveatContext,
+    ) -> Result<CaveatOutcome, CaveatError> {
+        match self.eval(&self.expression, bound, request)? {
+            Partial::Known(CaveatValue::Bool(true)) => Ok(CaveatOutcome::Satisfied),
+            Partial::Known(_) => Ok(CaveatOutcome::Unsatisfied),
+            Partial::Missing(missing) => {
+                Ok(CaveatOutcome::MissingContext(missing.into_iter().collect()))
+            }
+        }
+    }
+
+    fn infer_type(
+        &self,
+        expression: &CaveatExpression,
+    ) -> Result<CaveatParameterType, CaveatError> {
+        match expression {
+            CaveatExpression::Bool(_) => Ok(CaveatParameterType::Bool),
+            CaveatExpression::Int(_) => Ok(CaveatParameterType::Int),
+            CaveatExpression::String(value) => {
+                if value.contains(['"', '\n']) {
+                    return Err(
+                        self.type_error("string literals cannot contain quotes or newlines")
+                    );
+                }
+                Ok(CaveatParameterType::String)
+            }
+            CaveatExpression::Parameter(name) => {
+                self.parameter_type(name)
+                    .cloned()
+                    .ok_or_else(|| CaveatError::UnknownParameter {
+                        caveat: self.name.to_string(),
+                        parameter: name.clone(),
+                    })
+            }
+            CaveatExpression::List(values) => {
+                let mut element = None::<CaveatParameterType>;
+                for value in values {
+                    let value_type = self.infer_type(value)?;
+                    match &element {
+                        Some(expected) if !expected.is_comparable_with(&value_type) => {
+                            return Err(self.type_error(format!(
+                                "list literal mixes {expected} and {value_type}"
+                            )));
+                        }
+                        Some(_) => {}
+                        None => element = Some(value_type),
+                    }
+                }
+                element
+                    .map(|element| CaveatParameterType::List(Box::new(element)))
+                    .ok_or_else(|| self.type_error("list literals must not be empty"))
+            }
+            CaveatExpression::Not(inner) => {
+                self.expect_type(inner, &CaveatParameterType::Bool, "!")?;
+                Ok(CaveatParameterType::Bool)
+            }
+            CaveatExpression::And(left, right) => {
+                self.expect_type(left, &CaveatParameterType::Bool, "&&")?;
+                self.expect_type(right, &CaveatParameterType::Bool, "&&")?;
+                Ok(CaveatParameterType::Bool)
+            }
+            CaveatExpression::Or(left, right) => {
+                self.expect_type(left, &CaveatParameterType::Bool, "||")?;
+                self.expect_type(right, &CaveatParameterType::Bool, "||")?;
+                Ok(CaveatParameterType::Bool)
+            }
+            CaveatExpression::Compare {
+                operator,
+                left,
+                right,
+            } => {
+                let left_type = self.infer_type(left)?;
+                let right_type = self.infer_type(right)?;
+                let valid = match operator {
+                    CaveatComparison::Eq | CaveatComparison::Ne => {
+                        left_type.is_comparable_with(&right_type)
+                    }
+                    CaveatComparison::Lt
+                    | CaveatComparison::Le
+                    | CaveatComparison::Gt
+                    | CaveatComparison::Ge => {
+                        left_type == CaveatParameterType::Int
+                            && right_type == CaveatParameterType::Int
+                    }
+                    CaveatComparison::In => match &right_type {
+                        CaveatParameterType::List(element) => left_type.is_comparable_with(element),
+                        _ => false,
+                    },
+                };
+                if !valid {
+                    return Err(self.type_error(format!(
+                        "'{}' cannot compare {left_type} with {right_type}",
+                        operator.symbol()
+                    )));
+                }
+                Ok(CaveatParameterType::Bool)
+            }
+            CaveatExpression::InCidr { address, network } => {
+                let address_type = self.infer_type(address)?;
+                if !address_type.is_comparable_with(&CaveatParameterType::IpAddress) {
+                    return Err(self.type_error(format!(
+                        "in_cidr address must be ipaddress, found {address_type}"
+                    )));
+                }
+                self.expect_type(network, &CaveatParameterType::String, "in_cidr network")?;
+                if let CaveatExpression::String(value) = network.as_ref() {
+                    parse_network(value)?;
+                }
+                Ok(CaveatParameterType::Bool)
+            }
+        }
+    }
+
+    fn expect_type(
+        &self,
+        expression: &CaveatExpression,
+        expected: &CaveatParameterType,
+        operator: &str,
+    ) -> Result<(), CaveatError> {
+        let actual = self.infer_type(expression)?;
+        if &actual == expected {
+            Ok(())
+        } else {
+            Err(self.type_error(format!(
+                "'{operator}' expects {expected} operands, found {actual}"
+            )))
+        }
+    }
+
+    fn eval(
+        &self,
+        expression: &CaveatExpression,
+        bound: &CaveatContext,
+        request: &CaveatContext,
+    ) -> Result<Partial, CaveatError> {
+        match expression {
+            CaveatExpression::Bool(value) => Ok(Partial::Known(CaveatValue::Bool(*value))),
+            CaveatExpression::Int(value) => Ok(Partial::Known(CaveatValue::Int(*value))),
+            CaveatExpression::String(value) => {
+                Ok(Partial::Known(CaveatValue::String(value.clone())))
+            }
+            CaveatExpression::Parameter(name) => {
+                let Some(value) = bound.get(name).or_else(|| request.get(name)) else {
+                    return Ok(Partial::Missing(BTreeSet::from([name.clone()])));
+                };
+                let expected =
+                    self.parameter_type(name)
+                        .ok_or_else(|| CaveatError::UnknownParameter {
+                            caveat: self.name.to_string(),
+                            parameter: name.clone(),
+                        })?;
+                if !expected.accepts(value) {
+                    return Err(self.parameter_type_error(name, expected));
+                }
+                Ok(Partial::Known(value.clone()))
+            }
+            CaveatExpression::List(values) => {
+                let mut known = Vec::with_capacity(values.len());
+                let mut missing = BTreeSet::new();
+                for value in values {
+                    match self.eval(value, bound, request)? {
+                        Partial::Known(value) => known.push(value),
+                        Partial::Missing(names) => missing.extend(names),
+                    }
+                }
+                if missing.is_empty() {
+                    Ok(Partial::Known(CaveatValue::List(known)))
+                } else {
+                    Ok(Partial::Missing(missing))
+                }
+            }
+            CaveatExpression::Not(inner) => Ok(match self.eval(inner, bound, request)? {
+                Partial::Known(value) => Partial::Known(CaveatValue::Bool(!value.is_true())),
+                missing @ Partial::Missing(_) => missing,
+            }),
+            CaveatExpression::And(left, right) => {
+                let left = self.eval(left, bound, request)?;
+                if left.is_known_bool(false) {
+                    return Ok(left);
+                }
+                let right = self.eval(right, bound, request)?;
+                Ok(match (left, right) {
+                    (_, right) if right.is_known_bool(false) => right,
+                    (Partial::Known(_), right) => right,
+                    (left @ Partial::Missing(_), Partial::Known(_)) => left,
+                    (Partial::Missing(mut left), Partial::Missing(right)) => {
+                        left.extend(right);
+                        Partial::Missing(left)
+                    }
+                })
+            }
+            CaveatExpression::Or(left, right) => {
+                let left = self.eval(left, bound, request)?;
+                if left.is_known_bool(true) {
+                    return Ok(left);
+                }
+                let right = self.eval(right, bound, request)?;
+                Ok(match (left, right) {
+                    (_, right) if right.is_known_bool(true) => right,
+                    (Partial::Known(_), right) => right,
+                    (left @ Partial::Missing(_), Partial::Known(_)) => left,
+                    (Partial::Missing(mut left), Partial::Missing(right)) => {
+                        left.extend(right);
+                        Partial::Missing(left)
+                    }
+                })
+            }
+            CaveatExpression::Compare {
+                operator,
+                left,
+                right,
+            } => {
+                let (left, right) = match (
+                    self.eval(left, bound, request)?,
+                    self.eval(right, bound, request)?,
+                ) {
+                    (Partial::Known(left), Partial::Known(right)) => (left, right),
+                    (left, right) => return Ok(left.merge_missing(right)),
+                };
+                Ok(Partial::Known(CaveatValue::Bool(compare(
+                    *operator, &left, &right,
+                ))))
+            }
+            CaveatExpression::InCidr { address, network } => {
+                let (address, network) = match (
+                    self.eval(address, bound, request)?,
+                    self.eval(network, bound, request)?,
+                ) {
+                    (
+                        Partial::Known(CaveatValue::String(address)),
+                        Partial::Known(CaveatValue::String(network)),
+                    ) => (address, network),
+                    (left @ Partial::Missing(_), right) | (left, right @ Partial::Missing(_)) => {
+                        return Ok(left.merge_missing(right));
+                    }
+                    _ => return Err(self.type_error("in_cidr operands must be strings")),
+                };
+                let Ok(address) = address.parse::<IpAddr>() else {
+                    return Ok(Partial::Known(CaveatValue::Bool(false)));
+                };
+                Ok(Partial::Known(CaveatValue::Bool(network_contains(
+                    parse_network(&network)?,
+                    address,
+                ))))
+            }
+        }
+    }
+
+    fn type_error(&self, reason: impl Into<String>) -> CaveatError {
+        CaveatError::TypeMismatch {
+            caveat: self.name.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    fn parameter_type_error(&self, parameter: &str, expected: &CaveatParameterType) -> CaveatError {
+        CaveatError::ParameterTypeMismatch {
+            caveat: self.name.to_string(),
+            parameter: parameter.to_string(),
+            expected: expected.clone(),
+        }
+    }
+}
+
+impl fmt::Display for CaveatDefinition {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "caveat {}(", self.name)?;
+        for (index, (name, parameter_type)) in self.parameters.iter().enumerate() {
+            if index > 0 {
+                formatter.write_str(", ")?;
+            }
+            write!(formatter, "{name} {parameter_type}")?;
+        }
+        write!(formatter, ") {{\n    {}\n}}", self.expression)
+    }
+}
+
+#[derive(Debug)]
+enum Partial {
+    Known(CaveatValue),
+    Missing(BTreeSet<String>),
+}
+
+impl Partial {
+    fn is_known_bool(&self, expected: bool) -> bool {
+        matches!(self, Self::Known(CaveatValue::Bool(value)) if *value == expected)
+    }
+
+    fn merge_missing(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Missing(mut left), Self::Missing(right)) => {
+                left.extend(right);
+                Self::Missing(left)
+            }
+            (missing @ Self::Missing(_), Self::Known(_))
+            | (Self::Known(_), missing @ Self::Missing(_)) => missing,
+            (known @ Self::Known(_), Self::Known(_)) => known,
+        }
+    }
+}
+
+impl CaveatValue {
+    fn is_true(&self) -> bool {
+        matches!(self, Self::Bool(true))
+    }
+}
+
+fn compare(operator: CaveatComparison, left: &CaveatValue, right: &CaveatValue) -> bool {
+    match (operator, left, right) {
+        (CaveatComparison::Eq, left, right) => left == right,
+        (CaveatComparison::Ne, left, right) => left != right,
+        (CaveatComparison::Lt, CaveatValue::Int(left), CaveatValue::Int(right)) => left < right,
+        (CaveatComparison::Le, CaveatValue::Int(left), CaveatValue::Int(right)) => left <= right,
+        (CaveatComparison::Gt, CaveatValue::Int(left), CaveatValue::Int(right)) => left > right,
+        (CaveatComparison::Ge, CaveatValue::Int(left), CaveatValue::Int(right)) => left >= right,
+        (CaveatComparison::In, left, CaveatValue::List(values)) => values.contains(left),
+        _ => false,
+    }
+}
+
+fn parse_network(value: &str) -> Result<(IpAddr, u8), CaveatError> {
+    let invalid = || CaveatError::InvalidNetwork {
+        value: value.to_string(),
+    };
+    let (address, prefix) = value.split_once('/').ok_or_else(invalid)?;
+    let address = address.parse::<IpAddr>().map_err(|_| invalid())?;
+    let prefix = prefix.parse::<u8>().map_err(|_| invalid())?;
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    if prefix > max_prefix {
+        return Err(invalid());
+    }
+    Ok((address, prefix))
+}
+
+fn network_contains((network, prefix): (IpAddr, u8), address: IpAddr) -> bool {
+    match (network, address) {
+        (IpAddr::V4(network), IpAddr::V4(address)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(network) & mask == u32::from(address) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(address)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(network) & mask == u128::from(address) & mask
+        }
+        _ => false,
+    }
+}
+
+fn write_json_string(formatter: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    formatter.write_str("\"")?;
+    for character in value.chars() {
+        match character {
+            '"' => formatter.write_str("\\\"")?,
+            '\\' => formatter.write_str("\\\\")?,
+            '\n' => formatter.write_str("\\n")?,
+            '\r' => formatter.write_str("\\r")?,
+            '\t' => formatter.write_str("\\t")?,
+            character if character.is_control() => {
+                write!(formatter, "\\u{:04x}", u32::from(character))?;
+            }
+            character => write!(formatter, "{character}")?,
+        }
+    }
+    formatter.write_str("\"")
+}
+
+const fn malformed(reason: &'static str) -> CaveatError {
+    CaveatError::MalformedContext { reason }
+}
+
+/// Minimal JSON reader for caveat context objects.
+///
+/// Only the value shapes representable by [`CaveatValue`] are accepted; floats, `null`, and nested
+/// objects are rejected so that context text always round-trips through [`CaveatContext`].
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    const fn new(value: &'a str) -> Self {
+        Self {
+            bytes: value.as_bytes(),
+            offset: 0,
+        }
+    }
+
+    const fn is_done(&self) -> bool {
+        self.offset >= self.bytes.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.offset).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|byte| matches!(byte, b' ' | b'\t' | b'\r' | b'\n'))
+        {
+            self.offset += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: u8, reason: &'static str) -> Result<(), CaveatError> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.offset += 1;
+            Ok(())
+        } else {
+            Err(malformed(reason))
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<CaveatContext, CaveatError> {
+        self.expect(b'{', "context must be a JSON object")?;
+        let mut context = CaveatContext::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.offset += 1;
+            return Ok(context);
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.expect(b':', "expected ':' after context key")?;
+            let value = self.parse_value(1)?;
+            if context.0.insert(key, value).is_some() {
+                return Err(malformed("context keys must be unique"));
+            }
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b'}') => {
+                    self.offset += 1;
+                    return Ok(context);
+                }
+                _ => return Err(malformed("expected ',' or '}' in context object")),
+            }
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<CaveatValue, CaveatError> {
+        if depth > MAX_CONTEXT_DEPTH {
+            return Err(malformed("context values are nested too deeply"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'"') => self.parse_string().map(CaveatValue::String),
+            Some(b't') => self.parse_keyword("true", CaveatValue::Bool(true)),
+            Some(b'f') => self.parse_keyword("false", CaveatValue::Bool(false)),
+            Some(b'n') => Err(malformed("null context values are not supported")),
+            Some(b'{') => Err(malformed("nested context objects are not supported")),
+            Some(b'[') => {
+                self.offset += 1;
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.offset += 1;
+                    return Ok(CaveatValue::List(values));
+                }
+                loop {
+                    values.push(self.parse_value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.offset += 1,
+                        Some(b']') => {
+                            self.offset += 1;
+                            return Ok(CaveatValue::List(values));
+                        }
+                        _ => return Err(malformed("expected ',' or ']' in context list")),
+                    }
+                }
+            }
+            Some(b'-' | b'0'..=b'9') => self.parse_integer(),
+            _ => Err(malformed("unexpected context value")),
+        }
+    }
+
+    fn parse_keyword(
+        &mut self,
+        keyword: &'static str,
+        value: CaveatValue,
+    ) -> Result<CaveatValue, CaveatError> {
+        let end = self.offset + keyword.len();
+        if self.bytes.get(self.offset..end) == Some(keyword.as_bytes()) {
+            self.offset = end;
+            Ok(value)
+        } else {
+            Err(malformed("unexpected context value"))
+        }
+    }
+
+    fn parse_integer(&mut self) -> Result<CaveatValue, CaveatError> {
+        let start = self.offset;
+        if self.peek() == Some(b'-') {
+            self.offset += 1;
+        }
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.offset += 1;
+        }
+        if matches!(self.peek(), Some(b'.' | b'e' | b'E')) {
+            return Err(malformed("only integer context numbers are supported"));
+        }
+        std::str::from_utf8(self.bytes.get(start..self.offset).unwrap_or_default())
+            .ok()
+            .and_then(|text| text.parse::<i64>().ok())
+            .map(CaveatValue::Int)
+            .ok_or(malformed("context integer is out of range"))
+    }
+
+    fn parse_string(&mut self) -> Result<String, CaveatError> {
+        if self.peek() != Some(b'"') {
+            return Err(malformed("expected string"));
+        }
+        self.offset += 1;
+        let mut value = Vec::new();
+        loop {
+            let byte = self.peek().ok_or(malformed("unterminated string"))?;
+            self.offset += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = self.peek().ok_or(malformed("unterminated string"))?;
+                    self.offset += 1;
+                    match escape {
+                        b'"' => value.push(b'"'),
+                        b'\\' => value.push(b'\\'),
+                        b'/' => value.push(b'/'),
+                        b'b' => value.push(0x08),
+                        b'f' => value.push(0x0c),
+                        b'n' => value.push(b'\n'),
+                        b'r' => value.push(b'\r'),
+                        b't' => value.push(b'\t'),
+                        b'u' => {
+                            let character = self.parse_unicode_escape()?;
+                            let mut buffer = [0_u8; 4];
+                            value.extend_from_slice(character.encode_utf8(&mut buffer).as_bytes());
+                        }
+                        _ => return Err(malformed("invalid string escape")),
+                    }
+                }
+                byte if byte < 0x20 => return Err(malformed("control character in string")),
+                byte => value.push(byte),
+            }
+        }
+        String::from_utf8(value).map_err(|_| malformed("string is not valid utf-8"))
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, CaveatError> {
+        let high = self.parse_hex4()?;
+        if (0xd800..0xdc00).contains(&high) {
+            if self.bytes.get(self.offset..self.offset + 2) != Some(b"\\u") {
+                return Err(malformed("unpaired surrogate in string"));
+            }
+            self.offset += 2;
+            let low = self.parse_hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(malformed("unpaired surrogate in string"));
+            }
+            let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+            return char::from_u32(code).ok_or(malformed("invalid unicode escape"));
+        }
+        char::from_u32(high).ok_or(malformed("invalid unicode escape"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, CaveatError> {
+        let digits = self
+            .bytes
+            .get(self.offset..self.offset + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or(malformed("invalid unicode escape"))?;
+        self.offset += 4;
+        Ok(digits)
+    }
+}
@@ -0,0 +1,574 @@
+//! Incremental delta artifacts layered on a base snapshot.
+//!
+//! A delta names its base `.szsnap` by the footer digest of the base payload, and the artifact it
+//! continues by that artifact's digest: the base footer for the first delta, otherwise the
+//! previous delta's checksum. It records only what changed between two revisions: removed and added
+//! relationship rows, identifiers missing from the base symbol table, and the new canonical schema
+//! when it changed. Row fields are symbol ids; ids below the base symbol count index the base
+//! artifact's symbol table and the rest index the delta's own symbols. Deltas in a
+//! [`SnapshotChain`] apply in order, each starting at the revision the previous one ended on, and
+//! are validated as untrusted input like snapshots.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    str,
+    sync::Arc,
+};
+
+use crate::{
+    caveat::{CaveatContext, RelationshipCaveat},
+    domain::{CaveatName, Relationship, RelationshipExpiration, SubjectRef},
+    policy,
+    relationship::{RelationshipStoreView, StoreError},
+    revision::{DatastoreId, PublishedSnapshot, Revision, SchemaHash},
+    snapshot::{
+        self, BinaryCursor, LoadedSnapshot, SnapshotContentHash, SnapshotIntegrityMode,
+        SnapshotIoError, SnapshotLoadOptions, SnapshotSaveOptions, SnapshotValidationMode,
+        checked_u32_from_usize, checked_usize_from_u32,
+    },
+};
+
+const DELTA_MAGIC: [u8; 8] = *b"SZDELTA\x01";
+const CHECKSUM_LEN: usize = 32;
+const NO_SYMBOL: u32 = u32::MAX;
+const ROW_HAS_CAVEAT: u8 = 0b01;
+const ROW_HAS_EXPIRATION: u8 = 0b10;
+
+/// A base snapshot artifact followed by the delta artifacts to apply on top of it, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotChain {
+    base: PathBuf,
+    deltas: Vec<PathBuf>,
+}
+
+impl SnapshotChain {
+    /// Creates a chain holding only the base snapshot.
+    #[must_use]
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self {
+            base: base.into(),
+            deltas: Vec::new(),
+        }
+    }
+
+    /// Returns the chain with `delta` applied after the deltas already in it.
+    #[must_use]
+    pub fn with_delta(mut self, delta: impl Into<PathBuf>) -> Self {
+        self.deltas.push(delta.into());
+        self
+    }
+
+    /// Returns the base snapshot path.
+    #[must_use]
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Returns the delta paths in application order.
+    #[must_use]
+    pub fn deltas(&self) -> &[PathBuf] {
+        &self.deltas
+    }
+}
+
+/// Fixed fields at the start of every delta artifact.
+#[derive(Debug, Clone, Copy)]
+struct DeltaHeader {
+    base: SnapshotContentHash,
+    previous: SnapshotContentHash,
+    datastore_id: DatastoreId,
+    from_revision: Revision,
+    from_schema_hash: SchemaHash,
+    to_revision: Revision,
+    to_schema_hash: SchemaHash,
+    base_symbol_count: u32,
+}
+
+/// Relationship fields as symbol ids, with [`NO_SYMBOL`] for a missing subject relation.
+type SymbolRow = [u32; 6];
+
+pub(crate) fn save_delta_file(
+    chain: &SnapshotChain,
+    path: &Path,
+    snapshot: &PublishedSnapshot,
+    datastore_id: DatastoreId,
+    options: SnapshotSaveOptions,
+) -> Result<(), SnapshotIoError> {
+    snapshot::validate_compression_options(options)?;
+    let (previous, base, previous_digest) = load_chain(chain, SnapshotLoadOptions::default())?;
+    if snapshot.revision() < previous.revision {
+        return Err(SnapshotIoError::DeltaMismatch {
+            reason: "engine revision is older than the end of the snapshot chain",
+        });
+    }
+
+    let mut symbols = SymbolEncoder::new(base.checkpoint_symbols()?)?;
+    let current = snapshot.relationships().rows();
+    let current_keys = current.iter().collect::<HashSet<_>>();
+    let previous_rows = previous.relationships.rows();
+    let previous_by_key = previous_rows.iter().collect::<HashSet<_>>();
+    let mut removed = Vec::new();
+    for relationship in &previous_rows {
+        if !current_keys.contains(relationship) {
+            removed.push(symbols.row(relationship)?);
+        }
+    }
+    let mut added = Vec::new();
+    for relationship in &current {
+        let unchanged = previous_by_key.get(relationship).is_some_and(|previous| {
+            previous.caveat() == relationship.caveat()
+                && previous.expiration() == relationship.expiration()
+        });
+        if !unchanged {
+            added.push((symbols.row(relationship)?, relationship));
+        }
+    }
+
+    let header = DeltaHeader {
+        base: previous.content_hash,
+        previous: previous_digest,
+        datastore_id,
+        from_revision: previous.revision,
+        from_schema_hash: previous.schema_hash,
+        to_revision: snapshot.revision(),
+        to_schema_hash: snapshot.schema_hash(),
+        base_symbol_count: symbols.base_count,
+    };
+    let mut bytes = Vec::new();
+    write_header(&mut bytes, &header);
+    if snapshot.schema_hash() == previous.schema_hash {
+        bytes.push(0);
+    } else {
+        bytes.push(1);
+        let source =
+            policy::canonical_schema_source(snapshot.configs(), snapshot.schema().caveats());
+        push_text(&mut bytes, &source)?;
+    }
+    push_count(&mut bytes, symbols.added.len())?;
+    for symbol in &symbols.added {
+        push_text(&mut bytes, symbol)?;
+    }
+    push_count(&mut bytes, removed.len())?;
+    for row in &removed {
+        push_row(&mut bytes, row);
+    }
+    push_count(&mut bytes, added.len())?;
+    for (row, relationship) in &added {
+        push_row(&mut bytes, row);
+        push_payload(&mut bytes, relationship)?;
+    }
+    let checksum = blake3::hash(&bytes);
+    bytes.extend_from_slice(checksum.as_bytes());
+
+    let payload = snapshot::encode_payload(bytes, options)?;
+    let tmp_path = snapshot::snapshot_tmp_path(path, snapshot.revision());
+    let result = fs::write(&tmp_path, payload)
+        .and_then(|()| fs::rename(&tmp_path, path))
+        .map_err(Into::into);
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+pub(crate) fn load_snapshot_chain(
+    chain: &SnapshotChain,
+    options: SnapshotLoadOptions,
+) -> Result<LoadedSnapshot, SnapshotIoError> {
+    load_chain(chain, options).map(|(loaded, _, _)| loaded)
+}
+
+/// Loads the chain and also returns the base view, whose checkpoint symbols delta rows index, and
+/// the digest of the last artifact in the chain.
+fn load_chain(
+    chain: &SnapshotChain,
+    options: SnapshotLoadOptions,
+) -> Result<
+    (
+        LoadedSnapshot,
+        Arc<RelationshipStoreView>,
+        SnapshotContentHash,
+    ),
+    SnapshotIoError,
+> {
+    let base_options = SnapshotLoadOptions {
+        compression: snapshot::detect_compression(chain.base())?,
+        ..options
+    };
+    let mut loaded = snapshot::load_snapshot_file(chain.base(), base_options)?;
+    let base = Arc::clone(&loaded.relationships);
+    let symbols = base.checkpoint_symbols()?;
+    let mut digest = loaded.content_hash;
+    for path in chain.deltas() {
+        digest = apply_delta_file(&mut loaded, &symbols, digest, path, options)?;
+    }
+    Ok((loaded, base, digest))
+}
+
+/// Applies one delta artifact and returns its checksum, which the next delta must name.
+fn apply_delta_file(
+    loaded: &mut LoadedSnapshot,
+    base_symbols: &[&str],
+    previous: SnapshotContentHash,
+    path: &Path,
+    options: SnapshotLoadOptions,
+) -> Result<SnapshotContentHash, SnapshotIoError> {
+    let file = snapshot::read_capped_file(path, options.max_file_bytes)?;
+    let compression = snapshot::compression_of(&file);
+    let bytes = snapshot::decode_payload_bounded(file, compression, options.max_file_bytes)?;
+    let body_len = bytes
+        .len()
+        .checked_sub(CHECKSUM_LEN)
+        .ok_or(SnapshotIoError::Format {
+            reason: "delta artifact is truncated",
+        })?;
+    let (body, checksum) = bytes.split_at(body_len);
+    let digest = SnapshotContentHash::try_from(checksum).map_err(|_| SnapshotIoError::Format {
+        reason: "delta checksum is truncated",
+    })?;
+    if options.integrity == SnapshotIntegrityMode::Checksum
+        && blake3::hash(body).as_bytes().as_slice() != checksum
+    {
+        return Err(SnapshotIoError::Format {
+            reason: "delta checksum mismatch",
+        });
+    }
+
+    let mut cursor = BinaryCursor::new(body);
+    let header = read_header(&mut cursor)?;
+    if header.base != loaded.content_hash {
+        return Err(SnapshotIoError::DeltaMismatch {
+            reason: "delta was saved against a different base snapshot",
+        });
+    }
+    if header.previous != previous {
+        return Err(SnapshotIoError::DeltaMismatch {
+            reason: "delta was saved after a different artifact than the one before it",
+        });
+    }
+    if header.from_revision != loaded.revision || header.from_schema_hash != loaded.schema_hash {
+        return Err(SnapshotIoError::DeltaMismatch {
+            reason: "delta does not start at the revision the chain has reached",
+        });
+    }
+    if checked_usize_from_u32(header.base_symbol_count)? != base_symbols.len() {
+        return Err(SnapshotIoError::Format {
+            reason: "delta base symbol count does not match the base snapshot",
+        });
+    }
+    if header.to_revision < header.from_revision {
+        return Err(SnapshotIoError::Format {
+            reason: "delta ends before it starts",
+        });
+    }
+
+    let schema = match cursor.read_array::<1>()? {
+        [0] => None,
+        [1] => {
+            let (configs, schema) = snapshot::compile_schema_source(read_text(&mut cursor)?)?;
+            if SchemaHash::for_schema(&schema) != header.to_schema_hash {
+                return Err(SnapshotIoError::Format {
+                    reason: "schema hash does not match delta schema",
+                });
+            }
+            Some((configs, schema))
+        }
+        _ => {
+            return Err(SnapshotIoError::Format {
+                reason: "delta schema flag is invalid",
+            });
+        }
+    };
+    if schema.is_none() && header.to_schema_hash != header.from_schema_hash {
+        return Err(SnapshotIoError::Format {
+            reason: "delta changes the schema hash without schema text",
+        });
+    }
+
+    let symbol_count = read_count(&mut cursor)?;
+    let mut delta_symbols = Vec::new();
+    for _ in 0..symbol_count {
+        delta_symbols.push(read_text(&mut cursor)?);
+    }
+    let symbols = SymbolDecoder {
+        base: base_symbols,
+        delta: &delta_symbols,
+    };
+    let removed_count = read_count(&mut cursor)?;
+    let mut removed = Vec::new();
+    for _ in 0..removed_count {
+        removed.push(symbols.relationship(read_row(&mut cursor)?)?);
+    }
+    let added_count = read_count(&mut cursor)?;
+    let mut added = Vec::new();
+    for _ in 0..added_count {
+        let relationship = symbols.relationship(read_row(&mut cursor)?)?;
+        added.push(read_payload(&mut cursor, relationship)?);
+    }
+    if !cursor.is_empty() {
+        return Err(SnapshotIoError::Format {
+            reason: "delta artifact has trailing bytes",
+        });
+    }
+
+    let relationships = loaded
+        .relationships
+        .apply_snapshot_delta(removed, added)
+        .map_err(|error| match error {
+            StoreError::RelationshipNotFound { .. } => SnapshotIoError::DeltaMismatch {
+                reason: "delta removes a relationship the chain does not hold",
+            },
+            error => error.into(),
+        })?;
+    if let Some((configs, schema)) = schema {
+        loaded.configs = configs
+            .into_iter()
+            .map(|config| (config.name.clone(), config))
+            .collect();
+        loaded.schema = schema;
+    }
+    if options.validation == SnapshotValidationMode::Full {
+        relationships
+            .validate_caveats(&loaded.schema)
+            .map_err(|source| SnapshotIoError::Schema {
+                source: source.into(),
+            })?;
+    }
+    loaded.relationships = relationships;
+    loaded.revision = header.to_revision;
+    loaded.schema_hash = header.to_schema_hash;
+    loaded.datastore_id = Some(header.datastore_id);
+    Ok(digest)
+}
+
+/// Assigns symbol ids, reusing the base artifact's ids and appending identifiers it lacks.
+struct SymbolEncoder<'a> {
+    ids: HashMap<&'a str, u32>,
+    base_count: u32,
+    added: Vec<String>,
+    added_ids: HashMap<String, u32>,
+}
+
+impl<'a> SymbolEncoder<'a> {
+    fn new(base: Vec<&'a str>) -> Result<Self, SnapshotIoError> {
+        let base_count = checked_u32_from_usize(base.len())?;
+        let ids = base.into_iter().zip(0..).collect();
+        Ok(Self {
+            ids,
+            base_count,
+            added: Vec::new(),
+            added_ids: HashMap::new(),
+        })
+    }
+
+    fn id(&mut self, symbol: &str) -> Result<u32, SnapshotIoError> {
+        if let Some(id) = self.ids.get(symbol).or_else(|| self.added_ids.get(symbol)) {
+            return Ok(*id);
+        }
+        let id = self
+            .base_count
+            .checked_add(checked_u32_from_usize(self.added.len())?)
+            .filter(|id| *id != NO_SYMBOL)
+            .ok_or(SnapshotIoError::LimitExceeded {
+                component: "delta symbols",
+            })?;
+        self.added.push(symbol.to_string());
+        self.added_ids.insert(symbol.to_string(), id);
+        Ok(id)
+    }
+
+    fn row(&mut self, relationship: &Relationship) -> Result<SymbolRow, SnapshotIoError> {
+        let resource = relationship.resource();
+        let (subject_type, subject_id, subject_relation) = match relationship.subject() {
+            SubjectRef::Object(object) => (
+                object.object_type().as_str(),
+                object.object_id().as_str(),
+                None,
+            ),
+            SubjectRef::Userset { object, relation } => (
+                object.object_type().as_str(),
+                object.object_id().as_str(),
+                Some(relation.as_str()),
+            ),
+            SubjectRef::Wildcard(object_type) => (object_type.as_str(), "*", None),
+        };
+        Ok([
+            self.id(resource.object_type().as_str())?,
+            self.id(resource.object_id().as_str())?,
+            self.id(relationship.relation().as_str())?,
+            self.id(subject_type)?,
+            self.id(subject_id)?,
+            match subject_relation {
+                Some(relation) => self.id(relation)?,
+                None => NO_SYMBOL,
+            },
+        ])
+    }
+}
+
+/// Resolves symbol ids against the base symbol table followed by the delta's symbols.
+struct SymbolDecoder<'a> {
+    base: &'a [&'a str],
+    delta: &'a [&'a str],
+}
+
+impl SymbolDecoder<'_> {
+    fn symbol(&self, id: u32) -> Result<&str, SnapshotIoError> {
+        let index = checked_usize_from_u32(id)?;
+        let symbol = match index.checked_sub(self.base.len()) {
+            None => self.base.get(index),
+            Some(index) => self.delta.get(index),
+        };
+        symbol.copied().ok_or(SnapshotIoError::Format {
+            reason: "delta row references an unknown symbol",
+        })
+    }
+
+    fn relationship(&self, row: SymbolRow) -> Result<Relationship, SnapshotIoError> {
+        let [
+            resource_type,
+            resource_id,
+            relation,
+            subject_type,
+            subject_id,
+            subject_relation,
+        ] = row;
+        let mut text = format!(
+            "{}:{}#{}@{}:{}",
+            self.symbol(resource_type)?,
+            self.symbol(resource_id)?,
+            self.symbol(relation)?,
+            self.symbol(subject_type)?,
+            self.symbol(subject_id)?
+        );
+        if subject_relation != NO_SYMBOL {
+            text.push('#');
+            text.push_str(self.symbol(subject_relation)?);
+        }
+        Ok(text.parse()?)
+    }
+}
+
+fn write_header(bytes: &mut Vec<u8>, header: &DeltaHeader) {
+    bytes.extend_from_slice(&DELTA_MAGIC);
+    bytes.extend_from_slice(&header.base);
+    bytes.extend_from_slice(&header.previous);
+    bytes.extend_from_slice(header.datastore_id.as_bytes());
+    bytes.extend_from_slice(&header.from_revision.get().to_le_bytes());
+    bytes.extend_from_slice(header.from_schema_hash.as_bytes());
+    bytes.extend_from_slice(&header.to_revision.get().to_le_bytes());
+    bytes.extend_from_slice(header.to_schema_hash.as_bytes());
+    bytes.extend_from_slice(&header.base_symbol_count.to_le_bytes());
+}
+
+fn read_header(cursor: &mut BinaryCursor<'_>) -> Result<DeltaHeader, SnapshotIoError> {
+    if cursor.read_array::<8>()? != DELTA_MAGIC {
+        return Err(SnapshotIoError::Format {
+            reason: "delta magic or version is invalid",
+        });
+    }
+    Ok(DeltaHeader {
+        base: cursor.read_array()?,
+        previous: cursor.read_array()?,
+        datastore_id: DatastoreId::from_bytes(cursor.read_array()?),
+        from_revision: read_revision(cursor)?,
+        from_schema_hash: SchemaHash::from_bytes(cursor.read_array()?),
+        to_revision: read_revision(cursor)?,
+        to_schema_hash: SchemaHash::from_bytes(cursor.read_array()?),
+        base_symbol_count: cursor.read_u32()?,
+    })
+}
+
+fn read_revision(cursor: &mut BinaryCursor<'_>) -> Result<Revision, SnapshotIoError> {
+    std::num::NonZeroU64::new(cursor.read_u64()?)
+        .map(Revision::new)
+        .ok_or(SnapshotIoError::Format {
+            reason: "delta revision must be non-zero",
+        })
+}
+
+fn push_row(bytes: &mut Vec<u8>, row: &SymbolRow) {
+    for id in row {
+        bytes.extend_from_slice(&id.to_le_bytes());
+    }
+}
+
+fn read_row(cursor: &mut BinaryCursor<'_>) -> Result<SymbolRow, SnapshotIoError> {
+    let mut row = [0; 6];
+    for id in &mut row {
+        *id = cursor.read_u32()?;
+    }
+    Ok(row)
+}
+
+fn push_payload(bytes: &mut Vec<u8>, relationship: &Relationship) -> Result<(), SnapshotIoError> {
+    let caveat = relationship.caveat();
+    let expiration = relationship.expiration();
+    let mut flags = 0;
+    if caveat.is_some() {
+        flags |= ROW_HAS_CAVEAT;
+    }
+    if expiration.is_some() {
+        flags |= ROW_HAS_EXPIRATION;
+    }
+    bytes.push(flags);
+    if let Some(caveat) = caveat {
+        push_text(bytes, caveat.name().as_str())?;
+        push_text(bytes, &caveat.context().to_string())?;
+    }
+    if let Some(expiration) = expiration {
+        bytes.extend_from_slice(&expiration.unix_seconds().to_le_bytes());
+    }
+    Ok(())
+}
+
+fn read_payload(
+    cursor: &mut BinaryCursor<'_>,
+    mut relationship: Relationship,
+) -> Result<Relationship, SnapshotIoError> {
+    let [flags] = cursor.read_array::<1>()?;
+    if flags & !(ROW_HAS_CAVEAT | ROW_HAS_EXPIRATION) != 0 {
+        return Err(SnapshotIoError::Format {
+            reason: "delta row flags are invalid",
+        });
+    }
+    if flags & ROW_HAS_CAVEAT != 0 {
+        let name = CaveatName::new(read_text(cursor)?)?;
+        let context =
+            read_text(cursor)?
+                .parse::<CaveatContext>()
+                .map_err(|_| SnapshotIoError::Format {
+                    reason: "delta caveat context is malformed",
+                })?;
+        relationship = relationship.with_caveat(RelationshipCaveat::new(name, context));
+    }
+    if flags & ROW_HAS_EXPIRATION != 0 {
+        relationship = relationship.with_expiration(RelationshipExpiration::from_unix_seconds(
+            cursor.read_u64()?,
+        )?);
+    }
+    Ok(relationship)
+}
+
+fn push_count(bytes: &mut Vec<u8>, count: usize) -> Result<(), SnapshotIoError> {
+    bytes.extend_from_slice(&checked_u32_from_usize(count)?.to_le_bytes());
+    Ok(())
+}
+
+fn read_count(cursor: &mut BinaryCursor<'_>) -> Result<usize, SnapshotIoError> {
+    checked_usize_from_u32(cursor.read_u32()?)
+}
+
+fn push_text(bytes: &mut Vec<u8>, text: &str) -> Result<(), SnapshotIoError> {
+    push_count(bytes, text.len())?;
+    bytes.extend_from_slice(text.as_bytes());
+    Ok(())
+}
+
+fn read_text<'a>(cursor: &mut BinaryCursor<'a>) -> Result<&'a str, SnapshotIoError> {
+    str::from_utf8(cursor.read_len_prefixed()?).map_err(|_| SnapshotIoError::Format {
+        reason: "delta text is not valid utf-8",
+    })
+}
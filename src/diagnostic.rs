@@ -0,0 +1,175 @@
+//! Source-located diagnostics for schema and relationship text.
+
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::{domain::DomainError, error::ZanzibarError, schema::SchemaError};
+
+/// Byte range inside one source text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SourceSpan {
+    /// Byte offset of the first byte.
+    pub start: usize,
+    /// Byte offset one past the last byte.
+    pub end: usize,
+}
+
+impl SourceSpan {
+    /// Creates a span covering `start..end`.
+    #[must_use]
+    pub const fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+impl From<pest::Span<'_>> for SourceSpan {
+    fn from(span: pest::Span<'_>) -> Self {
+        Self::new(span.start(), span.end())
+    }
+}
+
+/// Underlying failure reported by a [`Diagnostic`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DiagnosticError {
+    /// The text does not match the grammar.
+    #[error("{0}")]
+    Syntax(String),
+    /// An identifier or relationship string is invalid.
+    #[error(transparent)]
+    Domain(DomainError),
+    /// A schema definition or reference is invalid.
+    #[error(transparent)]
+    Schema(SchemaError),
+}
+
+impl From<DomainError> for DiagnosticError {
+    fn from(error: DomainError) -> Self {
+        Self::Domain(error)
+    }
+}
+
+impl From<SchemaError> for DiagnosticError {
+    fn from(error: SchemaError) -> Self {
+        Self::Schema(error)
+    }
+}
+
+impl From<ZanzibarError> for DiagnosticError {
+    fn from(error: ZanzibarError) -> Self {
+        match error {
+            ZanzibarError::Domain(error) => Self::Domain(error),
+            ZanzibarError::Schema(error) => Self::Schema(error),
+            ZanzibarError::ParseError(message) => Self::Syntax(message),
+            error => Self::Syntax(error.to_string()),
+        }
+    }
+}
+
+/// One error located in a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Source name, such as [`crate::schema::SchemaSource::name`] or a relationship file path.
+    pub file: Option<String>,
+    /// One-based line of the span start.
+    pub line: usize,
+    /// One-based column of the span start, counted in characters.
+    pub column: usize,
+    /// Byte range the error applies to.
+    pub span: SourceSpan,
+    /// What went wrong.
+    pub error: DiagnosticError,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic for `span` in `text`, computing its line and column.
+    pub(crate) fn new(
+        file: Option<&str>,
+        text: &str,
+        span: SourceSpan,
+        error: impl Into<DiagnosticError>,
+    ) -> Self {
+        let start = span.start.min(text.len());
+        let before = text.get(..start).unwrap_or_default();
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        Self {
+            file: file.map(str::to_string),
+            line: before.matches('\n').count() + 1,
+            column: before
+                .get(line_start..)
+                .map_or(0, |prefix| prefix.chars().count())
+                + 1,
+            span,
+            error: error.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{}:{}:{}: {}",
+            self.file.as_deref().unwrap_or("<input>"),
+            self.line,
+            self.column,
+            self.error
+        )
+    }
+}
+
+/// Every error found in one pass over a source, in source order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Error)]
+pub struct Diagnostics {
+    /// Individual diagnostics.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// Returns the first diagnostic, if any.
+    #[must_use]
+    pub fn first(&self) -> Option<&Diagnostic> {
+        self.diagnostics.first()
+    }
+
+    /// Returns the diagnostics in source order.
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    /// Returns the number of diagnostics.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    /// Returns true when no diagnostics were collected.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Returns `Ok(())` when empty and `Err(self)` otherwise.
+    pub(crate) fn into_result(self) -> Result<(), Self> {
+        if self.diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = "";
+        for diagnostic in &self.diagnostics {
+            write!(formatter, "{separator}{diagnostic}")?;
+            separator = "\n";
+        }
+        Ok(())
+    }
+}
@@ -1,14 +1,31 @@
 //! Validated domain primitives for the local Zanzibar engine.
 
-use std::{fmt, str::FromStr};
-
-use crate::model::{Object, Relation, RelationTuple, User};
+use std::{
+    borrow::Cow,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    caveat::{CaveatError, RelationshipCaveat},
+    model::{Object, Relation, RelationTuple, User},
+};
 
 const MAX_TYPE_BYTES: usize = 64;
 const MAX_RELATION_BYTES: usize = 64;
 const MAX_ID_BYTES: usize = 256;
 const MAX_RELATIONSHIP_BYTES: usize = 768;
+const MAX_CAVEAT_SUFFIX_BYTES: usize = 4096;
 const LEGACY_USER_SUBJECT_TYPE: &str = "user";
+pub(crate) const WILDCARD_SUBJECT_ID: &str = "*";
+const EXPIRATION_SUFFIX_PREFIX: &str = "[expiration:";
+/// Caveat name that would collide with the `[expiration:...]` relationship suffix.
+const RESERVED_CAVEAT_NAME: &str = "expiration";
+const SECONDS_PER_DAY: u64 = 86_400;
+/// `9999-12-31T23:59:59Z`, the last instant representable as a four-digit RFC 3339 year.
+const MAX_EXPIRATION_UNIX_SECONDS: u64 = 253_402_300_799;
 
 /// Identifies a kind of validated domain identifier.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -23,6 +40,8 @@ pub enum IdentifierKind {
     SubjectType,
     /// Subject identifier inside a subject type.
     SubjectId,
+    /// Caveat or caveat parameter name.
+    CaveatName,
 }
 
 impl fmt::Display for IdentifierKind {
@@ -33,6 +52,7 @@ impl fmt::Display for IdentifierKind {
             Self::RelationName => formatter.write_str("relation name"),
             Self::SubjectType => formatter.write_str("subject type"),
             Self::SubjectId => formatter.write_str("subject id"),
+            Self::CaveatName => formatter.write_str("caveat name"),
         }
     }
 }
@@ -65,12 +85,28 @@ pub enum DomainError {
         offset: usize,
     },
 
+    /// An identifier uses a name the relationship grammar reserves.
+    #[error("identifier {kind} must not be the reserved name '{name}'")]
+    ReservedIdentifier {
+        /// Identifier category.
+        kind: IdentifierKind,
+        /// Reserved name that was rejected.
+        name: &'static str,
+    },
+
     /// A relationship string does not match the accepted grammar.
     #[error("relationship is malformed: {reason}")]
     MalformedRelationship {
         /// Static parse failure reason.
         reason: &'static str,
     },
+
+    /// A relationship expiration is not a supported UTC timestamp.
+    #[error("relationship expiration is invalid: {reason}")]
+    InvalidExpiration {
+        /// Static validation failure reason.
+        reason: &'static str,
+    },
 }
 
 macro_rules! validated_identifier {
@@ -84,8 +120,8 @@ macro_rules! validated_identifier {
             ///
             /// # Errors
             ///
-            /// Returns [`DomainError`] when the value is empty, too long, or contains bytes outside
-            /// the identifier allowlist.
+            /// Returns [`DomainError`] when the value is empty, too long, contains bytes outside
+            /// the identifier allowlist, or is a reserved name.
             pub fn new(value: impl Into<String>) -> Result<Self, DomainError> {
                 let value = value.into();
                 $validator($kind, &value, $max)?;
@@ -162,7 +198,7 @@ validated_identifier!(
     ObjectId,
     IdentifierKind::ObjectId,
     MAX_ID_BYTES,
-    validate_id_identifier
+    validate_object_id_identifier
 );
 validated_identifier!(
     RelationName,
@@ -182,6 +218,26 @@ validated_identifier!(
     MAX_ID_BYTES,
     validate_id_identifier
 );
+validated_identifier!(
+    CaveatName,
+    IdentifierKind::CaveatName,
+    MAX_RELATION_BYTES,
+    validate_caveat_name
+);
+
+impl SubjectId {
+    /// Returns the reserved `*` subject id that matches every object of a subject type.
+    #[must_use]
+    pub fn wildcard() -> Self {
+        Self(WILDCARD_SUBJECT_ID.to_owned())
+    }
+
+    /// Returns true when this is the reserved wildcard subject id.
+    #[must_use]
+    pub fn is_wildcard(&self) -> bool {
+        self.0 == WILDCARD_SUBJECT_ID
+    }
+}
 
 /// A validated object reference.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -275,6 +331,8 @@ pub enum SubjectRef {
         /// Userset relation.
         relation: RelationName,
     },
+    /// A wildcard subject such as `user:*`, matching every direct subject of the type.
+    Wildcard(ObjectType),
 }
 
 impl fmt::Display for SubjectRef {
@@ -282,6 +340,7 @@ impl fmt::Display for SubjectRef {
         match self {
             Self::Object(object) => write!(formatter, "{object}"),
             Self::Userset { object, relation } => write!(formatter, "{object}#{relation}"),
+            Self::Wildcard(object_type) => write!(formatter, "{object_type}:{WILDCARD_SUBJECT_ID}"),
         }
     }
 }
@@ -295,7 +354,15 @@ impl FromStr for SubjectRef {
                 object: object.parse()?,
                 relation: RelationName::try_from(relation)?,
             }),
-            None => Ok(Self::Object(parse_subject_object(value)?)),
+            None => match value
+                .strip_suffix(WILDCARD_SUBJECT_ID)
+                .and_then(|prefix| prefix.strip_suffix(':'))
+            {
+                Some(subject_type) => Ok(Self::Wildcard(ObjectType::new(
+                    SubjectType::try_from(subject_type)?.as_str(),
+                )?)),
+                None => Ok(Self::Object(parse_subject_object(value)?)),
+            },
         }
     }
 }
@@ -334,16 +401,192 @@ impl TryFrom<&User> for SubjectRef {
                 object: ObjectRef::try_from(object)?,
                 relation: RelationName::try_from(relation.0.as_str())?,
             }),
+            User::Wildcard => Ok(Self::Wildcard(ObjectType::try_from(
+                LEGACY_USER_SUBJECT_TYPE,
+            )?)),
+        }
+    }
+}
+
+impl TryFrom<&SubjectRef> for User {
+    type Error = DomainError;
+
+    /// Converts `user:<id>`, `user:*`, and userset subjects; other direct subject types have no
+    /// [`User`] form.
+    fn try_from(value: &SubjectRef) -> Result<Self, Self::Error> {
+        match value {
+            SubjectRef::Object(object)
+                if object.object_type().as_str() == LEGACY_USER_SUBJECT_TYPE =>
+            {
+                Ok(Self::UserId(object.object_id().as_str().to_string()))
+            }
+            SubjectRef::Userset { object, relation } => Ok(Self::Userset(
+                Object::new(object.object_type().as_str(), object.object_id().as_str()),
+                Relation::new(relation.as_str()),
+            )),
+            SubjectRef::Wildcard(object_type)
+                if object_type.as_str() == LEGACY_USER_SUBJECT_TYPE =>
+            {
+                Ok(Self::Wildcard)
+            }
+            SubjectRef::Object(_) | SubjectRef::Wildcard(_) => {
+                Err(DomainError::MalformedRelationship {
+                    reason: "subject must be a user, user wildcard, or userset",
+                })
+            }
+        }
+    }
+}
+
+/// Instant after which a relationship no longer grants access, with one-second precision.
+///
+/// The text form is an RFC 3339 UTC timestamp such as `2026-01-31T00:00:00Z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RelationshipExpiration(u64);
+
+impl RelationshipExpiration {
+    /// Creates an expiration from seconds since the Unix epoch.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DomainError::InvalidExpiration`] when the instant is after year 9999.
+    pub fn from_unix_seconds(seconds: u64) -> Result<Self, DomainError> {
+        if seconds > MAX_EXPIRATION_UNIX_SECONDS {
+            return Err(DomainError::InvalidExpiration {
+                reason: "expiration must not be after year 9999",
+            });
+        }
+        Ok(Self(seconds))
+    }
+
+    /// Creates an expiration from a system time, truncated to whole seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DomainError::InvalidExpiration`] when the instant is before the Unix epoch or
+    /// after year 9999.
+    pub fn from_system_time(time: SystemTime) -> Result<Self, DomainError> {
+        let since_epoch =
+            time.duration_since(UNIX_EPOCH)
+                .map_err(|_| DomainError::InvalidExpiration {
+                    reason: "expiration must not be before the unix epoch",
+                })?;
+        Self::from_unix_seconds(since_epoch.as_secs())
+    }
+
+    /// Returns seconds since the Unix epoch.
+    #[must_use]
+    pub const fn unix_seconds(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the expiration as a system time.
+    #[must_use]
+    pub fn to_system_time(self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.0)
+    }
+
+    /// Returns true when a relationship with this expiration is no longer visible at `now`.
+    #[must_use]
+    pub fn is_expired_at(self, now: SystemTime) -> bool {
+        now >= self.to_system_time()
+    }
+}
+
+impl fmt::Display for RelationshipExpiration {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.0 / SECONDS_PER_DAY;
+        let seconds = self.0 % SECONDS_PER_DAY;
+        let (year, month, day) = civil_from_days(days);
+        write!(
+            formatter,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+            seconds / 3_600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+impl FromStr for RelationshipExpiration {
+    type Err = DomainError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let malformed = DomainError::InvalidExpiration {
+            reason: "expiration must be an RFC 3339 UTC timestamp like 2026-01-31T00:00:00Z",
+        };
+        let bytes = value.as_bytes();
+        if bytes.len() != 20
+            || bytes[4] != b'-'
+            || bytes[7] != b'-'
+            || bytes[10] != b'T'
+            || bytes[13] != b':'
+            || bytes[16] != b':'
+            || bytes[19] != b'Z'
+        {
+            return Err(malformed);
         }
+        let field = |start: usize, end: usize| -> Result<u64, DomainError> {
+            let digits = &value[start..end];
+            if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+                return Err(malformed.clone());
+            }
+            digits.parse().map_err(|_| malformed.clone())
+        };
+        let (year, month, day) = (field(0, 4)?, field(5, 7)?, field(8, 10)?);
+        let (hour, minute, second) = (field(11, 13)?, field(14, 16)?, field(17, 19)?);
+        if year < 1970
+            || !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return Err(malformed);
+        }
+        Self::from_unix_seconds(
+            days_from_civil(year, month, day) * SECONDS_PER_DAY
+                + hour * 3_600
+                + minute * 60
+                + second,
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for RelationshipExpiration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RelationshipExpiration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Self::from_str(&value).map_err(serde::de::Error::custom)
     }
 }
 
 /// A validated relationship tuple.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// Equality and hashing cover only the resource, relation, and subject. The optional caveat and
+/// expiration are payload attached to that identity, so a relationship can be re-touched with a
+/// different caveat or expiration without becoming a second row.
+#[derive(Debug, Clone)]
 pub struct Relationship {
     resource: ObjectRef,
     relation: RelationName,
     subject: SubjectRef,
+    caveat: Option<RelationshipCaveat>,
+    expiration: Option<RelationshipExpiration>,
 }
 
 impl Relationship {
@@ -354,9 +597,25 @@ impl Relationship {
             resource,
             relation,
             subject,
+            caveat: None,
+            expiration: None,
         }
     }
 
+    /// Returns this relationship guarded by a caveat.
+    #[must_use]
+    pub fn with_caveat(mut self, caveat: RelationshipCaveat) -> Self {
+        self.caveat = Some(caveat);
+        self
+    }
+
+    /// Returns this relationship with a time after which it no longer grants access.
+    #[must_use]
+    pub fn with_expiration(mut self, expiration: RelationshipExpiration) -> Self {
+        self.expiration = Some(expiration);
+        self
+    }
+
     /// Returns the relationship resource object.
     #[must_use]
     pub fn resource(&self) -> &ObjectRef {
@@ -374,15 +633,53 @@ impl Relationship {
     pub fn subject(&self) -> &SubjectRef {
         &self.subject
     }
+
+    /// Returns the caveat guarding this relationship, if any.
+    #[must_use]
+    pub fn caveat(&self) -> Option<&RelationshipCaveat> {
+        self.caveat.as_ref()
+    }
+
+    /// Returns the time after which this relationship no longer grants access, if any.
+    #[must_use]
+    pub const fn expiration(&self) -> Option<RelationshipExpiration> {
+        self.expiration
+    }
+}
+
+impl PartialEq for Relationship {
+    fn eq(&self, other: &Self) -> bool {
+        self.resource == other.resource
+            && self.relation == other.relation
+            && self.subject == other.subject
+    }
+}
+
+impl Eq for Relationship {}
+
+impl Hash for Relationship {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.resource.hash(state);
+        self.relation.hash(state);
+        self.subject.hash(state);
+    }
 }
 
 impl fmt::Display for Relationship {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            formatter,
-            "{}#{}@{}",
-            self.resource, self.relation, self.subject
-        )
+        let core = format!("{}#{}@{}", self.resource, self.relation, self.subject);
+        if core.contains(['[', ']']) {
+            formatter.write_str(&core.replace('[', "\\[").replace(']', "\\]"))?;
+        } else {
+            formatter.write_str(&core)?;
+        }
+        if let Some(caveat) = &self.caveat {
+            write!(formatter, "[{caveat}]")?;
+        }
+        if let Some(expiration) = self.expiration {
+            write!(formatter, "{EXPIRATION_SUFFIX_PREFIX}{expiration}]")?;
+        }
+        Ok(())
     }
 }
 
@@ -390,6 +687,16 @@ impl FromStr for Relationship {
     type Err = DomainError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (value, expiration) = split_expiration_suffix(value)?;
+        let (value, caveat) = split_caveat_suffix(value)?;
+        if value
+            .match_indices(['[', ']'])
+            .any(|(index, _)| !is_escaped(value, index))
+        {
+            return Err(unescaped_bracket_error());
+        }
+        let value = unescape_brackets(value);
+        let value = &*value;
         if value.len() > MAX_RELATIONSHIP_BYTES {
             return Err(DomainError::IdentifierTooLong {
                 kind: IdentifierKind::ObjectId,
@@ -413,11 +720,19 @@ impl FromStr for Relationship {
             });
         }
 
-        Ok(Self::new(
+        let relationship = Self::new(
             object.parse()?,
             RelationName::try_from(relation)?,
             subject.parse()?,
-        ))
+        );
+        let relationship = match caveat {
+            Some(caveat) => relationship.with_caveat(caveat),
+            None => relationship,
+        };
+        Ok(match expiration {
+            Some(expiration) => relationship.with_expiration(expiration),
+            None => relationship,
+        })
     }
 }
 
@@ -454,6 +769,79 @@ impl TryFrom<&RelationTuple> for Relationship {
     }
 }
 
+fn split_expiration_suffix(
+    value: &str,
+) -> Result<(&str, Option<RelationshipExpiration>), DomainError> {
+    let Some(core) = value.strip_suffix(']') else {
+        return Ok((value, None));
+    };
+    let Some(start) = core
+        .rfind(EXPIRATION_SUFFIX_PREFIX)
+        .filter(|&start| !is_escaped(core, start))
+    else {
+        return Ok((value, None));
+    };
+    let expiration = core[start + EXPIRATION_SUFFIX_PREFIX.len()..].parse()?;
+    Ok((&core[..start], Some(expiration)))
+}
+
+fn split_caveat_suffix(value: &str) -> Result<(&str, Option<RelationshipCaveat>), DomainError> {
+    let Some(core) = value.strip_suffix(']') else {
+        return Ok((value, None));
+    };
+    if is_escaped(value, core.len()) {
+        return Ok((value, None));
+    }
+    let start = core
+        .match_indices('[')
+        .map(|(start, _)| start)
+        .find(|&start| !is_escaped(core, start))
+        .ok_or(DomainError::MalformedRelationship {
+            reason: "relationship caveat must start with '['",
+        })?;
+    let (core, caveat) = (&core[..start], &core[start + 1..]);
+    if !core.contains('@') {
+        return Err(unescaped_bracket_error());
+    }
+    if caveat.len() > MAX_CAVEAT_SUFFIX_BYTES {
+        return Err(DomainError::IdentifierTooLong {
+            kind: IdentifierKind::CaveatName,
+            max_bytes: MAX_CAVEAT_SUFFIX_BYTES,
+        });
+    }
+    let caveat = caveat
+        .parse::<RelationshipCaveat>()
+        .map_err(|error| match error {
+            CaveatError::Domain(error) => error,
+            _ => DomainError::MalformedRelationship {
+                reason: "relationship caveat context is malformed",
+            },
+        })?;
+    Ok((core, Some(caveat)))
+}
+
+/// Returns true when the byte at `index` follows a backslash, which relationship text only uses to
+/// escape a bracket inside an object id.
+fn is_escaped(value: &str, index: usize) -> bool {
+    value[..index].ends_with('\\')
+}
+
+/// Object ids written before brackets were escaped read as a caveat suffix when the subject id
+/// ends in `[...]`, and are rejected everywhere else.
+const fn unescaped_bracket_error() -> DomainError {
+    DomainError::MalformedRelationship {
+        reason: "brackets in object ids must be escaped as '\\[' and '\\]'",
+    }
+}
+
+fn unescape_brackets(value: &str) -> Cow<'_, str> {
+    if value.contains('\\') {
+        Cow::Owned(value.replace("\\[", "[").replace("\\]", "]"))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
 fn parse_subject_object(value: &str) -> Result<ObjectRef, DomainError> {
     let (subject_type, subject_id) = split_once(value, ':', "subject reference must contain ':'")?;
     Ok(ObjectRef::new(
@@ -480,6 +868,21 @@ fn split_once<'a>(
     Ok((left, right))
 }
 
+fn validate_caveat_name(
+    kind: IdentifierKind,
+    value: &str,
+    max_bytes: usize,
+) -> Result<(), DomainError> {
+    validate_type_identifier(kind, value, max_bytes)?;
+    if value == RESERVED_CAVEAT_NAME {
+        return Err(DomainError::ReservedIdentifier {
+            kind,
+            name: RESERVED_CAVEAT_NAME,
+        });
+    }
+    Ok(())
+}
+
 fn validate_type_identifier(
     kind: IdentifierKind,
     value: &str,
@@ -530,6 +933,17 @@ fn validate_id_identifier(
     Ok(())
 }
 
+fn validate_object_id_identifier(
+    kind: IdentifierKind,
+    value: &str,
+    max_bytes: usize,
+) -> Result<(), DomainError> {
+    if value == WILDCARD_SUBJECT_ID {
+        return Err(DomainError::InvalidIdentifierByte { kind, offset: 0 });
+    }
+    validate_id_identifier(kind, value, max_bytes)
+}
+
 impl TryFrom<&Relation> for RelationName {
     type Error = DomainError;
 
@@ -549,3 +963,44 @@ impl From<&ObjectId> for SubjectId {
         Self(value.0.clone())
     }
 }
+
+/// Converts days since the Unix epoch to a proleptic Gregorian `(year, month, day)`.
+const fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Converts a proleptic Gregorian date on or after 1970-01-01 to days since the Unix epoch.
+const fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+const fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
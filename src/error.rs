@@ -2,7 +2,7 @@
 use thiserror::Error;
 
 use crate::{
-    domain::DomainError, eval::EvaluationError, relationship::StoreError,
+    diagnostic::Diagnostics, domain::DomainError, eval::EvaluationError, relationship::StoreError,
     revision::ConsistencyError, schema::SchemaError,
 };
 
@@ -48,4 +48,8 @@ pub enum ZanzibarError {
     /// Graph evaluation failed.
     #[error(transparent)]
     Evaluation(#[from] EvaluationError),
+
+    /// Schema or relationship text had one or more located errors.
+    #[error(transparent)]
+    Diagnostics(#[from] Diagnostics),
 }
@@ -5,24 +5,30 @@ use std::cell::Cell;
 #[cfg(feature = "bench-internals")]
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
     num::{NonZeroU32, NonZeroUsize},
 };
 
 use thiserror::Error;
 
 use crate::{
-    domain::{ObjectRef as DomainObjectRef, ObjectType, RelationName, SubjectId, SubjectType},
+    caveat::{CaveatContext, CaveatError, CaveatOutcome},
+    domain::{
+        ObjectRef as DomainObjectRef, ObjectType, RelationName, SubjectId, SubjectType,
+        WILDCARD_SUBJECT_ID,
+    },
     error::ZanzibarError,
     model::{
-        ExpandedUserset, LookupResources, LookupResourcesRequest, LookupSubjects,
-        LookupSubjectsRequest, Object, Relation, User,
+        CheckResponse, ExpandedUserset, LookupResources, LookupResourcesRequest, LookupSubjects,
+        LookupSubjectsRequest, Object, Permissionship, Relation, User,
     },
-    relationship::{QueryLimit, StoreCheckKey, SubjectFilter},
+    relationship::{QueryLimit, RelationshipRef, StoreCheckKey, SubjectFilter},
     revision::PublishedSnapshot,
     schema::{
-        CompiledUsersetExpression, RelationDefinition as SchemaRelationDefinition, SchemaRelationId,
+        AllowedSubjectTypes, CompiledUsersetExpression,
+        RelationDefinition as SchemaRelationDefinition, SchemaError, SchemaRelationId,
     },
+    trace::{CheckTraceKind, CheckTraceNote, CheckTraceRecorder, CheckTraceResponse},
 };
 
 const DEFAULT_MAX_DEPTH: u32 = 50;
@@ -442,6 +448,10 @@ pub enum EvaluationError {
         /// Configured fanout limit.
         limit: NonZeroU32,
     },
+
+    /// A relationship caveat could not be evaluated against the request context.
+    #[error(transparent)]
+    Caveat(#[from] CaveatError),
 }
 
 /// Immutable key for evaluator depth errors.
@@ -563,7 +573,7 @@ pub enum Membership {
     Allowed,
     /// The subject is not a member.
     Denied,
-    /// Reserved for future caveat/condition support.
+    /// Membership depends on caveat parameters the request did not supply.
     Conditional,
 }
 
@@ -609,6 +619,16 @@ impl Membership {
     }
 }
 
+impl From<Membership> for Permissionship {
+    fn from(membership: Membership) -> Self {
+        match membership {
+            Membership::Allowed => Self::HasPermission,
+            Membership::Denied => Self::NoPermission,
+            Membership::Conditional => Self::ConditionalPermission,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum CheckMemoKey {
     Store(StoreCheckKey),
@@ -948,6 +968,9 @@ pub struct EvaluationContext<'a> {
     expand_stack: Vec<ExpandKey>,
     check_frames: Vec<CheckFrame>,
     check_memo: Option<RequestCheckMemo>,
+    caveat_context: CaveatContext,
+    missing_caveat_parameters: BTreeSet<String>,
+    trace: Option<CheckTraceRecorder>,
     #[cfg(feature = "bench-internals")]
     completed_check_keys: HashSet<CheckKey>,
 }
@@ -974,11 +997,27 @@ impl<'a> EvaluationContext<'a> {
             expand_stack: Vec::new(),
             check_frames: Vec::new(),
             check_memo: None,
+            caveat_context: CaveatContext::new(),
+            missing_caveat_parameters: BTreeSet::new(),
+            trace: None,
             #[cfg(feature = "bench-internals")]
             completed_check_keys: HashSet::new(),
         }
     }
 
+    /// Returns this context with request-supplied caveat parameters.
+    #[must_use]
+    pub fn with_caveat_context(mut self, caveat_context: CaveatContext) -> Self {
+        self.caveat_context = caveat_context;
+        self
+    }
+
+    /// Returns caveat parameters that were missing while evaluating conditional relationships.
+    #[must_use]
+    pub fn missing_caveat_parameters(&self) -> Vec<String> {
+        self.missing_caveat_parameters.iter().cloned().collect()
+    }
+
     #[must_use]
     pub(crate) fn new_with_request_memo(
         snapshot: &'a PublishedSnapshot,
@@ -995,6 +1034,7 @@ impl<'a> EvaluationContext<'a> {
         self.check_stack.clear();
         self.expand_stack.clear();
         self.check_frames.clear();
+        self.missing_caveat_parameters.clear();
         if let Some(generation) = self
             .generation
             .get()
@@ -1038,6 +1078,7 @@ impl<'a> EvaluationContext<'a> {
     fn evaluate_check_key(
         &mut self,
         key: &CheckKey,
+        target: (&Object, &str, &User),
         evaluate: impl FnOnce(&mut Self) -> Result<Membership, ZanzibarError>,
     ) -> Result<Membership, ZanzibarError> {
         if self.is_check_active(key) {
@@ -1045,17 +1086,26 @@ impl<'a> EvaluationContext<'a> {
             if self.check_memo.is_some() {
                 record_check_memo_active_cycle_skip();
             }
+            self.trace_shortcut(target, CheckTraceNote::CycleDenied, Membership::Denied);
             return Ok(Membership::Denied);
         }
         let memo_eligible = self.check_memo.is_some() && !self.check_stack.is_empty();
         #[cfg(feature = "bench-internals")]
         self.record_check_started(key, memo_eligible);
         if memo_eligible && let Some(membership) = self.memo_lookup(key)? {
+            self.trace_shortcut(target, CheckTraceNote::MemoHit, membership);
             return Ok(membership);
         }
 
         let entry_remaining_depth = self.remaining_depth;
-        self.enter(EvaluationKey::Check(key.clone()))?;
+        if let Some(trace) = &mut self.trace {
+            trace.open(check_trace_kind(target));
+        }
+        if let Err(error) = self.enter(EvaluationKey::Check(key.clone())) {
+            let result = Err(error);
+            self.trace_close(&result);
+            return result;
+        }
         if memo_eligible {
             self.push_check_frame(entry_remaining_depth);
         }
@@ -1077,9 +1127,37 @@ impl<'a> EvaluationContext<'a> {
         }
         #[cfg(feature = "bench-internals")]
         self.record_check_completed(key, &result, memo_eligible);
+        self.trace_close(&result);
         result
     }
 
+    fn trace_shortcut(
+        &mut self,
+        target: (&Object, &str, &User),
+        note: CheckTraceNote,
+        membership: Membership,
+    ) {
+        if let Some(trace) = &mut self.trace {
+            trace.shortcut(check_trace_kind(target), note, membership);
+        }
+    }
+
+    fn trace_close(&mut self, result: &Result<Membership, ZanzibarError>) {
+        if let Some(trace) = &mut self.trace {
+            trace.close(result);
+        }
+    }
+
+    fn trace_relationship(
+        &mut self,
+        relationship: RelationshipRef<'_>,
+    ) -> Result<(), ZanzibarError> {
+        if let Some(trace) = &mut self.trace {
+            trace.relationship(relationship.to_relationship()?);
+        }
+        Ok(())
+    }
+
     fn memo_lookup(&self, key: &CheckKey) -> Result<Option<Membership>, ZanzibarError> {
         let Some(memo) = &self.check_memo else {
             return Ok(None);
@@ -1122,7 +1200,7 @@ impl<'a> EvaluationContext<'a> {
         user: &User,
     ) -> Result<Membership, ZanzibarError> {
         let key = CheckKey::new(self.snapshot, object, relation, user);
-        self.evaluate_check_key(&key, |context| {
+        self.evaluate_check_key(&key, (object, &relation.0, user), |context| {
             context.check_entered(object, relation, user)
         })
     }
@@ -1135,7 +1213,7 @@ impl<'a> EvaluationContext<'a> {
         relation_definition: &SchemaRelationDefinition,
     ) -> Result<Membership, ZanzibarError> {
         let key = CheckKey::new(self.snapshot, object, relation, user);
-        self.evaluate_check_key(&key, |context| {
+        self.evaluate_check_key(&key, (object, &relation.0, user), |context| {
             match relation_definition.compiled_userset_rewrite() {
                 Some(expression) => context.eval_compiled_schema_expression(
                     object,
@@ -1156,7 +1234,7 @@ impl<'a> EvaluationContext<'a> {
         residuals: &[LookupResidualCheck],
     ) -> Result<Membership, ZanzibarError> {
         let key = CheckKey::from_relation_name(self.snapshot, object, root_relation, user);
-        self.evaluate_check_key(&key, |context| {
+        self.evaluate_check_key(&key, (object, root_relation.as_str(), user), |context| {
             context.verify_lookup_residuals_entered(object, user, residuals)
         })
     }
@@ -1195,7 +1273,7 @@ impl<'a> EvaluationContext<'a> {
         user: &User,
     ) -> Result<Membership, ZanzibarError> {
         let key = CheckKey::from_relation_name(self.snapshot, object, relation_name, user);
-        self.evaluate_check_key(&key, |context| {
+        self.evaluate_check_key(&key, (object, relation_name.as_str(), user), |context| {
             context.check_relation_name_entered(object, relation_name, user)
         })
     }
@@ -1208,7 +1286,7 @@ impl<'a> EvaluationContext<'a> {
         user: &User,
     ) -> Result<Membership, ZanzibarError> {
         let key = CheckKey::from_relation_name(self.snapshot, object, relation_name, user);
-        self.evaluate_check_key(&key, |context| {
+        self.evaluate_check_key(&key, (object, relation_name.as_str(), user), |context| {
             context.check_relation_id_entered(object, relation_name, relation_id, user)
         })
     }
@@ -1332,6 +1410,28 @@ impl<'a> EvaluationContext<'a> {
         relation_name: &RelationName,
         user: &User,
         expression: &CompiledUsersetExpression,
+    ) -> Result<Membership, ZanzibarError> {
+        let Some(trace) = &mut self.trace else {
+            return self.eval_compiled_schema_expression_node(
+                object,
+                relation_name,
+                user,
+                expression,
+            );
+        };
+        trace.open(expression_trace_kind(expression));
+        let result =
+            self.eval_compiled_schema_expression_node(object, relation_name, user, expression);
+        self.trace_close(&result);
+        result
+    }
+
+    fn eval_compiled_schema_expression_node(
+        &mut self,
+        object: &Object,
+        relation_name: &RelationName,
+        user: &User,
+        expression: &CompiledUsersetExpression,
     ) -> Result<Membership, ZanzibarError> {
         match expression {
             CompiledUsersetExpression::This => self.eval_this(object, relation_name, user),
@@ -1384,7 +1484,7 @@ impl<'a> EvaluationContext<'a> {
         user: &User,
     ) -> Result<Membership, ZanzibarError> {
         let key = CheckKey::from_relation_name(self.snapshot, object, relation_name, user);
-        self.evaluate_check_key(&key, |context| {
+        self.evaluate_check_key(&key, (object, relation_name.as_str(), user), |context| {
             context.eval_this(object, relation_name, user)
         })
     }
@@ -1397,34 +1497,81 @@ impl<'a> EvaluationContext<'a> {
     ) -> Result<Membership, ZanzibarError> {
         let resource = DomainObjectRef::try_from(object)?;
         let subject = SubjectFilter::try_from(user)?;
-        if self.snapshot.relationships().any_resource_relation_subject(
-            &resource,
-            relation_name,
-            &subject,
-        ) {
-            return Ok(Membership::Allowed);
+        let wildcard = subject.wildcard();
+        let relationships = self.snapshot.relationships();
+        let mut result = Membership::Denied;
+        for subject in [Some(&subject), wildcard.as_ref()].into_iter().flatten() {
+            if relationships.has_caveats() {
+                for relationship in
+                    relationships.resource_relation_subject(&resource, relation_name, subject)
+                {
+                    self.trace_relationship(relationship)?;
+                    result = result.union(self.relationship_caveat_membership(relationship)?);
+                    if result == Membership::Allowed {
+                        return Ok(result);
+                    }
+                }
+            } else if let Some(relationship) = relationships
+                .resource_relation_subject(&resource, relation_name, subject)
+                .next()
+            {
+                self.trace_relationship(relationship)?;
+                return Ok(Membership::Allowed);
+            }
         }
 
         let mut fanout = 0_u32;
-        for relationship in self.snapshot.relationships().resource_relation(
-            &resource,
-            relation_name,
-            unbounded_query_limit(),
-        ) {
+        for relationship in
+            relationships.resource_relation(&resource, relation_name, unbounded_query_limit())
+        {
             if let Some((nested_object, nested_relation)) =
                 relationship.subject_userset_relation_name()?
             {
                 self.increment_fanout(&mut fanout)?;
-                if self
-                    .check_relation_name(&nested_object, &nested_relation, user)?
-                    .is_allowed()
-                {
-                    return Ok(Membership::Allowed);
+                self.trace_relationship(relationship)?;
+                let edge = self.relationship_caveat_membership(relationship)?;
+                if edge == Membership::Denied {
+                    continue;
+                }
+                result = result.union(edge.intersection(self.check_relation_name(
+                    &nested_object,
+                    &nested_relation,
+                    user,
+                )?));
+                if result == Membership::Allowed {
+                    return Ok(result);
                 }
             }
         }
 
-        Ok(Membership::Denied)
+        Ok(result)
+    }
+
+    fn relationship_caveat_membership(
+        &mut self,
+        relationship: RelationshipRef<'_>,
+    ) -> Result<Membership, ZanzibarError> {
+        let Some(caveat) = relationship.caveat() else {
+            return Ok(Membership::Allowed);
+        };
+        let definition = self
+            .snapshot
+            .schema()
+            .caveat(caveat.name())
+            .ok_or_else(|| SchemaError::UnknownCaveat {
+                caveat: caveat.name().to_string(),
+            })?;
+        match definition
+            .evaluate(caveat.context(), &self.caveat_context)
+            .map_err(EvaluationError::from)?
+        {
+            CaveatOutcome::Satisfied => Ok(Membership::Allowed),
+            CaveatOutcome::Unsatisfied => Ok(Membership::Denied),
+            CaveatOutcome::MissingContext(parameters) => {
+                self.missing_caveat_parameters.extend(parameters);
+                Ok(Membership::Conditional)
+            }
+        }
     }
 
     fn eval_tuple_to_userset(
@@ -1435,23 +1582,72 @@ impl<'a> EvaluationContext<'a> {
         computed_userset_relation: &RelationName,
     ) -> Result<Membership, ZanzibarError> {
         let mut fanout = 0_u32;
+        let mut result = Membership::Denied;
         let resource = DomainObjectRef::try_from(object)?;
         for relationship in self.snapshot.relationships().resource_relation(
             &resource,
             tupleset_relation,
             unbounded_query_limit(),
         ) {
-            if let Some((intermediate_object, _)) = relationship.subject_userset_relation_name()? {
+            if let Some(intermediate_object) = self.tuple_to_userset_target(
+                relationship,
+                &resource,
+                tupleset_relation,
+                computed_userset_relation,
+            )? {
                 self.increment_fanout(&mut fanout)?;
-                if self
-                    .check_relation_name(&intermediate_object, computed_userset_relation, user)?
-                    .is_allowed()
-                {
-                    return Ok(Membership::Allowed);
+                self.trace_relationship(relationship)?;
+                let edge = self.relationship_caveat_membership(relationship)?;
+                if edge == Membership::Denied {
+                    continue;
+                }
+                result = result.union(edge.intersection(self.check_relation_name(
+                    &intermediate_object,
+                    computed_userset_relation,
+                    user,
+                )?));
+                if result == Membership::Allowed {
+                    return Ok(result);
                 }
             }
         }
-        Ok(Membership::Denied)
+        Ok(result)
+    }
+
+    /// Returns the object an arrow evaluates `computed_userset_relation` on for one tupleset row.
+    ///
+    /// Userset subjects contribute their object. Plain object subjects contribute themselves only
+    /// when the tupleset relation declares their type, as in `relation parent: folder`, and that
+    /// type defines the computed relation; tuplesets without declared subject types ignore them.
+    fn tuple_to_userset_target(
+        &self,
+        relationship: RelationshipRef<'_>,
+        resource: &DomainObjectRef,
+        tupleset_relation: &RelationName,
+        computed_userset_relation: &RelationName,
+    ) -> Result<Option<Object>, ZanzibarError> {
+        if let Some((intermediate_object, _)) = relationship.subject_userset_relation_name()? {
+            return Ok(Some(intermediate_object));
+        }
+        let Some(intermediate_object) = relationship.direct_subject_object() else {
+            return Ok(None);
+        };
+        let object_type = ObjectType::try_from(intermediate_object.namespace.as_str())?;
+        let resolver = self.snapshot.schema().resolver();
+        let declared = match resolver
+            .relation(resource.object_type(), tupleset_relation)?
+            .allowed_subject_types()
+        {
+            AllowedSubjectTypes::Unspecified => false,
+            AllowedSubjectTypes::Explicit(subjects) => subjects.iter().any(|subject| {
+                subject.relation().is_none() && subject.object_type() == &object_type
+            }),
+        };
+        let defines_relation = declared
+            && resolver
+                .relation(&object_type, computed_userset_relation)
+                .is_ok();
+        Ok(defines_relation.then_some(intermediate_object))
     }
 
     fn eval_compiled_schema_union(
@@ -1570,9 +1766,12 @@ impl<'a> EvaluationContext<'a> {
                     &tupleset_relation,
                     unbounded_query_limit(),
                 ) {
-                    if let Some((intermediate_object, _)) =
-                        relationship.subject_userset_relation_name()?
-                    {
+                    if let Some(intermediate_object) = self.tuple_to_userset_target(
+                        relationship,
+                        &resource,
+                        &tupleset_relation,
+                        computed_userset_relation,
+                    )? {
                         self.increment_fanout(&mut fanout)?;
                         users.push(self.expand_relation_name(
                             &intermediate_object,
@@ -1745,9 +1944,12 @@ impl<'a> EvaluationContext<'a> {
                         record_lookup_subjects_result_limit_exit();
                         return Ok(());
                     }
-                    if let Some((intermediate_object, _)) =
-                        relationship.subject_userset_relation_name()?
-                    {
+                    if let Some(intermediate_object) = self.tuple_to_userset_target(
+                        relationship,
+                        &resource,
+                        &tupleset_relation,
+                        computed_userset_relation,
+                    )? {
                         self.increment_fanout(&mut fanout)?;
                         self.stream_lookup_subjects_relation(
                             collector,
@@ -2007,14 +2209,79 @@ pub(crate) fn check_prepared_with_snapshot(
     relation: &Relation,
     user: &User,
     relation_definition: &SchemaRelationDefinition,
+    caveat_context: &CaveatContext,
     limits: EvaluationLimits,
-) -> Result<Membership, ZanzibarError> {
-    EvaluationContext::new(snapshot, limits).check_prepared(
-        object,
-        relation,
-        user,
-        relation_definition,
-    )
+) -> Result<(Membership, Vec<String>), ZanzibarError> {
+    let mut context =
+        EvaluationContext::new(snapshot, limits).with_caveat_context(caveat_context.clone());
+    let membership = context.check_prepared(object, relation, user, relation_definition)?;
+    let missing = if membership == Membership::Conditional {
+        context.missing_caveat_parameters()
+    } else {
+        Vec::new()
+    };
+    Ok((membership, missing))
+}
+
+pub(crate) fn check_prepared_with_trace(
+    snapshot: &PublishedSnapshot,
+    object: &Object,
+    relation: &Relation,
+    user: &User,
+    relation_definition: &SchemaRelationDefinition,
+    caveat_context: &CaveatContext,
+    limits: EvaluationLimits,
+) -> Result<CheckTraceResponse, ZanzibarError> {
+    let mut context = EvaluationContext::new_with_request_memo(snapshot, limits)
+        .with_caveat_context(caveat_context.clone());
+    context.trace = Some(CheckTraceRecorder::default());
+    let response = match context.check_prepared(object, relation, user, relation_definition) {
+        Ok(membership) => Some(CheckResponse {
+            allowed: membership.is_allowed(),
+            permissionship: membership.into(),
+            missing_context: if membership == Membership::Conditional {
+                context.missing_caveat_parameters()
+            } else {
+                Vec::new()
+            },
+            token: None,
+        }),
+        Err(ZanzibarError::Evaluation(
+            EvaluationError::DepthExceeded { .. } | EvaluationError::FanoutExceeded { .. },
+        )) => None,
+        Err(error) => return Err(error),
+    };
+    context.trace.take().unwrap_or_default().finish(response)
+}
+
+fn check_trace_kind((object, relation, user): (&Object, &str, &User)) -> CheckTraceKind {
+    CheckTraceKind::Check {
+        object: object.clone(),
+        relation: Relation(relation.to_string()),
+        subject: user.clone(),
+    }
+}
+
+fn expression_trace_kind(expression: &CompiledUsersetExpression) -> CheckTraceKind {
+    match expression {
+        CompiledUsersetExpression::This => CheckTraceKind::This,
+        CompiledUsersetExpression::ComputedUserset { relation, .. } => {
+            CheckTraceKind::ComputedUserset {
+                relation: Relation(relation.as_str().to_string()),
+            }
+        }
+        CompiledUsersetExpression::TupleToUserset {
+            tupleset_relation,
+            computed_userset_relation,
+            ..
+        } => CheckTraceKind::TupleToUserset {
+            tupleset: Relation(tupleset_relation.as_str().to_string()),
+            computed_userset: Relation(computed_userset_relation.as_str().to_string()),
+        },
+        CompiledUsersetExpression::Union(_) => CheckTraceKind::Union,
+        CompiledUsersetExpression::Intersection(_) => CheckTraceKind::Intersection,
+        CompiledUsersetExpression::Exclusion { .. } => CheckTraceKind::Exclusion,
+    }
 }
 
 /// Evaluates a snapshot-backed expand request.
@@ -2047,7 +2314,12 @@ pub fn lookup_resources_with_snapshot(
         .schema()
         .resolver()
         .relation(&resource_type, &permission)?;
-    let producer_plan = lookup_producer_plan(snapshot, &resource_type, &permission)?;
+    // Caveated rows can make a candidate conditional, so every candidate needs a full root check.
+    let producer_plan = if snapshot.relationships().has_caveats() {
+        None
+    } else {
+        lookup_producer_plan(snapshot, &resource_type, &permission)?
+    };
     if producer_plan.is_none() {
         record_lookup_resources_planner_fallback();
     }
@@ -2065,6 +2337,18 @@ pub fn lookup_resources_with_snapshot(
     let mut seen = HashSet::new();
     let mut resources = Vec::new();
     let mut check_context = EvaluationContext::new_with_request_memo(snapshot, limits);
+    // A `user:*` row grants the requested user as directly as its own row does.
+    if matches!(request.subject, User::UserId(_))
+        && snapshot
+            .relationships()
+            .has_reverse_subject_candidates(&SubjectFilter::try_from(&User::Wildcard)?)
+        && visited_subjects.insert(User::Wildcard)
+    {
+        frontier.push_back(LookupFrontierEntry::new(
+            User::Wildcard,
+            LookupSubjectProof::exact_root(),
+        ));
+    }
 
     while let Some(frontier_entry) = frontier.pop_front() {
         record_lookup_resources_frontier_subject();
@@ -2098,7 +2382,11 @@ pub fn lookup_resources_with_snapshot(
                 &mut resources,
                 &mut check_context,
             )? {
-                return Ok(LookupResources { resources });
+                return Ok(LookupResources {
+                    resources,
+                    cursor: None,
+                    token: None,
+                });
             }
         }
         if process_tuple_to_userset_ignored_relation_edges(
@@ -2114,11 +2402,19 @@ pub fn lookup_resources_with_snapshot(
             &mut check_context,
             &mut tuple_relation_expansions,
         )? {
-            return Ok(LookupResources { resources });
+            return Ok(LookupResources {
+                resources,
+                cursor: None,
+                token: None,
+            });
         }
     }
 
-    Ok(LookupResources { resources })
+    Ok(LookupResources {
+        resources,
+        cursor: None,
+        token: None,
+    })
 }
 
 #[allow(
@@ -2813,10 +3109,14 @@ pub fn lookup_subjects_with_snapshot(
         &mut collector,
         &request.resource,
         &permission,
-        false,
+        snapshot.relationships().has_caveats(),
     )?;
 
-    Ok(LookupSubjects { subjects })
+    Ok(LookupSubjects {
+        subjects,
+        cursor: None,
+        token: None,
+    })
 }
 
 fn compiled_schema_invariant_error() -> ZanzibarError {
@@ -3109,7 +3409,11 @@ impl LookupSubjectCollector<'_, '_> {
         if self.subject_type.as_str() != "user" {
             return Ok(());
         }
-        let subject = User::UserId(id.to_string());
+        let subject = if id == WILDCARD_SUBJECT_ID {
+            User::Wildcard
+        } else {
+            User::UserId(id.to_string())
+        };
         record_lookup_subjects_candidate_subject();
         if self.seen_subjects.insert(subject.clone()) {
             if !verify_candidate {
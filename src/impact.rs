@@ -0,0 +1,156 @@
+//! Access-impact analysis: which grants a pending change would add or remove.
+
+use std::collections::HashSet;
+
+use crate::{
+    domain::{DomainError, ObjectType, RelationName},
+    model::{Object, Relation, User},
+    relationship::RelationshipMutation,
+    revision::{Consistency, ConsistencyToken},
+    schema::{CompiledSchema, SchemaSource},
+};
+
+/// Request to compare grants before and after a speculative change.
+///
+/// The change is a candidate schema, relationship mutations, or both; the schema is applied first
+/// so mutations may use relations it introduces.
+#[derive(Debug, Clone)]
+pub struct AccessImpactRequest<'a> {
+    /// Relationship mutations to apply speculatively.
+    pub mutations: Vec<RelationshipMutation>,
+    /// Candidate schema that replaces the current one, if the change edits the schema.
+    pub schema: Option<SchemaSource<'a>>,
+    /// Permissions whose grants are compared.
+    pub scopes: Vec<AccessImpactScope>,
+    /// Snapshot the change is applied on top of.
+    pub consistency: Consistency,
+}
+
+impl<'a> AccessImpactRequest<'a> {
+    /// Creates a request comparing `scopes` at the latest revision with no change yet attached.
+    #[must_use]
+    pub fn new(scopes: impl IntoIterator<Item = AccessImpactScope>) -> Self {
+        Self {
+            mutations: Vec::new(),
+            schema: None,
+            scopes: scopes.into_iter().collect(),
+            consistency: Consistency::Latest,
+        }
+    }
+
+    /// Sets the relationship mutations to evaluate.
+    #[must_use]
+    pub fn with_mutations(
+        mut self,
+        mutations: impl IntoIterator<Item = RelationshipMutation>,
+    ) -> Self {
+        self.mutations = mutations.into_iter().collect();
+        self
+    }
+
+    /// Sets the candidate schema to evaluate.
+    #[must_use]
+    pub const fn with_schema(mut self, schema: SchemaSource<'a>) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Sets the snapshot the change is applied on top of.
+    #[must_use]
+    pub fn with_consistency(mut self, consistency: Consistency) -> Self {
+        self.consistency = consistency;
+        self
+    }
+}
+
+/// One resource type, permission, and subject type whose grants are compared.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessImpactScope {
+    /// Resource namespace/type to enumerate.
+    pub resource_type: String,
+    /// Permission or relation evaluated on each resource.
+    pub permission: Relation,
+    /// Subject namespace/type whose access is reported.
+    pub subject_type: String,
+}
+
+impl AccessImpactScope {
+    /// Creates a scope for `permission` on `resource_type` held by `subject_type` subjects.
+    #[must_use]
+    pub fn new(
+        resource_type: impl Into<String>,
+        permission: Relation,
+        subject_type: impl Into<String>,
+    ) -> Self {
+        Self {
+            resource_type: resource_type.into(),
+            permission,
+            subject_type: subject_type.into(),
+        }
+    }
+
+    /// Returns whether `schema` defines this scope's permission, so a candidate schema that drops
+    /// it reports every previous grant as lost instead of failing.
+    pub(crate) fn resolves_in(&self, schema: &CompiledSchema) -> Result<bool, DomainError> {
+        let object_type = ObjectType::try_from(self.resource_type.as_str())?;
+        let relation = RelationName::try_from(self.permission.0.as_str())?;
+        Ok(schema.resolver().relation(&object_type, &relation).is_ok())
+    }
+}
+
+/// One subject's permission on one resource.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AccessGrant {
+    /// Resource the grant applies to.
+    pub resource: Object,
+    /// Permission granted.
+    pub permission: Relation,
+    /// Subject holding the permission.
+    pub subject: User,
+}
+
+/// Grants a change would add and remove.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessImpact {
+    /// Grants present only after the change, in scope then lookup order.
+    pub gained: Vec<AccessGrant>,
+    /// Grants present only before the change, in scope then lookup order.
+    pub lost: Vec<AccessGrant>,
+    /// Token of the snapshot the change was applied on top of.
+    pub token: ConsistencyToken,
+}
+
+impl AccessImpact {
+    pub(crate) const fn new(token: ConsistencyToken) -> Self {
+        Self {
+            gained: Vec::new(),
+            lost: Vec::new(),
+            token,
+        }
+    }
+
+    /// Records the difference between one scope's grants before and after the change.
+    pub(crate) fn record(&mut self, before: Vec<AccessGrant>, after: Vec<AccessGrant>) {
+        let previous = before.iter().cloned().collect::<HashSet<_>>();
+        let next = after.iter().cloned().collect::<HashSet<_>>();
+        self.gained
+            .extend(after.into_iter().filter(|grant| !previous.contains(grant)));
+        self.lost
+            .extend(before.into_iter().filter(|grant| !next.contains(grant)));
+    }
+}
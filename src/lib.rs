@@ -42,6 +42,7 @@
 //!             subject: alice.clone(),
 //!             permission: viewer.clone(),
 //!             resource_type: "doc".to_string(),
+//!             cursor: None,
 //!         })?
 //!         .resources,
 //!     vec![doc.clone()],
@@ -52,6 +53,7 @@
 //!             resource: doc,
 //!             permission: viewer,
 //!             subject_type: "user".to_string(),
+//!             cursor: None,
 //!         })?
 //!         .subjects,
 //!     vec![alice],
@@ -64,51 +66,68 @@
 #![warn(rust_2024_compatibility, missing_docs, missing_debug_implementations)]
 
 pub mod api;
+pub mod caveat;
+pub mod delta;
+pub mod diagnostic;
 pub mod domain;
 pub mod error;
 pub mod eval;
+pub mod impact;
 pub mod model;
 pub mod parser;
 pub mod policy;
 pub mod relationship;
+pub mod reload;
 pub mod revision;
 mod runtime;
 pub mod schema;
 pub mod snapshot;
 pub mod store;
+pub mod trace;
+pub mod validation;
+pub mod wal;
+pub mod watch;
 
 use std::{
     collections::{HashMap, VecDeque},
     fmt,
-    num::NonZeroUsize,
+    num::{NonZeroU32, NonZeroUsize},
     path::Path,
-    sync::Arc,
+    sync::{Arc, mpsc::Sender},
+    time::SystemTime,
 };
 
 use arc_swap::ArcSwapOption;
 
 pub use crate::{
     api::{EngineError, TenantId, ZanzibarEngine, ZanzibarEngineBuilder, ZanzibarTenantShards},
+    delta::SnapshotChain,
     policy::{PolicyIoError, PolicyText, PolicyTextFile},
     snapshot::{
-        IndexProfile, SnapshotCompression, SnapshotIntegrityMode, SnapshotIoError,
-        SnapshotLoadOptions, SnapshotLoadProfile, SnapshotSaveOptions, SnapshotValidationMode,
+        IndexProfile, SnapshotCompression, SnapshotIdentityMode, SnapshotIntegrityMode,
+        SnapshotIoError, SnapshotLoadOptions, SnapshotLoadProfile, SnapshotSaveOptions,
+        SnapshotValidationMode,
     },
+    wal::WalError,
 };
 use crate::{
+    caveat::CaveatDefinition,
+    domain::Relationship,
     error::ZanzibarError,
     eval::EvaluationLimits,
-    model::{NamespaceConfig, Relation},
+    model::{DeleteRelationships, NamespaceConfig, Relation},
     relationship::{
         IndexedRelationshipStore, Precondition, RelationshipFilter, RelationshipMutation,
         RelationshipStoreView, SubjectFilter,
     },
     revision::{
-        ConsistencyError, ConsistencyToken, DatastoreId, PublishedSnapshot, Revision, SchemaHash,
-        default_retained_snapshots,
+        Consistency, ConsistencyError, ConsistencyToken, DatastoreId, PublishedSnapshot, Revision,
+        SchemaHash, default_retained_snapshots,
     },
     runtime::{EngineState, SharedEngineState},
-    schema::CompiledSchema,
+    schema::{CompiledSchema, MigrationStep, SchemaSource},
+    wal::{WalOperation, WalRecord, WriteAheadLog},
+    watch::{RelationshipUpdate, SchemaHashChange, WatchEvent},
 };
 
 pub(crate) struct WriterState {
@@ -122,6 +141,9 @@ pub(crate) struct WriterState {
     last_revision: Option<Revision>,
     evaluation_limits: EvaluationLimits,
     published_state: SharedEngineState,
+    change_log: VecDeque<WatchEvent>,
+    watchers: Vec<Sender<WatchEvent>>,
+    wal: Option<WriteAheadLog>,
 }
 
 impl fmt::Debug for WriterState {
@@ -138,6 +160,9 @@ impl fmt::Debug for WriterState {
             .field("last_revision", &self.last_revision)
             .field("evaluation_limits", &self.evaluation_limits)
             .field("published_state", &self.published_state)
+            .field("change_log", &self.change_log)
+            .field("watchers", &self.watchers.len())
+            .field("wal", &self.wal)
             .finish()
     }
 }
@@ -167,6 +192,9 @@ impl WriterState {
             last_revision: None,
             evaluation_limits: EvaluationLimits::default(),
             published_state,
+            change_log: VecDeque::new(),
+            watchers: Vec::new(),
+            wal: None,
         }
     }
 
@@ -193,6 +221,12 @@ impl WriterState {
         self
     }
 
+    #[must_use]
+    pub(crate) const fn with_datastore_id(mut self, datastore_id: DatastoreId) -> Self {
+        self.datastore_id = datastore_id;
+        self
+    }
+
     /// Builds a new service from canonical or hand-authored policy text.
     ///
     /// Relationship files accept one relationship per line. Blank lines and full-line `#` or `//`
@@ -223,28 +257,51 @@ impl WriterState {
         policy::apply_policy_text_to_service(self, policy)
     }
 
-    /// Parses a DSL string, adds the resulting configurations, and returns a consistency token.
+    /// Parses schema source in its declared syntax, adds the resulting configurations, and
+    /// returns a consistency token.
     ///
     /// # Errors
     ///
-    /// Returns [`ZanzibarError::ParseError`] when the DSL cannot be parsed.
-    pub fn add_dsl_with_token(&mut self, dsl: &str) -> Result<ConsistencyToken, ZanzibarError> {
-        schema::compile_legacy_dsl(dsl)?;
-        let configs = parser::parse_dsl(dsl)?;
-        self.apply_namespace_configs(configs)
+    /// Returns [`ZanzibarError::Diagnostics`] when the source cannot be compiled.
+    pub fn add_schema_with_token(
+        &mut self,
+        source: SchemaSource<'_>,
+    ) -> Result<ConsistencyToken, ZanzibarError> {
+        let added_schema = schema::compile_source(source)?;
+        let configs = parser::parse_source(source.text, source.syntax)?;
+        let mut caveats = self.current_caveats();
+        for caveat in added_schema.caveats() {
+            caveats.retain(|existing| existing.name() != caveat.name());
+            caveats.push(caveat.clone());
+        }
+        self.apply_namespace_configs_with_caveats(configs, caveats)
     }
 
     pub(crate) fn apply_namespace_configs(
         &mut self,
         configs: impl IntoIterator<Item = NamespaceConfig>,
+    ) -> Result<ConsistencyToken, ZanzibarError> {
+        let caveats = self.current_caveats();
+        self.apply_namespace_configs_with_caveats(configs, caveats)
+    }
+
+    fn apply_namespace_configs_with_caveats(
+        &mut self,
+        configs: impl IntoIterator<Item = NamespaceConfig>,
+        caveats: Vec<CaveatDefinition>,
     ) -> Result<ConsistencyToken, ZanzibarError> {
         let mut next_configs = self.configs.clone();
         for config in configs {
             next_configs.insert(config.name.clone(), config);
         }
-        let compiled_schema = schema::compile_legacy_configs(next_configs.values().cloned())?;
+        let compiled_schema = compile_configs_with_caveats(&next_configs, caveats)?;
         let next_relationships = self.relationship_store_for_schema(&compiled_schema)?;
-        self.publish_snapshot(next_configs, compiled_schema, next_relationships)
+        self.publish_snapshot(
+            next_configs,
+            compiled_schema,
+            next_relationships,
+            Vec::new(),
+        )
     }
 
     /// Replaces the complete schema DSL and returns a consistency token.
@@ -254,15 +311,77 @@ impl WriterState {
     /// Returns [`ZanzibarError`] when the DSL cannot be parsed or existing relationships do not
     /// validate against the replacement schema.
     pub fn replace_dsl_with_token(&mut self, dsl: &str) -> Result<ConsistencyToken, ZanzibarError> {
-        schema::compile_legacy_dsl(dsl)?;
-        let configs = parser::parse_dsl(dsl)?;
-        let next_configs = configs
-            .into_iter()
-            .map(|config| (config.name.clone(), config))
-            .collect::<HashMap<_, _>>();
-        let compiled_schema = schema::compile_legacy_configs(next_configs.values().cloned())?;
+        self.replace_schema_with_token(SchemaSource::legacy(dsl))
+    }
+
+    /// Replaces the complete schema with source in its declared syntax and returns a consistency
+    /// token.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ZanzibarError`] when the source cannot be parsed or existing relationships do not
+    /// validate against the replacement schema.
+    pub fn replace_schema_with_token(
+        &mut self,
+        source: SchemaSource<'_>,
+    ) -> Result<ConsistencyToken, ZanzibarError> {
+        let (next_configs, compiled_schema) = compile_replacement_schema(source)?;
         let next_relationships = self.relationship_store_for_schema(&compiled_schema)?;
-        self.publish_snapshot(next_configs, compiled_schema, next_relationships)
+        self.publish_snapshot(
+            next_configs,
+            compiled_schema,
+            next_relationships,
+            Vec::new(),
+        )
+    }
+
+    /// Replaces the complete schema and rewrites stored relationships through `steps` in one
+    /// revision.
+    ///
+    /// Every relationship is passed through the steps in order and the result is validated
+    /// against the new schema, so a rename or move never leaves a window where checks see the
+    /// old relation missing or the new one empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ZanzibarError`] when the source cannot be parsed or a migrated relationship does
+    /// not validate against the new schema; the current state is then unchanged.
+    pub fn migrate_schema_with_token(
+        &mut self,
+        source: SchemaSource<'_>,
+        steps: &[MigrationStep],
+    ) -> Result<ConsistencyToken, ZanzibarError> {
+        let (configs, schema) = compile_replacement_schema(source)?;
+        let mut relationships = IndexedRelationshipStore::default();
+        let mut updates = Vec::new();
+        for relationship in self.relationships.rows() {
+            let migrated = steps
+                .iter()
+                .try_fold(relationship.clone(), |current, step| step.apply(current));
+            let Some(migrated) = migrated else {
+                updates.push(RelationshipUpdate::Delete(relationship));
+                continue;
+            };
+            schema.validate_relationship(&migrated)?;
+            if migrated != relationship {
+                updates.push(RelationshipUpdate::Delete(relationship));
+                updates.push(RelationshipUpdate::Touch(migrated.clone()));
+            }
+            relationships.apply_mutations([RelationshipMutation::Touch(migrated)], [])?;
+        }
+        let operation = WalOperation::Migration {
+            schema: policy::canonical_schema_source(&configs, schema.caveats()),
+            steps: steps.to_vec(),
+        };
+        self.publish_snapshot_logged(
+            configs,
+            schema,
+            Arc::new(RelationshipStoreView::from_checkpoint(Arc::new(
+                relationships,
+            ))),
+            updates,
+            Some(operation),
+        )
     }
 
     /// Deletes one namespace definition and publishes a new revision.
@@ -281,9 +400,14 @@ impl WriterState {
         if next_configs.remove(namespace).is_none() {
             return Err(ZanzibarError::NamespaceNotFound(namespace.to_string()));
         }
-        let compiled_schema = schema::compile_legacy_configs(next_configs.values().cloned())?;
+        let compiled_schema = compile_configs_with_caveats(&next_configs, self.current_caveats())?;
         let next_relationships = self.relationship_store_for_schema(&compiled_schema)?;
-        self.publish_snapshot(next_configs, compiled_schema, next_relationships)
+        self.publish_snapshot(
+            next_configs,
+            compiled_schema,
+            next_relationships,
+            Vec::new(),
+        )
     }
 
     /// Deletes one relation definition and publishes a new revision.
@@ -314,9 +438,14 @@ impl WriterState {
                 namespace.to_string(),
             ));
         }
-        let compiled_schema = schema::compile_legacy_configs(next_configs.values().cloned())?;
+        let compiled_schema = compile_configs_with_caveats(&next_configs, self.current_caveats())?;
         let next_relationships = self.relationship_store_for_schema(&compiled_schema)?;
-        self.publish_snapshot(next_configs, compiled_schema, next_relationships)
+        self.publish_snapshot(
+            next_configs,
+            compiled_schema,
+            next_relationships,
+            Vec::new(),
+        )
     }
 
     /// Applies a validated batch of relationship mutations.
@@ -341,10 +470,82 @@ impl WriterState {
             validate_precondition_filter(&schema, precondition)?;
         }
 
+        let updates = mutations
+            .iter()
+            .map(RelationshipUpdate::from_mutation)
+            .collect();
         let next_relationships = self
             .relationships
             .apply_mutations(mutations, preconditions)?;
-        self.publish_snapshot(self.configs.clone(), schema, next_relationships)
+        self.publish_snapshot(self.configs.clone(), schema, next_relationships, updates)
+    }
+
+    /// Deletes live relationships matching `filter` in one new revision.
+    ///
+    /// Matches are resolved against the current store view, so the deletes and the preconditions
+    /// see the same state. At most `limit` relationships, capped at one mutation batch, are
+    /// deleted; `truncated` reports whether more matches remained.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ZanzibarError::SchemaRequired`] when no schema has been loaded, or a typed
+    /// validation/store error when the filter, a precondition, or the delete batch is invalid.
+    pub fn delete_relationships(
+        &mut self,
+        filter: &RelationshipFilter,
+        preconditions: impl IntoIterator<Item = Precondition>,
+        limit: NonZeroU32,
+    ) -> Result<DeleteRelationships, ZanzibarError> {
+        let schema = self.schema.as_ref().ok_or(ZanzibarError::SchemaRequired)?;
+        validate_relationship_filter(schema, filter)?;
+        let limit = usize::try_from(limit.get())
+            .unwrap_or(usize::MAX)
+            .min(relationship::MAX_MUTATIONS_PER_BATCH);
+        let mut deletes = self
+            .relationships
+            .query_compact_relationships(&filter.unbounded())
+            .take(limit.saturating_add(1))
+            .map(|relationship| {
+                relationship
+                    .to_relationship()
+                    .map(RelationshipMutation::Delete)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let truncated = deletes.len() > limit;
+        deletes.truncate(limit);
+        let deleted = deletes.len();
+        let token = self.apply_relationship_mutations(deletes, preconditions)?;
+        Ok(DeleteRelationships {
+            token,
+            deleted,
+            truncated,
+        })
+    }
+
+    /// Deletes relationships whose expiration has passed at `now` in one new revision.
+    ///
+    /// Expired relationships are already invisible to reads; the sweep reclaims their rows and
+    /// reports them to watchers as deletes. One sweep deletes at most one mutation batch, so a
+    /// large backlog is reclaimed over several sweeps. Returns `None` when nothing has expired.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ZanzibarError`] when the delete batch cannot be applied or published.
+    pub fn sweep_expired_relationships(
+        &mut self,
+        now: SystemTime,
+    ) -> Result<Option<ConsistencyToken>, ZanzibarError> {
+        let expired = self
+            .relationships
+            .expired_relationships(now)
+            .into_iter()
+            .take(relationship::MAX_MUTATIONS_PER_BATCH)
+            .map(RelationshipMutation::Delete)
+            .collect::<Vec<_>>();
+        if expired.is_empty() {
+            return Ok(None);
+        }
+        self.apply_relationship_mutations(expired, []).map(Some)
     }
 
     /// Saves the latest published snapshot to a versioned `.szsnap` artifact.
@@ -368,7 +569,7 @@ impl WriterState {
             .ok_or(SnapshotIoError::Format {
                 reason: "schema snapshot is required before saving",
             })?;
-        snapshot::save_snapshot_file(path.as_ref(), &snapshot, options)
+        snapshot::save_snapshot_file(path.as_ref(), &snapshot, self.datastore_id, options)
     }
 
     pub(crate) fn load_snapshot_with_publisher(
@@ -377,6 +578,31 @@ impl WriterState {
         published_state: SharedEngineState,
     ) -> Result<Self, SnapshotIoError> {
         let loaded = snapshot::load_snapshot_file(path.as_ref(), options)?;
+        Ok(Self::from_loaded_snapshot(
+            loaded,
+            options.identity,
+            published_state,
+        ))
+    }
+
+    pub(crate) fn load_snapshot_chain_with_publisher(
+        chain: &SnapshotChain,
+        options: SnapshotLoadOptions,
+        published_state: SharedEngineState,
+    ) -> Result<Self, SnapshotIoError> {
+        let loaded = delta::load_snapshot_chain(chain, options)?;
+        Ok(Self::from_loaded_snapshot(
+            loaded,
+            options.identity,
+            published_state,
+        ))
+    }
+
+    fn from_loaded_snapshot(
+        loaded: snapshot::LoadedSnapshot,
+        identity: SnapshotIdentityMode,
+        published_state: SharedEngineState,
+    ) -> Self {
         let snapshot = Arc::new(PublishedSnapshot::new(
             loaded.revision,
             loaded.schema_hash,
@@ -388,6 +614,11 @@ impl WriterState {
             snapshot::one_snapshot_retention(),
             published_state,
         );
+        if identity == SnapshotIdentityMode::Restore
+            && let Some(datastore_id) = loaded.datastore_id
+        {
+            service.datastore_id = datastore_id;
+        }
         service.configs = loaded.configs;
         service.schema = Some(loaded.schema);
         service.relationships = loaded.relationships;
@@ -395,7 +626,108 @@ impl WriterState {
         service.snapshot_history.push_back(snapshot);
         service.last_revision = Some(loaded.revision);
         service.publish_current_engine_state();
-        Ok(service)
+        service
+    }
+
+    /// Starts an independent writer whose first revision is `snapshot`.
+    ///
+    /// The fork shares the snapshot's relationship view, so its own writes only build deltas on
+    /// top of the parent's checkpoint. It gets a fresh datastore id and never writes ahead.
+    pub(crate) fn fork_with_publisher(
+        snapshot: Arc<PublishedSnapshot>,
+        evaluation_limits: EvaluationLimits,
+        published_state: SharedEngineState,
+    ) -> Self {
+        let mut service =
+            Self::new_with_publisher(published_state).with_evaluation_limits(evaluation_limits);
+        service.configs = snapshot.configs().clone();
+        service.schema = Some(snapshot.schema().clone());
+        service.relationships = snapshot.shared_relationships();
+        service.last_revision = Some(snapshot.revision());
+        service.current_snapshot.store(Some(Arc::clone(&snapshot)));
+        service.snapshot_history.push_back(snapshot);
+        service.publish_current_engine_state();
+        service
+    }
+
+    /// Replaces this writer's data with a separately loaded snapshot `candidate`.
+    ///
+    /// The candidate keeps this engine's retention and evaluation limits, takes over its
+    /// publisher and subscribers, and is published with one atomic store so readers holding the
+    /// previous engine state finish on it undisturbed.
+    pub(crate) fn install_reloaded_snapshot(
+        &mut self,
+        mut candidate: WriterState,
+    ) -> Result<ConsistencyToken, SnapshotIoError> {
+        if self.wal.is_some() {
+            return Err(SnapshotIoError::UnsupportedOption {
+                option: "reload_snapshot on a durable engine",
+            });
+        }
+        let (Some(snapshot), Some(revision)) = (
+            candidate.current_snapshot.load_full(),
+            candidate.last_revision,
+        ) else {
+            return Err(SnapshotIoError::Format {
+                reason: "reloaded snapshot has no schema",
+            });
+        };
+        if candidate.datastore_id == self.datastore_id
+            && let Some(current) = self.last_revision
+            && revision < current
+        {
+            return Err(SnapshotIoError::StaleRevision {
+                snapshot: revision,
+                current,
+            });
+        }
+
+        let previous_schema_hash = self
+            .current_snapshot
+            .load()
+            .as_ref()
+            .map(|previous| previous.schema_hash());
+        let updates = if self.watchers.is_empty() {
+            Vec::new()
+        } else {
+            self.relationships
+                .rows()
+                .into_iter()
+                .map(RelationshipUpdate::Delete)
+                .chain(
+                    candidate
+                        .relationships
+                        .rows()
+                        .into_iter()
+                        .map(RelationshipUpdate::Touch),
+                )
+                .collect()
+        };
+        let token = ConsistencyToken::new(revision, snapshot.schema_hash(), candidate.datastore_id);
+
+        candidate.retained_snapshots = self.retained_snapshots;
+        candidate.evaluation_limits = self.evaluation_limits;
+        candidate.watchers = std::mem::take(&mut self.watchers);
+        candidate.replace_publisher(Arc::clone(&self.published_state));
+        candidate.record_change(WatchEvent {
+            token: token.clone(),
+            updates,
+            schema_change: (previous_schema_hash != Some(snapshot.schema_hash())).then_some(
+                SchemaHashChange {
+                    previous: previous_schema_hash,
+                    current: snapshot.schema_hash(),
+                },
+            ),
+        });
+        *self = candidate;
+        Ok(token)
+    }
+
+    fn current_caveats(&self) -> Vec<CaveatDefinition> {
+        self.schema
+            .as_ref()
+            .map(|schema| schema.caveats().to_vec())
+            .unwrap_or_default()
     }
 
     fn relationship_store_for_schema(
@@ -427,9 +759,28 @@ impl WriterState {
         configs: HashMap<String, NamespaceConfig>,
         schema: CompiledSchema,
         relationships: Arc<RelationshipStoreView>,
+        updates: Vec<RelationshipUpdate>,
+    ) -> Result<ConsistencyToken, ZanzibarError> {
+        self.publish_snapshot_logged(configs, schema, relationships, updates, None)
+    }
+
+    /// Publishes a snapshot, logging `operation` instead of the inferred schema or relationship
+    /// record when the change cannot be replayed from either alone.
+    fn publish_snapshot_logged(
+        &mut self,
+        configs: HashMap<String, NamespaceConfig>,
+        schema: CompiledSchema,
+        relationships: Arc<RelationshipStoreView>,
+        updates: Vec<RelationshipUpdate>,
+        operation: Option<WalOperation>,
     ) -> Result<ConsistencyToken, ZanzibarError> {
         let revision = self.next_revision()?;
         let schema_hash = SchemaHash::for_schema(&schema);
+        let previous_schema_hash = self
+            .current_snapshot
+            .load()
+            .as_ref()
+            .map(|snapshot| snapshot.schema_hash());
         let snapshot = Arc::new(PublishedSnapshot::new(
             revision,
             schema_hash,
@@ -438,6 +789,19 @@ impl WriterState {
             Arc::clone(&relationships),
         ));
         let token = ConsistencyToken::new(revision, schema_hash, self.datastore_id);
+        if self.wal.is_some() {
+            let operation = operation.unwrap_or_else(|| {
+                if previous_schema_hash == Some(schema_hash) && !updates.is_empty() {
+                    WalOperation::Relationships(updates.clone())
+                } else {
+                    WalOperation::Schema(policy::canonical_schema_source(
+                        &configs,
+                        schema.caveats(),
+                    ))
+                }
+            });
+            self.append_write_ahead_log(&token, operation)?;
+        }
 
         self.configs = configs;
         self.schema = Some(schema);
@@ -449,9 +813,207 @@ impl WriterState {
         }
         self.last_revision = Some(revision);
         self.publish_current_engine_state();
+        self.record_change(WatchEvent {
+            token: token.clone(),
+            updates,
+            schema_change: (previous_schema_hash != Some(schema_hash)).then_some(
+                SchemaHashChange {
+                    previous: previous_schema_hash,
+                    current: schema_hash,
+                },
+            ),
+        });
         Ok(token)
     }
 
+    /// Opens a durable writer from `directory`, replaying logged revisions after its checkpoint.
+    pub(crate) fn open_durable(
+        directory: &Path,
+        retained_snapshots: NonZeroUsize,
+        evaluation_limits: EvaluationLimits,
+        published_state: SharedEngineState,
+    ) -> Result<Self, WalError> {
+        let (wal, records) = WriteAheadLog::open(directory)?;
+        let mut service = match WriteAheadLog::latest_snapshot(directory)? {
+            Some(path) => Self::load_snapshot_with_publisher(
+                path,
+                SnapshotLoadOptions::default(),
+                published_state,
+            )?,
+            None => Self::new_with_publisher(published_state),
+        };
+        service.retained_snapshots = retained_snapshots;
+        service.evaluation_limits = evaluation_limits;
+        service.datastore_id = wal.datastore_id();
+        for record in records {
+            if service
+                .last_revision
+                .is_some_and(|revision| record.revision <= revision)
+            {
+                continue;
+            }
+            let replayed = match &record.operation {
+                WalOperation::Relationships(updates) => service.apply_relationship_mutations(
+                    updates.iter().cloned().map(|update| match update {
+                        RelationshipUpdate::Touch(relationship) => {
+                            RelationshipMutation::Touch(relationship)
+                        }
+                        RelationshipUpdate::Delete(relationship) => {
+                            RelationshipMutation::Delete(relationship)
+                        }
+                    }),
+                    [],
+                ),
+                WalOperation::Schema(source) => service.replace_dsl_with_token(source),
+                WalOperation::Migration { schema, steps } => {
+                    service.migrate_schema_with_token(SchemaSource::legacy(schema), steps)
+                }
+                WalOperation::PolicyText(policy) => service.apply_policy_text(policy),
+            };
+            match replayed {
+                Ok(token) if record.matches(&token) => {}
+                Ok(_) => {
+                    return Err(WalError::Replay {
+                        revision: record.revision,
+                        source: ZanzibarError::StorageError(
+                            "replayed revision does not match the logged revision".to_string(),
+                        ),
+                    });
+                }
+                Err(source) => {
+                    return Err(WalError::Replay {
+                        revision: record.revision,
+                        source,
+                    });
+                }
+            }
+        }
+        service.change_log.clear();
+        service.wal = Some(wal);
+        service.publish_current_engine_state();
+        Ok(service)
+    }
+
+    /// Saves the latest revision as the durable checkpoint and clears the replayed log.
+    pub(crate) fn checkpoint(&mut self) -> Result<ConsistencyToken, WalError> {
+        let wal = self.wal.as_mut().ok_or(WalError::NotDurable)?;
+        let snapshot = self
+            .current_snapshot
+            .load_full()
+            .ok_or(SnapshotIoError::Format {
+                reason: "schema snapshot is required before saving",
+            })?;
+        let datastore_id = self.datastore_id;
+        wal.checkpoint(snapshot.revision(), |path| {
+            snapshot::save_snapshot_file(
+                path,
+                &snapshot,
+                datastore_id,
+                SnapshotSaveOptions::default(),
+            )
+        })?;
+        Ok(ConsistencyToken::new(
+            snapshot.revision(),
+            snapshot.schema_hash(),
+            self.datastore_id,
+        ))
+    }
+
+    /// Durably logs one revision before it is published.
+    pub(crate) fn append_write_ahead_log(
+        &mut self,
+        token: &ConsistencyToken,
+        operation: WalOperation,
+    ) -> Result<(), ZanzibarError> {
+        let Some(wal) = &mut self.wal else {
+            return Ok(());
+        };
+        wal.append(&WalRecord {
+            revision: token.revision(),
+            schema_hash: token.schema_hash(),
+            operation,
+        })
+        .map_err(|error| {
+            ZanzibarError::StorageError(format!("write-ahead log append failed: {error}"))
+        })
+    }
+
+    /// Registers a change subscriber and replays retained events newer than `from`.
+    pub(crate) fn subscribe(
+        &mut self,
+        from: Consistency,
+        subscriber: Sender<WatchEvent>,
+    ) -> Result<(), ZanzibarError> {
+        if let Consistency::AtLeastAsFresh(_) = &from {
+            self.published_state
+                .load_full()
+                .ok_or(ZanzibarError::SchemaRequired)?
+                .snapshot_for_consistency(from.clone())?;
+        }
+        if let Consistency::Exact(token) = from {
+            let state = self
+                .published_state
+                .load_full()
+                .ok_or(ZanzibarError::SchemaRequired)?;
+            state.snapshot_for_consistency(Consistency::Exact(token.clone()))?;
+            for event in self
+                .change_log
+                .iter()
+                .filter(|event| event.revision() > token.revision())
+            {
+                if subscriber.send(event.clone()).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        self.watchers.push(subscriber);
+        Ok(())
+    }
+
+    /// Moves change history and subscribers from the state this candidate replaces.
+    ///
+    /// The candidate's first revision is rewritten to report every relationship it removed, so
+    /// subscribers observe the replacement as ordinary deletes followed by imported touches.
+    pub(crate) fn inherit_change_stream(
+        &mut self,
+        previous: &mut WriterState,
+        removed: Vec<Relationship>,
+    ) {
+        let previous_schema_hash = previous
+            .current_snapshot
+            .load()
+            .as_ref()
+            .map(|snapshot| snapshot.schema_hash());
+        let mut events = std::mem::take(&mut self.change_log);
+        if let Some(first) = events.front_mut() {
+            let mut updates = removed
+                .into_iter()
+                .map(RelationshipUpdate::Delete)
+                .collect::<Vec<_>>();
+            updates.append(&mut first.updates);
+            first.updates = updates;
+            first.schema_change = (previous_schema_hash != Some(first.token.schema_hash()))
+                .then_some(SchemaHashChange {
+                    previous: previous_schema_hash,
+                    current: first.token.schema_hash(),
+                });
+        }
+        self.change_log = std::mem::take(&mut previous.change_log);
+        self.watchers = std::mem::take(&mut previous.watchers);
+        for event in events {
+            self.record_change(event);
+        }
+    }
+
+    fn record_change(&mut self, event: WatchEvent) {
+        self.watchers
+            .retain(|watcher| watcher.send(event.clone()).is_ok());
+        self.change_log.push_back(event);
+        while self.change_log.len() > self.retained_snapshots.get() {
+            self.change_log.pop_front();
+        }
+    }
+
     pub(crate) fn replace_publisher(&mut self, published_state: SharedEngineState) {
         self.published_state = published_state;
         self.publish_current_engine_state();
@@ -480,6 +1042,27 @@ impl WriterState {
     }
 }
 
+/// Compiles a complete replacement schema the same way the writer publishes it.
+pub(crate) fn compile_replacement_schema(
+    source: SchemaSource<'_>,
+) -> Result<(HashMap<String, NamespaceConfig>, CompiledSchema), ZanzibarError> {
+    let replacement_schema = schema::compile_source(source)?;
+    let configs = parser::parse_source(source.text, source.syntax)?
+        .into_iter()
+        .map(|config| (config.name.clone(), config))
+        .collect::<HashMap<_, _>>();
+    let compiled_schema =
+        compile_configs_with_caveats(&configs, replacement_schema.caveats().to_vec())?;
+    Ok((configs, compiled_schema))
+}
+
+fn compile_configs_with_caveats(
+    configs: &HashMap<String, NamespaceConfig>,
+    caveats: Vec<CaveatDefinition>,
+) -> Result<CompiledSchema, ZanzibarError> {
+    Ok(schema::compile_legacy_configs(configs.values().cloned())?.with_caveats(caveats)?)
+}
+
 fn validate_precondition_filter(
     schema: &CompiledSchema,
     precondition: &Precondition,
@@ -491,15 +1074,15 @@ fn validate_precondition_filter(
     }
 }
 
-fn validate_relationship_filter(
+pub(crate) fn validate_relationship_filter(
     schema: &CompiledSchema,
     filter: &RelationshipFilter,
 ) -> Result<(), ZanzibarError> {
-    schema.resolver().namespace(filter.resource_type())?;
-    if let Some(relation) = filter.optional_relation() {
-        schema
-            .resolver()
-            .relation(filter.resource_type(), relation)?;
+    if let Some(resource_type) = filter.optional_resource_type() {
+        schema.resolver().namespace(resource_type)?;
+        if let Some(relation) = filter.optional_relation() {
+            schema.resolver().relation(resource_type, relation)?;
+        }
     }
     if let Some(subject) = filter.optional_subject() {
         validate_subject_filter(schema, subject)?;
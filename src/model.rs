@@ -3,10 +3,11 @@
 use std::hash::Hash;
 
 use crate::{
+    caveat::CaveatContext,
     domain::{
         DomainError, ObjectRef, ObjectType, RelationName, Relationship, SubjectRef, SubjectType,
     },
-    revision::Consistency,
+    revision::{Consistency, ConsistencyToken, LookupCursor},
 };
 
 /// Represents a namespaced digital object.
@@ -118,6 +119,8 @@ pub enum User {
     /// A set of users, identified by an object-relation pair.
     /// e.g., `group:eng#member`
     Userset(Object, Relation),
+    /// Every user, written `user:*` in relationships.
+    Wildcard,
 }
 
 impl User {
@@ -155,11 +158,13 @@ impl<'de> serde::Deserialize<'de> for User {
         enum UserSerde {
             UserId(String),
             Userset(Object, Relation),
+            Wildcard,
         }
 
         let user = match UserSerde::deserialize(deserializer)? {
             UserSerde::UserId(id) => Self::UserId(id),
             UserSerde::Userset(object, relation) => Self::Userset(object, relation),
+            UserSerde::Wildcard => Self::Wildcard,
         };
         user.validate().map_err(serde::de::Error::custom)?;
         Ok(user)
@@ -247,6 +252,12 @@ pub struct CheckRequest {
     pub user: User,
     /// Consistency selector for the read.
     pub consistency: Consistency,
+    /// Caveat parameters supplied with the request.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "CaveatContext::is_empty")
+    )]
+    pub context: CaveatContext,
 }
 
 impl CheckRequest {
@@ -258,9 +269,17 @@ impl CheckRequest {
             relation,
             user,
             consistency,
+            context: CaveatContext::new(),
         }
     }
 
+    /// Returns this request with caveat parameters used to evaluate conditional relationships.
+    #[must_use]
+    pub fn with_context(mut self, context: CaveatContext) -> Self {
+        self.context = context;
+        self
+    }
+
     /// Validates domain fields in this check request.
     ///
     /// # Errors
@@ -279,10 +298,41 @@ impl CheckRequest {
     derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "camelCase", deny_unknown_fields)
 )]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CheckResponse {
     /// Whether the subject has the requested relation or permission.
     pub allowed: bool,
+    /// Three-valued check outcome, distinguishing caveat-dependent results.
+    pub permissionship: Permissionship,
+    /// Caveat parameters that must be supplied to resolve a conditional result, sorted by name.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub missing_context: Vec<String>,
+    /// Token of the snapshot this response was evaluated at; `None` from snapshot-level
+    /// evaluators that run outside an engine.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub token: Option<ConsistencyToken>,
+}
+
+/// Three-valued outcome of a check.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permissionship {
+    /// The subject has the relation or permission.
+    HasPermission,
+    /// The subject does not have the relation or permission.
+    NoPermission,
+    /// The result depends on caveat parameters the request did not supply.
+    ConditionalPermission,
 }
 
 /// Request for expanding an object relation or permission at a specified consistency level.
@@ -333,6 +383,13 @@ impl ExpandRequest {
 pub struct ExpandResponse {
     /// Expanded userset tree.
     pub expanded: ExpandedUserset,
+    /// Token of the snapshot this response was evaluated at; `None` from snapshot-level
+    /// evaluators that run outside an engine.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub token: Option<ConsistencyToken>,
 }
 
 /// Request for resources of one type that a subject can access through a permission.
@@ -349,6 +406,12 @@ pub struct LookupResourcesRequest {
     pub permission: Relation,
     /// Resource namespace/type to return.
     pub resource_type: String,
+    /// Continuation cursor returned by a previous page of this lookup.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cursor: Option<LookupCursor>,
 }
 
 impl LookupResourcesRequest {
@@ -359,9 +422,17 @@ impl LookupResourcesRequest {
             subject,
             permission,
             resource_type: resource_type.into(),
+            cursor: None,
         }
     }
 
+    /// Returns this request resuming after the page that produced `cursor`.
+    #[must_use]
+    pub fn with_cursor(mut self, cursor: LookupCursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
     /// Validates domain fields in this lookup request.
     ///
     /// # Errors
@@ -386,6 +457,8 @@ impl<'de> serde::Deserialize<'de> for LookupResourcesRequest {
             subject: User,
             permission: Relation,
             resource_type: String,
+            #[serde(default)]
+            cursor: Option<LookupCursor>,
         }
 
         let value = LookupResourcesRequestSerde::deserialize(deserializer)?;
@@ -393,6 +466,7 @@ impl<'de> serde::Deserialize<'de> for LookupResourcesRequest {
             subject: value.subject,
             permission: value.permission,
             resource_type: value.resource_type,
+            cursor: value.cursor,
         };
         request.validate().map_err(serde::de::Error::custom)?;
         Ok(request)
@@ -409,6 +483,19 @@ impl<'de> serde::Deserialize<'de> for LookupResourcesRequest {
 pub struct LookupResources {
     /// De-duplicated resources that passed the shared check evaluator.
     pub resources: Vec<Object>,
+    /// Cursor for the next page, or `None` when this page is the last.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cursor: Option<LookupCursor>,
+    /// Token of the snapshot this response was evaluated at; `None` from snapshot-level
+    /// evaluators that run outside an engine.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub token: Option<ConsistencyToken>,
 }
 
 /// Request for subjects of one type that can access a resource through a permission.
@@ -425,6 +512,12 @@ pub struct LookupSubjectsRequest {
     pub permission: Relation,
     /// Subject namespace/type to return.
     pub subject_type: String,
+    /// Continuation cursor returned by a previous page of this lookup.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cursor: Option<LookupCursor>,
 }
 
 impl LookupSubjectsRequest {
@@ -435,9 +528,17 @@ impl LookupSubjectsRequest {
             resource,
             permission,
             subject_type: subject_type.into(),
+            cursor: None,
         }
     }
 
+    /// Returns this request resuming after the page that produced `cursor`.
+    #[must_use]
+    pub fn with_cursor(mut self, cursor: LookupCursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
     /// Validates domain fields in this lookup request.
     ///
     /// # Errors
@@ -462,6 +563,8 @@ impl<'de> serde::Deserialize<'de> for LookupSubjectsRequest {
             resource: Object,
             permission: Relation,
             subject_type: String,
+            #[serde(default)]
+            cursor: Option<LookupCursor>,
         }
 
         let value = LookupSubjectsRequestSerde::deserialize(deserializer)?;
@@ -469,6 +572,7 @@ impl<'de> serde::Deserialize<'de> for LookupSubjectsRequest {
             resource: value.resource,
             permission: value.permission,
             subject_type: value.subject_type,
+            cursor: value.cursor,
         };
         request.validate().map_err(serde::de::Error::custom)?;
         Ok(request)
@@ -485,6 +589,19 @@ impl<'de> serde::Deserialize<'de> for LookupSubjectsRequest {
 pub struct LookupSubjects {
     /// De-duplicated subjects that passed the shared check evaluator.
     pub subjects: Vec<User>,
+    /// Cursor for the next page, or `None` when this page is the last.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cursor: Option<LookupCursor>,
+    /// Token of the snapshot this response was evaluated at; `None` from snapshot-level
+    /// evaluators that run outside an engine.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub token: Option<ConsistencyToken>,
 }
 
 /// Request for all permissions a subject has on one resource.
@@ -535,6 +652,13 @@ impl LookupPermissionsRequest {
 pub struct LookupPermissions {
     /// Sorted relations or permissions that evaluated to allowed.
     pub permissions: Vec<Relation>,
+    /// Token of the snapshot this response was evaluated at; `None` from snapshot-level
+    /// evaluators that run outside an engine.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub token: Option<ConsistencyToken>,
 }
 
 /// Request for subjects grouped by every permission they have on one resource.
@@ -551,6 +675,12 @@ pub struct LookupObjectPermissionsRequest {
     pub subject_type: String,
     /// Consistency selector for the read.
     pub consistency: Consistency,
+    /// Continuation cursor returned by a previous page of this lookup.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cursor: Option<LookupCursor>,
 }
 
 impl LookupObjectPermissionsRequest {
@@ -565,9 +695,17 @@ impl LookupObjectPermissionsRequest {
             resource,
             subject_type: subject_type.into(),
             consistency,
+            cursor: None,
         }
     }
 
+    /// Returns this request resuming after the page that produced `cursor`.
+    #[must_use]
+    pub fn with_cursor(mut self, cursor: LookupCursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
     /// Validates domain fields in this lookup request.
     ///
     /// # Errors
@@ -591,6 +729,8 @@ impl<'de> serde::Deserialize<'de> for LookupObjectPermissionsRequest {
             resource: Object,
             subject_type: String,
             consistency: Consistency,
+            #[serde(default)]
+            cursor: Option<LookupCursor>,
         }
 
         let value = LookupObjectPermissionsRequestSerde::deserialize(deserializer)?;
@@ -598,6 +738,7 @@ impl<'de> serde::Deserialize<'de> for LookupObjectPermissionsRequest {
             resource: value.resource,
             subject_type: value.subject_type,
             consistency: value.consistency,
+            cursor: value.cursor,
         };
         request.validate().map_err(serde::de::Error::custom)?;
         Ok(request)
@@ -614,6 +755,55 @@ impl<'de> serde::Deserialize<'de> for LookupObjectPermissionsRequest {
 pub struct LookupObjectPermissions {
     /// Permission groups with non-empty subjects.
     pub permissions: Vec<PermissionSubjects>,
+    /// Cursor for the next page, or `None` when this page is the last.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cursor: Option<LookupCursor>,
+    /// Token of the snapshot this response was evaluated at; `None` from snapshot-level
+    /// evaluators that run outside an engine.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub token: Option<ConsistencyToken>,
+}
+
+/// Stored relationships returned by a read request.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadRelationships {
+    /// Live relationships matching the filter, in store order.
+    pub relationships: Vec<Relationship>,
+    /// Cursor for the next page, or `None` when this page is the last.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cursor: Option<LookupCursor>,
+    /// Token of the snapshot the relationships were read at.
+    pub token: ConsistencyToken,
+}
+
+/// Outcome of deleting relationships by filter.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeleteRelationships {
+    /// Revision that published the deletes.
+    pub token: ConsistencyToken,
+    /// Number of relationships removed.
+    pub deleted: usize,
+    /// Whether more matching relationships remained after the limit was reached.
+    pub truncated: bool,
 }
 
 /// Subjects that have one permission on a resource.
@@ -656,6 +846,19 @@ pub struct RelationConfig {
     pub name: Relation,
     /// Optional userset rewrite for computed permissions.
     pub userset_rewrite: Option<UsersetExpression>,
+    /// Declared direct subjects such as `user` or `group#member`, or `None` when the relation
+    /// accepts any subject.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub allowed_subject_types: Option<Vec<String>>,
+    /// Subject types that may be granted this relation through a wildcard such as `user:*`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub wildcard_subject_types: Vec<String>,
 }
 
 /// Represents a tree of userset computations, forming the core of the policy language.
@@ -706,6 +909,8 @@ pub enum UsersetExpression {
 pub enum ExpandedUserset {
     /// A specific user who has the permission.
     User(String),
+    /// Every user, granted through a `user:*` relationship.
+    Wildcard,
     /// A reference to another userset that contributes to the permission.
     Userset(Object, Relation),
     /// The union of multiple expanded usersets.
@@ -9,8 +9,12 @@ use pest::{
 };
 
 use crate::{
+    caveat::{CaveatComparison, CaveatDefinition, CaveatExpression, CaveatParameterType},
+    diagnostic::SourceSpan,
+    domain::CaveatName,
     error::ZanzibarError,
     model::{NamespaceConfig, Relation, RelationConfig, UsersetExpression},
+    schema::{SchemaError, SchemaSyntax},
 };
 
 mod generated {
@@ -28,29 +32,234 @@ mod generated {
 #[derive(Debug, Clone)]
 pub(crate) struct LegacyNamespaceAst {
     pub(crate) name: String,
+    /// Span of the namespace name, or empty for schemas built from configs.
+    pub(crate) span: SourceSpan,
     pub(crate) relations: Vec<LegacyRelationAst>,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct LegacyRelationAst {
     pub(crate) name: String,
+    /// Span of the relation name, or empty for schemas built from configs.
+    pub(crate) span: SourceSpan,
     pub(crate) rewrite: Option<UsersetExpression>,
+    pub(crate) allowed_subject_types: Option<Vec<String>>,
+    pub(crate) wildcard_subject_types: Vec<String>,
 }
 
-pub(crate) fn parse_dsl_ast(dsl: &str) -> Result<Vec<LegacyNamespaceAst>, ZanzibarError> {
-    let pairs = ZanzibarParser::parse(Rule::file, dsl)
-        .map_err(|e| ZanzibarError::ParseError(e.to_string()))?;
+#[derive(Debug, Default)]
+pub(crate) struct LegacySchemaAst {
+    pub(crate) namespaces: Vec<LegacyNamespaceAst>,
+    pub(crate) caveats: Vec<CaveatDefinition>,
+    /// Span of each entry in `caveats`.
+    pub(crate) caveat_spans: Vec<SourceSpan>,
+    /// Definitions that matched the grammar but could not be built, with their spans.
+    pub(crate) errors: Vec<(SourceSpan, ZanzibarError)>,
+}
+
+/// Source text that does not match the grammar.
+#[derive(Debug, Clone)]
+pub(crate) struct SyntaxError {
+    /// Position or range the parser stopped at.
+    pub(crate) span: SourceSpan,
+    /// Short description of what the parser expected.
+    pub(crate) message: String,
+    /// Full `pest` rendering, including the offending line.
+    pub(crate) rendered: String,
+}
+
+/// Parses schema source written in `syntax` into the shared namespace AST.
+pub(crate) fn parse_source_ast(
+    text: &str,
+    syntax: SchemaSyntax,
+) -> Result<LegacySchemaAst, ZanzibarError> {
+    let mut schema = parse_source_collecting(text, syntax)
+        .map_err(|error| ZanzibarError::ParseError(error.rendered))?;
+    if schema.errors.is_empty() {
+        Ok(schema)
+    } else {
+        Err(schema.errors.swap_remove(0).1)
+    }
+}
 
-    let mut namespaces = Vec::new();
+/// Parses schema source into the shared AST, keeping every definition-level error in
+/// [`LegacySchemaAst::errors`] instead of stopping at the first one.
+pub(crate) fn parse_source_collecting(
+    text: &str,
+    syntax: SchemaSyntax,
+) -> Result<LegacySchemaAst, SyntaxError> {
+    let rule = match syntax {
+        SchemaSyntax::Legacy => Rule::file,
+        SchemaSyntax::SpiceDb => Rule::spicedb_file,
+    };
+    let pairs = ZanzibarParser::parse(rule, text).map_err(|error| SyntaxError {
+        span: match error.location {
+            pest::error::InputLocation::Pos(position) => SourceSpan::new(position, position),
+            pest::error::InputLocation::Span((start, end)) => SourceSpan::new(start, end),
+        },
+        message: error.variant.message().into_owned(),
+        rendered: error.to_string(),
+    })?;
+
+    let mut schema = LegacySchemaAst::default();
     for pair in pairs {
         for inner_pair in pair.into_inner() {
-            if inner_pair.as_rule() == Rule::namespace_def {
-                namespaces.push(parse_namespace_ast(inner_pair)?);
+            let span = SourceSpan::from(inner_pair.as_span());
+            let parsed = match inner_pair.as_rule() {
+                Rule::namespace_def => parse_namespace_ast(inner_pair)
+                    .map(|namespace| schema.namespaces.push(namespace)),
+                Rule::definition_def => parse_definition_ast(inner_pair)
+                    .map(|namespace| schema.namespaces.push(namespace)),
+                Rule::caveat_def => parse_caveat_def(inner_pair).map(|caveat| {
+                    schema.caveats.push(caveat);
+                    schema.caveat_spans.push(span);
+                }),
+                _ => Ok(()),
+            };
+            if let Err(error) = parsed {
+                schema.errors.push((span, error));
             }
         }
     }
 
-    Ok(namespaces)
+    Ok(schema)
+}
+
+/// Parses schema source written in `syntax` into `NamespaceConfig`s.
+pub(crate) fn parse_source(
+    text: &str,
+    syntax: SchemaSyntax,
+) -> Result<Vec<NamespaceConfig>, ZanzibarError> {
+    parse_source_ast(text, syntax)?
+        .namespaces
+        .into_iter()
+        .map(TryFrom::try_from)
+        .collect()
+}
+
+fn parse_definition_ast(pair: Pair<Rule>) -> Result<LegacyNamespaceAst, ZanzibarError> {
+    let mut inner = pair.into_inner();
+
+    let _definition_keyword = next_pair(&mut inner, "definition keyword")?;
+    let name_pair = next_pair(&mut inner, "definition name")?;
+    let name = name_pair.as_str().to_string();
+    let span = SourceSpan::from(name_pair.as_span());
+    let mut relations = Vec::new();
+
+    for member_pair in inner {
+        let is_permission = member_pair.as_rule() == Rule::spicedb_permission;
+        let mut member = member_pair.into_inner();
+        let _keyword = next_pair(&mut member, "relation or permission keyword")?;
+        let member_name_pair = next_pair(&mut member, "relation or permission name")?;
+        let member_name = member_name_pair.as_str().to_string();
+        let member_span = SourceSpan::from(member_name_pair.as_span());
+        if is_permission {
+            let expression = next_pair(&mut member, "permission expression")?;
+            relations.push(LegacyRelationAst {
+                name: member_name,
+                span: member_span,
+                rewrite: Some(parse_permission_expression(expression)?),
+                // Permissions are computed, so no relationship may be written to them.
+                allowed_subject_types: Some(Vec::new()),
+                wildcard_subject_types: Vec::new(),
+            });
+            continue;
+        }
+
+        let mut allowed_subject_types = Vec::new();
+        let mut wildcard_subject_types = Vec::new();
+        for subject_pair in member {
+            let mut subject_inner = subject_pair.into_inner();
+            let subject = next_pair(&mut subject_inner, "allowed subject")?;
+            if subject_inner.next().is_some() {
+                return Err(SchemaError::UnsupportedSubjectCaveat {
+                    namespace: name,
+                    relation: member_name,
+                    subject: subject.as_str().to_string(),
+                    caveat: subject_inner
+                        .filter(|caveat| caveat.as_rule() == Rule::IDENTIFIER)
+                        .map(|caveat| caveat.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" and "),
+                }
+                .into());
+            }
+            match subject.as_str().strip_suffix(":*") {
+                Some(subject_type) => wildcard_subject_types.push(subject_type.to_string()),
+                None => allowed_subject_types.push(subject.as_str().to_string()),
+            }
+        }
+        relations.push(LegacyRelationAst {
+            name: member_name,
+            span: member_span,
+            rewrite: None,
+            allowed_subject_types: Some(allowed_subject_types),
+            wildcard_subject_types,
+        });
+    }
+
+    Ok(LegacyNamespaceAst {
+        name,
+        span,
+        relations,
+    })
+}
+
+/// Maps `-`, `&`, `+`, and `->` onto exclusion, intersection, union, and tuple-to-userset.
+///
+/// Operators bind from loosest to tightest in that order, and chains associate to the left.
+fn parse_permission_expression(pair: Pair<Rule>) -> Result<UsersetExpression, ZanzibarError> {
+    match pair.as_rule() {
+        Rule::permission_exclusion => {
+            let mut inner = pair.into_inner();
+            let mut expression =
+                parse_permission_expression(next_pair(&mut inner, "permission operand")?)?;
+            for exclude in inner {
+                expression = UsersetExpression::Exclusion {
+                    base: Box::new(expression),
+                    exclude: Box::new(parse_permission_expression(exclude)?),
+                };
+            }
+            Ok(expression)
+        }
+        Rule::permission_intersection | Rule::permission_union => {
+            let is_union = pair.as_rule() == Rule::permission_union;
+            let mut expressions = pair
+                .into_inner()
+                .map(parse_permission_expression)
+                .collect::<Result<Vec<_>, _>>()?;
+            if expressions.len() == 1 {
+                return expressions.pop().ok_or_else(|| {
+                    ZanzibarError::ParseError("Expected permission operand".into())
+                });
+            }
+            if is_union {
+                Ok(UsersetExpression::Union(expressions))
+            } else {
+                Ok(UsersetExpression::Intersection(expressions))
+            }
+        }
+        Rule::permission_arrow => {
+            let mut inner = pair.into_inner();
+            let tupleset = next_pair(&mut inner, "arrow tupleset relation")?;
+            let computed = next_pair(&mut inner, "arrow computed relation")?;
+            Ok(UsersetExpression::TupleToUserset {
+                tupleset_relation: Relation(tupleset.as_str().to_string()),
+                computed_userset_relation: Relation(computed.as_str().to_string()),
+            })
+        }
+        Rule::IDENTIFIER => Ok(UsersetExpression::ComputedUserset {
+            relation: Relation(pair.as_str().to_string()),
+        }),
+        _ => Err(ZanzibarError::ParseError(format!(
+            "Unexpected rule: {:?}",
+            pair.as_rule()
+        ))),
+    }
+}
+
+pub(crate) fn parse_dsl_ast(dsl: &str) -> Result<Vec<LegacyNamespaceAst>, ZanzibarError> {
+    Ok(parse_source_ast(dsl, SchemaSyntax::Legacy)?.namespaces)
 }
 
 /// Parses a DSL string into a vector of `NamespaceConfig`s.
@@ -72,13 +281,18 @@ fn parse_namespace_ast(pair: Pair<Rule>) -> Result<LegacyNamespaceAst, ZanzibarE
     let _namespace_keyword = next_pair(&mut inner, "namespace keyword")?;
     let name_pair = next_pair(&mut inner, "namespace identifier")?;
     let name = name_pair.as_str().to_string();
+    let span = SourceSpan::from(name_pair.as_span());
     let mut relations = Vec::new();
 
     for relation_pair in inner {
         relations.push(parse_relation_ast(relation_pair)?);
     }
 
-    Ok(LegacyNamespaceAst { name, relations })
+    Ok(LegacyNamespaceAst {
+        name,
+        span,
+        relations,
+    })
 }
 
 fn parse_relation_ast(pair: Pair<Rule>) -> Result<LegacyRelationAst, ZanzibarError> {
@@ -88,19 +302,46 @@ fn parse_relation_ast(pair: Pair<Rule>) -> Result<LegacyRelationAst, ZanzibarErr
     let _relation_keyword = next_pair(&mut inner, "relation keyword")?;
     let name_pair = next_pair(&mut inner, "relation name")?;
     let name = name_pair.as_str().to_string();
-
-    let rewrite = match inner.next() {
-        Some(rewrite_pair) => {
-            if rewrite_pair.as_rule() == Rule::rewrite_rule {
-                Some(parse_rewrite(rewrite_pair)?)
-            } else {
-                None
+    let span = SourceSpan::from(name_pair.as_span());
+
+    let mut rewrite = None;
+    let mut allowed_subject_types = None;
+    let mut wildcard_subject_types = Vec::new();
+    for body_pair in inner {
+        match body_pair.as_rule() {
+            Rule::allowed_subjects => {
+                let mut declared = Vec::new();
+                for subject_pair in body_pair.into_inner() {
+                    let subject = subject_pair.as_str();
+                    match subject.strip_suffix(":*") {
+                        Some(subject_type) => {
+                            wildcard_subject_types.push(subject_type.to_string());
+                        }
+                        None => declared.push(subject.to_string()),
+                    }
+                }
+                allowed_subject_types = Some(declared);
             }
+            Rule::no_subjects => allowed_subject_types = Some(Vec::new()),
+            Rule::wildcard_rule => {
+                let mut wildcard_inner = body_pair.into_inner();
+                let _keyword = next_pair(&mut wildcard_inner, "wildcard keyword")?;
+                let subject_type_pair = next_pair(&mut wildcard_inner, "wildcard subject type")?;
+                wildcard_subject_types
+                    .push(subject_type_pair.as_str().trim_matches('\"').to_string());
+            }
+            Rule::rewrite_rule => rewrite = Some(parse_rewrite(body_pair)?),
+            _ => {}
         }
-        None => None,
-    };
+    }
 
-    Ok(LegacyRelationAst { name, rewrite })
+    Ok(LegacyRelationAst {
+        name,
+        span,
+        rewrite,
+        allowed_subject_types,
+        wildcard_subject_types,
+    })
 }
 
 impl TryFrom<LegacyNamespaceAst> for NamespaceConfig {
@@ -115,6 +356,8 @@ impl TryFrom<LegacyNamespaceAst> for NamespaceConfig {
                 RelationConfig {
                     name,
                     userset_rewrite: relation.rewrite,
+                    allowed_subject_types: relation.allowed_subject_types,
+                    wildcard_subject_types: relation.wildcard_subject_types,
                 },
             );
         }
@@ -214,6 +457,142 @@ fn parse_expression(pair: Pair<Rule>) -> Result<UsersetExpression, ZanzibarError
     }
 }
 
+fn parse_caveat_def(pair: Pair<Rule>) -> Result<CaveatDefinition, ZanzibarError> {
+    let mut inner = pair.into_inner();
+
+    // Skip the CAVEAT keyword.
+    let _caveat_keyword = next_pair(&mut inner, "caveat keyword")?;
+    let name = CaveatName::new(next_pair(&mut inner, "caveat name")?.as_str())?;
+    let mut parameters = Vec::new();
+    let mut expression = None;
+
+    for inner_pair in inner {
+        match inner_pair.as_rule() {
+            Rule::caveat_parameter => {
+                let mut parameter = inner_pair.into_inner();
+                let parameter_name = next_pair(&mut parameter, "caveat parameter name")?;
+                let parameter_type = next_pair(&mut parameter, "caveat parameter type")?
+                    .as_str()
+                    .parse::<CaveatParameterType>()
+                    .map_err(SchemaError::from)?;
+                parameters.push((parameter_name.as_str().to_string(), parameter_type));
+            }
+            _ => expression = Some(parse_caveat_expression(inner_pair)?),
+        }
+    }
+
+    let expression = expression
+        .ok_or_else(|| ZanzibarError::ParseError("Expected caveat expression".to_string()))?;
+    CaveatDefinition::new(name, parameters, expression)
+        .map_err(|error| SchemaError::from(error).into())
+}
+
+fn parse_caveat_expression(pair: Pair<Rule>) -> Result<CaveatExpression, ZanzibarError> {
+    match pair.as_rule() {
+        Rule::caveat_expr | Rule::caveat_and => {
+            let mut inner = pair.into_inner();
+            let mut expression = parse_caveat_expression(next_pair(&mut inner, "caveat operand")?)?;
+            while let Some(operator) = inner.next() {
+                let right = parse_caveat_expression(next_pair(&mut inner, "caveat operand")?)?;
+                expression = if operator.as_rule() == Rule::caveat_or_op {
+                    CaveatExpression::Or(Box::new(expression), Box::new(right))
+                } else {
+                    CaveatExpression::And(Box::new(expression), Box::new(right))
+                };
+            }
+            Ok(expression)
+        }
+        Rule::caveat_comparison => {
+            let mut inner = pair.into_inner();
+            let left = parse_caveat_expression(next_pair(&mut inner, "caveat operand")?)?;
+            let Some(operator) = inner.next() else {
+                return Ok(left);
+            };
+            let right = parse_caveat_expression(next_pair(&mut inner, "caveat operand")?)?;
+            Ok(CaveatExpression::Compare {
+                operator: parse_caveat_comparison(operator.as_str())?,
+                left: Box::new(left),
+                right: Box::new(right),
+            })
+        }
+        Rule::caveat_unary => {
+            let mut negations = 0_usize;
+            let mut expression = None;
+            for inner_pair in pair.into_inner() {
+                if inner_pair.as_rule() == Rule::caveat_not {
+                    negations += 1;
+                } else {
+                    expression = Some(parse_caveat_expression(inner_pair)?);
+                }
+            }
+            let mut expression = expression
+                .ok_or_else(|| ZanzibarError::ParseError("Expected caveat operand".to_string()))?;
+            for _ in 0..negations {
+                expression = CaveatExpression::Not(Box::new(expression));
+            }
+            Ok(expression)
+        }
+        Rule::caveat_primary => {
+            let mut inner = pair.into_inner();
+            parse_caveat_expression(next_pair(&mut inner, "caveat primary expression")?)
+        }
+        Rule::CAVEAT_TRUE => Ok(CaveatExpression::Bool(true)),
+        Rule::CAVEAT_FALSE => Ok(CaveatExpression::Bool(false)),
+        Rule::CAVEAT_INT => pair
+            .as_str()
+            .parse::<i64>()
+            .map(CaveatExpression::Int)
+            .map_err(|error| ZanzibarError::ParseError(format!("Invalid caveat integer: {error}"))),
+        Rule::STRING_LITERAL => Ok(CaveatExpression::String(
+            pair.as_str().trim_matches('\"').to_string(),
+        )),
+        Rule::IDENTIFIER => Ok(CaveatExpression::Parameter(pair.as_str().to_string())),
+        Rule::caveat_list => pair
+            .into_inner()
+            .map(parse_caveat_expression)
+            .collect::<Result<Vec<_>, _>>()
+            .map(CaveatExpression::List),
+        Rule::caveat_call => {
+            let mut inner = pair.into_inner();
+            let function = next_pair(&mut inner, "caveat function name")?.as_str();
+            let arguments = inner
+                .map(parse_caveat_expression)
+                .collect::<Result<Vec<_>, _>>()?;
+            match (function, <[CaveatExpression; 2]>::try_from(arguments)) {
+                ("in_cidr", Ok([address, network])) => Ok(CaveatExpression::InCidr {
+                    address: Box::new(address),
+                    network: Box::new(network),
+                }),
+                ("in_cidr", Err(_)) => Err(ZanzibarError::ParseError(
+                    "in_cidr expects exactly 2 arguments".to_string(),
+                )),
+                _ => Err(ZanzibarError::ParseError(format!(
+                    "Unknown caveat function: {function}"
+                ))),
+            }
+        }
+        _ => Err(ZanzibarError::ParseError(format!(
+            "Unexpected rule: {:?}",
+            pair.as_rule()
+        ))),
+    }
+}
+
+fn parse_caveat_comparison(operator: &str) -> Result<CaveatComparison, ZanzibarError> {
+    match operator {
+        "==" => Ok(CaveatComparison::Eq),
+        "!=" => Ok(CaveatComparison::Ne),
+        "<" => Ok(CaveatComparison::Lt),
+        "<=" => Ok(CaveatComparison::Le),
+        ">" => Ok(CaveatComparison::Gt),
+        ">=" => Ok(CaveatComparison::Ge),
+        "in" => Ok(CaveatComparison::In),
+        _ => Err(ZanzibarError::ParseError(format!(
+            "Unknown caveat operator: {operator}"
+        ))),
+    }
+}
+
 fn next_pair<'input>(
     pairs: &mut Pairs<'input, Rule>,
     expected: &str,
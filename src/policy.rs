@@ -9,9 +9,12 @@ use std::{
 use thiserror::Error;
 
 use crate::{
+    caveat::CaveatDefinition,
+    diagnostic::{Diagnostic, DiagnosticError, Diagnostics, SourceSpan},
     domain::Relationship,
     error::ZanzibarError,
     model::{NamespaceConfig, RelationConfig, UsersetExpression},
+    schema::CompiledSchema,
     snapshot::{SnapshotIoError, SnapshotSaveOptions},
 };
 
@@ -54,6 +57,43 @@ impl PolicyText {
             }],
         }
     }
+
+    /// Reads policy files laid out by
+    /// [`ZanzibarEngine::export_policy_files`](crate::ZanzibarEngine::export_policy_files):
+    /// `schema.zed` plus every `relationships/*.zedtuples` file, in path order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PolicyIoError::Io`] when the schema file or relationship directory cannot be
+    /// read, or a file is not UTF-8.
+    pub fn read_directory(directory: impl AsRef<Path>) -> Result<Self, PolicyIoError> {
+        let directory = directory.as_ref();
+        let schema = fs::read_to_string(directory.join(SCHEMA_FILE_NAME))?;
+        let relationship_directory = directory.join(RELATIONSHIP_DIRECTORY_NAME);
+        let mut relationship_files = Vec::new();
+        if relationship_directory.is_dir() {
+            for entry in fs::read_dir(&relationship_directory)? {
+                let path = entry?.path();
+                if path.extension().and_then(|extension| extension.to_str())
+                    != Some(RELATIONSHIP_FILE_EXTENSION)
+                {
+                    continue;
+                }
+                let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                relationship_files.push(PolicyTextFile {
+                    path: format!("{RELATIONSHIP_DIRECTORY_NAME}/{file_name}"),
+                    contents: fs::read_to_string(&path)?,
+                });
+            }
+        }
+        relationship_files.sort_by(|left, right| left.path.cmp(&right.path));
+        Ok(Self {
+            schema,
+            relationship_files,
+        })
+    }
 }
 
 /// One reviewable policy text file.
@@ -130,7 +170,10 @@ impl From<SnapshotIoError> for PolicyIoError {
     }
 }
 
-pub(crate) fn canonical_schema_source(configs: &HashMap<String, NamespaceConfig>) -> String {
+pub(crate) fn canonical_schema_source(
+    configs: &HashMap<String, NamespaceConfig>,
+    caveats: &[CaveatDefinition],
+) -> String {
     let mut output = String::new();
     let mut namespaces = configs.values().collect::<Vec<_>>();
     namespaces.sort_by(|left, right| left.name.cmp(&right.name));
@@ -145,15 +188,22 @@ pub(crate) fn canonical_schema_source(configs: &HashMap<String, NamespaceConfig>
         }
         output.push_str("}\n\n");
     }
+    let mut caveats = caveats.iter().collect::<Vec<_>>();
+    caveats.sort_by(|left, right| left.name().cmp(right.name()));
+    for caveat in caveats {
+        output.push_str(&caveat.to_string());
+        output.push_str("\n\n");
+    }
     output
 }
 
 pub(crate) fn export_policy_text(
     configs: &HashMap<String, NamespaceConfig>,
+    caveats: &[CaveatDefinition],
     relationships: Vec<Relationship>,
 ) -> PolicyText {
     PolicyText {
-        schema: canonical_schema_source(configs),
+        schema: canonical_schema_source(configs, caveats),
         relationship_files: relationship_files(relationships),
     }
 }
@@ -193,9 +243,16 @@ pub(crate) fn apply_policy_text_to_service(
         .with_evaluation_limits(service.evaluation_limits);
     candidate.datastore_id = service.datastore_id;
     candidate.last_revision = service.last_revision;
-    let mut token = candidate.replace_dsl_with_token(&policy.schema)?;
+    let mut token = candidate.replace_schema_with_token(
+        crate::schema::SchemaSource::legacy(&policy.schema).with_name(SCHEMA_FILE_NAME),
+    )?;
+    let schema = candidate
+        .schema
+        .as_ref()
+        .ok_or(ZanzibarError::SchemaRequired)?;
+    let relationships = parse_relationships(policy, schema)?;
     let mut mutations = Vec::with_capacity(policy_import_batch_size());
-    for relationship in parse_relationships(policy)? {
+    for relationship in relationships {
         mutations.push(crate::relationship::RelationshipMutation::Create(
             relationship,
         ));
@@ -206,7 +263,11 @@ pub(crate) fn apply_policy_text_to_service(
     if !mutations.is_empty() {
         token = candidate.apply_relationship_mutations(mutations, [])?;
     }
+    service.append_write_ahead_log(&token, crate::wal::WalOperation::PolicyText(policy.clone()))?;
+    candidate.wal = service.wal.take();
     candidate.replace_publisher(published_state);
+    let removed = service.relationships.rows();
+    candidate.inherit_change_stream(service, removed);
     *service = candidate;
     Ok(token)
 }
@@ -248,32 +309,94 @@ fn relationship_files(relationships: Vec<Relationship>) -> Vec<PolicyTextFile> {
         .collect()
 }
 
-fn parse_relationships(policy: &PolicyText) -> Result<Vec<Relationship>, ZanzibarError> {
+/// Parses every relationship file and validates it against `schema`, reporting each bad tuple with
+/// its file path and line.
+fn parse_relationships(
+    policy: &PolicyText,
+    schema: &CompiledSchema,
+) -> Result<Vec<Relationship>, ZanzibarError> {
     let mut relationships = Vec::new();
+    let mut diagnostics = Diagnostics::default();
     for file in &policy.relationship_files {
-        for line in file.contents.lines() {
+        let mut offset = 0;
+        for line in file.contents.split_inclusive('\n') {
+            let line_start = offset;
+            offset += line.len();
             let trimmed = line.trim();
             if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("//") {
                 continue;
             }
-            relationships.push(trimmed.parse()?);
+            let parsed = trimmed
+                .parse::<Relationship>()
+                .map_err(DiagnosticError::from)
+                .and_then(|relationship| {
+                    schema
+                        .validate_relationship(&relationship)
+                        .map(|()| relationship)
+                        .map_err(DiagnosticError::from)
+                });
+            match parsed {
+                Ok(relationship) => relationships.push(relationship),
+                Err(error) => {
+                    let start = line_start + (line.len() - line.trim_start().len());
+                    diagnostics.push(Diagnostic::new(
+                        Some(&file.path),
+                        &file.contents,
+                        SourceSpan::new(start, start + trimmed.len()),
+                        error,
+                    ));
+                }
+            }
         }
     }
+    diagnostics.into_result()?;
     Ok(relationships)
 }
 
 fn push_relation(output: &mut String, relation: &RelationConfig) {
+    let mut wildcard_subject_types = relation.wildcard_subject_types.iter().collect::<Vec<_>>();
+    wildcard_subject_types.sort();
+    wildcard_subject_types.dedup();
+    output.push_str("    relation ");
+    output.push_str(&relation.name.0);
+    if let Some(allowed_subject_types) = &relation.allowed_subject_types {
+        let mut subjects = allowed_subject_types
+            .iter()
+            .cloned()
+            .chain(
+                wildcard_subject_types
+                    .drain(..)
+                    .map(|subject_type| format!("{subject_type}:*")),
+            )
+            .collect::<Vec<_>>();
+        subjects.sort();
+        subjects.dedup();
+        output.push(':');
+        if !subjects.is_empty() {
+            output.push(' ');
+            output.push_str(&subjects.join(" | "));
+        }
+        if relation.userset_rewrite.is_none() {
+            output.push('\n');
+            return;
+        }
+    }
+    if relation.userset_rewrite.is_none() && wildcard_subject_types.is_empty() {
+        output.push_str(" {}\n");
+        return;
+    }
+    output.push_str(" {\n");
+    for subject_type in wildcard_subject_types {
+        output.push_str("        wildcard(subject_type: \"");
+        output.push_str(subject_type);
+        output.push_str("\")\n");
+    }
     if let Some(expression) = &relation.userset_rewrite {
-        output.push_str("    relation ");
-        output.push_str(&relation.name.0);
-        output.push_str(" {\n        rewrite ");
+        output.push_str("        rewrite ");
         push_expression(output, expression);
-        output.push_str("\n    }\n");
-    } else {
-        output.push_str("    relation ");
-        output.push_str(&relation.name.0);
-        output.push_str(" {}\n");
+        output.push('\n');
     }
+    output.push_str("    }\n");
 }
 
 fn push_expression(output: &mut String, expression: &UsersetExpression) {
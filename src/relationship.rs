@@ -12,18 +12,20 @@ use std::{
     ops::Range,
     str,
     sync::Arc,
-    time::Instant,
+    time::{Instant, SystemTime},
 };
 
 use thiserror::Error;
 
 use crate::{
+    caveat::RelationshipCaveat,
     domain::{
-        DomainError, ObjectId, ObjectRef, ObjectType, RelationName, Relationship, SubjectId,
-        SubjectRef, SubjectType,
+        CaveatName, DomainError, ObjectId, ObjectRef, ObjectType, RelationName, Relationship,
+        RelationshipExpiration, SubjectId, SubjectRef, SubjectType, WILDCARD_SUBJECT_ID,
     },
     error::ZanzibarError,
     model::{Object, Relation, User},
+    schema::{CompiledSchema, SchemaError},
     snapshot::{
         BinaryCursor, IndexProfile, SectionKind, SnapshotEncodingLayout, SnapshotFormatVersion,
         SnapshotIoError, SnapshotLoadPhaseTimings, SnapshotLoadProfile, SnapshotReader,
@@ -33,7 +35,7 @@ use crate::{
 };
 
 const DEFAULT_QUERY_LIMIT: usize = 1_000;
-const MAX_MUTATIONS_PER_BATCH: usize = 10_000;
+pub(crate) const MAX_MUTATIONS_PER_BATCH: usize = 10_000;
 const MAX_PRECONDITIONS_PER_BATCH: usize = 100;
 const COMPACT_DEAD_ROWS: usize = 100_000;
 const STORE_VIEW_MAX_DELTA_MUTATIONS: usize = 100_000;
@@ -465,6 +467,19 @@ impl SubjectFilter {
     pub fn optional_relation(&self) -> Option<&RelationName> {
         self.optional_relation.as_ref()
     }
+
+    /// Returns the wildcard filter that also grants this exact direct subject, if any.
+    pub(crate) fn wildcard(&self) -> Option<Self> {
+        let subject_id = self.optional_subject_id.as_ref()?;
+        if subject_id.is_wildcard() || self.optional_relation.is_some() {
+            return None;
+        }
+        Some(Self::exact(
+            self.subject_type.clone(),
+            SubjectId::wildcard(),
+            None,
+        ))
+    }
 }
 
 impl TryFrom<&User> for SubjectFilter {
@@ -482,6 +497,11 @@ impl TryFrom<&User> for SubjectFilter {
                 SubjectId::try_from(object.id.as_str())?,
                 Some(RelationName::try_from(relation.0.as_str())?),
             )),
+            User::Wildcard => Ok(Self::exact(
+                SubjectType::try_from("user")?,
+                SubjectId::wildcard(),
+                None,
+            )),
         }
     }
 }
@@ -494,7 +514,7 @@ impl TryFrom<&User> for SubjectFilter {
 )]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RelationshipFilter {
-    resource_type: ObjectType,
+    resource_type: Option<ObjectType>,
     optional_resource_id: Option<ObjectId>,
     optional_relation: Option<RelationName>,
     optional_subject: Option<SubjectFilter>,
@@ -512,7 +532,7 @@ impl RelationshipFilter {
         limit: QueryLimit,
     ) -> Self {
         Self {
-            resource_type,
+            resource_type: Some(resource_type),
             optional_resource_id,
             optional_relation,
             optional_subject,
@@ -520,6 +540,25 @@ impl RelationshipFilter {
         }
     }
 
+    /// Creates a relationship filter that matches resources of every object type.
+    ///
+    /// With a subject filter the query walks the subject index; without one it scans every
+    /// stored relationship.
+    #[must_use]
+    pub fn any_resource_type(
+        optional_relation: Option<RelationName>,
+        optional_subject: Option<SubjectFilter>,
+        limit: QueryLimit,
+    ) -> Self {
+        Self {
+            resource_type: None,
+            optional_resource_id: None,
+            optional_relation,
+            optional_subject,
+            limit,
+        }
+    }
+
     /// Creates a filter for an exact resource, relation, and subject.
     #[must_use]
     pub fn for_exact_subject(
@@ -536,10 +575,10 @@ impl RelationshipFilter {
         )
     }
 
-    /// Returns the resource object type.
+    /// Returns the optional resource object type.
     #[must_use]
-    pub fn resource_type(&self) -> &ObjectType {
-        &self.resource_type
+    pub fn optional_resource_type(&self) -> Option<&ObjectType> {
+        self.resource_type.as_ref()
     }
 
     /// Returns the optional resource id.
@@ -559,6 +598,15 @@ impl RelationshipFilter {
     pub fn optional_subject(&self) -> Option<&SubjectFilter> {
         self.optional_subject.as_ref()
     }
+
+    /// Returns this filter without a result cap, for callers that page or batch matches
+    /// themselves.
+    pub(crate) fn unbounded(&self) -> Self {
+        Self {
+            limit: QueryLimit::new(NonZeroUsize::MAX),
+            ..self.clone()
+        }
+    }
 }
 
 /// Relationship mutation.
@@ -670,6 +718,8 @@ pub struct IndexedRelationshipStore {
     live_rows: LiveRows,
     dead_row_count: usize,
     uniqueness: UniquenessState,
+    caveats: HashMap<RowId, RelationshipCaveat>,
+    expirations: HashMap<RowId, RelationshipExpiration>,
     by_resource: PostingIndex<ResourceIndexKey>,
     by_resource_object: PostingIndex<ResourceObjectIndexKey>,
     by_resource_type_relation: PostingIndex<ResourceTypeRelationIndexKey>,
@@ -688,6 +738,8 @@ impl Default for IndexedRelationshipStore {
             live_rows: LiveRows::default(),
             dead_row_count: 0,
             uniqueness: UniquenessState::Ready(RelationshipIdentityIndex::default()),
+            caveats: HashMap::new(),
+            expirations: HashMap::new(),
             by_resource: PostingIndex::default(),
             by_resource_object: PostingIndex::default(),
             by_resource_type_relation: PostingIndex::default(),
@@ -708,6 +760,8 @@ impl Clone for IndexedRelationshipStore {
             live_rows: self.live_rows.clone(),
             dead_row_count: self.dead_row_count,
             uniqueness: self.uniqueness.clone(),
+            caveats: self.caveats.clone(),
+            expirations: self.expirations.clone(),
             by_resource: self.by_resource.clone(),
             by_resource_object: self.by_resource_object.clone(),
             by_resource_type_relation: self.by_resource_type_relation.clone(),
@@ -728,6 +782,8 @@ impl Clone for IndexedRelationshipStore {
 pub struct RelationshipStoreView {
     checkpoint: Arc<IndexedRelationshipStore>,
     delta: Option<StoreDelta>,
+    /// Instant that hides expired rows instead of the wall clock, for stable paging.
+    expiration_cutoff: Option<SystemTime>,
 }
 
 impl Default for RelationshipStoreView {
@@ -743,6 +799,15 @@ impl RelationshipStoreView {
         Self {
             checkpoint,
             delta: None,
+            expiration_cutoff: None,
+        }
+    }
+
+    /// Returns this view with expired rows hidden as of `cutoff` rather than the current time.
+    pub(crate) fn with_expiration_cutoff(&self, cutoff: SystemTime) -> Self {
+        Self {
+            expiration_cutoff: Some(cutoff),
+            ..self.clone()
         }
     }
 
@@ -769,8 +834,34 @@ impl RelationshipStoreView {
                 actual: mutations.len(),
             });
         }
+        self.apply_unbounded_mutations(&mutations).map(Arc::new)
+    }
+
+    /// Layers a snapshot delta's removed and added rows onto this view.
+    ///
+    /// Removed rows are deleted and added rows touched, so a row whose caveat or expiration
+    /// changed since the base is shadowed rather than duplicated. A removed row that is not live
+    /// fails with [`StoreError::RelationshipNotFound`]. The write batch limit does not apply; an
+    /// oversized overlay is folded into a new checkpoint as for ordinary writes.
+    pub(crate) fn apply_snapshot_delta(
+        &self,
+        removed: Vec<Relationship>,
+        added: Vec<Relationship>,
+    ) -> Result<Arc<Self>, StoreError> {
+        let mutations = removed
+            .into_iter()
+            .map(RelationshipMutation::Delete)
+            .chain(added.into_iter().map(RelationshipMutation::Touch))
+            .collect::<Vec<_>>();
+        self.apply_unbounded_mutations(&mutations).map(Arc::new)
+    }
+
+    fn apply_unbounded_mutations(
+        &self,
+        mutations: &[RelationshipMutation],
+    ) -> Result<Self, StoreError> {
         let mut seen = HashSet::with_capacity(mutations.len());
-        for mutation in &mutations {
+        for mutation in mutations {
             let relationship = mutation.relationship();
             if !seen.insert(relationship.clone()) {
                 return Err(StoreError::DuplicateMutation {
@@ -779,7 +870,7 @@ impl RelationshipStoreView {
             }
         }
         if mutations.is_empty() {
-            return Ok(Arc::new(self.clone()));
+            return Ok(self.clone());
         }
 
         let mut base = self.clone();
@@ -811,6 +902,7 @@ impl RelationshipStoreView {
                 component: "store view delta mutations",
             })?;
         let candidate = Self {
+            expiration_cutoff: None,
             checkpoint: Arc::clone(&base.checkpoint),
             delta: Some(StoreDelta {
                 inserted: Arc::new(inserted),
@@ -820,9 +912,9 @@ impl RelationshipStoreView {
             }),
         };
         if candidate.should_checkpoint() {
-            return Ok(Arc::new(candidate.checkpointed()?));
+            return candidate.checkpointed();
         }
-        Ok(Arc::new(candidate))
+        Ok(candidate)
     }
 
     /// Returns true when at least one resource-side relationship matches.
@@ -847,6 +939,13 @@ impl RelationshipStoreView {
         relationships
     }
 
+    /// Returns the checkpoint's interned identifiers in symbol id order.
+    ///
+    /// For a view loaded from a snapshot artifact this is the artifact's symbol table order.
+    pub(crate) fn checkpoint_symbols(&self) -> Result<Vec<&str>, StoreError> {
+        self.checkpoint.symbols()
+    }
+
     pub(crate) fn encode_snapshot_sections(
         &self,
         writer: &mut SnapshotSectionWriter,
@@ -862,16 +961,21 @@ impl RelationshipStoreView {
         filter: &RelationshipFilter,
     ) -> StoreViewCompactIter<'_> {
         record_store_view_query_call();
-        let inserted = self
-            .delta
-            .as_ref()
-            .map(|delta| delta.inserted.query_compact_relationships(filter));
+        let inserted = self.delta.as_ref().map(|delta| {
+            delta
+                .inserted
+                .query_compact_relationships(filter)
+                .with_expiration_cutoff(self.expiration_cutoff)
+        });
         if inserted.is_some() {
             record_delta_segment_inspected();
         }
         StoreViewCompactIter {
             inserted,
-            checkpoint: self.checkpoint.query_compact_relationships(filter),
+            checkpoint: self
+                .checkpoint
+                .query_compact_relationships(filter)
+                .with_expiration_cutoff(self.expiration_cutoff),
             deleted: self.delta.as_ref().map(|delta| delta.deleted_rows.as_ref()),
             phase: StoreViewIterPhase::Inserted,
         }
@@ -882,16 +986,21 @@ impl RelationshipStoreView {
         filter: &SubjectFilter,
     ) -> StoreViewCompactIter<'_> {
         record_store_view_query_call();
-        let inserted = self
-            .delta
-            .as_ref()
-            .map(|delta| delta.inserted.reverse_query_compact_relationships(filter));
+        let inserted = self.delta.as_ref().map(|delta| {
+            delta
+                .inserted
+                .reverse_query_compact_relationships(filter)
+                .with_expiration_cutoff(self.expiration_cutoff)
+        });
         if inserted.is_some() {
             record_delta_segment_inspected();
         }
         StoreViewCompactIter {
             inserted,
-            checkpoint: self.checkpoint.reverse_query_compact_relationships(filter),
+            checkpoint: self
+                .checkpoint
+                .reverse_query_compact_relationships(filter)
+                .with_expiration_cutoff(self.expiration_cutoff),
             deleted: self.delta.as_ref().map(|delta| delta.deleted_rows.as_ref()),
             phase: StoreViewIterPhase::Inserted,
         }
@@ -911,33 +1020,70 @@ impl RelationshipStoreView {
         limit: QueryLimit,
     ) -> StoreViewCompactIter<'_> {
         record_store_view_query_call();
-        let inserted = self
-            .delta
-            .as_ref()
-            .map(|delta| delta.inserted.resource_relation(resource, relation, limit));
+        let inserted = self.delta.as_ref().map(|delta| {
+            delta
+                .inserted
+                .resource_relation(resource, relation, limit)
+                .with_expiration_cutoff(self.expiration_cutoff)
+        });
         if inserted.is_some() {
             record_delta_segment_inspected();
         }
         StoreViewCompactIter {
             inserted,
-            checkpoint: self.checkpoint.resource_relation(resource, relation, limit),
+            checkpoint: self
+                .checkpoint
+                .resource_relation(resource, relation, limit)
+                .with_expiration_cutoff(self.expiration_cutoff),
             deleted: self.delta.as_ref().map(|delta| delta.deleted_rows.as_ref()),
             phase: StoreViewIterPhase::Inserted,
         }
     }
 
-    pub(crate) fn any_resource_relation_subject(
-        &self,
-        resource: &ObjectRef,
-        relation: &RelationName,
-        subject: &SubjectFilter,
-    ) -> bool {
-        self.resource_relation_subject(resource, relation, subject)
-            .next()
-            .is_some()
+    /// Validates every stored caveat reference against the schema caveat definitions.
+    pub(crate) fn validate_caveats(&self, schema: &CompiledSchema) -> Result<(), SchemaError> {
+        let inserted = self
+            .delta
+            .as_ref()
+            .map(|delta| delta.inserted.caveats.values());
+        for caveat in self
+            .checkpoint
+            .caveats
+            .values()
+            .chain(inserted.into_iter().flatten())
+        {
+            schema
+                .caveat(caveat.name())
+                .ok_or_else(|| SchemaError::UnknownCaveat {
+                    caveat: caveat.name().to_string(),
+                })?
+                .validate_context(caveat.context())?;
+        }
+        Ok(())
     }
 
-    fn resource_relation_subject(
+    /// Returns true when any relationship in this view may carry a caveat.
+    pub(crate) fn has_caveats(&self) -> bool {
+        !self.checkpoint.caveats.is_empty()
+            || self
+                .delta
+                .as_ref()
+                .is_some_and(|delta| !delta.inserted.caveats.is_empty())
+    }
+
+    /// Returns live relationships whose expiration has passed at `now`, in row order.
+    pub(crate) fn expired_relationships(&self, now: SystemTime) -> Vec<Relationship> {
+        let Some(delta) = &self.delta else {
+            return self.checkpoint.expired_relationships(now, None);
+        };
+        let mut expired = self
+            .checkpoint
+            .expired_relationships(now, Some(delta.deleted_rows.as_ref()));
+        expired.extend(delta.inserted.expired_relationships(now, None));
+        expired
+    }
+
+    pub(crate) fn resource_relation_subject(
         &self,
         resource: &ObjectRef,
         relation: &RelationName,
@@ -948,6 +1094,7 @@ impl RelationshipStoreView {
             delta
                 .inserted
                 .resource_relation_subject(resource, relation, subject)
+                .with_expiration_cutoff(self.expiration_cutoff)
         });
         if inserted.is_some() {
             record_delta_segment_inspected();
@@ -956,7 +1103,8 @@ impl RelationshipStoreView {
             inserted,
             checkpoint: self
                 .checkpoint
-                .resource_relation_subject(resource, relation, subject),
+                .resource_relation_subject(resource, relation, subject)
+                .with_expiration_cutoff(self.expiration_cutoff),
             deleted: self.delta.as_ref().map(|delta| delta.deleted_rows.as_ref()),
             phase: StoreViewIterPhase::Inserted,
         }
@@ -1082,15 +1230,24 @@ impl RelationshipStoreView {
         relationship: Relationship,
     ) -> Result<(), StoreError> {
         let location = self.relationship_location(inserted, deleted, &relationship);
+        let now = SystemTime::now();
         match location {
+            RelationshipLocation::Inserted if inserted.has_expired(&relationship, now) => {
+                inserted.touch(&relationship)
+            }
+            RelationshipLocation::CheckpointLive
+                if self.checkpoint.has_expired(&relationship, now) =>
+            {
+                deleted.insert(relationship.clone());
+                inserted.insert(&relationship)
+            }
             RelationshipLocation::Inserted | RelationshipLocation::CheckpointLive => {
                 Err(StoreError::RelationshipAlreadyExists {
                     relationship: Box::new(relationship),
                 })
             }
             RelationshipLocation::CheckpointDeleted => {
-                deleted.remove(&relationship);
-                Ok(())
+                self.restore_checkpoint_relationship(inserted, deleted, &relationship)
             }
             RelationshipLocation::Absent => inserted.insert(&relationship),
         }
@@ -1103,15 +1260,37 @@ impl RelationshipStoreView {
         relationship: &Relationship,
     ) -> Result<(), StoreError> {
         match self.relationship_location(inserted, deleted, relationship) {
-            RelationshipLocation::Inserted | RelationshipLocation::CheckpointLive => Ok(()),
+            RelationshipLocation::Inserted => inserted.touch(relationship),
+            RelationshipLocation::CheckpointLive => {
+                if self.checkpoint.has_same_payload(relationship) {
+                    return Ok(());
+                }
+                deleted.insert(relationship.clone());
+                inserted.insert(relationship)
+            }
             RelationshipLocation::CheckpointDeleted => {
-                deleted.remove(relationship);
-                Ok(())
+                self.restore_checkpoint_relationship(inserted, deleted, relationship)
             }
             RelationshipLocation::Absent => inserted.insert(relationship),
         }
     }
 
+    /// Revives a tombstoned checkpoint row, or shadows it with a delta row when the caveat or
+    /// expiration changed, since checkpoint rows are immutable.
+    fn restore_checkpoint_relationship(
+        &self,
+        inserted: &mut IndexedRelationshipStore,
+        deleted: &mut HashSet<Relationship>,
+        relationship: &Relationship,
+    ) -> Result<(), StoreError> {
+        if self.checkpoint.has_same_payload(relationship) {
+            deleted.remove(relationship);
+            Ok(())
+        } else {
+            inserted.insert(relationship)
+        }
+    }
+
     fn delete_delta_relationship(
         &self,
         inserted: &mut IndexedRelationshipStore,
@@ -1309,11 +1488,7 @@ impl IndexedRelationshipStore {
         for mutation in mutations {
             match mutation {
                 RelationshipMutation::Create(relationship) => candidate.create(relationship)?,
-                RelationshipMutation::Touch(relationship) => {
-                    if !candidate.contains_relationship(&relationship) {
-                        candidate.insert(&relationship)?;
-                    }
-                }
+                RelationshipMutation::Touch(relationship) => candidate.touch(&relationship)?,
                 RelationshipMutation::Delete(relationship) => candidate.delete(&relationship)?,
             }
         }
@@ -1347,10 +1522,7 @@ impl IndexedRelationshipStore {
             additional_bytes,
         )?;
         for mutation in mutations {
-            let relationship = mutation.relationship();
-            if !self.contains_relationship(relationship) {
-                self.insert(relationship)?;
-            }
+            self.touch(mutation.relationship())?;
         }
         Ok(())
     }
@@ -1527,6 +1699,9 @@ impl IndexedRelationshipStore {
             })?,
         )?;
 
+        self.encode_snapshot_caveats(writer)?;
+        self.encode_snapshot_expirations(writer)?;
+
         let indexes = EncodedSnapshotIndexes::from_rows(&disk_rows, index_profile)?;
         writer.add_section(
             SectionKind::IndexDirectory,
@@ -1589,6 +1764,8 @@ impl IndexedRelationshipStore {
             },
             phase_start,
         );
+        let decoded_caveats = decode_snapshot_caveats(reader, decoded_rows.rows.len())?;
+        let decoded_expirations = decode_snapshot_expirations(reader, decoded_rows.rows.len())?;
         let phase_start = Instant::now();
         let decoded_indexes =
             DecodedSnapshotIndexes::decode(reader, &decoded_rows.rows, profile, validation)?;
@@ -1606,6 +1783,8 @@ impl IndexedRelationshipStore {
             live_rows: decoded_rows.live_rows,
             dead_row_count: 0,
             uniqueness: decoded_rows.uniqueness,
+            caveats: decoded_caveats,
+            expirations: decoded_expirations,
             by_resource: decoded_indexes.resource,
             by_resource_object: decoded_indexes.resource_object,
             by_resource_type_relation: decoded_indexes.resource_type_relation,
@@ -1616,6 +1795,67 @@ impl IndexedRelationshipStore {
         })
     }
 
+    fn encode_snapshot_caveats(
+        &self,
+        writer: &mut SnapshotSectionWriter,
+    ) -> Result<(), SnapshotIoError> {
+        if self.caveats.is_empty() {
+            return Ok(());
+        }
+        let mut bytes = Vec::new();
+        let mut entry_count = 0_u64;
+        for (index, row) in self
+            .rows
+            .iter()
+            .filter(|row| self.live_rows.contains(row.row_id))
+            .enumerate()
+        {
+            let Some(caveat) = self.caveats.get(&row.row_id) else {
+                continue;
+            };
+            let name = caveat.name().as_str().as_bytes();
+            let context = caveat.context().to_string();
+            bytes.extend_from_slice(&checked_u32_from_usize(index)?.to_le_bytes());
+            bytes.extend_from_slice(&checked_u32_from_usize(name.len())?.to_le_bytes());
+            bytes.extend_from_slice(name);
+            bytes.extend_from_slice(&checked_u32_from_usize(context.len())?.to_le_bytes());
+            bytes.extend_from_slice(context.as_bytes());
+            entry_count += 1;
+        }
+        writer.add_section(SectionKind::RelationshipCaveats, bytes, entry_count)
+    }
+
+    fn encode_snapshot_expirations(
+        &self,
+        writer: &mut SnapshotSectionWriter,
+    ) -> Result<(), SnapshotIoError> {
+        if self.expirations.is_empty() {
+            return Ok(());
+        }
+        let mut bytes = Vec::new();
+        let mut entry_count = 0_u64;
+        for (index, row) in self
+            .rows
+            .iter()
+            .filter(|row| self.live_rows.contains(row.row_id))
+            .enumerate()
+        {
+            let Some(expiration) = self.expirations.get(&row.row_id) else {
+                continue;
+            };
+            bytes.extend_from_slice(&checked_u32_from_usize(index)?.to_le_bytes());
+            bytes.extend_from_slice(&expiration.unix_seconds().to_le_bytes());
+            entry_count += 1;
+        }
+        writer.add_section(SectionKind::RelationshipExpirations, bytes, entry_count)
+    }
+
+    fn symbols(&self) -> Result<Vec<&str>, StoreError> {
+        (0..self.interner.entries.len())
+            .map(|index| self.interner.resolve(SymbolId::from_index(index)?))
+            .collect()
+    }
+
     fn live_disk_rows(&self) -> Vec<DiskRelationshipRow> {
         let mut rows = Vec::with_capacity(self.rows.len().saturating_sub(self.dead_row_count));
         for row in self
@@ -1639,6 +1879,7 @@ impl IndexedRelationshipStore {
         CompactRelationshipIter {
             store: self,
             all_rows_live: self.live_rows.is_all_live(),
+            expired_at: self.expiration_cutoff(),
             candidates,
             matcher: matcher.map(CompactRelationshipMatcher::Resource),
         }
@@ -1655,6 +1896,7 @@ impl IndexedRelationshipStore {
         CompactRelationshipIter {
             store: self,
             all_rows_live: self.live_rows.is_all_live(),
+            expired_at: self.expiration_cutoff(),
             candidates,
             matcher: matcher.map(CompactRelationshipMatcher::Subject),
         }
@@ -1680,6 +1922,7 @@ impl IndexedRelationshipStore {
         CompactRelationshipIter {
             store: self,
             all_rows_live: self.live_rows.is_all_live(),
+            expired_at: self.expiration_cutoff(),
             candidates,
             matcher: matcher.map(CompactRelationshipMatcher::Resource),
         }
@@ -1698,6 +1941,7 @@ impl IndexedRelationshipStore {
         CompactRelationshipIter {
             store: self,
             all_rows_live: self.live_rows.is_all_live(),
+            expired_at: self.expiration_cutoff(),
             candidates,
             matcher: matcher.map(CompactRelationshipMatcher::Resource),
         }
@@ -1762,6 +2006,11 @@ impl IndexedRelationshipStore {
                 subject_id: SubjectIdId(self.interner.lookup(object.id.as_str())?),
                 relation: Some(RelationId(self.interner.lookup(relation.0.as_str())?)),
             }),
+            User::Wildcard => Some(SubjectIndexKey {
+                subject_type: SubjectTypeId(self.interner.lookup("user")?),
+                subject_id: SubjectIdId(self.interner.lookup(WILDCARD_SUBJECT_ID)?),
+                relation: None,
+            }),
         }
     }
 
@@ -1781,7 +2030,12 @@ impl IndexedRelationshipStore {
         }
     }
 
+    /// Inserts `relationship`, replacing a row that has expired but not been swept yet, since
+    /// reads already treat that row as absent.
     fn create(&mut self, relationship: Relationship) -> Result<(), StoreError> {
+        if self.has_expired(&relationship, SystemTime::now()) {
+            return self.touch(&relationship);
+        }
         if self.contains_relationship(&relationship) {
             return Err(StoreError::RelationshipAlreadyExists {
                 relationship: Box::new(relationship),
@@ -1794,12 +2048,7 @@ impl IndexedRelationshipStore {
     fn apply_single_mutation(&mut self, mutation: RelationshipMutation) -> Result<(), StoreError> {
         match mutation {
             RelationshipMutation::Create(relationship) => self.create(relationship),
-            RelationshipMutation::Touch(relationship) => {
-                if !self.contains_relationship(&relationship) {
-                    self.insert(&relationship)?;
-                }
-                Ok(())
-            }
+            RelationshipMutation::Touch(relationship) => self.touch(&relationship),
             RelationshipMutation::Delete(relationship) => self.delete(&relationship),
         }
     }
@@ -1812,6 +2061,35 @@ impl IndexedRelationshipStore {
         self.index_relationship(row_id, &row);
         self.rows.push(row);
         self.live_rows.insert(row_id);
+        if let Some(caveat) = relationship.caveat() {
+            self.caveats.insert(row_id, caveat.clone());
+        }
+        if let Some(expiration) = relationship.expiration() {
+            self.expirations.insert(row_id, expiration);
+        }
+        Ok(())
+    }
+
+    fn touch(&mut self, relationship: &Relationship) -> Result<(), StoreError> {
+        let Some(row_id) = self.live_row_id(relationship) else {
+            return self.insert(relationship);
+        };
+        match relationship.caveat() {
+            Some(caveat) => {
+                self.caveats.insert(row_id, caveat.clone());
+            }
+            None => {
+                self.caveats.remove(&row_id);
+            }
+        }
+        match relationship.expiration() {
+            Some(expiration) => {
+                self.expirations.insert(row_id, expiration);
+            }
+            None => {
+                self.expirations.remove(&row_id);
+            }
+        }
         Ok(())
     }
 
@@ -1828,17 +2106,61 @@ impl IndexedRelationshipStore {
             }
         })?;
         self.live_rows.remove(row_id);
+        self.caveats.remove(&row_id);
+        self.expirations.remove(&row_id);
         self.dead_row_count = self.dead_row_count.saturating_add(1);
 
         Ok(())
     }
 
     pub(crate) fn contains_relationship(&self, relationship: &Relationship) -> bool {
-        self.lookup_relationship_row(relationship)
-            .is_some_and(|row| {
-                self.uniqueness_ref()
-                    .is_some_and(|index| index.find(&self.rows, &row).is_some())
+        self.live_row_id(relationship).is_some()
+    }
+
+    /// Returns true when the live row for `relationship` has expired as of `now`.
+    fn has_expired(&self, relationship: &Relationship, now: SystemTime) -> bool {
+        self.live_row_id(relationship)
+            .and_then(|row_id| self.expirations.get(&row_id))
+            .is_some_and(|expiration| expiration.is_expired_at(now))
+    }
+
+    fn live_row_id(&self, relationship: &Relationship) -> Option<RowId> {
+        let row = self.lookup_relationship_row(relationship)?;
+        self.uniqueness_ref()?.find(&self.rows, &row)
+    }
+
+    /// Returns true when the live row for `relationship` carries the same caveat and expiration.
+    fn has_same_payload(&self, relationship: &Relationship) -> bool {
+        let row_id = self.live_row_id(relationship);
+        row_id.and_then(|row_id| self.caveats.get(&row_id)) == relationship.caveat()
+            && row_id.and_then(|row_id| self.expirations.get(&row_id).copied())
+                == relationship.expiration()
+    }
+
+    /// Returns the instant used to hide expired rows from a new query, if any row can expire.
+    fn expiration_cutoff(&self) -> Option<SystemTime> {
+        (!self.expirations.is_empty()).then(SystemTime::now)
+    }
+
+    fn expired_relationships(
+        &self,
+        now: SystemTime,
+        deleted: Option<&HashSet<RelationshipRow>>,
+    ) -> Vec<Relationship> {
+        let mut expired = self
+            .expirations
+            .iter()
+            .filter(|(row_id, expiration)| {
+                expiration.is_expired_at(now) && self.live_rows.contains(**row_id)
             })
+            .filter_map(|(row_id, _)| self.rows.get(row_id.index()))
+            .filter(|row| deleted.is_none_or(|deleted| !deleted.contains(*row)))
+            .collect::<Vec<_>>();
+        expired.sort_by_key(|row| row.row_id);
+        expired
+            .into_iter()
+            .filter_map(|row| self.relationship_from_row(row).ok())
+            .collect()
     }
 
     fn lookup_relationship_row(&self, relationship: &Relationship) -> Option<RelationshipRow> {
@@ -1851,18 +2173,28 @@ impl IndexedRelationshipStore {
             ObjectId::try_from(self.interner.resolve(row.resource_id.0)?)?,
         );
         let relation = RelationName::try_from(self.interner.resolve(row.relation.0)?)?;
-        let subject_object = ObjectRef::new(
-            ObjectType::try_from(self.interner.resolve(row.subject_type.0)?)?,
-            ObjectId::try_from(self.interner.resolve(row.subject_id.0)?)?,
-        );
+        let subject_type = ObjectType::try_from(self.interner.resolve(row.subject_type.0)?)?;
+        let subject_id = self.interner.resolve(row.subject_id.0)?;
         let subject = match row.subject_relation {
+            None if subject_id == WILDCARD_SUBJECT_ID => SubjectRef::Wildcard(subject_type),
             Some(relation) => SubjectRef::Userset {
-                object: subject_object,
+                object: ObjectRef::new(subject_type, ObjectId::try_from(subject_id)?),
                 relation: RelationName::try_from(self.interner.resolve(relation.0)?)?,
             },
-            None => SubjectRef::Object(subject_object),
+            None => SubjectRef::Object(ObjectRef::new(
+                subject_type,
+                ObjectId::try_from(subject_id)?,
+            )),
+        };
+        let relationship = Relationship::new(resource, relation, subject);
+        let relationship = match self.caveats.get(&row.row_id) {
+            Some(caveat) => relationship.with_caveat(caveat.clone()),
+            None => relationship,
         };
-        Ok(Relationship::new(resource, relation, subject))
+        Ok(match self.expirations.get(&row.row_id) {
+            Some(expiration) => relationship.with_expiration(*expiration),
+            None => relationship,
+        })
     }
 
     /// Creates a short-lived compatibility reader that yields owned-domain relationship refs.
@@ -1879,7 +2211,12 @@ impl IndexedRelationshipStore {
     }
 
     fn resource_matcher(&self, filter: &RelationshipFilter) -> Option<ResourceMatcher> {
-        let resource_type = self.interner.lookup(filter.resource_type.as_str())?;
+        let resource_type = match &filter.resource_type {
+            Some(resource_type) => {
+                Some(ObjectTypeId(self.interner.lookup(resource_type.as_str())?))
+            }
+            None => None,
+        };
         let optional_resource_id = match &filter.optional_resource_id {
             Some(resource_id) => match self.interner.lookup(resource_id.as_str()) {
                 Some(id) => Some(ObjectIdId(id)),
@@ -1902,7 +2239,7 @@ impl IndexedRelationshipStore {
             None => None,
         };
         Some(ResourceMatcher {
-            resource_type: ObjectTypeId(resource_type),
+            resource_type,
             optional_resource_id,
             optional_relation,
             optional_subject,
@@ -1917,7 +2254,9 @@ impl IndexedRelationshipStore {
         limit: QueryLimit,
     ) -> Option<ResourceMatcher> {
         Some(ResourceMatcher {
-            resource_type: ObjectTypeId(self.interner.lookup(resource.object_type().as_str())?),
+            resource_type: Some(ObjectTypeId(
+                self.interner.lookup(resource.object_type().as_str())?,
+            )),
             optional_resource_id: Some(ObjectIdId(
                 self.interner.lookup(resource.object_id().as_str())?,
             )),
@@ -1963,10 +2302,16 @@ impl IndexedRelationshipStore {
     }
 
     fn resource_candidate_row_ids(&self, matcher: &ResourceMatcher) -> CandidateRowIds<'_> {
+        let Some(resource_type) = matcher.resource_type else {
+            return matcher.optional_subject.as_ref().map_or_else(
+                || CandidateRowIds::Rows(self.rows.iter()),
+                |subject| self.subject_candidate_row_ids(subject),
+            );
+        };
         match (matcher.optional_resource_id, matcher.optional_relation) {
             (Some(resource_id), Some(relation)) => {
                 let key = ResourceIndexKey {
-                    object_type: matcher.resource_type,
+                    object_type: resource_type,
                     object_id: resource_id,
                     relation,
                 };
@@ -1974,19 +2319,19 @@ impl IndexedRelationshipStore {
             }
             (Some(resource_id), None) => {
                 let key = ResourceObjectIndexKey {
-                    object_type: matcher.resource_type,
+                    object_type: resource_type,
                     object_id: resource_id,
                 };
                 self.by_resource_object.candidates(&key)
             }
             (None, Some(relation)) => {
                 let key = ResourceTypeRelationIndexKey {
-                    object_type: matcher.resource_type,
+                    object_type: resource_type,
                     relation,
                 };
                 self.by_resource_type_relation.candidates(&key)
             }
-            (None, None) => self.by_resource_type.candidates(&matcher.resource_type),
+            (None, None) => self.by_resource_type.candidates(&resource_type),
         }
     }
 
@@ -2071,6 +2416,12 @@ impl IndexedRelationshipStore {
         self.index_relationship(row_id, &compacted);
         self.rows.push(compacted);
         self.live_rows.insert(row_id);
+        if let Some(caveat) = source.caveats.get(&row.row_id) {
+            self.caveats.insert(row_id, caveat.clone());
+        }
+        if let Some(expiration) = source.expirations.get(&row.row_id) {
+            self.expirations.insert(row_id, *expiration);
+        }
         Ok(())
     }
 
@@ -2202,10 +2553,21 @@ impl<'a> Iterator for RelationshipIter<'a> {
 pub(crate) struct CompactRelationshipIter<'a> {
     store: &'a IndexedRelationshipStore,
     all_rows_live: bool,
+    expired_at: Option<SystemTime>,
     candidates: CandidateRowIds<'a>,
     matcher: Option<CompactRelationshipMatcher>,
 }
 
+impl CompactRelationshipIter<'_> {
+    /// Hides expired rows as of `cutoff` when given, if this store has any expiring rows.
+    fn with_expiration_cutoff(mut self, cutoff: Option<SystemTime>) -> Self {
+        if let Some(cutoff) = cutoff {
+            self.expired_at = self.expired_at.map(|_| cutoff);
+        }
+        self
+    }
+}
+
 impl<'a> Iterator for CompactRelationshipIter<'a> {
     type Item = RelationshipRef<'a>;
 
@@ -2216,6 +2578,15 @@ impl<'a> Iterator for CompactRelationshipIter<'a> {
             if !self.all_rows_live && !self.store.live_rows.contains(row_id) {
                 continue;
             }
+            if let Some(now) = self.expired_at
+                && self
+                    .store
+                    .expirations
+                    .get(&row_id)
+                    .is_some_and(|expiration| expiration.is_expired_at(now))
+            {
+                continue;
+            }
             let row = self.store.rows.get(row_id.index())?;
             match matcher {
                 CompactRelationshipMatcher::Resource(resource) => {
@@ -2250,11 +2621,19 @@ pub(crate) struct RelationshipRef<'a> {
     row: &'a RelationshipRow,
 }
 
-impl RelationshipRef<'_> {
+impl<'a> RelationshipRef<'a> {
     fn is_deleted_by(&self, deleted: &HashSet<RelationshipRow>) -> bool {
         deleted.contains(self.row)
     }
 
+    pub(crate) fn caveat(&self) -> Option<&'a RelationshipCaveat> {
+        self.store.caveats.get(&self.row.row_id)
+    }
+
+    pub(crate) fn to_relationship(self) -> Result<Relationship, StoreError> {
+        self.store.relationship_from_row(self.row)
+    }
+
     pub(crate) fn resource_object_legacy(&self) -> crate::model::Object {
         crate::model::Object {
             namespace: self.store.resolve(self.row.resource_type.0).to_string(),
@@ -2274,6 +2653,7 @@ impl RelationshipRef<'_> {
         self.store.resolve(self.row.relation.0)
     }
 
+    /// Returns the direct `user` subject id, which is `*` for a `user:*` wildcard row.
     pub(crate) fn direct_user_subject_id(&self) -> Option<&str> {
         if self.row.subject_relation.is_none()
             && self.store.resolve(self.row.subject_type.0) == "user"
@@ -2301,6 +2681,18 @@ impl RelationshipRef<'_> {
             .transpose()
     }
 
+    /// Returns the subject object for a plain object subject, skipping usersets and wildcards.
+    pub(crate) fn direct_subject_object(&self) -> Option<crate::model::Object> {
+        if self.row.subject_relation.is_some() {
+            return None;
+        }
+        let subject_id = self.store.resolve(self.row.subject_id.0);
+        (subject_id != WILDCARD_SUBJECT_ID).then(|| crate::model::Object {
+            namespace: self.store.resolve(self.row.subject_type.0).to_string(),
+            id: subject_id.to_string(),
+        })
+    }
+
     pub(crate) fn expanded_subject(&self) -> Result<crate::model::ExpandedUserset, ZanzibarError> {
         match self.row.subject_relation {
             Some(relation) => Ok(crate::model::ExpandedUserset::Userset(
@@ -2311,9 +2703,12 @@ impl RelationshipRef<'_> {
                 crate::model::Relation(self.store.resolve(relation.0).to_string()),
             )),
             None if self.store.resolve(self.row.subject_type.0) == "user" => {
-                Ok(crate::model::ExpandedUserset::User(
-                    self.store.resolve(self.row.subject_id.0).to_string(),
-                ))
+                let subject_id = self.store.resolve(self.row.subject_id.0);
+                if subject_id == WILDCARD_SUBJECT_ID {
+                    Ok(crate::model::ExpandedUserset::Wildcard)
+                } else {
+                    Ok(crate::model::ExpandedUserset::User(subject_id.to_string()))
+                }
             }
             None => Err(ZanzibarError::StorageError(format!(
                 "legacy expand cannot represent direct subject type '{}'",
@@ -2332,6 +2727,7 @@ enum CandidateRowIds<'a> {
         rest: std::slice::Iter<'a, RowId>,
     },
     Slice(std::slice::Iter<'a, RowId>),
+    Rows(std::slice::Iter<'a, RelationshipRow>),
 }
 
 impl Iterator for CandidateRowIds<'_> {
@@ -2343,6 +2739,7 @@ impl Iterator for CandidateRowIds<'_> {
             Self::One(row_id) => row_id.take(),
             Self::OneThenSlice { first, rest } => first.take().or_else(|| rest.next().copied()),
             Self::Slice(indexes) => indexes.next().copied(),
+            Self::Rows(rows) => rows.next().map(|row| row.row_id),
         }
     }
 }
@@ -2488,7 +2885,7 @@ enum CompactRelationshipMatcher {
 
 #[derive(Debug, Clone, Copy)]
 struct ResourceMatcher {
-    resource_type: ObjectTypeId,
+    resource_type: Option<ObjectTypeId>,
     optional_resource_id: Option<ObjectIdId>,
     optional_relation: Option<RelationId>,
     optional_subject: Option<SubjectMatcher>,
@@ -3197,6 +3594,11 @@ impl RelationshipRow {
                 SubjectIdId(interner.intern(object.object_id().as_str())?),
                 Some(RelationId(interner.intern(relation.as_str())?)),
             ),
+            SubjectRef::Wildcard(object_type) => (
+                SubjectTypeId(interner.intern(object_type.as_str())?),
+                SubjectIdId(interner.intern(WILDCARD_SUBJECT_ID)?),
+                None,
+            ),
         };
         Ok(Self {
             row_id,
@@ -3229,6 +3631,11 @@ impl RelationshipRow {
                 SubjectIdId(interner.lookup(object.object_id().as_str())?),
                 Some(RelationId(interner.lookup(relation.as_str())?)),
             ),
+            SubjectRef::Wildcard(object_type) => (
+                SubjectTypeId(interner.lookup(object_type.as_str())?),
+                SubjectIdId(interner.lookup(WILDCARD_SUBJECT_ID)?),
+                None,
+            ),
         };
         Some(Self {
             row_id: RowId(NonZeroU32::MIN),
@@ -3242,7 +3649,9 @@ impl RelationshipRow {
     }
 
     fn matches_resource(&self, matcher: &ResourceMatcher) -> bool {
-        self.resource_type == matcher.resource_type
+        matcher
+            .resource_type
+            .is_none_or(|resource_type| self.resource_type == resource_type)
             && matcher
                 .optional_resource_id
                 .is_none_or(|resource_id| self.resource_id == resource_id)
@@ -5527,6 +5936,91 @@ fn row_bytes_to_array<const LEN: usize>(row_bytes: &[u8]) -> Result<[u8; LEN], S
     })
 }
 
+fn decode_snapshot_caveats(
+    reader: &SnapshotReader<'_>,
+    row_count: usize,
+) -> Result<HashMap<RowId, RelationshipCaveat>, SnapshotIoError> {
+    let Some(section) = reader.optional_section(SectionKind::RelationshipCaveats) else {
+        return Ok(HashMap::new());
+    };
+    let entry_count = checked_usize_from_u64(section.row_count())?;
+    if entry_count > row_count {
+        return Err(SnapshotIoError::Format {
+            reason: "relationship caveat count exceeds row count",
+        });
+    }
+    let mut cursor = BinaryCursor::new(section.bytes());
+    let mut caveats = HashMap::with_capacity(entry_count);
+    let mut next_index = 0_usize;
+    for _ in 0..entry_count {
+        let index = checked_usize_from_u32(cursor.read_u32()?)?;
+        if index < next_index || index >= row_count {
+            return Err(SnapshotIoError::Format {
+                reason: "relationship caveat row ids must be increasing and in range",
+            });
+        }
+        next_index = checked_add_usize(index, 1)?;
+        let name =
+            str::from_utf8(cursor.read_len_prefixed()?).map_err(|_| SnapshotIoError::Format {
+                reason: "relationship caveat name is not valid utf-8",
+            })?;
+        let context =
+            str::from_utf8(cursor.read_len_prefixed()?).map_err(|_| SnapshotIoError::Format {
+                reason: "relationship caveat context is not valid utf-8",
+            })?;
+        let context = context.parse().map_err(|_| SnapshotIoError::Format {
+            reason: "relationship caveat context is malformed",
+        })?;
+        caveats.insert(
+            RowId::from_len(index)?,
+            RelationshipCaveat::new(CaveatName::try_from(name)?, context),
+        );
+    }
+    if !cursor.is_empty() {
+        return Err(SnapshotIoError::Format {
+            reason: "relationship caveat section has trailing bytes",
+        });
+    }
+    Ok(caveats)
+}
+
+fn decode_snapshot_expirations(
+    reader: &SnapshotReader<'_>,
+    row_count: usize,
+) -> Result<HashMap<RowId, RelationshipExpiration>, SnapshotIoError> {
+    let Some(section) = reader.optional_section(SectionKind::RelationshipExpirations) else {
+        return Ok(HashMap::new());
+    };
+    let entry_count = checked_usize_from_u64(section.row_count())?;
+    if entry_count > row_count {
+        return Err(SnapshotIoError::Format {
+            reason: "relationship expiration count exceeds row count",
+        });
+    }
+    let mut cursor = BinaryCursor::new(section.bytes());
+    let mut expirations = HashMap::with_capacity(entry_count);
+    let mut next_index = 0_usize;
+    for _ in 0..entry_count {
+        let index = checked_usize_from_u32(cursor.read_u32()?)?;
+        if index < next_index || index >= row_count {
+            return Err(SnapshotIoError::Format {
+                reason: "relationship expiration row ids must be increasing and in range",
+            });
+        }
+        next_index = checked_add_usize(index, 1)?;
+        expirations.insert(
+            RowId::from_len(index)?,
+            RelationshipExpiration::from_unix_seconds(cursor.read_u64()?)?,
+        );
+    }
+    if !cursor.is_empty() {
+        return Err(SnapshotIoError::Format {
+            reason: "relationship expiration section has trailing bytes",
+        });
+    }
+    Ok(expirations)
+}
+
 fn validate_row_domains(
     interner: &IdentifierInterner,
     row: &RelationshipRow,
@@ -5537,6 +6031,7 @@ fn validate_row_domains(
     SubjectType::try_from(interner.resolve(row.subject_type.0)?)?;
     SubjectId::try_from(interner.resolve(row.subject_id.0)?)?;
     if let Some(relation) = row.subject_relation {
+        ObjectId::try_from(interner.resolve(row.subject_id.0)?)?;
         RelationName::try_from(interner.resolve(relation.0)?)?;
     }
     Ok(())
@@ -5646,6 +6141,10 @@ fn relationship_identifier_values(relationship: &Relationship, mut visit: impl F
             visit(object.object_id().as_str());
             visit(relation.as_str());
         }
+        SubjectRef::Wildcard(object_type) => {
+            visit(object_type.as_str());
+            visit(WILDCARD_SUBJECT_ID);
+        }
     }
 }
 
@@ -0,0 +1,204 @@
+//! Polling watcher that hot-reloads snapshot artifacts dropped into a directory.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Weak,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    api::ZanzibarEngine,
+    revision::ConsistencyToken,
+    snapshot::{SnapshotCompression, SnapshotIoError, SnapshotLoadOptions},
+};
+
+/// Outcome of one automatic reload attempted by a [`SnapshotDirectoryWatcher`].
+#[derive(Debug)]
+pub enum SnapshotReloadEvent {
+    /// The artifact at `path` was published into the engine.
+    Reloaded {
+        /// Artifact that was loaded.
+        path: PathBuf,
+        /// Token of the reloaded snapshot.
+        token: ConsistencyToken,
+    },
+    /// The artifact at `path` could not be reloaded; the engine keeps its previous state.
+    Failed {
+        /// Artifact that was rejected, or the watched directory when it could not be listed.
+        path: PathBuf,
+        /// Load, validation, or directory listing failure.
+        error: SnapshotIoError,
+    },
+}
+
+/// Background thread that polls a directory and reloads its newest snapshot artifact.
+///
+/// Only files named `*.szsnap`, or `*.szsnap.zst` when the load options expect zstd, are
+/// considered; the newest by modification time wins, with ties broken by file name. Whatever is
+/// newest when the watcher starts counts as already loaded, so only artifacts dropped or rewritten
+/// afterwards trigger a reload. Producers should write to another name and rename into place so a
+/// poll never observes a partial file.
+///
+/// The watcher holds the engine weakly and stops once the engine is dropped or the watcher itself
+/// is dropped.
+#[derive(Debug)]
+pub struct SnapshotDirectoryWatcher {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+    events: Receiver<SnapshotReloadEvent>,
+}
+
+impl SnapshotDirectoryWatcher {
+    pub(crate) fn start(
+        engine: &Arc<ZanzibarEngine>,
+        directory: &Path,
+        options: SnapshotLoadOptions,
+        poll_interval: Duration,
+    ) -> Result<Self, SnapshotIoError> {
+        let mut poller = DirectoryPoller {
+            engine: Arc::downgrade(engine),
+            directory: directory.to_path_buf(),
+            options,
+            seen: newest_artifact(directory, options.compression)?,
+            listing_failed: false,
+        };
+        let (stop, stopped) = mpsc::channel();
+        let (events, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(poll_interval) {
+                if !poller.poll(&events) {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            stop: Some(stop),
+            handle: Some(handle),
+            events: receiver,
+        })
+    }
+
+    /// Returns the next reload outcome without blocking.
+    ///
+    /// Returns `None` when no outcome is pending or the watcher has stopped.
+    pub fn try_next(&mut self) -> Option<SnapshotReloadEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Waits up to `timeout` for the next reload outcome.
+    ///
+    /// Returns `None` when the timeout elapses or the watcher has stopped.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<SnapshotReloadEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for SnapshotDirectoryWatcher {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            drop(handle.join());
+        }
+    }
+}
+
+/// Identity of one artifact version: a rewrite in place changes its timestamp or length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ArtifactStamp {
+    modified: SystemTime,
+    len: u64,
+    path: PathBuf,
+}
+
+struct DirectoryPoller {
+    engine: Weak<ZanzibarEngine>,
+    directory: PathBuf,
+    options: SnapshotLoadOptions,
+    seen: Option<ArtifactStamp>,
+    listing_failed: bool,
+}
+
+impl DirectoryPoller {
+    /// Runs one poll and returns `false` once the engine or the event receiver is gone.
+    fn poll(&mut self, events: &Sender<SnapshotReloadEvent>) -> bool {
+        let Some(engine) = self.engine.upgrade() else {
+            return false;
+        };
+        let newest = match newest_artifact(&self.directory, self.options.compression) {
+            Ok(newest) => {
+                self.listing_failed = false;
+                newest
+            }
+            Err(error) => {
+                // Report a listing failure once rather than on every poll until it recovers.
+                if self.listing_failed {
+                    return true;
+                }
+                self.listing_failed = true;
+                return events
+                    .send(SnapshotReloadEvent::Failed {
+                        path: self.directory.clone(),
+                        error,
+                    })
+                    .is_ok();
+            }
+        };
+        let Some(newest) = newest else {
+            return true;
+        };
+        if self.seen.as_ref() == Some(&newest) {
+            return true;
+        }
+        let path = newest.path.clone();
+        self.seen = Some(newest);
+        let event = match engine.reload_snapshot(&path, self.options) {
+            Ok(token) => SnapshotReloadEvent::Reloaded { path, token },
+            Err(error) => SnapshotReloadEvent::Failed { path, error },
+        };
+        events.send(event).is_ok()
+    }
+}
+
+fn newest_artifact(
+    directory: &Path,
+    compression: SnapshotCompression,
+) -> Result<Option<ArtifactStamp>, SnapshotIoError> {
+    let suffix = match compression {
+        SnapshotCompression::None => ".szsnap",
+        SnapshotCompression::Zstd => ".szsnap.zst",
+    };
+    let mut newest: Option<ArtifactStamp> = None;
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        if !entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.ends_with(suffix))
+        {
+            continue;
+        }
+        let metadata = match entry.metadata() {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => continue,
+            // The file was removed between listing and stat.
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(error.into()),
+        };
+        let stamp = ArtifactStamp {
+            modified: metadata.modified()?,
+            len: metadata.len(),
+            path: entry.path(),
+        };
+        if newest.as_ref().is_none_or(|current| {
+            (&stamp.modified, &stamp.path) > (&current.modified, &current.path)
+        }) {
+            newest = Some(stamp);
+        }
+    }
+    Ok(newest)
+}
@@ -3,6 +3,7 @@
 use std::{
     collections::HashMap,
     fmt,
+    hash::{BuildHasher, Hasher, RandomState},
     num::{NonZeroU64, NonZeroUsize},
     str::FromStr,
     sync::{
@@ -24,7 +25,9 @@ use crate::{
 };
 
 const TOKEN_VERSION: &str = "sz1";
+const CURSOR_VERSION: &str = "lc3";
 const MAX_CONSISTENCY_TOKEN_BYTES: usize = 122;
+const MAX_LOOKUP_CURSOR_BYTES: usize = 216;
 const DEFAULT_RETAINED_SNAPSHOTS: usize = 32;
 static NEXT_DATASTORE_COUNTER: AtomicU64 = AtomicU64::new(1);
 
@@ -66,6 +69,17 @@ pub enum ConsistencyError {
     /// The engine cannot publish another revision without overflowing.
     #[error("revision counter overflowed")]
     RevisionOverflow,
+
+    /// A lookup cursor string does not match the supported grammar.
+    #[error("invalid lookup cursor: {reason}")]
+    InvalidCursor {
+        /// Static parse failure reason.
+        reason: &'static str,
+    },
+
+    /// A lookup cursor was issued for a different lookup request or engine, or was modified.
+    #[error("lookup cursor was issued for a different request or was modified")]
+    CursorMismatch,
 }
 
 /// Monotonic non-zero revision identifier.
@@ -168,6 +182,13 @@ impl DatastoreId {
     }
 }
 
+impl fmt::Display for DatastoreId {
+    /// Writes the id as lowercase hex, as it appears in consistency tokens.
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(formatter, &self.0)
+    }
+}
+
 /// Canonical hash of a compiled schema.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SchemaHash([u8; 32]);
@@ -184,6 +205,13 @@ impl SchemaHash {
             update_namespace(&mut hasher, namespace);
         }
 
+        let mut caveats = schema.caveats().iter().collect::<Vec<_>>();
+        caveats.sort_by(|left, right| left.name().as_str().cmp(right.name().as_str()));
+        for caveat in caveats {
+            update_str(&mut hasher, "caveat");
+            update_str(&mut hasher, &caveat.to_string());
+        }
+
         Self(*hasher.finalize().as_bytes())
     }
 
@@ -200,6 +228,13 @@ impl SchemaHash {
     }
 }
 
+impl fmt::Display for SchemaHash {
+    /// Writes the hash as lowercase hex, as it appears in consistency tokens.
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(formatter, &self.0)
+    }
+}
+
 /// Stable exact-snapshot token returned by writes.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConsistencyToken {
@@ -244,10 +279,11 @@ impl ConsistencyToken {
 
 impl fmt::Display for ConsistencyToken {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(formatter, "{TOKEN_VERSION}:{}:", self.revision)?;
-        write_hex(formatter, self.schema_hash.as_bytes())?;
-        formatter.write_str(":")?;
-        write_hex(formatter, self.datastore_id.as_bytes())
+        write!(
+            formatter,
+            "{TOKEN_VERSION}:{}:{}:{}",
+            self.revision, self.schema_hash, self.datastore_id
+        )
     }
 }
 
@@ -295,6 +331,181 @@ impl FromStr for ConsistencyToken {
     }
 }
 
+/// Opaque continuation cursor returned by paged lookups.
+///
+/// A cursor pins the exact snapshot the first page was read from, and the second at which it
+/// hid expired relationships, so every later page is evaluated at the same revision and sees the
+/// same rows. It is sealed with a key private to the engine that issued it, so it is only valid
+/// for the request that produced it, on that engine, and with its fields unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupCursor {
+    token: ConsistencyToken,
+    seal: [u8; 16],
+    expiration_cutoff: u64,
+    group: u32,
+    offset: u64,
+}
+
+impl LookupCursor {
+    pub(crate) const fn new(
+        token: ConsistencyToken,
+        seal: [u8; 16],
+        expiration_cutoff: u64,
+        group: u32,
+        offset: u64,
+    ) -> Self {
+        Self {
+            token,
+            seal,
+            expiration_cutoff,
+            group,
+            offset,
+        }
+    }
+
+    /// Returns the token of the snapshot every page is read from.
+    #[must_use]
+    pub const fn token(&self) -> &ConsistencyToken {
+        &self.token
+    }
+
+    /// Returns the Unix second at which expired relationships are hidden on every page.
+    pub(crate) const fn expiration_cutoff(&self) -> u64 {
+        self.expiration_cutoff
+    }
+
+    pub(crate) const fn group(&self) -> u32 {
+        self.group
+    }
+
+    pub(crate) const fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub(crate) fn ensure_sealed(&self, seal: [u8; 16]) -> Result<(), ConsistencyError> {
+        if self.seal == seal {
+            Ok(())
+        } else {
+            Err(ConsistencyError::CursorMismatch)
+        }
+    }
+}
+
+impl fmt::Display for LookupCursor {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{CURSOR_VERSION}:{}:{}:{}:",
+            self.offset, self.group, self.expiration_cutoff
+        )?;
+        write_hex(formatter, &self.seal)?;
+        write!(formatter, ":{}", self.token)
+    }
+}
+
+impl FromStr for LookupCursor {
+    type Err = ConsistencyError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.len() > MAX_LOOKUP_CURSOR_BYTES {
+            return Err(ConsistencyError::InvalidCursor {
+                reason: "cursor exceeds maximum byte length",
+            });
+        }
+
+        let mut parts = value.splitn(6, ':');
+        if parts.next() != Some(CURSOR_VERSION) {
+            return Err(ConsistencyError::InvalidCursor {
+                reason: "unsupported cursor version",
+            });
+        }
+        let offset = parts.next().and_then(|part| part.parse().ok()).ok_or(
+            ConsistencyError::InvalidCursor {
+                reason: "offset must be an unsigned integer",
+            },
+        )?;
+        let group = parts.next().and_then(|part| part.parse().ok()).ok_or(
+            ConsistencyError::InvalidCursor {
+                reason: "group must be an unsigned integer",
+            },
+        )?;
+        let expiration_cutoff = parts.next().and_then(|part| part.parse().ok()).ok_or(
+            ConsistencyError::InvalidCursor {
+                reason: "expiration cutoff must be an unsigned integer",
+            },
+        )?;
+        let seal = parts.next().and_then(|part| decode_hex(part).ok()).ok_or(
+            ConsistencyError::InvalidCursor {
+                reason: "cursor seal is invalid",
+            },
+        )?;
+        let token = parts.next().and_then(|part| part.parse().ok()).ok_or(
+            ConsistencyError::InvalidCursor {
+                reason: "cursor token is invalid",
+            },
+        )?;
+        Ok(Self::new(token, seal, expiration_cutoff, group, offset))
+    }
+}
+
+/// Hashes the identifying fields of a lookup request for cursor binding.
+pub(crate) fn lookup_request_hash(kind: &str, fields: &[&str]) -> [u8; 16] {
+    let mut hasher = blake3::Hasher::new();
+    update_str(&mut hasher, kind);
+    for field in fields {
+        update_str(&mut hasher, field);
+    }
+    let hash = hasher.finalize();
+    let mut bytes = [0_u8; 16];
+    for (target, source) in bytes.iter_mut().zip(hash.as_bytes().iter()) {
+        *target = *source;
+    }
+    bytes
+}
+
+/// Per-engine secret that seals lookup cursors, so clients cannot move the pinned snapshot or
+/// expiration cutoff of a cursor they were issued.
+#[derive(Clone, Copy)]
+pub(crate) struct CursorKey([u8; 32]);
+
+impl CursorKey {
+    /// Creates a key from the process-random seeds the standard library uses for hash maps.
+    pub(crate) fn new_random() -> Self {
+        let mut bytes = [0_u8; 32];
+        for (index, chunk) in bytes.chunks_exact_mut(8).enumerate() {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_usize(index);
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+        Self(*blake3::hash(&bytes).as_bytes())
+    }
+
+    /// Seals the request hash, snapshot token, and expiration cutoff of one cursor.
+    pub(crate) fn seal(
+        &self,
+        request_hash: [u8; 16],
+        token: &ConsistencyToken,
+        expiration_cutoff: u64,
+    ) -> [u8; 16] {
+        let mut hasher = blake3::Hasher::new_keyed(&self.0);
+        hasher.update(&request_hash);
+        update_str(&mut hasher, &token.to_string());
+        hasher.update(&expiration_cutoff.to_le_bytes());
+        let hash = hasher.finalize();
+        let mut bytes = [0_u8; 16];
+        for (target, source) in bytes.iter_mut().zip(hash.as_bytes().iter()) {
+            *target = *source;
+        }
+        bytes
+    }
+}
+
+impl fmt::Debug for CursorKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("CursorKey(..)")
+    }
+}
+
 /// Read consistency mode.
 #[cfg_attr(
     feature = "serde",
@@ -307,6 +518,11 @@ pub enum Consistency {
     Latest,
     /// Read exactly at a previously returned token.
     Exact(ConsistencyToken),
+    /// Read the latest published snapshot, provided it is at or after the token's revision.
+    ///
+    /// Unlike [`Self::Exact`], this never fails with [`ConsistencyError::RevisionExpired`], so it
+    /// suits read-your-writes after an arbitrarily old write.
+    AtLeastAsFresh(ConsistencyToken),
 }
 
 #[cfg(feature = "serde")]
@@ -330,6 +546,27 @@ impl<'de> serde::Deserialize<'de> for ConsistencyToken {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for LookupCursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LookupCursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Self::from_str(&value).map_err(serde::de::Error::custom)
+    }
+}
+
 /// Published immutable snapshot.
 #[derive(Debug, Clone)]
 pub struct PublishedSnapshot {
@@ -388,6 +625,18 @@ impl PublishedSnapshot {
     pub fn relationships(&self) -> &RelationshipStoreView {
         &self.relationships
     }
+
+    pub(crate) fn shared_relationships(&self) -> Arc<RelationshipStoreView> {
+        Arc::clone(&self.relationships)
+    }
+
+    /// Returns this snapshot with expired relationships hidden as of `cutoff` instead of now.
+    pub(crate) fn with_expiration_cutoff(&self, cutoff: SystemTime) -> Self {
+        Self {
+            relationships: Arc::new(self.relationships.with_expiration_cutoff(cutoff)),
+            ..self.clone()
+        }
+    }
 }
 
 /// Returns the default snapshot retention.
@@ -416,13 +665,19 @@ fn update_relation(hasher: &mut blake3::Hasher, relation: &RelationDefinition) {
         AllowedSubjectTypes::Unspecified => update_str(hasher, "subjects_unspecified"),
         AllowedSubjectTypes::Explicit(subjects) => {
             update_str(hasher, "subjects_explicit");
-            let mut subject_types = subjects.iter().collect::<Vec<_>>();
-            subject_types.sort_by(|left, right| left.as_str().cmp(right.as_str()));
-            for subject_type in subject_types {
-                update_str(hasher, subject_type.as_str());
+            let mut subjects = subjects.iter().map(ToString::to_string).collect::<Vec<_>>();
+            subjects.sort();
+            for subject in subjects {
+                update_str(hasher, &subject);
             }
         }
     }
+    if !relation.wildcard_subject_types().is_empty() {
+        update_str(hasher, "wildcard_subjects");
+        for subject_type in relation.wildcard_subject_types() {
+            update_str(hasher, subject_type.as_str());
+        }
+    }
     match relation.userset_rewrite() {
         Some(expression) => update_expression(hasher, expression),
         None => update_str(hasher, "rewrite_none"),
@@ -48,6 +48,18 @@ impl EngineState {
         self.evaluation_limits
     }
 
+    pub(crate) const fn datastore_id(&self) -> DatastoreId {
+        self.datastore_id
+    }
+
+    pub(crate) const fn token_for(&self, snapshot: &PublishedSnapshot) -> ConsistencyToken {
+        ConsistencyToken::new(
+            snapshot.revision(),
+            snapshot.schema_hash(),
+            self.datastore_id,
+        )
+    }
+
     pub(crate) fn snapshot_for_consistency(
         &self,
         consistency: Consistency,
@@ -55,7 +67,24 @@ impl EngineState {
         match consistency {
             Consistency::Latest => Ok(Arc::clone(&self.latest_snapshot)),
             Consistency::Exact(token) => self.snapshot_for_token(&token),
+            Consistency::AtLeastAsFresh(token) => self.snapshot_at_least_as_fresh(&token),
+        }
+    }
+
+    fn snapshot_at_least_as_fresh(
+        &self,
+        token: &ConsistencyToken,
+    ) -> Result<Arc<PublishedSnapshot>, ZanzibarError> {
+        if token.datastore_id() != self.datastore_id {
+            return Err(ConsistencyError::WrongDatastore.into());
+        }
+        if token.revision() > self.latest_snapshot.revision() {
+            return Err(ConsistencyError::RevisionUnavailable {
+                revision: token.revision(),
+            }
+            .into());
         }
+        Ok(Arc::clone(&self.latest_snapshot))
     }
 
     fn snapshot_for_token(
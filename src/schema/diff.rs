@@ -0,0 +1,522 @@
+//! Structural schema diff with breaking-change classification.
+
+use std::collections::BTreeMap;
+
+use super::{
+    AllowedSubject, AllowedSubjectTypes, CompiledSchema, RelationDefinition, UsersetExpression,
+};
+use crate::{
+    domain::{CaveatName, ObjectType, RelationName, Relationship, SubjectRef},
+    revision::ConsistencyToken,
+};
+
+/// Whether a schema change can invalidate relationships that are valid today.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SchemaChangeSeverity {
+    /// Every relationship accepted before the change is still accepted.
+    ///
+    /// Safe changes may still change evaluated access; rewrite edits are safe in this sense.
+    Safe,
+    /// Some relationships accepted before the change may be rejected after it.
+    Breaking,
+}
+
+/// One difference between two compiled schemas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaChange {
+    /// A namespace was added.
+    NamespaceAdded {
+        /// Added namespace.
+        namespace: ObjectType,
+    },
+    /// A namespace and all of its relations were removed.
+    NamespaceRemoved {
+        /// Removed namespace.
+        namespace: ObjectType,
+    },
+    /// A relation or permission was added to an existing namespace.
+    RelationAdded {
+        /// Namespace holding the relation.
+        namespace: ObjectType,
+        /// Added relation.
+        relation: RelationName,
+    },
+    /// A relation or permission was removed from a namespace that still exists.
+    RelationRemoved {
+        /// Namespace that held the relation.
+        namespace: ObjectType,
+        /// Removed relation.
+        relation: RelationName,
+    },
+    /// A relation's userset rewrite changed.
+    RewriteChanged {
+        /// Namespace holding the relation.
+        namespace: ObjectType,
+        /// Relation whose rewrite changed.
+        relation: RelationName,
+        /// Rewrite before the change.
+        previous: Option<UsersetExpression>,
+        /// Rewrite after the change.
+        current: Option<UsersetExpression>,
+    },
+    /// A relation started accepting one more declared subject.
+    SubjectAllowed {
+        /// Namespace holding the relation.
+        namespace: ObjectType,
+        /// Relation accepting the subject.
+        relation: RelationName,
+        /// Newly accepted subject.
+        subject: AllowedSubject,
+    },
+    /// A relation stopped accepting one declared subject.
+    SubjectDisallowed {
+        /// Namespace holding the relation.
+        namespace: ObjectType,
+        /// Relation no longer accepting the subject.
+        relation: RelationName,
+        /// Subject that is no longer accepted.
+        subject: AllowedSubject,
+    },
+    /// A relation without declared subjects now declares them, rejecting every other subject.
+    SubjectTypesRestricted {
+        /// Namespace holding the relation.
+        namespace: ObjectType,
+        /// Relation that now declares its subjects.
+        relation: RelationName,
+    },
+    /// A relation dropped its declared subjects and now accepts any subject.
+    SubjectTypesUnrestricted {
+        /// Namespace holding the relation.
+        namespace: ObjectType,
+        /// Relation that no longer declares its subjects.
+        relation: RelationName,
+    },
+    /// A relation started accepting `subject_type:*`.
+    WildcardAllowed {
+        /// Namespace holding the relation.
+        namespace: ObjectType,
+        /// Relation accepting the wildcard.
+        relation: RelationName,
+        /// Wildcard subject type.
+        subject_type: ObjectType,
+    },
+    /// A relation stopped accepting `subject_type:*`.
+    WildcardDisallowed {
+        /// Namespace holding the relation.
+        namespace: ObjectType,
+        /// Relation no longer accepting the wildcard.
+        relation: RelationName,
+        /// Wildcard subject type.
+        subject_type: ObjectType,
+    },
+    /// A caveat was added.
+    CaveatAdded {
+        /// Added caveat.
+        caveat: CaveatName,
+    },
+    /// A caveat was removed.
+    CaveatRemoved {
+        /// Removed caveat.
+        caveat: CaveatName,
+    },
+    /// A caveat's parameter list changed, so bound contexts may no longer type-check.
+    CaveatParametersChanged {
+        /// Changed caveat.
+        caveat: CaveatName,
+    },
+    /// A caveat's expression changed while its parameters stayed the same.
+    CaveatExpressionChanged {
+        /// Changed caveat.
+        caveat: CaveatName,
+    },
+}
+
+impl SchemaChange {
+    /// Returns whether this change can invalidate stored relationships.
+    #[must_use]
+    pub const fn severity(&self) -> SchemaChangeSeverity {
+        match self {
+            Self::NamespaceRemoved { .. }
+            | Self::RelationRemoved { .. }
+            | Self::SubjectDisallowed { .. }
+            | Self::SubjectTypesRestricted { .. }
+            | Self::WildcardDisallowed { .. }
+            | Self::CaveatRemoved { .. }
+            | Self::CaveatParametersChanged { .. } => SchemaChangeSeverity::Breaking,
+            Self::NamespaceAdded { .. }
+            | Self::RelationAdded { .. }
+            | Self::RewriteChanged { .. }
+            | Self::SubjectAllowed { .. }
+            | Self::SubjectTypesUnrestricted { .. }
+            | Self::WildcardAllowed { .. }
+            | Self::CaveatAdded { .. }
+            | Self::CaveatExpressionChanged { .. } => SchemaChangeSeverity::Safe,
+        }
+    }
+
+    /// Returns whether `relationship` falls under this change, so a rejection can be attributed
+    /// to it.
+    fn affects(&self, relationship: &Relationship) -> bool {
+        let resource_type = relationship.resource().object_type();
+        let on = |namespace: &ObjectType, relation: &RelationName| {
+            resource_type == namespace && relationship.relation() == relation
+        };
+        match self {
+            Self::NamespaceRemoved { namespace } => {
+                resource_type == namespace
+                    || matches!(relationship.subject(), SubjectRef::Userset { object, .. }
+                        if object.object_type() == namespace)
+            }
+            Self::RelationRemoved {
+                namespace,
+                relation,
+            } => {
+                on(namespace, relation)
+                    || matches!(relationship.subject(), SubjectRef::Userset { object, relation: subject_relation }
+                        if object.object_type() == namespace && subject_relation == relation)
+            }
+            Self::SubjectDisallowed {
+                namespace,
+                relation,
+                subject,
+            } => {
+                on(namespace, relation)
+                    && match relationship.subject() {
+                        SubjectRef::Object(object) => {
+                            object.object_type() == subject.object_type()
+                                && subject.relation().is_none()
+                        }
+                        SubjectRef::Userset {
+                            object,
+                            relation: subject_relation,
+                        } => {
+                            object.object_type() == subject.object_type()
+                                && subject.relation() == Some(subject_relation)
+                        }
+                        SubjectRef::Wildcard(_) => false,
+                    }
+            }
+            Self::SubjectTypesRestricted {
+                namespace,
+                relation,
+            } => {
+                on(namespace, relation)
+                    && !matches!(relationship.subject(), SubjectRef::Wildcard(_))
+            }
+            Self::WildcardDisallowed {
+                namespace,
+                relation,
+                subject_type,
+            } => {
+                on(namespace, relation)
+                    && matches!(relationship.subject(), SubjectRef::Wildcard(wildcard)
+                        if wildcard == subject_type)
+            }
+            Self::CaveatRemoved { caveat } | Self::CaveatParametersChanged { caveat } => {
+                relationship
+                    .caveat()
+                    .is_some_and(|bound| bound.name() == caveat)
+            }
+            Self::NamespaceAdded { .. }
+            | Self::RelationAdded { .. }
+            | Self::RewriteChanged { .. }
+            | Self::SubjectAllowed { .. }
+            | Self::SubjectTypesUnrestricted { .. }
+            | Self::WildcardAllowed { .. }
+            | Self::CaveatAdded { .. }
+            | Self::CaveatExpressionChanged { .. } => false,
+        }
+    }
+}
+
+/// Ordered list of changes between two schemas.
+///
+/// Namespaces, relations, and caveats are reported in name order so the same pair of schemas
+/// always produces the same diff.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    /// Individual changes.
+    pub changes: Vec<SchemaChange>,
+}
+
+impl SchemaDiff {
+    /// Returns true when the schemas are structurally identical.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns true when any change is [`SchemaChangeSeverity::Breaking`].
+    #[must_use]
+    pub fn is_breaking(&self) -> bool {
+        self.breaking_changes().next().is_some()
+    }
+
+    /// Returns the breaking changes in diff order.
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &SchemaChange> {
+        self.changes
+            .iter()
+            .filter(|change| change.severity() == SchemaChangeSeverity::Breaking)
+    }
+
+    /// Counts, for each change, the relationships that `new` rejects because of it.
+    ///
+    /// A rejected relationship is attributed to every breaking change that covers it, so counts
+    /// may overlap when several changes touch the same relation.
+    pub(crate) fn impact(
+        self,
+        new: &CompiledSchema,
+        relationships: impl IntoIterator<Item = Relationship>,
+    ) -> Vec<SchemaChangeImpact> {
+        let mut impacts = self
+            .changes
+            .into_iter()
+            .map(|change| SchemaChangeImpact {
+                change,
+                invalidated_relationships: 0,
+            })
+            .collect::<Vec<_>>();
+        for relationship in relationships {
+            if new.validate_relationship(&relationship).is_ok() {
+                continue;
+            }
+            for impact in &mut impacts {
+                if impact.change.affects(&relationship) {
+                    impact.invalidated_relationships =
+                        impact.invalidated_relationships.saturating_add(1);
+                }
+            }
+        }
+        impacts
+    }
+}
+
+/// One schema change with the number of stored relationships it would invalidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaChangeImpact {
+    /// Schema change.
+    pub change: SchemaChange,
+    /// Stored relationships the candidate schema rejects because of this change; always zero for
+    /// safe changes.
+    pub invalidated_relationships: usize,
+}
+
+/// Schema diff measured against an engine's stored relationships.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaDiffReport {
+    /// Changes in diff order with their impact.
+    pub changes: Vec<SchemaChangeImpact>,
+    /// Token of the snapshot whose schema and relationships were compared.
+    pub token: ConsistencyToken,
+}
+
+impl SchemaDiffReport {
+    /// Returns the total number of stored relationships the candidate schema would reject.
+    #[must_use]
+    pub fn invalidated_relationships(&self) -> usize {
+        self.changes
+            .iter()
+            .map(|impact| impact.invalidated_relationships)
+            .fold(0, usize::saturating_add)
+    }
+}
+
+/// Lists the namespace, relation, subject-type, rewrite, and caveat changes from `old` to `new`.
+#[must_use]
+pub fn diff(old: &CompiledSchema, new: &CompiledSchema) -> SchemaDiff {
+    let mut changes = Vec::new();
+    let old_namespaces = old
+        .definitions()
+        .iter()
+        .map(|namespace| (namespace.name(), namespace))
+        .collect::<BTreeMap<_, _>>();
+    let new_namespaces = new
+        .definitions()
+        .iter()
+        .map(|namespace| (namespace.name(), namespace))
+        .collect::<BTreeMap<_, _>>();
+    for (name, namespace) in &old_namespaces {
+        let Some(replacement) = new_namespaces.get(name) else {
+            changes.push(SchemaChange::NamespaceRemoved {
+                namespace: (*name).clone(),
+            });
+            continue;
+        };
+        diff_relations(
+            name,
+            namespace.relations(),
+            replacement.relations(),
+            &mut changes,
+        );
+    }
+    for name in new_namespaces.keys() {
+        if !old_namespaces.contains_key(name) {
+            changes.push(SchemaChange::NamespaceAdded {
+                namespace: (*name).clone(),
+            });
+        }
+    }
+
+    let old_caveats = old
+        .caveats()
+        .iter()
+        .map(|caveat| (caveat.name(), caveat))
+        .collect::<BTreeMap<_, _>>();
+    let new_caveats = new
+        .caveats()
+        .iter()
+        .map(|caveat| (caveat.name(), caveat))
+        .collect::<BTreeMap<_, _>>();
+    for (name, caveat) in &old_caveats {
+        let caveat_name = (*name).clone();
+        match new_caveats.get(name) {
+            None => changes.push(SchemaChange::CaveatRemoved {
+                caveat: caveat_name,
+            }),
+            Some(replacement) if replacement.parameters() != caveat.parameters() => {
+                changes.push(SchemaChange::CaveatParametersChanged {
+                    caveat: caveat_name,
+                });
+            }
+            Some(replacement) if replacement.expression() != caveat.expression() => {
+                changes.push(SchemaChange::CaveatExpressionChanged {
+                    caveat: caveat_name,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    for name in new_caveats.keys() {
+        if !old_caveats.contains_key(name) {
+            changes.push(SchemaChange::CaveatAdded {
+                caveat: (*name).clone(),
+            });
+        }
+    }
+    SchemaDiff { changes }
+}
+
+fn diff_relations(
+    namespace: &ObjectType,
+    old: &[RelationDefinition],
+    new: &[RelationDefinition],
+    changes: &mut Vec<SchemaChange>,
+) {
+    let old_relations = old
+        .iter()
+        .map(|relation| (relation.name(), relation))
+        .collect::<BTreeMap<_, _>>();
+    let new_relations = new
+        .iter()
+        .map(|relation| (relation.name(), relation))
+        .collect::<BTreeMap<_, _>>();
+    for (name, relation) in &old_relations {
+        match new_relations.get(name) {
+            Some(replacement) => diff_relation(namespace, relation, replacement, changes),
+            None => changes.push(SchemaChange::RelationRemoved {
+                namespace: namespace.clone(),
+                relation: (*name).clone(),
+            }),
+        }
+    }
+    for name in new_relations.keys() {
+        if !old_relations.contains_key(name) {
+            changes.push(SchemaChange::RelationAdded {
+                namespace: namespace.clone(),
+                relation: (*name).clone(),
+            });
+        }
+    }
+}
+
+fn diff_relation(
+    namespace: &ObjectType,
+    old: &RelationDefinition,
+    new: &RelationDefinition,
+    changes: &mut Vec<SchemaChange>,
+) {
+    let relation = old.name();
+    if old.userset_rewrite() != new.userset_rewrite() {
+        changes.push(SchemaChange::RewriteChanged {
+            namespace: namespace.clone(),
+            relation: relation.clone(),
+            previous: old.userset_rewrite().cloned(),
+            current: new.userset_rewrite().cloned(),
+        });
+    }
+    match (old.allowed_subject_types(), new.allowed_subject_types()) {
+        (AllowedSubjectTypes::Unspecified, AllowedSubjectTypes::Unspecified) => {}
+        (AllowedSubjectTypes::Unspecified, AllowedSubjectTypes::Explicit(_)) => {
+            changes.push(SchemaChange::SubjectTypesRestricted {
+                namespace: namespace.clone(),
+                relation: relation.clone(),
+            });
+        }
+        (AllowedSubjectTypes::Explicit(_), AllowedSubjectTypes::Unspecified) => {
+            changes.push(SchemaChange::SubjectTypesUnrestricted {
+                namespace: namespace.clone(),
+                relation: relation.clone(),
+            });
+        }
+        (AllowedSubjectTypes::Explicit(previous), AllowedSubjectTypes::Explicit(current)) => {
+            let mut removed = previous
+                .iter()
+                .filter(|subject| !current.contains(subject))
+                .collect::<Vec<_>>();
+            removed.sort();
+            changes.extend(
+                removed
+                    .into_iter()
+                    .map(|subject| SchemaChange::SubjectDisallowed {
+                        namespace: namespace.clone(),
+                        relation: relation.clone(),
+                        subject: subject.clone(),
+                    }),
+            );
+            let mut added = current
+                .iter()
+                .filter(|subject| !previous.contains(subject))
+                .collect::<Vec<_>>();
+            added.sort();
+            changes.extend(
+                added
+                    .into_iter()
+                    .map(|subject| SchemaChange::SubjectAllowed {
+                        namespace: namespace.clone(),
+                        relation: relation.clone(),
+                        subject: subject.clone(),
+                    }),
+            );
+        }
+    }
+    let mut removed = old
+        .wildcard_subject_types()
+        .iter()
+        .filter(|subject_type| !new.allows_wildcard(subject_type))
+        .collect::<Vec<_>>();
+    removed.sort();
+    changes.extend(
+        removed
+            .into_iter()
+            .map(|subject_type| SchemaChange::WildcardDisallowed {
+                namespace: namespace.clone(),
+                relation: relation.clone(),
+                subject_type: subject_type.clone(),
+            }),
+    );
+    let mut added = new
+        .wildcard_subject_types()
+        .iter()
+        .filter(|subject_type| !old.allows_wildcard(subject_type))
+        .collect::<Vec<_>>();
+    added.sort();
+    changes.extend(
+        added
+            .into_iter()
+            .map(|subject_type| SchemaChange::WildcardAllowed {
+                namespace: namespace.clone(),
+                relation: relation.clone(),
+                subject_type: subject_type.clone(),
+            }),
+    );
+}
@@ -0,0 +1,437 @@
+//! Schema lint pass for rewrites that compile but can never behave as intended.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use super::{AllowedSubjectTypes, CompiledSchema, RelationDefinition, UsersetExpression};
+use crate::domain::{ObjectType, RelationName};
+
+/// How seriously a lint finding should be treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchemaLintSeverity {
+    /// Likely dead or redundant schema that does not change any decision.
+    Warning,
+    /// An expression that always denies, usually a typo or a missing relation.
+    Error,
+}
+
+/// What a lint finding reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaLintKind {
+    /// A relation without a rewrite that no rewrite, arrow, or userset subject reads, in a
+    /// namespace whose permissions are written as rewrites.
+    UnusedRelation,
+    /// An intersection operand that can never grant, so the whole intersection always denies.
+    EmptyIntersectionOperand {
+        /// Operand that can never grant.
+        operand: UsersetExpression,
+    },
+    /// An exclusion whose base can never grant, so the exclusion always denies.
+    EmptyExclusionBase {
+        /// Base expression that can never grant.
+        base: UsersetExpression,
+    },
+    /// An exclusion whose subtracted expression can never grant, so the exclusion is a no-op.
+    EmptyExclusionSubtrahend {
+        /// Subtracted expression that can never grant.
+        exclude: UsersetExpression,
+    },
+    /// An arrow whose tupleset relation accepts no subject the arrow can follow, such as a
+    /// permission or a wildcard-only relation, so it never grants.
+    UnreachableTupleToUserset {
+        /// Relation the arrow reads.
+        tupleset_relation: RelationName,
+        /// Relation the arrow would evaluate on each tupleset subject.
+        computed_userset_relation: RelationName,
+    },
+    /// Relations on one object that reference each other through computed usersets and have no
+    /// other way to grant.
+    DenyOnlyCycle {
+        /// Relations in the cycle, in name order.
+        cycle: Vec<RelationName>,
+    },
+}
+
+impl SchemaLintKind {
+    /// Returns how seriously this finding should be treated.
+    #[must_use]
+    pub const fn severity(&self) -> SchemaLintSeverity {
+        match self {
+            Self::UnusedRelation | Self::EmptyExclusionSubtrahend { .. } => {
+                SchemaLintSeverity::Warning
+            }
+            Self::EmptyIntersectionOperand { .. }
+            | Self::EmptyExclusionBase { .. }
+            | Self::UnreachableTupleToUserset { .. }
+            | Self::DenyOnlyCycle { .. } => SchemaLintSeverity::Error,
+        }
+    }
+}
+
+/// One lint finding on a relation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaLint {
+    /// Namespace holding the relation.
+    pub namespace: ObjectType,
+    /// Relation the finding is reported on; the first relation in name order for cycles.
+    pub relation: RelationName,
+    /// What was found.
+    pub kind: SchemaLintKind,
+}
+
+impl SchemaLint {
+    /// Returns how seriously this finding should be treated.
+    #[must_use]
+    pub const fn severity(&self) -> SchemaLintSeverity {
+        self.kind.severity()
+    }
+}
+
+/// Reports unused relations, operands that can never grant, arrows that can never be followed,
+/// and computed-userset cycles that can only deny.
+///
+/// Findings are ordered by namespace, then relation, then position in the rewrite. A relation
+/// "can grant" when some chain of `this`, computed usersets, and arrows reaches stored tuples;
+/// caveats and expirations are not considered.
+#[must_use]
+pub fn lint(schema: &CompiledSchema) -> Vec<SchemaLint> {
+    let granting = granting_relations(schema);
+    let referenced = referenced_relations(schema);
+    let namespaces = schema
+        .definitions()
+        .iter()
+        .map(|namespace| (namespace.name(), namespace))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut lints = Vec::new();
+    for (name, namespace) in namespaces {
+        let has_rewrites = namespace
+            .relations()
+            .iter()
+            .any(|relation| relation.userset_rewrite().is_some());
+        let relations = namespace
+            .relations()
+            .iter()
+            .map(|relation| (relation.name(), relation))
+            .collect::<BTreeMap<_, _>>();
+        let cycles = deny_only_cycles(name, &relations, &granting);
+        for (relation_name, relation) in &relations {
+            let mut push = |kind| {
+                lints.push(SchemaLint {
+                    namespace: name.clone(),
+                    relation: (*relation_name).clone(),
+                    kind,
+                });
+            };
+            match relation.userset_rewrite() {
+                None => {
+                    if has_rewrites && !referenced.contains(&(name, *relation_name)) {
+                        push(SchemaLintKind::UnusedRelation);
+                    }
+                }
+                Some(expression) => {
+                    let context = LintContext {
+                        schema,
+                        namespace: name,
+                        granting: &granting,
+                    };
+                    context.lint_expression(expression, &mut push);
+                }
+            }
+            if let Some(cycle) = cycles.get(*relation_name) {
+                push(SchemaLintKind::DenyOnlyCycle {
+                    cycle: cycle.clone(),
+                });
+            }
+        }
+    }
+    lints
+}
+
+type RelationKey<'a> = (&'a ObjectType, &'a RelationName);
+
+struct LintContext<'a> {
+    schema: &'a CompiledSchema,
+    namespace: &'a ObjectType,
+    granting: &'a HashSet<RelationKey<'a>>,
+}
+
+impl LintContext<'_> {
+    fn lint_expression(
+        &self,
+        expression: &UsersetExpression,
+        push: &mut impl FnMut(SchemaLintKind),
+    ) {
+        match expression {
+            UsersetExpression::This | UsersetExpression::ComputedUserset { .. } => {}
+            UsersetExpression::TupleToUserset {
+                tupleset_relation,
+                computed_userset_relation,
+            } => {
+                let mut targets = arrow_targets(
+                    self.schema,
+                    self.namespace,
+                    tupleset_relation,
+                    computed_userset_relation,
+                );
+                if targets.next().is_none() {
+                    push(SchemaLintKind::UnreachableTupleToUserset {
+                        tupleset_relation: tupleset_relation.clone(),
+                        computed_userset_relation: computed_userset_relation.clone(),
+                    });
+                }
+            }
+            UsersetExpression::Union(expressions) => {
+                for child in expressions {
+                    self.lint_expression(child, push);
+                }
+            }
+            UsersetExpression::Intersection(expressions) => {
+                for child in expressions {
+                    if !can_grant(self.schema, self.namespace, child, self.granting) {
+                        push(SchemaLintKind::EmptyIntersectionOperand {
+                            operand: child.clone(),
+                        });
+                    }
+                    self.lint_expression(child, push);
+                }
+            }
+            UsersetExpression::Exclusion { base, exclude } => {
+                if !can_grant(self.schema, self.namespace, base, self.granting) {
+                    push(SchemaLintKind::EmptyExclusionBase {
+                        base: base.as_ref().clone(),
+                    });
+                }
+                if !can_grant(self.schema, self.namespace, exclude, self.granting) {
+                    push(SchemaLintKind::EmptyExclusionSubtrahend {
+                        exclude: exclude.as_ref().clone(),
+                    });
+                }
+                self.lint_expression(base, push);
+                self.lint_expression(exclude, push);
+            }
+        }
+    }
+}
+
+/// Computes the relations that can grant anything, as the least fixpoint over all rewrites.
+fn granting_relations(schema: &CompiledSchema) -> HashSet<RelationKey<'_>> {
+    let mut granting = HashSet::new();
+    loop {
+        let mut changed = false;
+        for namespace in schema.definitions() {
+            for relation in namespace.relations() {
+                let key = (namespace.name(), relation.name());
+                if granting.contains(&key) {
+                    continue;
+                }
+                let grants = relation.userset_rewrite().is_none_or(|expression| {
+                    can_grant(schema, namespace.name(), expression, &granting)
+                });
+                if grants {
+                    granting.insert(key);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return granting;
+        }
+    }
+}
+
+fn can_grant(
+    schema: &CompiledSchema,
+    namespace: &ObjectType,
+    expression: &UsersetExpression,
+    granting: &HashSet<RelationKey<'_>>,
+) -> bool {
+    match expression {
+        UsersetExpression::This => true,
+        UsersetExpression::ComputedUserset { relation } => {
+            granting.contains(&(namespace, relation))
+        }
+        UsersetExpression::TupleToUserset {
+            tupleset_relation,
+            computed_userset_relation,
+        } => arrow_targets(
+            schema,
+            namespace,
+            tupleset_relation,
+            computed_userset_relation,
+        )
+        .any(|target| granting.contains(&(target, computed_userset_relation))),
+        UsersetExpression::Union(expressions) => expressions
+            .iter()
+            .any(|child| can_grant(schema, namespace, child, granting)),
+        UsersetExpression::Intersection(expressions) => expressions
+            .iter()
+            .all(|child| can_grant(schema, namespace, child, granting)),
+        UsersetExpression::Exclusion { base, .. } => can_grant(schema, namespace, base, granting),
+    }
+}
+
+/// Returns the namespaces an arrow may evaluate `computed_userset_relation` on.
+fn arrow_targets<'a>(
+    schema: &'a CompiledSchema,
+    namespace: &ObjectType,
+    tupleset_relation: &RelationName,
+    computed_userset_relation: &'a RelationName,
+) -> impl Iterator<Item = &'a ObjectType> {
+    let explicit = match schema
+        .resolver()
+        .relation(namespace, tupleset_relation)
+        .map(RelationDefinition::allowed_subject_types)
+    {
+        Ok(AllowedSubjectTypes::Explicit(subjects)) => Some(
+            subjects
+                .iter()
+                .map(|subject| subject.object_type().clone())
+                .collect::<HashSet<_>>(),
+        ),
+        Ok(AllowedSubjectTypes::Unspecified) | Err(_) => None,
+    };
+    schema
+        .definitions()
+        .iter()
+        .map(|definition| definition.name())
+        .filter(move |name| {
+            explicit
+                .as_ref()
+                .is_none_or(|subject_types| subject_types.contains(*name))
+                && schema
+                    .resolver()
+                    .relation(name, computed_userset_relation)
+                    .is_ok()
+        })
+}
+
+/// Collects relations read by some rewrite, arrow, or declared userset subject.
+fn referenced_relations(schema: &CompiledSchema) -> HashSet<RelationKey<'_>> {
+    let mut referenced = HashSet::new();
+    for namespace in schema.definitions() {
+        for relation in namespace.relations() {
+            if let AllowedSubjectTypes::Explicit(subjects) = relation.allowed_subject_types() {
+                referenced.extend(subjects.iter().filter_map(|subject| {
+                    subject
+                        .relation()
+                        .map(|relation| (subject.object_type(), relation))
+                }));
+            }
+            if let Some(expression) = relation.userset_rewrite() {
+                collect_references(schema, namespace.name(), expression, &mut referenced);
+            }
+        }
+    }
+    referenced
+}
+
+fn collect_references<'a>(
+    schema: &'a CompiledSchema,
+    namespace: &'a ObjectType,
+    expression: &'a UsersetExpression,
+    referenced: &mut HashSet<RelationKey<'a>>,
+) {
+    match expression {
+        UsersetExpression::This => {}
+        UsersetExpression::ComputedUserset { relation } => {
+            referenced.insert((namespace, relation));
+        }
+        UsersetExpression::TupleToUserset {
+            tupleset_relation,
+            computed_userset_relation,
+        } => {
+            referenced.insert((namespace, tupleset_relation));
+            referenced.extend(
+                arrow_targets(
+                    schema,
+                    namespace,
+                    tupleset_relation,
+                    computed_userset_relation,
+                )
+                .map(|target| (target, computed_userset_relation)),
+            );
+        }
+        UsersetExpression::Union(expressions) | UsersetExpression::Intersection(expressions) => {
+            for child in expressions {
+                collect_references(schema, namespace, child, referenced);
+            }
+        }
+        UsersetExpression::Exclusion { base, exclude } => {
+            collect_references(schema, namespace, base, referenced);
+            collect_references(schema, namespace, exclude, referenced);
+        }
+    }
+}
+
+/// Finds cycles of computed usersets among relations that cannot grant, keyed by the first
+/// relation of each cycle in name order.
+fn deny_only_cycles<'a>(
+    namespace: &'a ObjectType,
+    relations: &BTreeMap<&'a RelationName, &'a RelationDefinition>,
+    granting: &HashSet<RelationKey<'a>>,
+) -> BTreeMap<&'a RelationName, Vec<RelationName>> {
+    let edges = relations
+        .iter()
+        .filter(|(name, _)| !granting.contains(&(namespace, **name)))
+        .map(|(name, relation)| {
+            let mut targets = BTreeSet::new();
+            if let Some(expression) = relation.userset_rewrite() {
+                computed_targets(expression, &mut targets);
+            }
+            targets.retain(|target| {
+                relations.contains_key(*target) && !granting.contains(&(namespace, *target))
+            });
+            (*name, targets)
+        })
+        .collect::<BTreeMap<_, _>>();
+    let reach = edges
+        .keys()
+        .map(|start| {
+            let mut seen = BTreeSet::new();
+            let mut stack = edges[start].iter().copied().collect::<Vec<_>>();
+            while let Some(next) = stack.pop() {
+                if seen.insert(next) {
+                    stack.extend(edges[next].iter().copied());
+                }
+            }
+            (*start, seen)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let mut cycles = BTreeMap::new();
+    let mut reported = HashSet::new();
+    for (start, reachable) in &reach {
+        if !reachable.contains(start) || reported.contains(start) {
+            continue;
+        }
+        let cycle = reachable
+            .iter()
+            .filter(|other| reach[*other].contains(start))
+            .copied()
+            .collect::<BTreeSet<_>>();
+        reported.extend(cycle.iter().copied());
+        cycles.insert(*start, cycle.into_iter().cloned().collect());
+    }
+    cycles
+}
+
+fn computed_targets<'a>(
+    expression: &'a UsersetExpression,
+    targets: &mut BTreeSet<&'a RelationName>,
+) {
+    match expression {
+        UsersetExpression::This | UsersetExpression::TupleToUserset { .. } => {}
+        UsersetExpression::ComputedUserset { relation } => {
+            targets.insert(relation);
+        }
+        UsersetExpression::Union(expressions) | UsersetExpression::Intersection(expressions) => {
+            for child in expressions {
+                computed_targets(child, targets);
+            }
+        }
+        UsersetExpression::Exclusion { base, exclude } => {
+            computed_targets(base, targets);
+            computed_targets(exclude, targets);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Schema migrations that rewrite stored relationships in the same revision.
+
+use super::SchemaSource;
+use crate::domain::{ObjectRef, ObjectType, RelationName, Relationship, SubjectRef};
+
+/// One relationship transformation applied while migrating to a new schema.
+///
+/// Steps run in order against every stored relationship, before the result is validated against
+/// the migration's schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationStep {
+    /// Renames a relation, rewriting relationships stored on it and userset subjects such as
+    /// `group:eng#from` that reference it.
+    RenameRelation {
+        /// Namespace holding the relation.
+        namespace: ObjectType,
+        /// Current relation name.
+        from: RelationName,
+        /// New relation name.
+        to: RelationName,
+    },
+    /// Moves relationships stored on one relation to another relation, possibly in another
+    /// namespace, keeping object ids, subjects, caveats, and expirations.
+    ///
+    /// Userset subjects that reference the source relation are left unchanged.
+    MoveRelationships {
+        /// Namespace of the relationships to move.
+        from_namespace: ObjectType,
+        /// Relation of the relationships to move.
+        from_relation: RelationName,
+        /// Namespace the relationships move to.
+        to_namespace: ObjectType,
+        /// Relation the relationships move to.
+        to_relation: RelationName,
+    },
+    /// Deletes relationships stored on a relation and relationships whose userset subject
+    /// references it.
+    DropRelation {
+        /// Namespace holding the relation.
+        namespace: ObjectType,
+        /// Relation to drop.
+        relation: RelationName,
+    },
+}
+
+impl MigrationStep {
+    /// Returns the transformed relationship, or `None` when this step deletes it.
+    pub(crate) fn apply(&self, relationship: Relationship) -> Option<Relationship> {
+        match self {
+            Self::RenameRelation {
+                namespace,
+                from,
+                to,
+            } => {
+                let relation = if relationship.resource().object_type() == namespace
+                    && relationship.relation() == from
+                {
+                    to.clone()
+                } else {
+                    relationship.relation().clone()
+                };
+                let subject = match relationship.subject() {
+                    SubjectRef::Userset { object, relation }
+                        if object.object_type() == namespace && relation == from =>
+                    {
+                        SubjectRef::Userset {
+                            object: object.clone(),
+                            relation: to.clone(),
+                        }
+                    }
+                    subject => subject.clone(),
+                };
+                Some(rebuild(
+                    &relationship,
+                    relationship.resource().clone(),
+                    relation,
+                    subject,
+                ))
+            }
+            Self::MoveRelationships {
+                from_namespace,
+                from_relation,
+                to_namespace,
+                to_relation,
+            } => {
+                if relationship.resource().object_type() != from_namespace
+                    || relationship.relation() != from_relation
+                {
+                    return Some(relationship);
+                }
+                let resource = ObjectRef::new(
+                    to_namespace.clone(),
+                    relationship.resource().object_id().clone(),
+                );
+                Some(rebuild(
+                    &relationship,
+                    resource,
+                    to_relation.clone(),
+                    relationship.subject().clone(),
+                ))
+            }
+            Self::DropRelation {
+                namespace,
+                relation,
+            } => {
+                let on_relation = relationship.resource().object_type() == namespace
+                    && relationship.relation() == relation;
+                let via_subject = matches!(relationship.subject(),
+                    SubjectRef::Userset { object, relation: subject_relation }
+                        if object.object_type() == namespace && subject_relation == relation);
+                (!on_relation && !via_subject).then_some(relationship)
+            }
+        }
+    }
+}
+
+/// A replacement schema plus the relationship rewrites that make stored data fit it.
+#[derive(Debug, Clone)]
+pub struct SchemaMigration<'a> {
+    /// Complete replacement schema.
+    pub schema: SchemaSource<'a>,
+    /// Relationship transformations, applied in order.
+    pub steps: Vec<MigrationStep>,
+}
+
+impl<'a> SchemaMigration<'a> {
+    /// Creates a migration to `schema` with no relationship transformations.
+    #[must_use]
+    pub const fn new(schema: SchemaSource<'a>) -> Self {
+        Self {
+            schema,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Appends one transformation step.
+    #[must_use]
+    pub fn with_step(mut self, step: MigrationStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+}
+
+fn rebuild(
+    original: &Relationship,
+    resource: ObjectRef,
+    relation: RelationName,
+    subject: SubjectRef,
+) -> Relationship {
+    let mut relationship = Relationship::new(resource, relation, subject);
+    if let Some(caveat) = original.caveat() {
+        relationship = relationship.with_caveat(caveat.clone());
+    }
+    if let Some(expiration) = original.expiration() {
+        relationship = relationship.with_expiration(expiration);
+    }
+    relationship
+}
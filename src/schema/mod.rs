@@ -2,19 +2,33 @@
 
 use std::{
     collections::{HashMap, HashSet},
-    slice,
+    fmt, slice,
     sync::Arc,
 };
 
 use thiserror::Error;
 
 use crate::{
-    domain::{ObjectType, RelationName, Relationship, SubjectRef},
+    caveat::{CaveatDefinition, CaveatError},
+    diagnostic::{Diagnostic, DiagnosticError, Diagnostics, SourceSpan},
+    domain::{CaveatName, ObjectType, RelationName, Relationship, SubjectRef},
     error::ZanzibarError,
     model::{NamespaceConfig, UsersetExpression as LegacyUsersetExpression},
     parser::{self, LegacyNamespaceAst, LegacyRelationAst},
 };
 
+mod diff;
+mod lint;
+mod migration;
+
+pub use self::{
+    diff::{
+        SchemaChange, SchemaChangeImpact, SchemaChangeSeverity, SchemaDiff, SchemaDiffReport, diff,
+    },
+    lint::{SchemaLint, SchemaLintKind, SchemaLintSeverity, lint},
+    migration::{MigrationStep, SchemaMigration},
+};
+
 /// A source schema document.
 #[cfg_attr(
     feature = "serde",
@@ -27,6 +41,58 @@ pub struct SchemaSource<'a> {
     pub name: Option<&'a str>,
     /// Schema source text.
     pub text: &'a str,
+    /// Surface syntax of `text`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub syntax: SchemaSyntax,
+}
+
+impl<'a> SchemaSource<'a> {
+    /// Creates an unnamed source in the legacy `namespace` DSL.
+    #[must_use]
+    pub const fn legacy(text: &'a str) -> Self {
+        Self {
+            name: None,
+            text,
+            syntax: SchemaSyntax::Legacy,
+        }
+    }
+
+    /// Creates an unnamed source in the SpiceDB `definition` syntax.
+    #[must_use]
+    pub const fn spicedb(text: &'a str) -> Self {
+        Self {
+            name: None,
+            text,
+            syntax: SchemaSyntax::SpiceDb,
+        }
+    }
+
+    /// Returns this source with a diagnostic name.
+    #[must_use]
+    pub const fn with_name(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+}
+
+/// Schema language accepted by a [`SchemaSource`].
+///
+/// Both syntaxes compile into the same [`CompiledSchema`] IR.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SchemaSyntax {
+    /// `namespace doc { relation viewer { rewrite ... } }` DSL.
+    #[default]
+    Legacy,
+    /// SpiceDB subset: `definition doc { relation viewer: user; permission view = viewer }`.
+    ///
+    /// Permissions support `+`, `&`, `-`, `->`, and parentheses. Caveat and trait names after
+    /// `with` in relation types are rejected with [`SchemaError::UnsupportedSubjectCaveat`].
+    SpiceDb,
 }
 
 /// Errors produced while compiling or validating schemas.
@@ -105,12 +171,73 @@ pub enum SchemaError {
         /// Minimum operand count.
         min_operands: usize,
     },
+
+    /// A SpiceDB relation type requires a caveat or trait after `with`, which is not enforced yet.
+    #[error(
+        "relation '{namespace}.{relation}' requires '{caveat}' on subject '{subject}'; `with` is \
+         not supported"
+    )]
+    UnsupportedSubjectCaveat {
+        /// Namespace containing the relation.
+        namespace: String,
+        /// Relation declaring the subject.
+        relation: String,
+        /// Declared subject.
+        subject: String,
+        /// Caveat or trait names joined with ` and `.
+        caveat: String,
+    },
+
+    /// A relationship grants a wildcard subject the relation does not allow.
+    #[error("relation '{namespace}.{relation}' does not allow wildcard subject '{subject_type}:*'")]
+    WildcardNotAllowed {
+        /// Resource namespace.
+        namespace: String,
+        /// Resource relation.
+        relation: String,
+        /// Wildcard subject type.
+        subject_type: String,
+    },
+
+    /// A relationship subject is not among the subjects its typed relation declares.
+    #[error(
+        "relation '{namespace}.{relation}' does not allow subject '{subject}'; expected {allowed}"
+    )]
+    SubjectNotAllowed {
+        /// Resource namespace.
+        namespace: String,
+        /// Resource relation.
+        relation: String,
+        /// Rejected subject type, with its relation for userset subjects.
+        subject: String,
+        /// Declared subjects joined with ` | `, or `no subjects` for a permission.
+        allowed: String,
+    },
+
+    /// The schema has two caveat definitions with the same name.
+    #[error("duplicate caveat definition '{caveat}'")]
+    DuplicateCaveat {
+        /// Duplicate caveat name.
+        caveat: String,
+    },
+
+    /// A relationship or evaluation references a caveat that is not defined.
+    #[error("caveat '{caveat}' not found")]
+    UnknownCaveat {
+        /// Missing caveat name.
+        caveat: String,
+    },
+
+    /// A caveat definition or relationship-bound caveat context is invalid.
+    #[error(transparent)]
+    Caveat(#[from] CaveatError),
 }
 
 /// Immutable compiled schema.
 #[derive(Debug, Clone)]
 pub struct CompiledSchema {
     definitions: Arc<[NamespaceDefinition]>,
+    caveats: Arc<[CaveatDefinition]>,
     resolver: SchemaResolver,
 }
 
@@ -119,6 +246,7 @@ impl CompiledSchema {
         let resolver = SchemaResolver::new(Arc::clone(&definitions))?;
         Ok(Self {
             definitions,
+            caveats: Arc::from([]),
             resolver,
         })
     }
@@ -146,6 +274,40 @@ impl CompiledSchema {
         &self.definitions
     }
 
+    /// Returns this schema with its caveat definitions replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SchemaError::DuplicateCaveat`] when two caveats share a name.
+    pub fn with_caveats(
+        mut self,
+        caveats: impl IntoIterator<Item = CaveatDefinition>,
+    ) -> Result<Self, SchemaError> {
+        let caveats = caveats.into_iter().collect::<Vec<_>>();
+        let mut names = HashSet::with_capacity(caveats.len());
+        for caveat in &caveats {
+            if !names.insert(caveat.name()) {
+                return Err(SchemaError::DuplicateCaveat {
+                    caveat: caveat.name().to_string(),
+                });
+            }
+        }
+        self.caveats = Arc::from(caveats.into_boxed_slice());
+        Ok(self)
+    }
+
+    /// Returns all caveat definitions in source order.
+    #[must_use]
+    pub fn caveats(&self) -> &[CaveatDefinition] {
+        &self.caveats
+    }
+
+    /// Returns one caveat definition by name.
+    #[must_use]
+    pub fn caveat(&self, name: &CaveatName) -> Option<&CaveatDefinition> {
+        self.caveats.iter().find(|caveat| caveat.name() == name)
+    }
+
     /// Returns the schema resolver.
     #[must_use]
     pub fn resolver(&self) -> &SchemaResolver {
@@ -157,14 +319,65 @@ impl CompiledSchema {
     /// # Errors
     ///
     /// Returns [`SchemaError::RelationNotFound`] or [`SchemaError::NamespaceNotFound`] when the
-    /// relationship resource does not match this schema.
+    /// relationship resource does not match this schema, [`SchemaError::WildcardNotAllowed`] when
+    /// the relation does not accept its wildcard subject, [`SchemaError::UnknownCaveat`] when its
+    /// caveat is not defined, or [`SchemaError::Caveat`] when its bound caveat context does not
+    /// match the caveat parameters.
     pub fn validate_relationship(&self, relationship: &Relationship) -> Result<(), SchemaError> {
-        self.resolver.relation(
+        let relation_definition = self.resolver.relation(
             relationship.resource().object_type(),
             relationship.relation(),
         )?;
-        if let SubjectRef::Userset { object, relation } = relationship.subject() {
-            self.resolver.relation(object.object_type(), relation)?;
+        let subject = match relationship.subject() {
+            SubjectRef::Object(object) => {
+                Some(AllowedSubject::new(object.object_type().clone(), None))
+            }
+            SubjectRef::Userset { object, relation } => {
+                self.resolver.relation(object.object_type(), relation)?;
+                Some(AllowedSubject::new(
+                    object.object_type().clone(),
+                    Some(relation.clone()),
+                ))
+            }
+            SubjectRef::Wildcard(_) => None,
+        };
+        if let (Some(subject), AllowedSubjectTypes::Explicit(allowed)) =
+            (subject, relation_definition.allowed_subject_types())
+            && !allowed.contains(&subject)
+        {
+            return Err(SchemaError::SubjectNotAllowed {
+                namespace: relationship.resource().object_type().to_string(),
+                relation: relationship.relation().to_string(),
+                subject: subject.to_string(),
+                allowed: if allowed.is_empty() {
+                    "no subjects".to_string()
+                } else {
+                    allowed
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                },
+            });
+        }
+        match relationship.subject() {
+            SubjectRef::Wildcard(subject_type)
+                if !relation_definition.allows_wildcard(subject_type) =>
+            {
+                return Err(SchemaError::WildcardNotAllowed {
+                    namespace: relationship.resource().object_type().to_string(),
+                    relation: relationship.relation().to_string(),
+                    subject_type: subject_type.to_string(),
+                });
+            }
+            SubjectRef::Object(_) | SubjectRef::Userset { .. } | SubjectRef::Wildcard(_) => {}
+        }
+        if let Some(caveat) = relationship.caveat() {
+            self.caveat(caveat.name())
+                .ok_or_else(|| SchemaError::UnknownCaveat {
+                    caveat: caveat.name().to_string(),
+                })?
+                .validate_context(caveat.context())?;
         }
         Ok(())
     }
@@ -202,6 +415,7 @@ impl NamespaceDefinition {
 pub struct RelationDefinition {
     name: RelationName,
     allowed_subject_types: AllowedSubjectTypes,
+    wildcard_subject_types: Arc<[ObjectType]>,
     userset_rewrite: Option<UsersetExpression>,
     compiled_userset_rewrite: Option<CompiledUsersetExpression>,
 }
@@ -213,6 +427,7 @@ impl RelationDefinition {
         Self {
             name,
             allowed_subject_types: AllowedSubjectTypes::Unspecified,
+            wildcard_subject_types: Arc::from([]),
             userset_rewrite,
             compiled_userset_rewrite: None,
         }
@@ -225,12 +440,29 @@ impl RelationDefinition {
         allowed_subject_types: Arc<[ObjectType]>,
         userset_rewrite: Option<UsersetExpression>,
     ) -> Self {
-        Self {
-            name,
-            allowed_subject_types: AllowedSubjectTypes::Explicit(allowed_subject_types),
-            userset_rewrite,
-            compiled_userset_rewrite: None,
-        }
+        Self::new(name, userset_rewrite).with_allowed_subjects(
+            allowed_subject_types
+                .iter()
+                .map(|object_type| AllowedSubject::new(object_type.clone(), None))
+                .collect(),
+        )
+    }
+
+    /// Returns this relation accepting only the declared direct and userset subjects.
+    #[must_use]
+    pub fn with_allowed_subjects(mut self, allowed_subjects: Arc<[AllowedSubject]>) -> Self {
+        self.allowed_subject_types = AllowedSubjectTypes::Explicit(allowed_subjects);
+        self
+    }
+
+    /// Returns this relation allowing wildcard subjects such as `user:*` of the given types.
+    #[must_use]
+    pub fn with_wildcard_subject_types(
+        mut self,
+        wildcard_subject_types: Arc<[ObjectType]>,
+    ) -> Self {
+        self.wildcard_subject_types = wildcard_subject_types;
+        self
     }
 
     /// Returns the relation name.
@@ -245,6 +477,18 @@ impl RelationDefinition {
         &self.allowed_subject_types
     }
 
+    /// Returns subject types that may be granted this relation through a wildcard.
+    #[must_use]
+    pub fn wildcard_subject_types(&self) -> &[ObjectType] {
+        &self.wildcard_subject_types
+    }
+
+    /// Returns true when relationships may grant this relation to `subject_type:*`.
+    #[must_use]
+    pub fn allows_wildcard(&self, subject_type: &ObjectType) -> bool {
+        self.wildcard_subject_types.contains(subject_type)
+    }
+
     /// Returns the optional userset rewrite.
     #[must_use]
     pub fn userset_rewrite(&self) -> Option<&UsersetExpression> {
@@ -261,8 +505,47 @@ impl RelationDefinition {
 pub enum AllowedSubjectTypes {
     /// Legacy schema source did not declare allowed subjects.
     Unspecified,
-    /// The relation accepts userset subjects from these object types.
-    Explicit(Arc<[ObjectType]>),
+    /// The relation accepts only these subjects, plus any declared wildcard subjects.
+    Explicit(Arc<[AllowedSubject]>),
+}
+
+/// One declared subject of a typed relation, such as `user` or `group#member`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AllowedSubject {
+    object_type: ObjectType,
+    relation: Option<RelationName>,
+}
+
+impl AllowedSubject {
+    /// Creates a declared subject; `relation` is set for userset subjects.
+    #[must_use]
+    pub const fn new(object_type: ObjectType, relation: Option<RelationName>) -> Self {
+        Self {
+            object_type,
+            relation,
+        }
+    }
+
+    /// Returns the subject object type.
+    #[must_use]
+    pub fn object_type(&self) -> &ObjectType {
+        &self.object_type
+    }
+
+    /// Returns the subject relation for userset subjects.
+    #[must_use]
+    pub fn relation(&self) -> Option<&RelationName> {
+        self.relation.as_ref()
+    }
+}
+
+impl fmt::Display for AllowedSubject {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.relation {
+            Some(relation) => write!(formatter, "{}#{relation}", self.object_type),
+            None => write!(formatter, "{}", self.object_type),
+        }
+    }
 }
 
 /// Typed userset expression.
@@ -546,10 +829,122 @@ impl<'a> Iterator for SortedRelations<'a> {
 ///
 /// # Errors
 ///
-/// Returns [`ZanzibarError::ParseError`] for syntax errors, [`crate::domain::DomainError`] for
-/// invalid identifiers, or [`SchemaError`] for invalid schema references.
+/// Returns [`ZanzibarError::Diagnostics`] with every syntax, identifier, and reference error.
 pub fn compile_legacy_dsl(source: &str) -> Result<CompiledSchema, ZanzibarError> {
-    compile_legacy_ast(parser::parse_dsl_ast(source)?)
+    compile_source(SchemaSource::legacy(source))
+}
+
+/// Compiles schema source in either supported syntax into a typed schema.
+///
+/// Compilation does not stop at the first problem: every invalid identifier, duplicate
+/// definition, and unresolved reference is reported with its location in `source`.
+///
+/// # Errors
+///
+/// Returns [`ZanzibarError::Diagnostics`] with one [`Diagnostic`] per error, named after
+/// [`SchemaSource::name`]. A syntax error stops parsing, so it is always reported alone.
+pub fn compile_source(source: SchemaSource<'_>) -> Result<CompiledSchema, ZanzibarError> {
+    let locate = |span, error| Diagnostic::new(source.name, source.text, span, error);
+    let schema = parser::parse_source_collecting(source.text, source.syntax).map_err(|error| {
+        ZanzibarError::Diagnostics(Diagnostics {
+            diagnostics: vec![locate(error.span, DiagnosticError::Syntax(error.message))],
+        })
+    })?;
+    let mut located = schema
+        .errors
+        .into_iter()
+        .map(|(span, error)| (span, DiagnosticError::from(error)))
+        .collect::<Vec<_>>();
+
+    let mut spans = HashMap::new();
+    let mut definitions = Vec::with_capacity(schema.namespaces.len());
+    for namespace in schema.namespaces {
+        let object_type = match ObjectType::try_from(namespace.name.as_str()) {
+            Ok(object_type) => object_type,
+            Err(error) => {
+                located.push((namespace.span, error.into()));
+                continue;
+            }
+        };
+        if spans.contains_key(&(object_type.clone(), None)) {
+            located.push((
+                namespace.span,
+                SchemaError::DuplicateNamespace {
+                    namespace: namespace.name,
+                }
+                .into(),
+            ));
+            continue;
+        }
+        spans.insert((object_type.clone(), None), namespace.span);
+        let mut relations = Vec::with_capacity(namespace.relations.len());
+        for relation in namespace.relations {
+            let span = relation.span;
+            let definition = match compile_legacy_relation(relation) {
+                Ok(definition) => definition,
+                Err(error) => {
+                    located.push((span, error.into()));
+                    continue;
+                }
+            };
+            let key = (object_type.clone(), Some(definition.name().clone()));
+            if spans.contains_key(&key) {
+                located.push((
+                    span,
+                    SchemaError::DuplicateRelation {
+                        namespace: namespace.name.clone(),
+                        relation: definition.name().to_string(),
+                    }
+                    .into(),
+                ));
+                continue;
+            }
+            spans.insert(key, span);
+            relations.push(definition);
+        }
+        definitions.push(NamespaceDefinition::new(
+            object_type,
+            Arc::from(relations.into_boxed_slice()),
+        ));
+    }
+
+    let unresolved = CompiledSchema::new(Arc::from(definitions.as_slice()))?;
+    located.extend(reference_errors(&unresolved).into_iter().map(
+        |(namespace, relation, error)| {
+            let span = spans
+                .get(&(namespace.clone(), Some(relation.clone())))
+                .copied()
+                .unwrap_or_default();
+            (span, error.into())
+        },
+    ));
+
+    let mut caveat_names = HashSet::with_capacity(schema.caveats.len());
+    let mut caveats = Vec::with_capacity(schema.caveats.len());
+    for (caveat, span) in schema.caveats.into_iter().zip(schema.caveat_spans) {
+        if caveat_names.insert(caveat.name().clone()) {
+            caveats.push(caveat);
+        } else {
+            located.push((
+                span,
+                SchemaError::DuplicateCaveat {
+                    caveat: caveat.name().to_string(),
+                }
+                .into(),
+            ));
+        }
+    }
+
+    if !located.is_empty() {
+        located.sort_by_key(|(span, _)| span.start);
+        return Err(ZanzibarError::Diagnostics(Diagnostics {
+            diagnostics: located
+                .into_iter()
+                .map(|(span, error)| locate(span, error))
+                .collect(),
+        }));
+    }
+    Ok(CompiledSchema::from_definitions(definitions)?.with_caveats(caveats)?)
 }
 
 /// Compiles legacy namespace configs into a typed schema.
@@ -567,11 +962,15 @@ pub fn compile_legacy_configs(
         for relation in config.relations.into_values() {
             relations.push(LegacyRelationAst {
                 name: relation.name.0,
+                span: SourceSpan::default(),
                 rewrite: relation.userset_rewrite,
+                allowed_subject_types: relation.allowed_subject_types,
+                wildcard_subject_types: relation.wildcard_subject_types,
             });
         }
         namespaces.push(LegacyNamespaceAst {
             name: config.name,
+            span: SourceSpan::default(),
             relations,
         });
     }
@@ -599,13 +998,42 @@ fn compile_legacy_ast(
 fn compile_legacy_relation(
     relation: LegacyRelationAst,
 ) -> Result<RelationDefinition, ZanzibarError> {
-    Ok(RelationDefinition::new(
+    let mut wildcard_subject_types = relation
+        .wildcard_subject_types
+        .iter()
+        .map(|subject_type| ObjectType::try_from(subject_type.as_str()))
+        .collect::<Result<Vec<_>, _>>()?;
+    wildcard_subject_types.sort();
+    wildcard_subject_types.dedup();
+    let definition = RelationDefinition::new(
         RelationName::try_from(relation.name.as_str())?,
         relation
             .rewrite
             .map(compile_legacy_expression)
             .transpose()?,
-    ))
+    )
+    .with_wildcard_subject_types(Arc::from(wildcard_subject_types));
+    let Some(allowed_subject_types) = relation.allowed_subject_types else {
+        return Ok(definition);
+    };
+    let mut allowed_subjects = allowed_subject_types
+        .iter()
+        .map(|subject| {
+            let (object_type, relation) = match subject.split_once('#') {
+                Some((object_type, relation)) => {
+                    (object_type, Some(RelationName::try_from(relation)?))
+                }
+                None => (subject.as_str(), None),
+            };
+            Ok(AllowedSubject::new(
+                ObjectType::try_from(object_type)?,
+                relation,
+            ))
+        })
+        .collect::<Result<Vec<_>, ZanzibarError>>()?;
+    allowed_subjects.sort();
+    allowed_subjects.dedup();
+    Ok(definition.with_allowed_subjects(Arc::from(allowed_subjects)))
 }
 
 fn compile_legacy_expression(
@@ -660,6 +1088,7 @@ fn compile_resolved_definitions(
             relations.push(RelationDefinition {
                 name: relation.name.clone(),
                 allowed_subject_types: relation.allowed_subject_types.clone(),
+                wildcard_subject_types: Arc::clone(&relation.wildcard_subject_types),
                 userset_rewrite: relation.userset_rewrite.clone(),
                 compiled_userset_rewrite,
             });
@@ -729,14 +1158,69 @@ fn validate_references(compiled: &CompiledSchema) -> Result<(), SchemaError> {
                 });
             }
         }
+    }
+    match reference_errors(compiled).into_iter().next() {
+        Some((_, _, error)) => Err(error),
+        None => Ok(()),
+    }
+}
 
+/// Collects every unresolved reference with the namespace and relation that owns it.
+fn reference_errors(compiled: &CompiledSchema) -> Vec<(&ObjectType, &RelationName, SchemaError)> {
+    let mut located = Vec::new();
+    for namespace in compiled.definitions() {
         for relation in namespace.relations() {
+            let mut errors = Vec::new();
+            if let AllowedSubjectTypes::Explicit(subjects) = relation.allowed_subject_types() {
+                validate_allowed_subjects(
+                    compiled,
+                    namespace,
+                    relation.name(),
+                    subjects,
+                    &mut errors,
+                );
+            }
             if let Some(expression) = relation.userset_rewrite() {
-                validate_expression(compiled, namespace, relation.name(), expression)?;
+                validate_expression(
+                    compiled,
+                    namespace,
+                    relation.name(),
+                    expression,
+                    &mut errors,
+                );
             }
+            located.extend(
+                errors
+                    .into_iter()
+                    .map(|error| (namespace.name(), relation.name(), error)),
+            );
+        }
+    }
+    located
+}
+
+fn validate_allowed_subjects(
+    compiled: &CompiledSchema,
+    namespace: &NamespaceDefinition,
+    owner: &RelationName,
+    subjects: &[AllowedSubject],
+    errors: &mut Vec<SchemaError>,
+) {
+    for subject in subjects {
+        if let Some(relation) = subject.relation()
+            && compiled
+                .resolver()
+                .relation(subject.object_type(), relation)
+                .is_err()
+        {
+            errors.push(SchemaError::MissingRelationReference {
+                namespace: namespace.name().to_string(),
+                owner: owner.to_string(),
+                relation: "allowed subject",
+                missing: subject.to_string(),
+            });
         }
     }
-    Ok(())
 }
 
 fn validate_expression(
@@ -744,8 +1228,9 @@ fn validate_expression(
     namespace: &NamespaceDefinition,
     owner: &RelationName,
     expression: &UsersetExpression,
-) -> Result<(), SchemaError> {
-    match expression {
+    errors: &mut Vec<SchemaError>,
+) {
+    let result = match expression {
         UsersetExpression::This => Ok(()),
         UsersetExpression::ComputedUserset { relation } => ensure_relation_in_namespace(
             compiled,
@@ -758,14 +1243,14 @@ fn validate_expression(
         UsersetExpression::TupleToUserset {
             tupleset_relation,
             computed_userset_relation,
-        } => {
-            let tupleset_relation_definition = ensure_relation_in_namespace(
-                compiled,
-                namespace.name(),
-                owner,
-                "tuple-to-userset tupleset",
-                tupleset_relation,
-            )?;
+        } => ensure_relation_in_namespace(
+            compiled,
+            namespace.name(),
+            owner,
+            "tuple-to-userset tupleset",
+            tupleset_relation,
+        )
+        .and_then(|tupleset_relation_definition| {
             validate_tuple_to_userset_target(
                 compiled,
                 namespace,
@@ -773,25 +1258,27 @@ fn validate_expression(
                 tupleset_relation_definition,
                 computed_userset_relation,
             )
-        }
+        }),
         UsersetExpression::Union(expressions) => {
-            validate_operands(namespace, owner, "union", 1, expressions)?;
             for child in expressions {
-                validate_expression(compiled, namespace, owner, child)?;
+                validate_expression(compiled, namespace, owner, child, errors);
             }
-            Ok(())
+            validate_operands(namespace, owner, "union", 1, expressions)
         }
         UsersetExpression::Intersection(expressions) => {
-            validate_operands(namespace, owner, "intersection", 1, expressions)?;
             for child in expressions {
-                validate_expression(compiled, namespace, owner, child)?;
+                validate_expression(compiled, namespace, owner, child, errors);
             }
-            Ok(())
+            validate_operands(namespace, owner, "intersection", 1, expressions)
         }
         UsersetExpression::Exclusion { base, exclude } => {
-            validate_expression(compiled, namespace, owner, base)?;
-            validate_expression(compiled, namespace, owner, exclude)
+            validate_expression(compiled, namespace, owner, base, errors);
+            validate_expression(compiled, namespace, owner, exclude, errors);
+            Ok(())
         }
+    };
+    if let Err(error) = result {
+        errors.push(error);
     }
 }
 
@@ -840,8 +1327,8 @@ fn validate_tuple_to_userset_target(
     computed_userset_relation: &RelationName,
 ) -> Result<(), SchemaError> {
     match tupleset_relation.allowed_subject_types() {
-        AllowedSubjectTypes::Explicit(object_types) => {
-            for object_type in object_types.iter() {
+        AllowedSubjectTypes::Explicit(subjects) => {
+            for object_type in subjects.iter().map(AllowedSubject::object_type) {
                 if compiled
                     .resolver()
                     .relation(object_type, computed_userset_relation)
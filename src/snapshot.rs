@@ -7,6 +7,7 @@
 use std::{
     collections::{HashMap, HashSet},
     ffi::OsString,
+    fmt,
     fs::{self, File},
     io::{self, BufReader, BufWriter, Read, Write},
     num::{NonZeroU64, NonZeroUsize},
@@ -23,7 +24,7 @@ use crate::{
     model::NamespaceConfig,
     policy,
     relationship::{IndexedRelationshipStore, RelationshipStoreView, StoreError},
-    revision::{Revision, SchemaHash},
+    revision::{DatastoreId, Revision, SchemaHash},
     schema::{self, CompiledSchema},
 };
 
@@ -36,10 +37,28 @@ const HEADER_LEN_U32: u32 = 76;
 const DIRECTORY_ENTRY_LEN: usize = 28;
 const FOOTER_LEN: usize = 32;
 const REQUIRED_SECTION_COUNT: usize = 11;
-const REQUIRED_SECTION_COUNT_U32: u32 = 11;
+const MAX_SECTION_COUNT: usize = 14;
+const REQUIRED_SECTION_KINDS: [SectionKind; REQUIRED_SECTION_COUNT] = [
+    SectionKind::Schema,
+    SectionKind::SymbolBytes,
+    SectionKind::SymbolTable,
+    SectionKind::RelationshipRows,
+    SectionKind::IndexDirectory,
+    SectionKind::IndexKeys,
+    SectionKind::PostingRanges,
+    SectionKind::PostingRowIds,
+    SectionKind::SymbolHashes,
+    SectionKind::SymbolLookup,
+    SectionKind::Footer,
+];
 const MAX_SCHEMA_BYTES: usize = 4 * 1024 * 1024;
 const DEFAULT_MAX_FILE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
 const DEFAULT_ZSTD_LEVEL: i32 = 3;
+const DATASTORE_ID_LEN: usize = 16;
+const ZSTD_FRAME_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Footer digest of an uncompressed snapshot payload, identifying the artifact's content.
+pub(crate) type SnapshotContentHash = [u8; FOOTER_LEN];
 
 /// Options used when saving a compact snapshot artifact.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -238,6 +257,8 @@ pub struct SnapshotLoadOptions {
     pub max_file_bytes: NonZeroU64,
     /// Minimum index capability required by the caller.
     pub required_index_profile: IndexProfile,
+    /// Whether the loaded engine keeps the saved datastore identity or starts a new one.
+    pub identity: SnapshotIdentityMode,
 }
 
 impl Default for SnapshotLoadOptions {
@@ -249,6 +270,7 @@ impl Default for SnapshotLoadOptions {
             integrity: SnapshotIntegrityMode::Checksum,
             max_file_bytes: non_zero_u64(DEFAULT_MAX_FILE_BYTES),
             required_index_profile: IndexProfile::Full,
+            identity: SnapshotIdentityMode::Restore,
         }
     }
 }
@@ -270,6 +292,20 @@ impl SnapshotLoadOptions {
     }
 }
 
+/// Datastore identity handling when loading a snapshot artifact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotIdentityMode {
+    /// Keep the datastore id recorded by the saving engine, so its consistency tokens remain valid.
+    ///
+    /// Artifacts written before the identity section existed load with a new id.
+    Restore,
+    /// Start a new datastore id, rejecting tokens issued by the saving engine.
+    ///
+    /// Use this when the loaded engine will accept writes independently of the saving engine, so
+    /// the two cannot issue conflicting tokens for the same revision.
+    Fork,
+}
+
 /// Runtime index profile for a loaded snapshot.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SnapshotLoadProfile {
@@ -376,6 +412,22 @@ pub enum SnapshotIoError {
         #[source]
         source: DomainError,
     },
+
+    /// A delta artifact does not continue the snapshot chain it was applied to.
+    #[error("snapshot delta does not apply: {reason}")]
+    DeltaMismatch {
+        /// Static mismatch reason.
+        reason: &'static str,
+    },
+
+    /// A reloaded artifact from the same datastore is older than the running engine.
+    #[error("snapshot revision {snapshot} is older than engine revision {current}")]
+    StaleRevision {
+        /// Revision stored in the artifact.
+        snapshot: Revision,
+        /// Latest revision published by the engine.
+        current: Revision,
+    },
 }
 
 impl From<io::Error> for SnapshotIoError {
@@ -396,28 +448,160 @@ impl From<DomainError> for SnapshotIoError {
     }
 }
 
+/// Header fields, section sizes, and verification results for one snapshot artifact.
+///
+/// Produced by [`inspect`] without building an engine.
+#[derive(Debug)]
+pub struct SnapshotInspection {
+    /// Size of the file on disk.
+    pub file_bytes: u64,
+    /// Compression detected from the file's leading bytes.
+    pub compression: SnapshotCompression,
+    /// Size of the `.szsnap` payload, after decompression for zstd files.
+    pub payload_bytes: u64,
+    /// Artifact format version.
+    pub format_version: u16,
+    /// Lookup indexes the artifact carries.
+    pub index_profile: IndexProfile,
+    /// Revision the artifact was saved at.
+    pub revision: Revision,
+    /// Hash of the schema recorded in the header.
+    pub schema_hash: SchemaHash,
+    /// Datastore id of the saving engine, when present and well formed.
+    pub datastore_id: Option<DatastoreId>,
+    /// Interned identifiers recorded in the header.
+    pub symbol_count: u32,
+    /// Relationship rows recorded in the header.
+    pub relationship_count: u32,
+    /// Sections in file order.
+    pub sections: Vec<SnapshotSectionInfo>,
+    /// Verification steps, in the order they ran.
+    pub checks: Vec<SnapshotCheck>,
+}
+
+impl SnapshotInspection {
+    /// Returns the section of `kind`, if the artifact has one.
+    #[must_use]
+    pub fn section(&self, kind: SectionKind) -> Option<&SnapshotSectionInfo> {
+        self.sections.iter().find(|section| section.kind == kind)
+    }
+
+    /// Returns true when every verification step passed.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| matches!(check.outcome, SnapshotCheckOutcome::Passed))
+    }
+
+    /// Returns the verification steps that did not pass.
+    pub fn failures(&self) -> impl Iterator<Item = &SnapshotCheck> {
+        self.checks
+            .iter()
+            .filter(|check| !matches!(check.outcome, SnapshotCheckOutcome::Passed))
+    }
+}
+
+/// Location and size of one snapshot section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotSectionInfo {
+    /// Section identifier.
+    pub kind: SectionKind,
+    /// Byte offset in the payload.
+    pub offset: u64,
+    /// Section length in bytes.
+    pub bytes: u64,
+    /// Row count from the section directory.
+    pub row_count: u64,
+}
+
+/// Verification step run by [`inspect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnapshotCheckKind {
+    /// The footer checksum matches every preceding byte.
+    Checksum,
+    /// The schema section parses, compiles, and matches the header hash.
+    Schema,
+    /// Symbols, rows, and indexes decode and satisfy every semantic invariant a full load checks.
+    Relationships,
+    /// Every relationship caveat names a caveat the schema defines.
+    Caveats,
+    /// The datastore identity section, when present, is well formed.
+    DatastoreIdentity,
+}
+
+/// Outcome of one verification step.
+#[derive(Debug)]
+pub enum SnapshotCheckOutcome {
+    /// The step passed.
+    Passed,
+    /// The step failed; loading the artifact would fail with this error.
+    Failed(SnapshotIoError),
+    /// The step depends on one that failed and did not run.
+    Skipped,
+}
+
+/// One verification step and its outcome.
+#[derive(Debug)]
+pub struct SnapshotCheck {
+    /// Step that ran.
+    pub kind: SnapshotCheckKind,
+    /// What happened.
+    pub outcome: SnapshotCheckOutcome,
+}
+
+impl SnapshotCheck {
+    fn new(kind: SnapshotCheckKind, result: Result<(), SnapshotIoError>) -> Self {
+        Self {
+            kind,
+            outcome: result.map_or_else(SnapshotCheckOutcome::Failed, |()| {
+                SnapshotCheckOutcome::Passed
+            }),
+        }
+    }
+}
+
 pub(crate) struct LoadedSnapshot {
     pub(crate) configs: HashMap<String, NamespaceConfig>,
     pub(crate) schema: CompiledSchema,
     pub(crate) relationships: Arc<RelationshipStoreView>,
     pub(crate) revision: Revision,
     pub(crate) schema_hash: SchemaHash,
+    pub(crate) datastore_id: Option<DatastoreId>,
+    pub(crate) content_hash: SnapshotContentHash,
 }
 
 /// Stable snapshot section identifiers.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
-pub(crate) enum SectionKind {
+pub enum SectionKind {
+    /// Canonical schema text.
     Schema = 1,
+    /// Concatenated identifier bytes.
     SymbolBytes = 2,
+    /// Offsets and lengths into the symbol bytes.
     SymbolTable = 3,
+    /// Relationship rows as symbol ids.
     RelationshipRows = 4,
+    /// Directory of the serialized lookup indexes.
     IndexDirectory = 5,
+    /// Sorted index keys.
     IndexKeys = 6,
+    /// Posting-list ranges for each index key.
     PostingRanges = 7,
+    /// Posting-list row ids.
     PostingRowIds = 8,
+    /// Symbol hashes used to rebuild the symbol lookup.
     SymbolHashes = 9,
+    /// Symbol lookup table.
     SymbolLookup = 10,
+    /// Checksum of every preceding byte.
     Footer = 11,
+    /// Caveat names and contexts attached to relationship rows.
+    RelationshipCaveats = 12,
+    /// Relationship expiration instants.
+    RelationshipExpirations = 13,
+    /// Datastore id of the saving engine.
+    DatastoreIdentity = 14,
 }
 
 impl SectionKind {
@@ -434,6 +618,9 @@ impl SectionKind {
             9 => Ok(Self::SymbolHashes),
             10 => Ok(Self::SymbolLookup),
             11 => Ok(Self::Footer),
+            12 => Ok(Self::RelationshipCaveats),
+            13 => Ok(Self::RelationshipExpirations),
+            14 => Ok(Self::DatastoreIdentity),
             _ => Err(SnapshotIoError::Format {
                 reason: "unknown snapshot section kind",
             }),
@@ -443,6 +630,33 @@ impl SectionKind {
     const fn raw(self) -> u16 {
         self as u16
     }
+
+    /// Returns the section's snake-case name.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Schema => "schema",
+            Self::SymbolBytes => "symbol_bytes",
+            Self::SymbolTable => "symbol_table",
+            Self::RelationshipRows => "relationship_rows",
+            Self::IndexDirectory => "index_directory",
+            Self::IndexKeys => "index_keys",
+            Self::PostingRanges => "posting_ranges",
+            Self::PostingRowIds => "posting_row_ids",
+            Self::SymbolHashes => "symbol_hashes",
+            Self::SymbolLookup => "symbol_lookup",
+            Self::Footer => "footer",
+            Self::RelationshipCaveats => "relationship_caveats",
+            Self::RelationshipExpirations => "relationship_expirations",
+            Self::DatastoreIdentity => "datastore_identity",
+        }
+    }
+}
+
+impl fmt::Display for SectionKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -457,6 +671,8 @@ pub(crate) struct SnapshotHeader {
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct SnapshotSection<'a> {
+    kind: SectionKind,
+    offset: u64,
     bytes: &'a [u8],
     flags: u16,
     row_count: u64,
@@ -488,7 +704,7 @@ pub(crate) struct SnapshotReader<'a> {
     sections: SnapshotSections<'a>,
 }
 
-type SnapshotSections<'a> = [Option<SnapshotSection<'a>>; REQUIRED_SECTION_COUNT];
+type SnapshotSections<'a> = [Option<SnapshotSection<'a>>; MAX_SECTION_COUNT];
 
 impl<'a> SnapshotReader<'a> {
     /// Parses and validates the outer snapshot envelope.
@@ -500,7 +716,7 @@ impl<'a> SnapshotReader<'a> {
         let header = parse_header(bytes)?;
         let directory_start = checked_usize_from_u32(HEADER_LEN_U32)?;
         let section_count = parse_section_count(bytes)?;
-        if section_count != REQUIRED_SECTION_COUNT {
+        if !(REQUIRED_SECTION_COUNT..=MAX_SECTION_COUNT).contains(&section_count) {
             return Err(SnapshotIoError::Format {
                 reason: "unexpected snapshot section count",
             });
@@ -544,6 +760,8 @@ impl<'a> SnapshotReader<'a> {
             })?;
             if let Some(section) = sections.get_mut(slot) {
                 *section = Some(SnapshotSection {
+                    kind,
+                    offset,
                     bytes: section_bytes,
                     flags,
                     row_count,
@@ -581,10 +799,32 @@ impl<'a> SnapshotReader<'a> {
                 reason: "missing required snapshot section",
             })
     }
+
+    /// Returns an optional section when the snapshot contains it.
+    pub(crate) fn optional_section(&self, kind: SectionKind) -> Option<SnapshotSection<'a>> {
+        self.sections.get(section_slot(kind)).copied().flatten()
+    }
+
+    /// Returns the footer digest, which identifies the payload even when the checksum was not
+    /// verified.
+    pub(crate) fn content_hash(&self) -> Result<SnapshotContentHash, SnapshotIoError> {
+        SnapshotContentHash::try_from(self.section(SectionKind::Footer)?.bytes()).map_err(|_| {
+            SnapshotIoError::Format {
+                reason: "footer length is invalid",
+            }
+        })
+    }
+
+    /// Returns every present section in file order.
+    fn sections_in_file_order(&self) -> Vec<SnapshotSection<'a>> {
+        let mut sections = self.sections.iter().flatten().copied().collect::<Vec<_>>();
+        sections.sort_by_key(|section| section.offset);
+        sections
+    }
 }
 
 const fn empty_sections<'a>() -> SnapshotSections<'a> {
-    [None; REQUIRED_SECTION_COUNT]
+    [None; MAX_SECTION_COUNT]
 }
 
 const fn section_slot(kind: SectionKind) -> usize {
@@ -665,6 +905,7 @@ struct SectionDirectoryEntry {
 pub(crate) fn save_snapshot_file(
     path: &Path,
     snapshot: &crate::revision::PublishedSnapshot,
+    datastore_id: DatastoreId,
     options: SnapshotSaveOptions,
 ) -> Result<(), SnapshotIoError> {
     if !options.include_indexes {
@@ -675,13 +916,19 @@ pub(crate) fn save_snapshot_file(
     validate_compression_options(options)?;
 
     let mut writer = SnapshotSectionWriter::default();
-    let schema_source = policy::canonical_schema_source(snapshot.configs());
+    let schema_source =
+        policy::canonical_schema_source(snapshot.configs(), snapshot.schema().caveats());
     if schema_source.len() > MAX_SCHEMA_BYTES {
         return Err(SnapshotIoError::LimitExceeded {
             component: "schema section",
         });
     }
     writer.add_section(SectionKind::Schema, schema_source.into_bytes(), 1)?;
+    writer.add_section(
+        SectionKind::DatastoreIdentity,
+        datastore_id.as_bytes().to_vec(),
+        1,
+    )?;
     snapshot.relationships().encode_snapshot_sections(
         &mut writer,
         options.index_profile,
@@ -721,6 +968,95 @@ pub fn load_snapshot_phase_timings(
     Ok(timings)
 }
 
+/// Reads a raw or zstd snapshot artifact and verifies it as a full load would, without building
+/// an engine.
+///
+/// Every semantic check runs even after an earlier one fails, so the report lists all problems a
+/// load would hit one at a time. Files over the default 2 GiB load cap are rejected.
+///
+/// # Errors
+///
+/// Returns [`SnapshotIoError`] when the file cannot be read or decompressed, or its header or
+/// section directory is malformed, since then no section can be located.
+pub fn inspect(path: impl AsRef<Path>) -> Result<SnapshotInspection, SnapshotIoError> {
+    let max_file_bytes = non_zero_u64(DEFAULT_MAX_FILE_BYTES);
+    let file = read_capped_file(path.as_ref(), max_file_bytes)?;
+    let file_bytes = checked_u64_from_usize(file.len())?;
+    let compression = compression_of(&file);
+    let bytes = decode_payload_bounded(file, compression, max_file_bytes)?;
+    let reader = SnapshotReader::parse(&bytes, SnapshotIntegrityMode::External, None)?;
+    let header = reader.header();
+
+    let mut checks = vec![SnapshotCheck::new(
+        SnapshotCheckKind::Checksum,
+        validate_footer(&bytes, &reader.sections, SnapshotIntegrityMode::Checksum),
+    )];
+    let schema = compile_snapshot_schema(&reader).map(|(_, schema, _)| schema);
+    let relationships = IndexedRelationshipStore::decode_snapshot_sections(
+        &reader,
+        SnapshotLoadProfile::FastLoad,
+        SnapshotValidationMode::Full,
+    )
+    .map(|store| RelationshipStoreView::from_checkpoint(Arc::new(store)));
+    let caveats = match (&schema, &relationships) {
+        (Ok(schema), Ok(relationships)) => Some(relationships.validate_caveats(schema).map_err(
+            |source| SnapshotIoError::Schema {
+                source: source.into(),
+            },
+        )),
+        _ => None,
+    };
+    checks.push(SnapshotCheck::new(
+        SnapshotCheckKind::Schema,
+        schema.map(drop),
+    ));
+    checks.push(SnapshotCheck::new(
+        SnapshotCheckKind::Relationships,
+        relationships.map(drop),
+    ));
+    checks.push(match caveats {
+        Some(result) => SnapshotCheck::new(SnapshotCheckKind::Caveats, result),
+        None => SnapshotCheck {
+            kind: SnapshotCheckKind::Caveats,
+            outcome: SnapshotCheckOutcome::Skipped,
+        },
+    });
+    let (datastore_id, identity) = match decode_datastore_id(&reader) {
+        Ok(datastore_id) => (datastore_id, Ok(())),
+        Err(error) => (None, Err(error)),
+    };
+    checks.push(SnapshotCheck::new(
+        SnapshotCheckKind::DatastoreIdentity,
+        identity,
+    ));
+
+    Ok(SnapshotInspection {
+        file_bytes,
+        compression,
+        payload_bytes: checked_u64_from_usize(bytes.len())?,
+        format_version: header.format_version.raw(),
+        index_profile: header.index_profile,
+        revision: header.created_revision,
+        schema_hash: header.schema_hash,
+        datastore_id,
+        symbol_count: header.symbol_count,
+        relationship_count: header.relationship_count,
+        sections: reader
+            .sections_in_file_order()
+            .into_iter()
+            .map(|section| {
+                Ok(SnapshotSectionInfo {
+                    kind: section.kind,
+                    offset: section.offset,
+                    bytes: checked_u64_from_usize(section.bytes.len())?,
+                    row_count: section.row_count,
+                })
+            })
+            .collect::<Result<_, SnapshotIoError>>()?,
+        checks,
+    })
+}
+
 fn load_snapshot_file_inner(
     path: &Path,
     options: SnapshotLoadOptions,
@@ -744,6 +1080,13 @@ fn load_snapshot_file_inner(
         options.validation,
         &mut timings,
     )?;
+    if options.validation == SnapshotValidationMode::Full {
+        relationships
+            .validate_caveats(&schema)
+            .map_err(|source| SnapshotIoError::Schema {
+                source: source.into(),
+            })?;
+    }
     let phase_start = Instant::now();
     let configs = configs_vec
         .into_iter()
@@ -755,6 +1098,8 @@ fn load_snapshot_file_inner(
         relationships,
         revision: reader.header().created_revision,
         schema_hash,
+        datastore_id: decode_datastore_id(&reader)?,
+        content_hash: reader.content_hash()?,
     };
     record_phase(
         &mut timings,
@@ -766,6 +1111,20 @@ fn load_snapshot_file_inner(
     Ok(loaded)
 }
 
+fn decode_datastore_id(
+    reader: &SnapshotReader<'_>,
+) -> Result<Option<DatastoreId>, SnapshotIoError> {
+    let Some(section) = reader.optional_section(SectionKind::DatastoreIdentity) else {
+        return Ok(None);
+    };
+    let bytes = <[u8; DATASTORE_ID_LEN]>::try_from(section.bytes()).map_err(|_| {
+        SnapshotIoError::Format {
+            reason: "datastore identity section has an invalid length",
+        }
+    })?;
+    Ok(Some(DatastoreId::from_bytes(bytes)))
+}
+
 fn validate_load_options(options: SnapshotLoadOptions) -> Result<(), SnapshotIoError> {
     if options.validation == SnapshotValidationMode::TrustedFastLoad
         && options.profile != SnapshotLoadProfile::FastLoad
@@ -875,10 +1234,7 @@ fn compile_snapshot_schema(
         std::str::from_utf8(schema_section.bytes()).map_err(|_| SnapshotIoError::Format {
             reason: "schema section is not valid utf-8",
         })?;
-    let configs_vec = crate::parser::parse_dsl(schema_source)
-        .map_err(|source| SnapshotIoError::Schema { source })?;
-    let schema = schema::compile_legacy_configs(configs_vec.clone())
-        .map_err(|source| SnapshotIoError::Schema { source })?;
+    let (configs_vec, schema) = compile_schema_source(schema_source)?;
     let schema_hash = SchemaHash::for_schema(&schema);
     if schema_hash != reader.header().schema_hash {
         return Err(SnapshotIoError::Format {
@@ -888,6 +1244,17 @@ fn compile_snapshot_schema(
     Ok((configs_vec, schema, schema_hash))
 }
 
+/// Parses and compiles canonical schema text stored in an artifact.
+pub(crate) fn compile_schema_source(
+    schema_source: &str,
+) -> Result<(Vec<NamespaceConfig>, CompiledSchema), SnapshotIoError> {
+    let configs_vec = crate::parser::parse_dsl(schema_source)
+        .map_err(|source| SnapshotIoError::Schema { source })?;
+    let schema = schema::compile_legacy_dsl(schema_source)
+        .map_err(|source| SnapshotIoError::Schema { source })?;
+    Ok((configs_vec, schema))
+}
+
 fn decode_relationships_with_optional_timings(
     reader: &SnapshotReader<'_>,
     profile: SnapshotLoadProfile,
@@ -921,7 +1288,9 @@ fn record_phase(
     }
 }
 
-fn validate_compression_options(options: SnapshotSaveOptions) -> Result<(), SnapshotIoError> {
+pub(crate) fn validate_compression_options(
+    options: SnapshotSaveOptions,
+) -> Result<(), SnapshotIoError> {
     if options.compression == SnapshotCompression::Zstd
         && !zstd::compression_level_range().contains(&options.zstd_level)
     {
@@ -932,7 +1301,7 @@ fn validate_compression_options(options: SnapshotSaveOptions) -> Result<(), Snap
     Ok(())
 }
 
-fn encode_payload(
+pub(crate) fn encode_payload(
     bytes: Vec<u8>,
     options: SnapshotSaveOptions,
 ) -> Result<Vec<u8>, SnapshotIoError> {
@@ -948,12 +1317,38 @@ fn decode_payload(
     bytes: Vec<u8>,
     options: SnapshotLoadOptions,
 ) -> Result<Vec<u8>, SnapshotIoError> {
-    match options.compression {
+    decode_payload_bounded(bytes, options.compression, options.max_file_bytes)
+}
+
+pub(crate) fn decode_payload_bounded(
+    bytes: Vec<u8>,
+    compression: SnapshotCompression,
+    max_file_bytes: NonZeroU64,
+) -> Result<Vec<u8>, SnapshotIoError> {
+    match compression {
         SnapshotCompression::None => Ok(bytes),
-        SnapshotCompression::Zstd => decode_zstd_bounded(bytes.as_slice(), options.max_file_bytes),
+        SnapshotCompression::Zstd => decode_zstd_bounded(bytes.as_slice(), max_file_bytes),
+    }
+}
+
+/// Returns the compression of an artifact from its leading bytes.
+pub(crate) fn compression_of(bytes: &[u8]) -> SnapshotCompression {
+    if bytes.starts_with(&ZSTD_FRAME_MAGIC) {
+        SnapshotCompression::Zstd
+    } else {
+        SnapshotCompression::None
     }
 }
 
+/// Reads just enough of the file at `path` to tell whether it is zstd-wrapped.
+pub(crate) fn detect_compression(path: &Path) -> Result<SnapshotCompression, SnapshotIoError> {
+    let mut magic = Vec::with_capacity(ZSTD_FRAME_MAGIC.len());
+    File::open(path)?
+        .take(ZSTD_FRAME_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    Ok(compression_of(&magic))
+}
+
 fn decode_zstd_bounded<R>(reader: R, max_file_bytes: NonZeroU64) -> Result<Vec<u8>, SnapshotIoError>
 where
     R: Read,
@@ -976,7 +1371,10 @@ where
     Ok(output)
 }
 
-fn read_capped_file(path: &Path, max_file_bytes: NonZeroU64) -> Result<Vec<u8>, SnapshotIoError> {
+pub(crate) fn read_capped_file(
+    path: &Path,
+    max_file_bytes: NonZeroU64,
+) -> Result<Vec<u8>, SnapshotIoError> {
     let (file, metadata_len) = open_capped_file(path, max_file_bytes)?;
     let mut bytes = Vec::with_capacity(checked_usize_from_u64(metadata_len)?);
     file.take(metadata_len).read_to_end(&mut bytes)?;
@@ -1039,6 +1437,7 @@ fn write_uncompressed_snapshot_file_inner(
     let mut header = Vec::with_capacity(HEADER_LEN);
     write_header(
         &mut header,
+        checked_u32_from_usize(directory.len())?,
         snapshot.schema_hash(),
         relationship_count,
         symbol_count,
@@ -1090,7 +1489,10 @@ fn snapshot_sections_with_footer(
         bytes: vec![0; FOOTER_LEN],
         row_count: 1,
     });
-    if sections.len() != REQUIRED_SECTION_COUNT {
+    let has_required_sections = REQUIRED_SECTION_KINDS
+        .iter()
+        .all(|kind| sections.iter().any(|section| section.kind == *kind));
+    if !has_required_sections || sections.len() > MAX_SECTION_COUNT {
         return Err(SnapshotIoError::Format {
             reason: "snapshot writer did not produce all required sections",
         });
@@ -1145,7 +1547,7 @@ fn write_hashed(
     Ok(())
 }
 
-fn snapshot_tmp_path(path: &Path, revision: Revision) -> PathBuf {
+pub(crate) fn snapshot_tmp_path(path: &Path, revision: Revision) -> PathBuf {
     let mut file_name = path
         .file_name()
         .map_or_else(|| OsString::from("snapshot"), OsString::from);
@@ -1168,6 +1570,7 @@ fn encode_file(
     let mut bytes = Vec::with_capacity(file_len_usize);
     write_header(
         &mut bytes,
+        checked_u32_from_usize(directory.len())?,
         snapshot.schema_hash(),
         relationship_count,
         symbol_count,
@@ -1206,8 +1609,13 @@ fn encode_file(
     Ok(bytes)
 }
 
+#[allow(
+    clippy::too_many_arguments,
+    reason = "header fields are written in fixed on-disk order"
+)]
 fn write_header(
     target: &mut Vec<u8>,
+    section_count: u32,
     schema_hash: SchemaHash,
     relationship_count: u32,
     symbol_count: u32,
@@ -1219,7 +1627,7 @@ fn write_header(
     target.extend_from_slice(&CURRENT_FORMAT_VERSION.raw().to_le_bytes());
     target.extend_from_slice(&index_profile.flag_bits().to_le_bytes());
     target.extend_from_slice(&HEADER_LEN_U32.to_le_bytes());
-    target.extend_from_slice(&REQUIRED_SECTION_COUNT_U32.to_le_bytes());
+    target.extend_from_slice(&section_count.to_le_bytes());
     target.extend_from_slice(&file_len.to_le_bytes());
     target.extend_from_slice(schema_hash.as_bytes());
     target.extend_from_slice(&relationship_count.to_le_bytes());
@@ -1249,8 +1657,8 @@ fn parse_header(bytes: &[u8]) -> Result<SnapshotHeader, SnapshotIoError> {
             reason: "snapshot header length is unsupported",
         });
     }
-    let section_count = cursor.read_u32()?;
-    if section_count != REQUIRED_SECTION_COUNT_U32 {
+    let section_count = checked_usize_from_u32(cursor.read_u32()?)?;
+    if !(REQUIRED_SECTION_COUNT..=MAX_SECTION_COUNT).contains(&section_count) {
         return Err(SnapshotIoError::Format {
             reason: "snapshot section count is unsupported",
         });
@@ -1315,19 +1723,7 @@ fn validate_section_flags(
 }
 
 fn validate_required_sections(sections: &SnapshotSections<'_>) -> Result<(), SnapshotIoError> {
-    for kind in [
-        SectionKind::Schema,
-        SectionKind::SymbolBytes,
-        SectionKind::SymbolTable,
-        SectionKind::RelationshipRows,
-        SectionKind::IndexDirectory,
-        SectionKind::IndexKeys,
-        SectionKind::PostingRanges,
-        SectionKind::PostingRowIds,
-        SectionKind::SymbolHashes,
-        SectionKind::SymbolLookup,
-        SectionKind::Footer,
-    ] {
+    for kind in REQUIRED_SECTION_KINDS {
         if sections
             .get(section_slot(kind))
             .copied()
@@ -1448,6 +1844,20 @@ impl<'a> BinaryCursor<'a> {
         Ok(u64::from_le_bytes(self.read_array()?))
     }
 
+    /// Reads a `u32` length prefix followed by that many bytes.
+    pub(crate) fn read_len_prefixed(&mut self) -> Result<&'a [u8], SnapshotIoError> {
+        let len = checked_usize_from_u32(self.read_u32()?)?;
+        let end = checked_add_usize(self.offset, len)?;
+        let slice = self
+            .bytes
+            .get(self.offset..end)
+            .ok_or(SnapshotIoError::Format {
+                reason: "snapshot section is truncated",
+            })?;
+        self.offset = end;
+        Ok(slice)
+    }
+
     /// Returns true when no unread bytes remain.
     #[must_use]
     pub(crate) const fn is_empty(&self) -> bool {
@@ -1482,7 +1892,7 @@ fn checked_u32_from_u64(value: u64) -> Result<u32, SnapshotIoError> {
     })
 }
 
-fn checked_u64_from_usize(value: usize) -> Result<u64, SnapshotIoError> {
+pub(crate) fn checked_u64_from_usize(value: usize) -> Result<u64, SnapshotIoError> {
     u64::try_from(value).map_err(|_| SnapshotIoError::LimitExceeded {
         component: "snapshot u64 count",
     })
@@ -0,0 +1,235 @@
+//! Check evaluation traces explaining which path granted or denied a check.
+
+use crate::{
+    domain::Relationship,
+    error::ZanzibarError,
+    eval::{EvaluationError, Membership},
+    model::{CheckResponse, Object, Permissionship, Relation, User},
+};
+
+/// Response for a traced check request.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckTraceResponse {
+    /// Check outcome, or `None` when evaluation stopped at a depth or fanout limit.
+    pub response: Option<CheckResponse>,
+    /// Root check node of the evaluated tree.
+    pub root: CheckTraceNode,
+    /// Evaluation totals for this request.
+    pub stats: CheckTraceStats,
+}
+
+/// One evaluated node of a check trace.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckTraceNode {
+    /// Sub-check or userset expression evaluated at this node.
+    pub kind: CheckTraceKind,
+    /// Node outcome, or `None` when evaluation aborted inside this node.
+    pub result: Option<Permissionship>,
+    /// Why the node short-circuited or aborted, if it did.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub note: Option<CheckTraceNote>,
+    /// Relationships read by this node, in evaluation order.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub relationships: Vec<Relationship>,
+    /// Nested sub-checks and expressions, in evaluation order.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub children: Vec<CheckTraceNode>,
+}
+
+impl CheckTraceNode {
+    const fn new(kind: CheckTraceKind) -> Self {
+        Self {
+            kind,
+            result: None,
+            note: None,
+            relationships: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Kind of evaluation step recorded in a trace node.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", tag = "kind")
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckTraceKind {
+    /// Check of one relation or permission on an object.
+    Check {
+        /// Object being checked.
+        object: Object,
+        /// Relation or permission being checked.
+        relation: Relation,
+        /// Subject being checked.
+        subject: User,
+    },
+    /// Direct relationships on the checked relation.
+    This,
+    /// Another relation on the same object.
+    ComputedUserset {
+        /// Referenced relation.
+        relation: Relation,
+    },
+    /// A relation on objects reached through a tupleset relation.
+    TupleToUserset {
+        /// Relation holding the intermediate objects.
+        tupleset: Relation,
+        /// Relation evaluated on each intermediate object.
+        computed_userset: Relation,
+    },
+    /// Union of child expressions.
+    Union,
+    /// Intersection of child expressions.
+    Intersection,
+    /// Base expression minus an excluded expression.
+    Exclusion,
+}
+
+/// Reason a trace node short-circuited or aborted.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", tag = "kind")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckTraceNote {
+    /// The sub-check result came from the request memo without re-evaluation.
+    MemoHit,
+    /// The sub-check was already active higher in the tree and was denied to break the cycle.
+    CycleDenied,
+    /// Evaluation exceeded `EvaluationLimits::max_depth` entering this check.
+    DepthExceeded,
+    /// This node read more userset edges than `EvaluationLimits::max_fanout_per_step`.
+    FanoutExceeded {
+        /// Configured fanout limit.
+        limit: u32,
+    },
+}
+
+/// Per-request totals gathered while tracing a check.
+///
+/// These mirror the `bench-internals` read counters but are scoped to one request and are always
+/// available.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", deny_unknown_fields)
+)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CheckTraceStats {
+    /// Check nodes visited, including memo hits and cycle denials.
+    pub checks: u64,
+    /// Sub-checks answered from the request memo.
+    pub memo_hits: u64,
+    /// Sub-checks denied because they were already active.
+    pub cycle_denials: u64,
+    /// Relationships read across all nodes.
+    pub relationships_read: u64,
+}
+
+/// Builds a trace tree alongside one evaluation context.
+#[derive(Debug, Default)]
+pub(crate) struct CheckTraceRecorder {
+    stack: Vec<CheckTraceNode>,
+    root: Option<CheckTraceNode>,
+    stats: CheckTraceStats,
+    limit_recorded: bool,
+}
+
+impl CheckTraceRecorder {
+    pub(crate) fn open(&mut self, kind: CheckTraceKind) {
+        if matches!(kind, CheckTraceKind::Check { .. }) {
+            self.stats.checks = self.stats.checks.saturating_add(1);
+        }
+        self.stack.push(CheckTraceNode::new(kind));
+    }
+
+    pub(crate) fn close(&mut self, result: &Result<Membership, ZanzibarError>) {
+        let Some(mut node) = self.stack.pop() else {
+            return;
+        };
+        match result {
+            Ok(membership) => node.result = Some((*membership).into()),
+            Err(ZanzibarError::Evaluation(error)) if !self.limit_recorded => {
+                node.note = match error {
+                    EvaluationError::DepthExceeded { .. } => Some(CheckTraceNote::DepthExceeded),
+                    EvaluationError::FanoutExceeded { limit } => {
+                        Some(CheckTraceNote::FanoutExceeded { limit: limit.get() })
+                    }
+                    EvaluationError::Caveat(_) => None,
+                };
+                self.limit_recorded = node.note.is_some();
+            }
+            Err(_) => {}
+        }
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => self.root = Some(node),
+        }
+    }
+
+    /// Records a check answered without evaluating its subtree.
+    pub(crate) fn shortcut(
+        &mut self,
+        kind: CheckTraceKind,
+        note: CheckTraceNote,
+        membership: Membership,
+    ) {
+        match note {
+            CheckTraceNote::MemoHit => {
+                self.stats.memo_hits = self.stats.memo_hits.saturating_add(1);
+            }
+            CheckTraceNote::CycleDenied => {
+                self.stats.cycle_denials = self.stats.cycle_denials.saturating_add(1);
+            }
+            CheckTraceNote::DepthExceeded | CheckTraceNote::FanoutExceeded { .. } => {}
+        }
+        self.open(kind);
+        if let Some(node) = self.stack.last_mut() {
+            node.note = Some(note);
+        }
+        self.close(&Ok(membership));
+    }
+
+    pub(crate) fn relationship(&mut self, relationship: Relationship) {
+        self.stats.relationships_read = self.stats.relationships_read.saturating_add(1);
+        if let Some(node) = self.stack.last_mut() {
+            node.relationships.push(relationship);
+        }
+    }
+
+    pub(crate) fn finish(
+        self,
+        response: Option<CheckResponse>,
+    ) -> Result<CheckTraceResponse, ZanzibarError> {
+        let root = self.root.ok_or_else(|| {
+            ZanzibarError::StorageError("check trace recorded no root node".to_string())
+        })?;
+        Ok(CheckTraceResponse {
+            response,
+            root,
+            stats: self.stats,
+        })
+    }
+}
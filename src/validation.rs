@@ -0,0 +1,805 @@
+//! Checked-in validation files: a schema, relationships, and the decisions they must produce.
+//!
+//! The format is a YAML subset modeled on the SpiceDB playground's validation files:
+//!
+//! ```yaml
+//! schemaSyntax: spicedb        # optional; `legacy` by default
+//! schema: |-
+//!   definition user {}
+//!   definition doc {
+//!     relation viewer: user
+//!     permission view = viewer
+//!   }
+//! relationships: |-
+//!   doc:readme#viewer@user:alice
+//! assertions:
+//!   assertTrue:
+//!     - doc:readme#view@user:alice
+//!   assertFalse:
+//!     - doc:readme#view@user:bob
+//! validation:
+//!   doc:readme#view:
+//!     - "[user:alice] is <doc:readme#viewer>"
+//! ```
+//!
+//! Each `validation` entry pins the subjects the `expand` tree grants for one
+//! `resource#permission`: its leaves, with intersections and exclusions applied. The `is <...>`
+//! annotations the playground generates are accepted but not compared. Only block scalars, nested
+//! mappings, and sequences of scalars are supported; anchors, flow mappings, and multi-document
+//! streams are rejected.
+
+use std::{collections::BTreeSet, fmt};
+
+use crate::{
+    api::{EngineError, ZanzibarEngine},
+    diagnostic::{Diagnostic, DiagnosticError, Diagnostics, SourceSpan},
+    domain::{ObjectRef, RelationName, Relationship, SubjectRef},
+    model::{ExpandedUserset, Object, Relation, User},
+    policy::{PolicyText, PolicyTextFile},
+    schema::{SchemaSource, SchemaSyntax},
+};
+
+const LEGACY_USER_SUBJECT_TYPE: &str = "user";
+
+/// A parsed validation file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationFile {
+    /// File name used in diagnostics.
+    pub name: Option<String>,
+    /// Syntax of the schema block.
+    pub schema_syntax: SchemaSyntax,
+    /// Schema text and relationships, as written in the file.
+    pub policy: PolicyText,
+    /// Checks that must be allowed.
+    pub assert_true: Vec<ValidationAssertion>,
+    /// Checks that must be denied.
+    pub assert_false: Vec<ValidationAssertion>,
+    /// Pinned `expand` subjects, in file order.
+    pub expected_relations: Vec<ExpectedRelations>,
+    schema_origin: BlockOrigin,
+    relationships_origin: BlockOrigin,
+}
+
+/// One `resource#permission@subject` assertion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationAssertion {
+    /// Assertion as written.
+    pub text: String,
+    /// One-based line in the validation file.
+    pub line: usize,
+    /// Resource checked.
+    pub resource: Object,
+    /// Permission or relation checked.
+    pub permission: Relation,
+    /// Subject checked.
+    pub subject: User,
+}
+
+/// Expected subjects of the `expand` tree for one resource and permission.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedRelations {
+    /// `resource#permission` key as written.
+    pub text: String,
+    /// One-based line in the validation file.
+    pub line: usize,
+    /// Resource expanded.
+    pub resource: Object,
+    /// Permission or relation expanded.
+    pub permission: Relation,
+    /// Expected subjects such as `user:alice`, `user:*`, or `group:eng#member`, sorted.
+    pub subjects: Vec<String>,
+}
+
+/// Which part of a validation file a result belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValidationCheck {
+    /// An `assertTrue` entry.
+    AssertTrue,
+    /// An `assertFalse` entry.
+    AssertFalse,
+    /// A `validation` block entry.
+    ExpectedRelations,
+}
+
+impl fmt::Display for ValidationCheck {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::AssertTrue => "assertTrue",
+            Self::AssertFalse => "assertFalse",
+            Self::ExpectedRelations => "validation",
+        })
+    }
+}
+
+/// Result of one check.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationOutcome {
+    /// The engine produced the expected answer.
+    Passed,
+    /// The check was allowed when it should be denied, or the reverse.
+    AssertionFailed,
+    /// The subjects the `expand` tree grants differ from the pinned subjects.
+    RelationsDiffer {
+        /// Pinned subjects the expansion did not contain.
+        missing: Vec<String>,
+        /// Subjects the expansion contained that were not pinned.
+        unexpected: Vec<String>,
+    },
+    /// The engine rejected the check or expansion.
+    Error(EngineError),
+}
+
+/// One evaluated assertion or expected-relations entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationResult {
+    /// Kind of check.
+    pub check: ValidationCheck,
+    /// Assertion or `resource#permission` key as written.
+    pub text: String,
+    /// One-based line in the validation file.
+    pub line: usize,
+    /// What happened.
+    pub outcome: ValidationOutcome,
+}
+
+impl ValidationResult {
+    /// Returns true when the check passed.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.outcome == ValidationOutcome::Passed
+    }
+}
+
+/// Results of running a validation file, in file order within each check kind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationReport {
+    /// File name the results refer to.
+    pub name: Option<String>,
+    /// One result per assertion and expected-relations entry.
+    pub results: Vec<ValidationResult>,
+}
+
+impl ValidationReport {
+    /// Returns true when every check passed.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(ValidationResult::passed)
+    }
+
+    /// Returns the checks that did not pass.
+    pub fn failures(&self) -> impl Iterator<Item = &ValidationResult> {
+        self.results.iter().filter(|result| !result.passed())
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    /// Writes one `file:line: check text ...` line per failure, followed by `-`/`+` diff lines
+    /// for differing expansions.
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self.name.as_deref().unwrap_or("<input>");
+        let mut separator = "";
+        for result in self.failures() {
+            write!(
+                formatter,
+                "{separator}{file}:{}: {} {}",
+                result.line, result.check, result.text
+            )?;
+            separator = "\n";
+            match &result.outcome {
+                ValidationOutcome::Passed => {}
+                ValidationOutcome::AssertionFailed => {
+                    let actual = if result.check == ValidationCheck::AssertTrue {
+                        "denied"
+                    } else {
+                        "allowed"
+                    };
+                    write!(formatter, " failed: {actual}")?;
+                }
+                ValidationOutcome::RelationsDiffer {
+                    missing,
+                    unexpected,
+                } => {
+                    formatter.write_str(" differs:")?;
+                    for subject in missing {
+                        write!(formatter, "\n  - {subject}")?;
+                    }
+                    for subject in unexpected {
+                        write!(formatter, "\n  + {subject}")?;
+                    }
+                }
+                ValidationOutcome::Error(error) => write!(formatter, " errored: {error}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ValidationFile {
+    /// Parses validation file text; `name` labels diagnostics and reports.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Diagnostics`] with every syntax error, unknown key, and malformed assertion.
+    pub fn parse(name: Option<&str>, text: &str) -> Result<Self, Diagnostics> {
+        let mut reader = Reader {
+            name,
+            text,
+            lines: lines(text),
+            index: 0,
+            diagnostics: Diagnostics::default(),
+        };
+        let root = reader.mapping(0);
+        if let Some(line) = reader.lines.get(reader.index) {
+            reader.error(line.span(), "unexpected indentation");
+        }
+        let mut file = Self {
+            name: name.map(str::to_string),
+            schema_syntax: SchemaSyntax::Legacy,
+            policy: PolicyText::new(String::new(), Vec::new()),
+            assert_true: Vec::new(),
+            assert_false: Vec::new(),
+            expected_relations: Vec::new(),
+            schema_origin: BlockOrigin::default(),
+            relationships_origin: BlockOrigin::default(),
+        };
+        let mut has_schema = false;
+        for entry in root {
+            match entry.key.as_str() {
+                "schema" => {
+                    if let Some((schema, origin)) = reader.text_block(&entry) {
+                        file.policy.schema = schema;
+                        file.schema_origin = origin;
+                        has_schema = true;
+                    }
+                }
+                "schemaSyntax" => match reader.scalar(&entry).map(|(value, _)| value) {
+                    Some("legacy") => file.schema_syntax = SchemaSyntax::Legacy,
+                    Some("spicedb") => file.schema_syntax = SchemaSyntax::SpiceDb,
+                    Some(_) => reader.error(entry.span, "schemaSyntax must be legacy or spicedb"),
+                    None => {}
+                },
+                "relationships" => {
+                    if let Some((contents, origin)) = reader.text_block(&entry) {
+                        file.policy.relationship_files = vec![PolicyTextFile {
+                            path: name.unwrap_or("relationships").to_string(),
+                            contents,
+                        }];
+                        file.relationships_origin = origin;
+                    }
+                }
+                "assertions" => {
+                    for assertions in reader.nested(&entry) {
+                        let target = match assertions.key.as_str() {
+                            "assertTrue" => &mut file.assert_true,
+                            "assertFalse" => &mut file.assert_false,
+                            _ => {
+                                reader.error(assertions.span, "expected assertTrue or assertFalse");
+                                continue;
+                            }
+                        };
+                        for item in reader.items(assertions) {
+                            if let Some(assertion) = reader.assertion(item) {
+                                target.push(assertion);
+                            }
+                        }
+                    }
+                }
+                "validation" => {
+                    for expected in reader.nested(&entry) {
+                        if let Some(relations) = reader.expected_relations(expected) {
+                            file.expected_relations.push(relations);
+                        }
+                    }
+                }
+                _ => reader.error(entry.span, "unknown validation file key"),
+            }
+        }
+        if !has_schema {
+            reader.error(
+                SourceSpan::default(),
+                "validation file must contain a schema",
+            );
+        }
+        reader.diagnostics.into_result()?;
+        Ok(file)
+    }
+
+    /// Loads the schema and relationships into a throwaway engine and evaluates every assertion
+    /// and expected-relations entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError`] when the schema or relationships are invalid; diagnostics then
+    /// point at lines in the validation file. Failing checks are reported in the
+    /// [`ValidationReport`] instead.
+    pub fn run(&self) -> Result<ValidationReport, EngineError> {
+        let engine = ZanzibarEngine::builder().build();
+        engine
+            .apply_schema(SchemaSource {
+                name: self.name.as_deref(),
+                text: &self.policy.schema,
+                syntax: self.schema_syntax,
+            })
+            .map_err(|error| self.schema_origin.relocate(error, self.name.as_deref()))?;
+        let canonical = engine.export_policy_text()?.schema;
+        engine
+            .apply_policy_text(&PolicyText::new(
+                canonical,
+                self.policy.relationship_files.clone(),
+            ))
+            .map_err(|error| {
+                self.relationships_origin
+                    .relocate(error, self.name.as_deref())
+            })?;
+
+        let mut results = Vec::new();
+        for (check, assertions, expected) in [
+            (ValidationCheck::AssertTrue, &self.assert_true, true),
+            (ValidationCheck::AssertFalse, &self.assert_false, false),
+        ] {
+            for assertion in assertions {
+                let outcome = match engine.check_relation(
+                    &assertion.resource,
+                    &assertion.permission,
+                    &assertion.subject,
+                ) {
+                    Ok(allowed) if allowed == expected => ValidationOutcome::Passed,
+                    Ok(_) => ValidationOutcome::AssertionFailed,
+                    Err(error) => ValidationOutcome::Error(error),
+                };
+                results.push(ValidationResult {
+                    check,
+                    text: assertion.text.clone(),
+                    line: assertion.line,
+                    outcome,
+                });
+            }
+        }
+        for expected in &self.expected_relations {
+            let outcome = match engine.expand_relation(&expected.resource, &expected.permission) {
+                Ok(expanded) => {
+                    let actual = effective_subjects(&expanded);
+                    let pinned = expected.subjects.iter().cloned().collect::<BTreeSet<_>>();
+                    let missing = pinned.difference(&actual).cloned().collect::<Vec<_>>();
+                    let unexpected = actual.difference(&pinned).cloned().collect::<Vec<_>>();
+                    if missing.is_empty() && unexpected.is_empty() {
+                        ValidationOutcome::Passed
+                    } else {
+                        ValidationOutcome::RelationsDiffer {
+                            missing,
+                            unexpected,
+                        }
+                    }
+                }
+                Err(error) => ValidationOutcome::Error(error),
+            };
+            results.push(ValidationResult {
+                check: ValidationCheck::ExpectedRelations,
+                text: expected.text.clone(),
+                line: expected.line,
+                outcome,
+            });
+        }
+        Ok(ValidationReport {
+            name: self.name.clone(),
+            results,
+        })
+    }
+}
+
+/// Subjects the `expand` tree grants: leaves joined by unions, intersected by intersections, and
+/// with excluded leaves removed from their base.
+fn effective_subjects(expanded: &ExpandedUserset) -> BTreeSet<String> {
+    match expanded {
+        ExpandedUserset::User(id) => BTreeSet::from([format!("{LEGACY_USER_SUBJECT_TYPE}:{id}")]),
+        ExpandedUserset::Wildcard => BTreeSet::from([format!("{LEGACY_USER_SUBJECT_TYPE}:*")]),
+        ExpandedUserset::Userset(object, relation) => {
+            BTreeSet::from([format!("{}:{}#{}", object.namespace, object.id, relation.0)])
+        }
+        ExpandedUserset::Union(children) => children.iter().flat_map(effective_subjects).collect(),
+        ExpandedUserset::Intersection(children) => children
+            .iter()
+            .map(effective_subjects)
+            .reduce(|left, right| left.intersection(&right).cloned().collect())
+            .unwrap_or_default(),
+        ExpandedUserset::Exclusion { base, exclude } => {
+            let excluded = effective_subjects(exclude);
+            effective_subjects(base)
+                .into_iter()
+                .filter(|subject| !excluded.contains(subject))
+                .collect()
+        }
+    }
+}
+
+/// Where a block scalar starts in the validation file, so engine diagnostics against the block
+/// can be reported against the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct BlockOrigin {
+    line: usize,
+    offset: usize,
+}
+
+impl BlockOrigin {
+    fn relocate(self, error: EngineError, name: Option<&str>) -> EngineError {
+        let EngineError::Diagnostics(mut diagnostics) = error else {
+            return error;
+        };
+        for diagnostic in &mut diagnostics.diagnostics {
+            diagnostic.file = name.map(str::to_string);
+            diagnostic.line += self.line;
+            diagnostic.span = SourceSpan::new(
+                diagnostic.span.start + self.offset,
+                diagnostic.span.end + self.offset,
+            );
+        }
+        EngineError::Diagnostics(diagnostics)
+    }
+}
+
+/// One physical line with its indentation measured.
+#[derive(Debug, Clone, Copy)]
+struct Line<'a> {
+    /// Zero-based line index.
+    number: usize,
+    /// Byte offset of the line start.
+    offset: usize,
+    /// Line text without the line break.
+    raw: &'a str,
+    indent: usize,
+}
+
+impl Line<'_> {
+    fn content(&self) -> &str {
+        self.raw[self.indent..].trim_end()
+    }
+
+    fn is_blank(&self) -> bool {
+        let content = self.content();
+        content.is_empty() || content.starts_with('#')
+    }
+
+    fn span(&self) -> SourceSpan {
+        SourceSpan::new(self.offset + self.indent, self.offset + self.raw.len())
+    }
+}
+
+fn lines(text: &str) -> Vec<Line<'_>> {
+    let mut offset = 0;
+    text.split_inclusive('\n')
+        .enumerate()
+        .map(|(number, raw)| {
+            let line_offset = offset;
+            offset += raw.len();
+            let raw = raw.trim_end_matches(['\n', '\r']);
+            Line {
+                number,
+                offset: line_offset,
+                raw,
+                indent: raw.len() - raw.trim_start_matches(' ').len(),
+            }
+        })
+        .collect()
+}
+
+/// Value of one mapping entry.
+#[derive(Debug)]
+enum Node {
+    /// Inline scalar, unquoted.
+    Scalar(String, SourceSpan),
+    /// `|` block scalar text with its origin.
+    Block(String, BlockOrigin),
+    /// Nested mapping.
+    Mapping(Vec<Entry>),
+    /// Sequence of scalars.
+    Sequence(Vec<(String, SourceSpan)>),
+    /// Key with no value.
+    Empty,
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: String,
+    span: SourceSpan,
+    value: Node,
+}
+
+struct Reader<'a> {
+    name: Option<&'a str>,
+    text: &'a str,
+    lines: Vec<Line<'a>>,
+    index: usize,
+    diagnostics: Diagnostics,
+}
+
+impl<'a> Reader<'a> {
+    fn error(&mut self, span: SourceSpan, message: &str) {
+        self.diagnostics.push(Diagnostic::new(
+            self.name,
+            self.text,
+            span,
+            DiagnosticError::Syntax(message.to_string()),
+        ));
+    }
+
+    fn locate(&mut self, span: SourceSpan, error: impl Into<DiagnosticError>) {
+        self.diagnostics
+            .push(Diagnostic::new(self.name, self.text, span, error));
+    }
+
+    fn skip_blank(&mut self) -> Option<Line<'a>> {
+        while let Some(line) = self.lines.get(self.index) {
+            if !line.is_blank() {
+                return Some(*line);
+            }
+            self.index += 1;
+        }
+        None
+    }
+
+    /// Reads `key: value` entries indented by exactly `indent` spaces.
+    fn mapping(&mut self, indent: usize) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        while let Some(line) = self.skip_blank() {
+            if line.indent < indent {
+                break;
+            }
+            self.index += 1;
+            if line.indent > indent || line.raw.starts_with('\t') {
+                self.error(line.span(), "unexpected indentation");
+                continue;
+            }
+            let content = line.content();
+            let Some((key, value)) = split_key(content) else {
+                self.error(line.span(), "expected `key: value`");
+                continue;
+            };
+            let span = line.span();
+            let value_start = line.offset + line.indent + (content.len() - value.len());
+            let value = match value {
+                "" => self.child(indent),
+                "[]" => Node::Sequence(Vec::new()),
+                "|" | "|-" | "|+" => self.block(indent),
+                _ => Node::Scalar(
+                    unquote(value).to_string(),
+                    SourceSpan::new(value_start, value_start + value.len()),
+                ),
+            };
+            entries.push(Entry {
+                key: unquote(key).to_string(),
+                span,
+                value,
+            });
+        }
+        entries
+    }
+
+    fn child(&mut self, indent: usize) -> Node {
+        let Some(line) = self.skip_blank() else {
+            return Node::Empty;
+        };
+        if line.indent <= indent {
+            return Node::Empty;
+        }
+        if line.content().starts_with('-') {
+            return Node::Sequence(self.sequence(line.indent));
+        }
+        Node::Mapping(self.mapping(line.indent))
+    }
+
+    fn sequence(&mut self, indent: usize) -> Vec<(String, SourceSpan)> {
+        let mut items = Vec::new();
+        while let Some(line) = self.skip_blank() {
+            if line.indent < indent {
+                break;
+            }
+            self.index += 1;
+            let content = line.content();
+            let item = match content.strip_prefix("- ") {
+                Some(item) if line.indent == indent => item.trim(),
+                _ => {
+                    self.error(line.span(), "expected `- item`");
+                    continue;
+                }
+            };
+            let start = line.offset + line.indent + (content.len() - item.len());
+            items.push((
+                unquote(item).to_string(),
+                SourceSpan::new(start, start + item.len()),
+            ));
+        }
+        items
+    }
+
+    /// Reads the lines of a `|` block scalar, keeping their indentation so columns match.
+    fn block(&mut self, indent: usize) -> Node {
+        let mut origin = None;
+        let mut end = None;
+        while let Some(line) = self.lines.get(self.index) {
+            if !line.content().is_empty() && line.indent <= indent {
+                break;
+            }
+            let line = *line;
+            self.index += 1;
+            if line.content().is_empty() {
+                continue;
+            }
+            origin.get_or_insert(BlockOrigin {
+                line: line.number,
+                offset: line.offset,
+            });
+            end = Some(line.offset + line.raw.len());
+        }
+        match (origin, end) {
+            (Some(origin), Some(end)) => {
+                let mut text = self.text[origin.offset..end].to_string();
+                text.push('\n');
+                Node::Block(text, origin)
+            }
+            _ => Node::Block(String::new(), BlockOrigin::default()),
+        }
+    }
+
+    fn text_block(&mut self, entry: &Entry) -> Option<(String, BlockOrigin)> {
+        match &entry.value {
+            Node::Block(text, origin) => Some((text.clone(), *origin)),
+            Node::Scalar(text, span) => {
+                let line = self.text[..span.start].matches('\n').count();
+                let line_start = self.text[..span.start]
+                    .rfind('\n')
+                    .map_or(0, |index| index + 1);
+                // Keep the key's columns so diagnostics line up with the file.
+                let padding = " ".repeat(span.start - line_start);
+                Some((
+                    format!("{padding}{text}\n"),
+                    BlockOrigin {
+                        line,
+                        offset: line_start,
+                    },
+                ))
+            }
+            _ => {
+                self.error(entry.span, "expected text or a `|` block");
+                None
+            }
+        }
+    }
+
+    fn scalar<'e>(&mut self, entry: &'e Entry) -> Option<(&'e str, SourceSpan)> {
+        if let Node::Scalar(value, span) = &entry.value {
+            Some((value, *span))
+        } else {
+            self.error(entry.span, "expected a single value");
+            None
+        }
+    }
+
+    fn nested<'e>(&mut self, entry: &'e Entry) -> &'e [Entry] {
+        match &entry.value {
+            Node::Mapping(entries) => entries,
+            Node::Empty => &[],
+            _ => {
+                self.error(entry.span, "expected a nested mapping");
+                &[]
+            }
+        }
+    }
+
+    fn items<'e>(&mut self, entry: &'e Entry) -> &'e [(String, SourceSpan)] {
+        match &entry.value {
+            Node::Sequence(items) => items,
+            Node::Empty => &[],
+            _ => {
+                self.error(entry.span, "expected a `- item` list");
+                &[]
+            }
+        }
+    }
+
+    fn line_of(&self, span: SourceSpan) -> usize {
+        self.text[..span.start].matches('\n').count() + 1
+    }
+
+    fn assertion(&mut self, (text, span): &(String, SourceSpan)) -> Option<ValidationAssertion> {
+        let relationship = match text.parse::<Relationship>() {
+            Ok(relationship) => relationship,
+            Err(error) => {
+                self.locate(*span, error);
+                return None;
+            }
+        };
+        if relationship.caveat().is_some() || relationship.expiration().is_some() {
+            self.error(*span, "assertions cannot carry caveats or expirations");
+            return None;
+        }
+        let subject = match User::try_from(relationship.subject()) {
+            Ok(subject) => subject,
+            Err(error) => {
+                self.locate(*span, error);
+                return None;
+            }
+        };
+        Some(ValidationAssertion {
+            text: text.clone(),
+            line: self.line_of(*span),
+            resource: legacy_object(relationship.resource()),
+            permission: Relation::new(relationship.relation().as_str()),
+            subject,
+        })
+    }
+
+    fn expected_relations(&mut self, entry: &Entry) -> Option<ExpectedRelations> {
+        let parsed = entry
+            .key
+            .split_once('#')
+            .ok_or("expected `resource#permission`")
+            .and_then(|(resource, permission)| {
+                let resource = resource
+                    .parse::<ObjectRef>()
+                    .map_err(|_| "expected `resource#permission`")?;
+                let permission = RelationName::try_from(permission)
+                    .map_err(|_| "expected `resource#permission`")?;
+                Ok((resource, permission))
+            });
+        let (resource, permission) = match parsed {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                self.error(entry.span, message);
+                return None;
+            }
+        };
+        let mut subjects = Vec::new();
+        for (item, span) in self.items(entry) {
+            let subject = item
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once(']'))
+                .filter(|(_, rest)| rest.trim().is_empty() || rest.trim_start().starts_with("is "));
+            let Some((subject, _)) = subject else {
+                self.error(*span, "expected `[subject]` or `[subject] is <...>`");
+                continue;
+            };
+            match subject.parse::<SubjectRef>() {
+                Ok(subject) => subjects.push(subject.to_string()),
+                Err(error) => self.locate(*span, error),
+            }
+        }
+        subjects.sort();
+        subjects.dedup();
+        Some(ExpectedRelations {
+            text: entry.key.clone(),
+            line: self.line_of(entry.span),
+            resource: legacy_object(&resource),
+            permission: Relation::new(permission.as_str()),
+            subjects,
+        })
+    }
+}
+
+/// Splits `key: value` or `key:`, allowing colons inside keys such as `doc:readme#view`.
+fn split_key(content: &str) -> Option<(&str, &str)> {
+    if let Some(rest) = content.strip_prefix('"') {
+        let end = rest.find('"')? + 1;
+        let (key, rest) = content.split_at(end + 1);
+        return Some((key, rest.strip_prefix(':')?.trim()));
+    }
+    match content.find(": ") {
+        Some(index) => Some((&content[..index], content[index + 2..].trim())),
+        None => content.strip_suffix(':').map(|key| (key, "")),
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+        .unwrap_or(value)
+}
+
+fn legacy_object(object: &ObjectRef) -> Object {
+    Object::new(object.object_type().as_str(), object.object_id().as_str())
+}
@@ -0,0 +1,630 @@
+//! Append-only write-ahead log and snapshot checkpoints for durable engines.
+//!
+//! A durable engine directory holds at most one live `<revision>.szsnap` checkpoint plus
+//! `engine.wal`. The log header pins the [`DatastoreId`] so consistency tokens survive restarts.
+//! Every published revision appends one checksummed record that is flushed to stable storage
+//! before the writer acknowledges the command. Opening the directory loads the newest checkpoint
+//! and replays every logged revision after it.
+
+use std::{
+    ffi::OsStr,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    num::NonZeroU64,
+    path::{Path, PathBuf},
+    str,
+};
+
+use thiserror::Error;
+
+use crate::{
+    error::ZanzibarError,
+    policy::{PolicyText, PolicyTextFile},
+    revision::{ConsistencyToken, DatastoreId, Revision, SchemaHash},
+    schema::MigrationStep,
+    snapshot::{BinaryCursor, SnapshotIoError},
+    watch::RelationshipUpdate,
+};
+
+const WAL_FILE_NAME: &str = "engine.wal";
+const SNAPSHOT_EXTENSION: &str = "szsnap";
+const PARTIAL_SNAPSHOT_SUFFIX: &str = "partial";
+const WAL_MAGIC: [u8; 8] = *b"SZWAL\0\x01\0";
+const WAL_HEADER_LEN: u64 = 24;
+const RECORD_CHECKSUM_LEN: usize = 32;
+const MAX_RECORD_BYTES: usize = 1024 * 1024 * 1024;
+const OPERATION_RELATIONSHIPS: u8 = 1;
+const OPERATION_SCHEMA: u8 = 2;
+const OPERATION_POLICY_TEXT: u8 = 3;
+const OPERATION_MIGRATION: u8 = 4;
+const STEP_RENAME_RELATION: u8 = 1;
+const STEP_MOVE_RELATIONSHIPS: u8 = 2;
+const STEP_DROP_RELATION: u8 = 3;
+const UPDATE_TOUCH: u8 = 1;
+const UPDATE_DELETE: u8 = 2;
+
+/// Errors produced while opening, replaying, or checkpointing a durable engine directory.
+#[derive(Debug, Error)]
+pub enum WalError {
+    /// Filesystem I/O failed.
+    #[error("write-ahead log io failed")]
+    Io {
+        /// Source I/O error.
+        #[from]
+        source: io::Error,
+    },
+
+    /// The log file is not a valid write-ahead log.
+    #[error("write-ahead log format error: {reason}")]
+    Format {
+        /// Static format failure reason.
+        reason: &'static str,
+    },
+
+    /// The checkpoint snapshot could not be loaded or saved.
+    #[error(transparent)]
+    Snapshot(#[from] SnapshotIoError),
+
+    /// A logged revision could not be replayed onto the checkpoint.
+    #[error("write-ahead log replay failed at revision {revision}")]
+    Replay {
+        /// Logged revision that failed to replay.
+        revision: Revision,
+        /// Source engine error.
+        #[source]
+        source: ZanzibarError,
+    },
+
+    /// The engine was not opened from a durable directory.
+    #[error("engine has no write-ahead log")]
+    NotDurable,
+
+    /// The engine writer actor is unavailable.
+    #[error("engine writer actor unavailable during {operation}")]
+    WriterUnavailable {
+        /// Operation that attempted to use the writer actor.
+        operation: &'static str,
+    },
+}
+
+/// State change logged for one published revision.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum WalOperation {
+    /// Relationship writes, replayed as touches and deletes.
+    Relationships(Vec<RelationshipUpdate>),
+    /// Complete canonical schema source after a schema change.
+    Schema(String),
+    /// Full policy replacement, replayed through the policy importer.
+    PolicyText(PolicyText),
+    /// Schema migration, replayed by rewriting relationships through the same steps.
+    Migration {
+        /// Complete canonical schema source after the migration.
+        schema: String,
+        /// Relationship transformations applied with the schema.
+        steps: Vec<MigrationStep>,
+    },
+}
+
+/// One logged revision.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct WalRecord {
+    pub(crate) revision: Revision,
+    pub(crate) schema_hash: SchemaHash,
+    pub(crate) operation: WalOperation,
+}
+
+impl WalRecord {
+    /// Returns true when replay produced the same revision and schema this record logged.
+    pub(crate) fn matches(&self, token: &ConsistencyToken) -> bool {
+        token.revision() == self.revision && token.schema_hash() == self.schema_hash
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, WalError> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.revision.get().to_le_bytes());
+        bytes.extend_from_slice(self.schema_hash.as_bytes());
+        match &self.operation {
+            WalOperation::Relationships(updates) => {
+                bytes.push(OPERATION_RELATIONSHIPS);
+                push_len(&mut bytes, updates.len())?;
+                for update in updates {
+                    bytes.push(match update {
+                        RelationshipUpdate::Touch(_) => UPDATE_TOUCH,
+                        RelationshipUpdate::Delete(_) => UPDATE_DELETE,
+                    });
+                    push_text(&mut bytes, &update.relationship().to_string())?;
+                }
+            }
+            WalOperation::Schema(source) => {
+                bytes.push(OPERATION_SCHEMA);
+                push_text(&mut bytes, source)?;
+            }
+            WalOperation::PolicyText(policy) => {
+                bytes.push(OPERATION_POLICY_TEXT);
+                push_text(&mut bytes, &policy.schema)?;
+                push_len(&mut bytes, policy.relationship_files.len())?;
+                for file in &policy.relationship_files {
+                    push_text(&mut bytes, &file.path)?;
+                    push_text(&mut bytes, &file.contents)?;
+                }
+            }
+            WalOperation::Migration { schema, steps } => {
+                bytes.push(OPERATION_MIGRATION);
+                push_text(&mut bytes, schema)?;
+                push_len(&mut bytes, steps.len())?;
+                for step in steps {
+                    encode_migration_step(&mut bytes, step)?;
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, WalError> {
+        let mut cursor = BinaryCursor::new(bytes);
+        let revision = NonZeroU64::new(cursor.read_u64().map_err(malformed)?)
+            .map(Revision::new)
+            .ok_or(WalError::Format {
+                reason: "logged revision must be non-zero",
+            })?;
+        let schema_hash = SchemaHash::from_bytes(cursor.read_array().map_err(malformed)?);
+        let [tag] = cursor.read_array().map_err(malformed)?;
+        let operation = match tag {
+            OPERATION_RELATIONSHIPS => {
+                let count = read_len(&mut cursor)?;
+                let mut updates = Vec::with_capacity(count.min(bytes.len()));
+                for _ in 0..count {
+                    let [kind] = cursor.read_array().map_err(malformed)?;
+                    let relationship =
+                        read_text(&mut cursor)?
+                            .parse()
+                            .map_err(|_| WalError::Format {
+                                reason: "logged relationship is malformed",
+                            })?;
+                    updates.push(match kind {
+                        UPDATE_TOUCH => RelationshipUpdate::Touch(relationship),
+                        UPDATE_DELETE => RelationshipUpdate::Delete(relationship),
+                        _ => {
+                            return Err(WalError::Format {
+                                reason: "unknown logged relationship update kind",
+                            });
+                        }
+                    });
+                }
+                WalOperation::Relationships(updates)
+            }
+            OPERATION_SCHEMA => WalOperation::Schema(read_text(&mut cursor)?.to_string()),
+            OPERATION_POLICY_TEXT => {
+                let schema = read_text(&mut cursor)?.to_string();
+                let count = read_len(&mut cursor)?;
+                let mut files = Vec::with_capacity(count.min(bytes.len()));
+                for _ in 0..count {
+                    files.push(PolicyTextFile {
+                        path: read_text(&mut cursor)?.to_string(),
+                        contents: read_text(&mut cursor)?.to_string(),
+                    });
+                }
+                WalOperation::PolicyText(PolicyText::new(schema, files))
+            }
+            OPERATION_MIGRATION => {
+                let schema = read_text(&mut cursor)?.to_string();
+                let count = read_len(&mut cursor)?;
+                let mut steps = Vec::with_capacity(count.min(bytes.len()));
+                for _ in 0..count {
+                    steps.push(decode_migration_step(&mut cursor)?);
+                }
+                WalOperation::Migration { schema, steps }
+            }
+            _ => {
+                return Err(WalError::Format {
+                    reason: "unknown logged operation",
+                });
+            }
+        };
+        if !cursor.is_empty() {
+            return Err(WalError::Format {
+                reason: "logged record has trailing bytes",
+            });
+        }
+        Ok(Self {
+            revision,
+            schema_hash,
+            operation,
+        })
+    }
+}
+
+/// Open append handle on a durable engine directory.
+#[derive(Debug)]
+pub(crate) struct WriteAheadLog {
+    directory: PathBuf,
+    file: File,
+    datastore_id: DatastoreId,
+    /// Set when a failed append could not be rolled back, so the log may end in a partial frame.
+    poisoned: bool,
+    #[cfg(test)]
+    fail_next_append_after: Option<usize>,
+}
+
+impl WriteAheadLog {
+    /// Opens or creates the log in `directory` and returns every intact record.
+    ///
+    /// A record cut short by a crash at the end of the file was never acknowledged, so it is
+    /// truncated away. Damage anywhere before the tail is reported as a format error.
+    pub(crate) fn open(directory: &Path) -> Result<(Self, Vec<WalRecord>), WalError> {
+        fs::create_dir_all(directory)?;
+        let path = directory.join(WAL_FILE_NAME);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        if bytes.is_empty() {
+            let datastore_id = DatastoreId::new_unique();
+            file.write_all(&WAL_MAGIC)?;
+            file.write_all(datastore_id.as_bytes())?;
+            file.sync_all()?;
+            sync_directory(directory);
+            return Ok((Self::new(directory, file, datastore_id), Vec::new()));
+        }
+
+        let (datastore_id, records, intact_len) = decode_log(&bytes)?;
+        if intact_len < bytes.len() {
+            file.set_len(u64::try_from(intact_len).map_err(|_| WalError::Format {
+                reason: "write-ahead log is too large",
+            })?)?;
+            file.sync_all()?;
+        }
+        file.seek(SeekFrom::End(0))?;
+        Ok((Self::new(directory, file, datastore_id), records))
+    }
+
+    fn new(directory: &Path, file: File, datastore_id: DatastoreId) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            file,
+            datastore_id,
+            poisoned: false,
+            #[cfg(test)]
+            fail_next_append_after: None,
+        }
+    }
+
+    pub(crate) const fn datastore_id(&self) -> DatastoreId {
+        self.datastore_id
+    }
+
+    /// Appends one record and flushes it to stable storage.
+    ///
+    /// A failed write is truncated away so the next append starts on a frame boundary. If that
+    /// truncation also fails, the log refuses further appends rather than write after a partial
+    /// frame that would make the directory unopenable.
+    pub(crate) fn append(&mut self, record: &WalRecord) -> Result<(), WalError> {
+        if self.poisoned {
+            return Err(WalError::Format {
+                reason: "write-ahead log is unusable after a failed append",
+            });
+        }
+        let payload = record.encode()?;
+        let mut frame = Vec::with_capacity(payload.len() + 4 + RECORD_CHECKSUM_LEN);
+        push_len(&mut frame, payload.len())?;
+        frame.extend_from_slice(&payload);
+        frame.extend_from_slice(blake3::hash(&payload).as_bytes());
+        let intact_len = self.file.seek(SeekFrom::End(0))?;
+        if let Err(error) = self.write_frame(&frame) {
+            let rolled_back = self
+                .file
+                .set_len(intact_len)
+                .and_then(|()| self.file.seek(SeekFrom::Start(intact_len)))
+                .and_then(|_| self.file.sync_data());
+            self.poisoned = rolled_back.is_err();
+            return Err(error.into());
+        }
+        Ok(())
+    }
+
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        #[cfg(test)]
+        if let Some(written) = self.fail_next_append_after.take() {
+            self.file.write_all(&frame[..written.min(frame.len())])?;
+            return Err(io::Error::other("injected append failure"));
+        }
+        self.file.write_all(frame)?;
+        self.file.sync_data()
+    }
+
+    /// Returns the newest checkpoint snapshot in the log directory, if any.
+    pub(crate) fn latest_snapshot(directory: &Path) -> Result<Option<PathBuf>, WalError> {
+        Ok(checkpoint_snapshots(directory)?
+            .into_iter()
+            .max_by_key(|(revision, _)| *revision)
+            .map(|(_, path)| path))
+    }
+
+    /// Writes a checkpoint for `revision` through `save`, then drops older checkpoints and log
+    /// records.
+    ///
+    /// The log is only cleared once the checkpoint is durable, so a crash at any step leaves a
+    /// directory that reopens to the same state.
+    pub(crate) fn checkpoint(
+        &mut self,
+        revision: Revision,
+        save: impl FnOnce(&Path) -> Result<(), SnapshotIoError>,
+    ) -> Result<(), WalError> {
+        let path = self
+            .directory
+            .join(format!("{:020}.{SNAPSHOT_EXTENSION}", revision.get()));
+        let partial =
+            path.with_extension(format!("{SNAPSHOT_EXTENSION}.{PARTIAL_SNAPSHOT_SUFFIX}"));
+        save(&partial)?;
+        File::open(&partial)?.sync_all()?;
+        fs::rename(&partial, &path)?;
+        sync_directory(&self.directory);
+
+        self.file.set_len(WAL_HEADER_LEN)?;
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.sync_all()?;
+        for (older, older_path) in checkpoint_snapshots(&self.directory)? {
+            if older < revision.get() {
+                fs::remove_file(older_path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn decode_log(bytes: &[u8]) -> Result<(DatastoreId, Vec<WalRecord>, usize), WalError> {
+    let mut cursor = BinaryCursor::new(bytes);
+    let magic: [u8; 8] = cursor.read_array().map_err(|_| WalError::Format {
+        reason: "write-ahead log header is truncated",
+    })?;
+    if magic != WAL_MAGIC {
+        return Err(WalError::Format {
+            reason: "write-ahead log magic is invalid",
+        });
+    }
+    let datastore_id =
+        DatastoreId::from_bytes(cursor.read_array().map_err(|_| WalError::Format {
+            reason: "write-ahead log header is truncated",
+        })?);
+
+    let mut records = Vec::new();
+    let mut offset = usize::try_from(WAL_HEADER_LEN).map_err(|_| WalError::Format {
+        reason: "write-ahead log header is too large",
+    })?;
+    while offset < bytes.len() {
+        let Some(frame) = read_frame(&bytes[offset..]) else {
+            // Only the final record can be torn by a crash before it was acknowledged.
+            return Ok((datastore_id, records, offset));
+        };
+        let (payload, frame_len) = frame?;
+        records.push(WalRecord::decode(payload)?);
+        offset += frame_len;
+    }
+    Ok((datastore_id, records, offset))
+}
+
+/// Reads one framed record, or `None` when the bytes end before the frame does.
+fn read_frame(bytes: &[u8]) -> Option<Result<(&[u8], usize), WalError>> {
+    let len_bytes = bytes.get(..4)?;
+    let mut len = [0_u8; 4];
+    len.copy_from_slice(len_bytes);
+    let payload_len = usize::try_from(u32::from_le_bytes(len)).ok()?;
+    if payload_len > MAX_RECORD_BYTES {
+        return Some(Err(WalError::Format {
+            reason: "logged record exceeds the maximum size",
+        }));
+    }
+    let frame_len = 4 + payload_len + RECORD_CHECKSUM_LEN;
+    let Some(frame) = bytes.get(..frame_len) else {
+        // A torn frame is a prefix of one append, so it cannot be followed by an intact frame.
+        // Finding one means this length field was damaged in the middle of the log.
+        if (4..bytes.len()).any(|start| is_intact_frame(&bytes[start..])) {
+            return Some(Err(WalError::Format {
+                reason: "logged record length runs past later records",
+            }));
+        }
+        return None;
+    };
+    let payload = &frame[4..4 + payload_len];
+    if blake3::hash(payload).as_bytes() != &frame[4 + payload_len..] {
+        if frame_len == bytes.len() {
+            return None;
+        }
+        return Some(Err(WalError::Format {
+            reason: "logged record checksum mismatch",
+        }));
+    }
+    Some(Ok((payload, frame_len)))
+}
+
+/// Returns true when `bytes` starts with a complete frame whose checksum matches.
+fn is_intact_frame(bytes: &[u8]) -> bool {
+    let Some(len_bytes) = bytes.get(..4) else {
+        return false;
+    };
+    let mut len = [0_u8; 4];
+    len.copy_from_slice(len_bytes);
+    let Ok(payload_len) = usize::try_from(u32::from_le_bytes(len)) else {
+        return false;
+    };
+    if payload_len > MAX_RECORD_BYTES {
+        return false;
+    }
+    bytes
+        .get(4..4 + payload_len + RECORD_CHECKSUM_LEN)
+        .is_some_and(|frame| {
+            let (payload, checksum) = frame.split_at(payload_len);
+            blake3::hash(payload).as_bytes() == checksum
+        })
+}
+
+fn checkpoint_snapshots(directory: &Path) -> Result<Vec<(u64, PathBuf)>, WalError> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension() != Some(OsStr::new(SNAPSHOT_EXTENSION)) {
+            continue;
+        }
+        if let Some(revision) = path
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .and_then(|stem| stem.parse::<u64>().ok())
+        {
+            snapshots.push((revision, path));
+        }
+    }
+    Ok(snapshots)
+}
+
+fn sync_directory(directory: &Path) {
+    // Directory handles cannot be synced on every platform; file data is synced regardless.
+    if let Ok(handle) = File::open(directory) {
+        drop(handle.sync_all());
+    }
+}
+
+fn push_len(bytes: &mut Vec<u8>, len: usize) -> Result<(), WalError> {
+    let len = u32::try_from(len).map_err(|_| WalError::Format {
+        reason: "logged field exceeds u32 length",
+    })?;
+    bytes.extend_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+fn push_text(bytes: &mut Vec<u8>, text: &str) -> Result<(), WalError> {
+    push_len(bytes, text.len())?;
+    bytes.extend_from_slice(text.as_bytes());
+    Ok(())
+}
+
+fn encode_migration_step(bytes: &mut Vec<u8>, step: &MigrationStep) -> Result<(), WalError> {
+    match step {
+        MigrationStep::RenameRelation {
+            namespace,
+            from,
+            to,
+        } => {
+            bytes.push(STEP_RENAME_RELATION);
+            push_text(bytes, namespace.as_str())?;
+            push_text(bytes, from.as_str())?;
+            push_text(bytes, to.as_str())
+        }
+        MigrationStep::MoveRelationships {
+            from_namespace,
+            from_relation,
+            to_namespace,
+            to_relation,
+        } => {
+            bytes.push(STEP_MOVE_RELATIONSHIPS);
+            push_text(bytes, from_namespace.as_str())?;
+            push_text(bytes, from_relation.as_str())?;
+            push_text(bytes, to_namespace.as_str())?;
+            push_text(bytes, to_relation.as_str())
+        }
+        MigrationStep::DropRelation {
+            namespace,
+            relation,
+        } => {
+            bytes.push(STEP_DROP_RELATION);
+            push_text(bytes, namespace.as_str())?;
+            push_text(bytes, relation.as_str())
+        }
+    }
+}
+
+fn decode_migration_step(cursor: &mut BinaryCursor<'_>) -> Result<MigrationStep, WalError> {
+    let [kind] = cursor.read_array().map_err(malformed)?;
+    match kind {
+        STEP_RENAME_RELATION => Ok(MigrationStep::RenameRelation {
+            namespace: read_identifier(cursor)?,
+            from: read_identifier(cursor)?,
+            to: read_identifier(cursor)?,
+        }),
+        STEP_MOVE_RELATIONSHIPS => Ok(MigrationStep::MoveRelationships {
+            from_namespace: read_identifier(cursor)?,
+            from_relation: read_identifier(cursor)?,
+            to_namespace: read_identifier(cursor)?,
+            to_relation: read_identifier(cursor)?,
+        }),
+        STEP_DROP_RELATION => Ok(MigrationStep::DropRelation {
+            namespace: read_identifier(cursor)?,
+            relation: read_identifier(cursor)?,
+        }),
+        _ => Err(WalError::Format {
+            reason: "unknown logged migration step",
+        }),
+    }
+}
+
+fn read_identifier<T>(cursor: &mut BinaryCursor<'_>) -> Result<T, WalError>
+where
+    T: for<'a> TryFrom<&'a str>,
+{
+    T::try_from(read_text(cursor)?).map_err(|_| WalError::Format {
+        reason: "logged migration identifier is malformed",
+    })
+}
+
+fn read_len(cursor: &mut BinaryCursor<'_>) -> Result<usize, WalError> {
+    usize::try_from(cursor.read_u32().map_err(malformed)?).map_err(|_| WalError::Format {
+        reason: "logged length does not fit in memory",
+    })
+}
+
+fn read_text<'a>(cursor: &mut BinaryCursor<'a>) -> Result<&'a str, WalError> {
+    str::from_utf8(cursor.read_len_prefixed().map_err(malformed)?).map_err(|_| WalError::Format {
+        reason: "logged text is not valid utf-8",
+    })
+}
+
+fn malformed(_: SnapshotIoError) -> WalError {
+    WalError::Format {
+        reason: "logged record is truncated",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use super::*;
+
+    #[test]
+    fn test_should_roll_back_failed_append_so_later_records_replay()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let directory = std::env::temp_dir().join(format!(
+            "simple_zanzibar_wal_failed_append_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        let record = |revision: u64| -> Result<WalRecord, WalError> {
+            Ok(WalRecord {
+                revision: Revision::new(NonZeroU64::new(revision).ok_or(WalError::Format {
+                    reason: "test revision is zero",
+                })?),
+                schema_hash: SchemaHash::from_bytes([7; 32]),
+                operation: WalOperation::Schema(format!("namespace doc{revision} {{}}")),
+            })
+        };
+
+        let (mut log, _) = WriteAheadLog::open(&directory)?;
+        log.append(&record(1)?)?;
+        log.fail_next_append_after = Some(9);
+        assert!(matches!(log.append(&record(2)?), Err(WalError::Io { .. })));
+        log.append(&record(3)?)?;
+        drop(log);
+
+        let (_, records) = WriteAheadLog::open(&directory)?;
+        let revisions = records
+            .iter()
+            .map(|record| record.revision.get())
+            .collect::<Vec<_>>();
+        assert_eq!(revisions, [1, 3]);
+        let _ = fs::remove_dir_all(&directory);
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+//! Ordered change stream of published relationship and schema revisions.
+
+use std::{sync::mpsc::Receiver, time::Duration};
+
+use crate::{
+    domain::Relationship,
+    relationship::RelationshipMutation,
+    revision::{ConsistencyToken, Revision, SchemaHash},
+};
+
+/// One relationship change recorded in a published revision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelationshipUpdate {
+    /// The relationship was created or touched and is present at the revision.
+    Touch(Relationship),
+    /// The relationship was deleted and is absent at the revision.
+    Delete(Relationship),
+}
+
+impl RelationshipUpdate {
+    /// Returns the changed relationship.
+    #[must_use]
+    pub const fn relationship(&self) -> &Relationship {
+        match self {
+            Self::Touch(relationship) | Self::Delete(relationship) => relationship,
+        }
+    }
+
+    pub(crate) fn from_mutation(mutation: &RelationshipMutation) -> Self {
+        match mutation {
+            RelationshipMutation::Create(relationship)
+            | RelationshipMutation::Touch(relationship) => Self::Touch(relationship.clone()),
+            RelationshipMutation::Delete(relationship) => Self::Delete(relationship.clone()),
+        }
+    }
+}
+
+/// Schema hash transition recorded in a published revision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaHashChange {
+    /// Schema hash before the revision, or `None` for the first published schema.
+    pub previous: Option<SchemaHash>,
+    /// Schema hash at the revision.
+    pub current: SchemaHash,
+}
+
+/// Changes published by one writer revision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchEvent {
+    /// Exact-snapshot token for the revision that published these changes.
+    pub token: ConsistencyToken,
+    /// Relationship changes in mutation order.
+    pub updates: Vec<RelationshipUpdate>,
+    /// Schema hash transition, when the revision changed the schema.
+    pub schema_change: Option<SchemaHashChange>,
+}
+
+impl WatchEvent {
+    /// Returns the revision that published these changes.
+    #[must_use]
+    pub const fn revision(&self) -> Revision {
+        self.token.revision()
+    }
+}
+
+/// Blocking iterator over [`WatchEvent`]s in revision order.
+///
+/// The stream ends once the engine writer shuts down. Events are buffered without bound until
+/// they are consumed, so long-lived subscribers should keep draining the stream.
+#[derive(Debug)]
+pub struct WatchStream {
+    receiver: Receiver<WatchEvent>,
+}
+
+impl WatchStream {
+    pub(crate) const fn new(receiver: Receiver<WatchEvent>) -> Self {
+        Self { receiver }
+    }
+
+    /// Returns the next buffered event without blocking.
+    ///
+    /// Returns `None` when no event is pending or the stream has ended.
+    pub fn try_next(&mut self) -> Option<WatchEvent> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Waits up to `timeout` for the next event.
+    ///
+    /// Returns `None` when the timeout elapses or the stream has ended.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<WatchEvent> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+}
+
+impl Iterator for WatchStream {
+    type Item = WatchEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{self, Command, Output},
+};
+
+use simple_zanzibar::ZanzibarEngine;
+
+const SCHEMA: &str = r#"
+namespace group {
+    relation member {}
+}
+
+namespace doc {
+    relation viewer {}
+    relation banned {}
+    relation can_view {
+        rewrite exclusion(
+            computed_userset(relation: "viewer"),
+            computed_userset(relation: "banned")
+        )
+    }
+}
+"#;
+
+#[test]
+fn test_should_answer_queries_against_policy_directory_and_snapshot()
+-> Result<(), Box<dyn std::error::Error>> {
+    let directory = temp_directory("queries");
+    let policy = directory.join("policy");
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(SCHEMA)?;
+    engine.touch_relationship("group:eng#member@user:alice")?;
+    engine.touch_relationship("doc:readme#viewer@group:eng#member")?;
+    engine.touch_relationship("doc:readme#banned@user:alice")?;
+    engine.touch_relationship("doc:guide#viewer@user:bob")?;
+    engine.export_policy_files(&policy)?;
+
+    let snapshot = directory.join("policy.szsnap.zst");
+    let built = zanzibar(&[
+        "snapshot",
+        "build",
+        path(&policy),
+        path(&snapshot),
+        "--zstd",
+    ]);
+    assert!(built.status.success(), "{}", stderr(&built));
+    assert_eq!(
+        stdout(&zanzibar(&["snapshot", "verify", path(&snapshot)])),
+        format!("ok: {}\n", snapshot.display())
+    );
+
+    for source in [&policy, &snapshot] {
+        let denied = zanzibar(&[
+            "check",
+            path(source),
+            "doc:readme#can_view@user:alice",
+            "--explain",
+        ]);
+        let explanation = stdout(&denied);
+        assert!(explanation.starts_with("denied\n"), "{explanation}");
+        assert!(explanation.contains("read doc:readme#banned@user:alice"));
+
+        let resources = zanzibar(&[
+            "--json",
+            "lookup-resources",
+            path(source),
+            "user:bob",
+            "can_view",
+            "doc",
+        ]);
+        let resources: serde_json::Value = serde_json::from_slice(&resources.stdout)?;
+        assert_eq!(resources, serde_json::json!({ "resources": ["doc:guide"] }));
+    }
+
+    let inspected = stdout(&zanzibar(&["snapshot", "inspect", path(&snapshot)]));
+    assert!(inspected.contains("compression:   zstd"), "{inspected}");
+    assert!(inspected.contains("relationships: 4"), "{inspected}");
+    assert!(inspected.contains("caveats"), "{inspected}");
+    let oversized = zanzibar(&[
+        "snapshot",
+        "verify",
+        path(&snapshot),
+        "--max-section-bytes",
+        "1",
+    ]);
+    assert_eq!(oversized.status.code(), Some(1));
+    assert!(stdout(&oversized).contains("over the 1 byte limit"));
+
+    let missing = zanzibar(&["check", path(&policy), "doc:readme#owner@user:alice"]);
+    assert_eq!(missing.status.code(), Some(2));
+    assert!(stderr(&missing).starts_with("error: "));
+
+    fs::remove_dir_all(directory).ok();
+    Ok(())
+}
+
+#[test]
+fn test_should_pass_check_context_to_caveats() -> Result<(), Box<dyn std::error::Error>> {
+    let directory = temp_directory("context");
+    let policy = directory.join("policy");
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(
+        r"
+        caveat under_limit(amount int, limit int) {
+            amount <= limit
+        }
+
+        namespace doc {
+            relation viewer {}
+        }
+        ",
+    )?;
+    engine.touch_relationship(r#"doc:readme#viewer@user:alice[under_limit:{"limit":10}]"#)?;
+    engine.export_policy_files(&policy)?;
+
+    let check = |context: Option<&str>| {
+        let mut arguments = vec!["check", path(&policy), "doc:readme#viewer@user:alice"];
+        if let Some(context) = context {
+            arguments.extend(["--context", context]);
+        }
+        stdout(&zanzibar(&arguments))
+    };
+    assert_eq!(check(None), "conditional: missing amount\n");
+    assert_eq!(check(Some(r#"{"amount":5}"#)), "allowed\n");
+    assert_eq!(check(Some(r#"{"amount":50}"#)), "denied\n");
+
+    let malformed = zanzibar(&[
+        "check",
+        path(&policy),
+        "doc:readme#viewer@user:alice",
+        "--context",
+        "{",
+    ]);
+    assert_eq!(malformed.status.code(), Some(2));
+
+    fs::remove_dir_all(directory).ok();
+    Ok(())
+}
+
+#[test]
+fn test_should_exit_nonzero_when_validation_file_fails() {
+    let directory = temp_directory("validate");
+    let file = directory.join("doc.validation.yaml");
+    fs::write(
+        &file,
+        "schema: |-\n  namespace doc { relation viewer {} }\nrelationships: |-\n  \
+         doc:readme#viewer@user:alice\nassertions:\n  assertTrue:\n    - \
+         doc:readme#viewer@user:bob\n",
+    )
+    .expect("write validation file");
+
+    let output = zanzibar(&["validate", path(&file)]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "{}:7: assertTrue doc:readme#viewer@user:bob failed: denied\n",
+            file.display()
+        )
+    );
+    fs::remove_dir_all(directory).ok();
+}
+
+fn zanzibar(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_zanzibar"))
+        .args(arguments)
+        .output()
+        .expect("run zanzibar")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn path(path: &Path) -> &str {
+    path.to_str().expect("temp paths are UTF-8")
+}
+
+fn temp_directory(name: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("simple-zanzibar-cli-{name}-{}", process::id()));
+    fs::remove_dir_all(&directory).ok();
+    fs::create_dir_all(&directory).expect("create temp directory");
+    directory
+}
@@ -4,17 +4,26 @@ use std::{
     num::{NonZeroU32, NonZeroU64},
     path::{Path, PathBuf},
     process,
-    sync::atomic::{AtomicU64, Ordering},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
 };
 
 use proptest::{prelude::*, test_runner::TestCaseError};
 use simple_zanzibar::{
-    SnapshotCompression, SnapshotIntegrityMode, SnapshotIoError, SnapshotLoadOptions,
-    SnapshotLoadProfile, SnapshotSaveOptions, SnapshotValidationMode, ZanzibarEngine,
+    SnapshotChain, SnapshotCompression, SnapshotIdentityMode, SnapshotIntegrityMode,
+    SnapshotIoError, SnapshotLoadOptions, SnapshotLoadProfile, SnapshotSaveOptions,
+    SnapshotValidationMode, ZanzibarEngine,
+    caveat::CaveatContext,
     eval::EvaluationLimits,
     model::{LookupResourcesRequest, LookupSubjectsRequest, Object, Relation, RelationTuple, User},
     relationship::RelationshipMutation,
-    revision::Consistency,
+    reload::SnapshotReloadEvent,
+    revision::{Consistency, ConsistencyError, DatastoreId},
+    snapshot::{self, SectionKind, SnapshotCheckKind, SnapshotCheckOutcome},
+    watch::RelationshipUpdate,
 };
 
 static NEXT_TEST_FILE: AtomicU64 = AtomicU64::new(1);
@@ -60,7 +69,21 @@ fn test_should_save_and_load_snapshot_with_equivalent_behavior()
         let loaded = ZanzibarEngine::load_snapshot(&path, options)?;
         assert_equivalent_behavior(&service, &loaded)?;
 
-        let writer_token_result = loaded.check_with_consistency(
+        assert!(loaded.check_with_consistency(
+            &doc("direct_doc"),
+            &Relation("can_view".to_string()),
+            &User::UserId("alice".to_string()),
+            Consistency::Exact(writer_token.clone()),
+        )?);
+
+        let forked = ZanzibarEngine::load_snapshot(
+            &path,
+            SnapshotLoadOptions {
+                identity: SnapshotIdentityMode::Fork,
+                ..options
+            },
+        )?;
+        let writer_token_result = forked.check_with_consistency(
             &doc("direct_doc"),
             &Relation("can_view".to_string()),
             &User::UserId("alice".to_string()),
@@ -68,7 +91,9 @@ fn test_should_save_and_load_snapshot_with_equivalent_behavior()
         );
         assert!(matches!(
             writer_token_result,
-            Err(simple_zanzibar::EngineError::Consistency(_))
+            Err(simple_zanzibar::EngineError::Consistency(
+                ConsistencyError::WrongDatastore
+            ))
         ));
 
         let bob_tuple = simple_zanzibar::model::RelationTuple {
@@ -120,6 +145,318 @@ fn test_should_save_and_load_snapshot_through_public_engine_api()
     Ok(())
 }
 
+#[test]
+fn test_should_reload_snapshot_into_shared_engine() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = Arc::new(tiny_service()?);
+    let reader = Arc::clone(&engine);
+    let older = temp_snapshot_path("reload_older");
+    engine.save_snapshot(&older, SnapshotSaveOptions::default())?;
+    let mut events = engine.watch(Consistency::Latest)?;
+
+    let producer = tiny_service()?;
+    producer.write_tuple_with_token(&RelationTuple {
+        object: doc("readme"),
+        relation: Relation("viewer".to_string()),
+        user: User::UserId("bob".to_string()),
+    })?;
+    let newer = temp_snapshot_path("reload_newer");
+    producer.save_snapshot(&newer, SnapshotSaveOptions::default())?;
+    let token = engine.reload_snapshot(&newer, SnapshotLoadOptions::default())?;
+
+    let bob = User::UserId("bob".to_string());
+    assert!(reader.check_relation(&doc("readme"), &Relation("viewer".to_string()), &bob)?);
+    assert!(reader.check_with_consistency(
+        &doc("readme"),
+        &Relation("viewer".to_string()),
+        &bob,
+        Consistency::Exact(token.clone()),
+    )?);
+    let event = events
+        .next_timeout(Duration::from_secs(5))
+        .ok_or("reload event")?;
+    assert_eq!(event.token, token);
+    assert!(event.updates.iter().any(|update| {
+        matches!(update, RelationshipUpdate::Touch(relationship)
+            if relationship.subject().to_string() == "user:bob")
+    }));
+
+    let stale = engine.reload_snapshot(&older, SnapshotLoadOptions::default());
+    assert!(matches!(stale, Err(SnapshotIoError::StaleRevision { .. })));
+    assert!(reader.check_relation(&doc("readme"), &Relation("viewer".to_string()), &bob)?);
+    remove_file(&older);
+    remove_file(&newer);
+    Ok(())
+}
+
+#[test]
+fn test_should_reload_snapshot_dropped_into_watched_directory()
+-> Result<(), Box<dyn std::error::Error>> {
+    let directory = temp_snapshot_path("reload_dir").with_extension("d");
+    fs::create_dir_all(&directory)?;
+    let engine = Arc::new(tiny_service()?);
+    let mut watcher = engine.watch_snapshot_directory(
+        &directory,
+        SnapshotLoadOptions::default(),
+        Duration::from_millis(10),
+    )?;
+
+    let producer = tiny_service()?;
+    producer.write_tuple_with_token(&RelationTuple {
+        object: doc("readme"),
+        relation: Relation("viewer".to_string()),
+        user: User::UserId("carol".to_string()),
+    })?;
+    let staged = directory.join("next.szsnap.partial");
+    producer.save_snapshot(&staged, SnapshotSaveOptions::default())?;
+    fs::rename(&staged, directory.join("next.szsnap"))?;
+
+    let event = watcher
+        .next_timeout(Duration::from_secs(5))
+        .ok_or("reload outcome")?;
+    assert!(matches!(event, SnapshotReloadEvent::Reloaded { .. }));
+    assert!(engine.check_relation(
+        &doc("readme"),
+        &Relation("viewer".to_string()),
+        &User::UserId("carol".to_string()),
+    )?);
+    drop(watcher);
+    let _ = fs::remove_dir_all(&directory);
+    Ok(())
+}
+
+#[test]
+fn test_should_layer_snapshot_deltas_onto_base_artifact() -> Result<(), Box<dyn std::error::Error>>
+{
+    let engine = tiny_service()?;
+    for relationship in ["doc:readme#viewer@user:bob", "doc:guide#viewer@user:carol"] {
+        engine.touch_relationship(relationship)?;
+    }
+    let base = temp_snapshot_path("delta_base").with_extension("szsnap.zst");
+    engine.save_snapshot(&base, SnapshotSaveOptions::zstd())?;
+
+    engine.delete_relationship("doc:guide#viewer@user:carol")?;
+    engine.touch_relationship("doc:handbook#viewer@user:dave")?;
+    let first = temp_snapshot_path("delta_first").with_extension("szdelta");
+    let chain = SnapshotChain::new(&base);
+    engine.save_snapshot_delta(&chain, &first, SnapshotSaveOptions::default())?;
+    let full = temp_snapshot_path("delta_full");
+    engine.save_snapshot(&full, SnapshotSaveOptions::default())?;
+    assert!(fs::metadata(&first)?.len() < fs::metadata(&full)?.len() / 2);
+
+    engine.add_dsl("namespace folder { relation viewer {} }")?;
+    let token = engine.touch_relationship("folder:eng#viewer@user:erin")?;
+    let chain = chain.with_delta(&first);
+    let second = temp_snapshot_path("delta_second").with_extension("szdelta.zst");
+    engine.save_snapshot_delta(&chain, &second, SnapshotSaveOptions::zstd())?;
+
+    let chain = chain.with_delta(&second);
+    let loaded = ZanzibarEngine::load_snapshot_chain(&chain, SnapshotLoadOptions::default())?;
+    assert_eq!(loaded.export_policy_text()?, engine.export_policy_text()?);
+    assert!(loaded.check_with_consistency(
+        &Object {
+            namespace: "folder".to_string(),
+            id: "eng".to_string(),
+        },
+        &Relation("viewer".to_string()),
+        &User::UserId("erin".to_string()),
+        Consistency::Exact(token),
+    )?);
+
+    let left = ZanzibarEngine::load_snapshot_chain(&chain, SnapshotLoadOptions::default())?;
+    let right = ZanzibarEngine::load_snapshot_chain(&chain, SnapshotLoadOptions::default())?;
+    left.touch_relationship("doc:readme#viewer@user:frank")?;
+    right.delete_relationship("doc:readme#viewer@user:bob")?;
+    let left_sibling = temp_snapshot_path("delta_left").with_extension("szdelta");
+    let right_sibling = temp_snapshot_path("delta_right").with_extension("szdelta");
+    left.save_snapshot_delta(&chain, &left_sibling, SnapshotSaveOptions::default())?;
+    right.save_snapshot_delta(&chain, &right_sibling, SnapshotSaveOptions::default())?;
+    left.touch_relationship("doc:guide#viewer@user:grace")?;
+    let after_left = temp_snapshot_path("delta_after_left").with_extension("szdelta");
+    left.save_snapshot_delta(
+        &chain.clone().with_delta(&left_sibling),
+        &after_left,
+        SnapshotSaveOptions::default(),
+    )?;
+    let continued = chain
+        .clone()
+        .with_delta(&left_sibling)
+        .with_delta(&after_left);
+    assert_eq!(
+        ZanzibarEngine::load_snapshot_chain(&continued, SnapshotLoadOptions::default())?
+            .export_policy_text()?,
+        left.export_policy_text()?
+    );
+    let crossed = chain
+        .clone()
+        .with_delta(&right_sibling)
+        .with_delta(&after_left);
+    assert!(matches!(
+        ZanzibarEngine::load_snapshot_chain(&crossed, SnapshotLoadOptions::default()),
+        Err(SnapshotIoError::DeltaMismatch {
+            reason: "delta was saved after a different artifact than the one before it"
+        })
+    ));
+
+    let skipped = SnapshotChain::new(&base).with_delta(&second);
+    assert!(matches!(
+        ZanzibarEngine::load_snapshot_chain(&skipped, SnapshotLoadOptions::default()),
+        Err(SnapshotIoError::DeltaMismatch { .. })
+    ));
+    let other_base = SnapshotChain::new(&full).with_delta(&first);
+    assert!(matches!(
+        ZanzibarEngine::load_snapshot_chain(&other_base, SnapshotLoadOptions::default()),
+        Err(SnapshotIoError::DeltaMismatch { .. })
+    ));
+
+    let mut corrupt = fs::read(&first)?;
+    let last = corrupt.len() - 1;
+    corrupt[last] ^= 0xff;
+    fs::write(&first, corrupt)?;
+    assert!(matches!(
+        ZanzibarEngine::load_snapshot_chain(&chain, SnapshotLoadOptions::default()),
+        Err(SnapshotIoError::Format {
+            reason: "delta checksum mismatch"
+        })
+    ));
+    for path in [
+        &base,
+        &first,
+        &second,
+        &full,
+        &left_sibling,
+        &right_sibling,
+        &after_left,
+    ] {
+        remove_file(path);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_should_round_trip_caveats_through_snapshot() -> Result<(), Box<dyn std::error::Error>> {
+    let service = ZanzibarEngine::builder().build();
+    service.add_dsl(
+        r"
+    caveat under_limit(amount int, limit int) {
+        amount <= limit
+    }
+
+    namespace doc {
+        relation viewer {}
+    }
+    ",
+    )?;
+    service.touch_relationship(r#"doc:readme#viewer@user:alice[under_limit:{"limit":10}]"#)?;
+    service.touch_relationship("doc:readme#viewer@user:bob")?;
+    let path = temp_snapshot_path("caveats");
+    service.save_snapshot(&path, SnapshotSaveOptions::default())?;
+
+    let loaded = ZanzibarEngine::load_snapshot(&path, SnapshotLoadOptions::default())?;
+    let check = |user: &str, amount: i64| {
+        loaded.check(
+            simple_zanzibar::model::CheckRequest::new(
+                doc("readme"),
+                Relation("viewer".to_string()),
+                User::UserId(user.to_string()),
+                Consistency::Latest,
+            )
+            .with_context(CaveatContext::new().with("amount", amount)),
+        )
+    };
+    assert!(check("alice", 5)?.allowed);
+    assert!(!check("alice", 50)?.allowed);
+    assert!(check("bob", 50)?.allowed);
+    let exported = loaded.export_policy_text()?;
+    assert_eq!(exported, service.export_policy_text()?);
+    assert!(
+        exported
+            .schema
+            .contains("caveat under_limit(amount int, limit int)")
+    );
+    assert!(
+        exported
+            .relationship_files
+            .iter()
+            .any(|file| file.contents.contains(r#"[under_limit:{"limit":10}]"#))
+    );
+
+    remove_file(&path);
+    Ok(())
+}
+
+#[test]
+fn test_should_round_trip_expirations_through_snapshot() -> Result<(), Box<dyn std::error::Error>> {
+    let service = ZanzibarEngine::builder().build();
+    service.add_dsl(
+        r"
+    namespace doc {
+        relation viewer {}
+    }
+    ",
+    )?;
+    service.touch_relationship("doc:readme#viewer@user:alice[expiration:2000-01-01T00:00:00Z]")?;
+    service.touch_relationship("doc:readme#viewer@user:bob[expiration:9999-01-01T00:00:00Z]")?;
+    let path = temp_snapshot_path("expirations");
+    service.save_snapshot(&path, SnapshotSaveOptions::default())?;
+
+    let loaded = ZanzibarEngine::load_snapshot(&path, SnapshotLoadOptions::default())?;
+    let viewer = Relation("viewer".to_string());
+    assert!(!loaded.check_relation(&doc("readme"), &viewer, &User::UserId("alice".to_string()))?);
+    assert!(loaded.check_relation(&doc("readme"), &viewer, &User::UserId("bob".to_string()))?);
+    let exported = loaded.export_policy_text()?;
+    assert_eq!(exported, service.export_policy_text()?);
+    assert!(exported.relationship_files.iter().any(|file| {
+        file.contents
+            .contains("doc:readme#viewer@user:bob[expiration:9999-01-01T00:00:00Z]")
+    }));
+    loaded.write_relationships([RelationshipMutation::create(
+        "doc:readme#viewer@user:alice",
+    )?])?;
+    assert!(loaded.check_relation(&doc("readme"), &viewer, &User::UserId("alice".to_string()))?);
+
+    remove_file(&path);
+    Ok(())
+}
+
+#[test]
+fn test_should_round_trip_wildcard_subjects_through_snapshot_and_policy_text()
+-> Result<(), Box<dyn std::error::Error>> {
+    let service = ZanzibarEngine::builder().build();
+    service.add_dsl(
+        r#"
+    namespace doc {
+        relation viewer {
+            wildcard(subject_type: "user")
+        }
+    }
+    "#,
+    )?;
+    service.touch_relationship("doc:readme#viewer@user:*")?;
+    let path = temp_snapshot_path("wildcards");
+    service.save_snapshot(&path, SnapshotSaveOptions::default())?;
+
+    let loaded = ZanzibarEngine::load_snapshot(&path, SnapshotLoadOptions::default())?;
+    let viewer = Relation("viewer".to_string());
+    assert!(loaded.check_relation(&doc("readme"), &viewer, &User::UserId("alice".to_string()))?);
+    let exported = loaded.export_policy_text()?;
+    assert!(exported.schema.contains("wildcard(subject_type: \"user\")"));
+    assert!(
+        exported
+            .relationship_files
+            .iter()
+            .any(|file| file.contents.contains("doc:readme#viewer@user:*"))
+    );
+
+    let reimported = ZanzibarEngine::from_policy_text(&exported)?;
+    assert!(reimported.check_relation(
+        &doc("readme"),
+        &viewer,
+        &User::UserId("bob".to_string())
+    )?);
+    remove_file(&path);
+    Ok(())
+}
+
 #[test]
 fn test_should_save_and_load_zstd_snapshot_through_service_and_engine()
 -> Result<(), Box<dyn std::error::Error>> {
@@ -303,6 +640,70 @@ fn test_should_reject_checksum_mismatch() -> Result<(), Box<dyn std::error::Erro
     Ok(())
 }
 
+#[test]
+fn test_should_inspect_snapshot_sections_and_report_every_failed_check()
+-> Result<(), Box<dyn std::error::Error>> {
+    let (service, token) = populated_service()?;
+    let path = temp_snapshot_path("inspect");
+    service.save_snapshot(&path, SnapshotSaveOptions::zstd())?;
+    let inspection = snapshot::inspect(&path)?;
+    remove_file(&path);
+
+    assert!(inspection.is_valid());
+    assert_eq!(inspection.compression, SnapshotCompression::Zstd);
+    assert_eq!(inspection.format_version, 3);
+    assert_eq!(inspection.revision, token.revision());
+    assert_eq!(inspection.schema_hash, token.schema_hash());
+    assert_eq!(inspection.datastore_id, Some(token.datastore_id()));
+    let rows = inspection
+        .section(SectionKind::RelationshipRows)
+        .ok_or("missing rows section")?;
+    assert_eq!(rows.row_count, u64::from(inspection.relationship_count));
+    assert_eq!(
+        inspection.sections.last().map(|section| section.kind),
+        Some(SectionKind::Footer)
+    );
+    assert_eq!(
+        inspection
+            .sections
+            .iter()
+            .map(|section| section.bytes)
+            .sum::<u64>()
+            + u64::try_from(HEADER_LEN + DIRECTORY_ENTRY_LEN * inspection.sections.len())?,
+        inspection.payload_bytes
+    );
+
+    let mut bytes = snapshot_bytes()?;
+    let symbol_bytes = section_range(&bytes, SECTION_KIND_SYMBOL_BYTES)?;
+    set_byte(&mut bytes, symbol_bytes.start, 0xFF)?;
+    let schema = section_range(&bytes, 1)?;
+    set_byte(&mut bytes, schema.start, b'X')?;
+    let path = temp_snapshot_path("inspect_corrupt");
+    fs::write(&path, &bytes)?;
+    let inspection = snapshot::inspect(&path)?;
+    remove_file(&path);
+
+    let failures = inspection
+        .failures()
+        .map(|check| {
+            (
+                check.kind,
+                matches!(check.outcome, SnapshotCheckOutcome::Skipped),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        [
+            (SnapshotCheckKind::Checksum, false),
+            (SnapshotCheckKind::Schema, false),
+            (SnapshotCheckKind::Relationships, false),
+            (SnapshotCheckKind::Caveats, true),
+        ]
+    );
+    Ok(())
+}
+
 #[test]
 fn test_should_support_external_integrity_only_for_trusted_fast_load()
 -> Result<(), Box<dyn std::error::Error>> {
@@ -589,28 +990,40 @@ fn assert_equivalent_behavior(
     }
 
     assert_eq!(
-        original.lookup_resources(&LookupResourcesRequest {
-            subject: alice.clone(),
-            permission: can_view.clone(),
-            resource_type: "doc".to_string(),
-        })?,
-        loaded.lookup_resources(&LookupResourcesRequest {
-            subject: alice.clone(),
-            permission: can_view.clone(),
-            resource_type: "doc".to_string(),
-        })?,
+        original
+            .lookup_resources(&LookupResourcesRequest {
+                subject: alice.clone(),
+                permission: can_view.clone(),
+                resource_type: "doc".to_string(),
+                cursor: None,
+            })?
+            .resources,
+        loaded
+            .lookup_resources(&LookupResourcesRequest {
+                subject: alice.clone(),
+                permission: can_view.clone(),
+                resource_type: "doc".to_string(),
+                cursor: None,
+            })?
+            .resources,
     );
     assert_eq!(
-        original.lookup_subjects(&LookupSubjectsRequest {
-            resource: doc("direct_doc"),
-            permission: can_view.clone(),
-            subject_type: "user".to_string(),
-        })?,
-        loaded.lookup_subjects(&LookupSubjectsRequest {
-            resource: doc("direct_doc"),
-            permission: can_view,
-            subject_type: "user".to_string(),
-        })?,
+        original
+            .lookup_subjects(&LookupSubjectsRequest {
+                resource: doc("direct_doc"),
+                permission: can_view.clone(),
+                subject_type: "user".to_string(),
+                cursor: None,
+            })?
+            .subjects,
+        loaded
+            .lookup_subjects(&LookupSubjectsRequest {
+                resource: doc("direct_doc"),
+                permission: can_view,
+                subject_type: "user".to_string(),
+                cursor: None,
+            })?
+            .subjects,
     );
     Ok(())
 }
@@ -681,10 +1094,11 @@ fn assert_random_direct_equivalence(
             subject: User::UserId(format!("user_{user_id}")),
             permission: viewer.clone(),
             resource_type: "doc".to_string(),
+            cursor: None,
         };
         assert_eq!(
-            original.lookup_resources(&request)?,
-            loaded.lookup_resources(&request)?,
+            original.lookup_resources(&request)?.resources,
+            loaded.lookup_resources(&request)?.resources,
         );
     }
 
@@ -728,7 +1142,9 @@ fn snapshot_bytes() -> Result<Vec<u8>, Box<dyn std::error::Error>> {
 }
 
 fn tiny_service() -> Result<ZanzibarEngine, Box<dyn std::error::Error>> {
-    let service = ZanzibarEngine::builder().build();
+    let service = ZanzibarEngine::builder()
+        .datastore_id(DatastoreId::from_bytes([0x5a; 16]))
+        .build();
     service.add_dsl(
         r"
     namespace doc {
@@ -775,6 +1191,7 @@ fn snapshot_load_options(
         integrity: SnapshotIntegrityMode::Checksum,
         max_file_bytes: non_zero_u64(16 * 1024 * 1024),
         required_index_profile: simple_zanzibar::IndexProfile::Full,
+        identity: simple_zanzibar::SnapshotIdentityMode::Restore,
     }
 }
 
@@ -786,6 +1203,7 @@ fn snapshot_external_load_options(validation: SnapshotValidationMode) -> Snapsho
         integrity: SnapshotIntegrityMode::External,
         max_file_bytes: non_zero_u64(16 * 1024 * 1024),
         required_index_profile: simple_zanzibar::IndexProfile::Full,
+        identity: simple_zanzibar::SnapshotIdentityMode::Restore,
     }
 }
 
@@ -1,6 +1,9 @@
 use std::str::FromStr;
 
-use simple_zanzibar::domain::{DomainError, IdentifierKind, ObjectType, Relationship, SubjectRef};
+use simple_zanzibar::domain::{
+    CaveatName, DomainError, IdentifierKind, ObjectType, RelationName, Relationship,
+    RelationshipExpiration, SubjectRef,
+};
 
 #[test]
 fn test_should_parse_direct_relationship() -> Result<(), DomainError> {
@@ -61,3 +64,148 @@ fn test_should_reject_malformed_relationship() {
         Some(DomainError::MalformedRelationship { .. })
     ));
 }
+
+#[test]
+fn test_should_round_trip_caveated_relationship() -> Result<(), DomainError> {
+    let relationship =
+        Relationship::from_str(r#"doc:readme#viewer@user:alice[under_limit:{"limit":10}]"#)?;
+
+    let caveat = relationship.caveat().map(|caveat| caveat.name().as_str());
+    assert_eq!(caveat, Some("under_limit"));
+    assert_eq!(
+        relationship.to_string(),
+        r#"doc:readme#viewer@user:alice[under_limit:{"limit":10}]"#
+    );
+    assert_eq!(
+        relationship,
+        Relationship::from_str("doc:readme#viewer@user:alice")?
+    );
+    assert!(Relationship::from_str("doc:readme#viewer@user:alice[under_limit:{").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_should_parse_wildcard_subject() -> Result<(), DomainError> {
+    let relationship = Relationship::from_str("doc:readme#viewer@user:*")?;
+
+    assert!(matches!(
+        relationship.subject(),
+        SubjectRef::Wildcard(object_type) if object_type.as_str() == "user"
+    ));
+    assert_eq!(relationship.to_string(), "doc:readme#viewer@user:*");
+    assert!(Relationship::from_str("doc:*#viewer@user:alice").is_err());
+    assert!(Relationship::from_str("doc:readme#viewer@group:*#member").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_should_escape_brackets_in_relationship_object_ids() -> Result<(), DomainError> {
+    let relationship = Relationship::new(
+        "doc:a[1]".parse()?,
+        RelationName::new("viewer")?,
+        "user:b[x]".parse()?,
+    );
+    assert_eq!(relationship.to_string(), r"doc:a\[1\]#viewer@user:b\[x\]");
+    assert_eq!(
+        Relationship::from_str(&relationship.to_string())?,
+        relationship
+    );
+    assert!(
+        Relationship::from_str(&relationship.to_string())?
+            .caveat()
+            .is_none()
+    );
+
+    let text = r#"doc:a\[1\]#viewer@user:b\[x\][under_limit:{"tags":["a]"]}][expiration:2030-01-01T00:00:00Z]"#;
+    let caveated = Relationship::from_str(text)?;
+    assert_eq!(caveated, relationship);
+    assert_eq!(
+        caveated.caveat().map(|caveat| caveat.name().as_str()),
+        Some("under_limit")
+    );
+    assert!(caveated.expiration().is_some());
+    assert_eq!(caveated.to_string(), text);
+    Ok(())
+}
+
+#[test]
+fn test_should_reject_unescaped_brackets_from_older_relationship_text() -> Result<(), DomainError> {
+    for older in [
+        "doc:a[1]#viewer@user:b",
+        "doc:1#viewer@user:a[b]x",
+        "doc:1#viewer@group:g[1]#member",
+    ] {
+        assert!(
+            matches!(
+                Relationship::from_str(older),
+                Err(DomainError::MalformedRelationship { reason })
+                    if reason.contains("must be escaped")
+            ),
+            "{older}"
+        );
+    }
+
+    let ambiguous = Relationship::from_str("doc:1#viewer@user:a[b]")?;
+    assert_eq!(ambiguous.subject().to_string(), "user:a");
+    assert_eq!(
+        ambiguous.caveat().map(|caveat| caveat.name().as_str()),
+        Some("b")
+    );
+    let escaped = Relationship::from_str(r"doc:1#viewer@user:a\[b\]")?;
+    assert_eq!(escaped.subject().to_string(), "user:a[b]");
+    assert!(escaped.caveat().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_should_reserve_expiration_caveat_name() -> Result<(), DomainError> {
+    assert!(matches!(
+        CaveatName::new("expiration"),
+        Err(DomainError::ReservedIdentifier {
+            kind: IdentifierKind::CaveatName,
+            name: "expiration",
+        })
+    ));
+
+    let text = r#"doc:readme#viewer@user:alice[expiration_window:{"days":7}][expiration:2030-01-01T00:00:00Z]"#;
+    let relationship = Relationship::from_str(text)?;
+    assert_eq!(
+        relationship.caveat().map(|caveat| caveat.name().as_str()),
+        Some("expiration_window")
+    );
+    assert!(relationship.expiration().is_some());
+    assert_eq!(relationship.to_string(), text);
+    assert_eq!(
+        Relationship::from_str(&relationship.to_string())?,
+        relationship
+    );
+    Ok(())
+}
+
+#[test]
+fn test_should_round_trip_expiring_relationship() -> Result<(), DomainError> {
+    let text = r#"doc:readme#viewer@user:alice[under_limit:{"limit":10}][expiration:2028-02-29T12:30:05Z]"#;
+    let relationship = Relationship::from_str(text)?;
+
+    let expiration = relationship
+        .expiration()
+        .map(RelationshipExpiration::unix_seconds);
+    assert_eq!(expiration, Some(1_835_440_205));
+    assert!(relationship.caveat().is_some());
+    assert_eq!(relationship.to_string(), text);
+    assert_eq!(
+        RelationshipExpiration::from_unix_seconds(0)?.to_string(),
+        "1970-01-01T00:00:00Z"
+    );
+    for invalid in [
+        "doc:readme#viewer@user:alice[expiration:2027-02-29T00:00:00Z]",
+        "doc:readme#viewer@user:alice[expiration:2027-01-01T00:00:00+01:00]",
+        "doc:readme#viewer@user:alice[expiration:1969-12-31T23:59:59Z]",
+    ] {
+        assert!(matches!(
+            Relationship::from_str(invalid),
+            Err(DomainError::InvalidExpiration { .. })
+        ));
+    }
+    Ok(())
+}
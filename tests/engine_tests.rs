@@ -1,23 +1,38 @@
 use std::{
+    collections::HashSet,
     io,
-    sync::{Arc, Barrier},
+    num::NonZeroU32,
+    sync::{
+        Arc, Barrier,
+        atomic::{AtomicBool, Ordering},
+    },
     thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use simple_zanzibar::{
-    EngineError, TenantId, ZanzibarEngine, ZanzibarTenantShards,
-    domain::Relationship,
+    EngineError, PolicyText, PolicyTextFile, TenantId, ZanzibarEngine, ZanzibarTenantShards,
+    caveat::CaveatContext,
+    domain::{Relationship, RelationshipExpiration},
+    eval::EvaluationLimits,
+    impact::{AccessGrant, AccessImpactRequest, AccessImpactScope},
     model::{
-        CheckRequest, ExpandRequest, ExpandedUserset, LookupResourcesRequest,
-        LookupSubjectsRequest, Object, Relation, User,
+        CheckRequest, ExpandRequest, ExpandedUserset, LookupObjectPermissionsRequest,
+        LookupResourcesRequest, LookupSubjectsRequest, Object, Permissionship, Relation, User,
     },
     relationship::{
-        Precondition, RelationshipFilter, RelationshipMutation, StoreError, SubjectFilter,
+        Precondition, QueryLimit, RelationshipFilter, RelationshipMutation, StoreError,
+        SubjectFilter,
+    },
+    revision::{Consistency, ConsistencyError, LookupCursor},
+    schema::{
+        MigrationStep, SchemaChange, SchemaChangeSeverity, SchemaError, SchemaMigration,
+        SchemaSource, SchemaSyntax,
     },
-    revision::Consistency,
-    schema::SchemaSource,
+    watch::RelationshipUpdate,
 };
 
+const WATCH_TIMEOUT: Duration = Duration::from_secs(5);
 const DOC_SCHEMA: &str = r"
     namespace doc {
         relation viewer {}
@@ -38,10 +53,12 @@ fn test_should_isolate_multiple_engine_instances() -> Result<(), Box<dyn std::er
     first.apply_schema(SchemaSource {
         name: Some("doc-schema"),
         text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
     })?;
     second.apply_schema(SchemaSource {
         name: Some("doc-schema"),
         text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
     })?;
 
     first.touch_relationship("doc:readme#viewer@user:alice")?;
@@ -66,6 +83,7 @@ fn test_should_shard_engines_by_tenant() -> Result<(), Box<dyn std::error::Error
         engine.apply_schema(SchemaSource {
             name: Some("doc-schema"),
             text: DOC_SCHEMA,
+            syntax: SchemaSyntax::Legacy,
         })?;
     }
     first_a.touch_relationship("doc:readme#viewer@user:alice")?;
@@ -82,6 +100,7 @@ fn test_should_use_public_engine_request_response_api() -> Result<(), Box<dyn st
     engine.apply_schema(SchemaSource {
         name: Some("doc-schema"),
         text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
     })?;
 
     let relationship: Relationship = "doc:readme#viewer@user:alice".parse()?;
@@ -136,6 +155,7 @@ fn test_should_use_public_engine_request_response_api() -> Result<(), Box<dyn st
                 subject: alice.clone(),
                 permission: relation.clone(),
                 resource_type: "doc".to_string(),
+                cursor: None,
             })?
             .resources,
         vec![object.clone()],
@@ -146,6 +166,7 @@ fn test_should_use_public_engine_request_response_api() -> Result<(), Box<dyn st
                 resource: object,
                 permission: relation,
                 subject_type: "user".to_string(),
+                cursor: None,
             })?
             .subjects,
         vec![alice],
@@ -174,6 +195,7 @@ fn test_should_persist_direct_non_user_subject_relationships()
     engine.apply_schema(SchemaSource {
         name: Some("doc-schema"),
         text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
     })?;
     let relationship: Relationship = "doc:readme#viewer@group:eng".parse()?;
 
@@ -191,6 +213,7 @@ fn test_should_persist_direct_non_user_subject_relationships()
     engine.apply_schema(SchemaSource {
         name: Some("doc-schema"),
         text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
     })?;
     engine.write_relationships([RelationshipMutation::Delete(relationship)])?;
     Ok(())
@@ -203,6 +226,7 @@ fn test_should_publish_atomic_snapshots_while_latest_readers_run()
     let schema_token = engine.apply_schema(SchemaSource {
         name: Some("doc-schema"),
         text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
     })?;
     let object = doc_object();
     let relation = viewer();
@@ -365,3 +389,1314 @@ fn test_should_validate_domain_values_during_deserialization() {
     assert!(invalid_lookup_resources.is_err());
     assert!(invalid_lookup_subjects.is_err());
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_should_serialize_check_trace() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.apply_schema(SchemaSource {
+        name: Some("doc-schema"),
+        text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
+    })?;
+    engine.touch_relationship("doc:readme#viewer@user:alice")?;
+
+    let trace = engine.check_with_trace(CheckRequest::new(
+        doc_object(),
+        viewer(),
+        User::user_id("alice"),
+        Consistency::Latest,
+    ))?;
+    let serialized = serde_json::to_value(&trace)?;
+
+    assert_eq!(serialized["root"]["kind"]["kind"], "check");
+    assert_eq!(
+        serialized["root"]["relationships"],
+        serde_json::json!(["doc:readme#viewer@user:alice"])
+    );
+    assert_eq!(serialized["stats"]["relationshipsRead"], 1);
+    let decoded: simple_zanzibar::trace::CheckTraceResponse = serde_json::from_value(serialized)?;
+    assert_eq!(decoded, trace);
+    Ok(())
+}
+
+const CAVEAT_SCHEMA: &str = r#"
+    caveat under_limit(amount int, limit int) {
+        amount <= limit
+    }
+
+    caveat on_network(user_ip ipaddress) {
+        in_cidr(user_ip, "10.0.0.0/8")
+    }
+
+    namespace group {
+        relation member {}
+    }
+
+    namespace doc {
+        relation viewer {}
+    }
+"#;
+
+#[test]
+fn test_should_evaluate_caveated_relationships_against_check_context()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(CAVEAT_SCHEMA)?;
+    engine.touch_relationship(r#"doc:readme#viewer@user:alice[under_limit:{"limit":10}]"#)?;
+
+    let check = |context: CaveatContext| {
+        engine.check(
+            CheckRequest::new(
+                doc_object(),
+                viewer(),
+                User::user_id("alice"),
+                Consistency::Latest,
+            )
+            .with_context(context),
+        )
+    };
+
+    let allowed = check(CaveatContext::new().with("amount", 5))?;
+    assert!(allowed.allowed);
+    assert_eq!(allowed.permissionship, Permissionship::HasPermission);
+
+    let denied = check(CaveatContext::new().with("amount", 20))?;
+    assert!(!denied.allowed);
+    assert_eq!(denied.permissionship, Permissionship::NoPermission);
+
+    let conditional = check(CaveatContext::new())?;
+    assert!(!conditional.allowed);
+    assert_eq!(
+        conditional.permissionship,
+        Permissionship::ConditionalPermission
+    );
+    assert_eq!(conditional.missing_context, vec!["amount".to_owned()]);
+
+    let bound = check(CaveatContext::new().with("amount", 5).with("limit", 1))?;
+    assert_eq!(bound.permissionship, Permissionship::HasPermission);
+    Ok(())
+}
+
+#[test]
+fn test_should_check_bulk_with_per_item_results() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(CAVEAT_SCHEMA)?;
+    engine.write_relationships([
+        RelationshipMutation::touch("group:eng#member@user:alice")?,
+        RelationshipMutation::touch("doc:readme#viewer@group:eng#member")?,
+        RelationshipMutation::touch("doc:guide#viewer@group:eng#member")?,
+        RelationshipMutation::touch(r#"doc:readme#viewer@user:bob[under_limit:{"limit":10}]"#)?,
+    ])?;
+    let foreign = ZanzibarEngine::builder().build();
+    let foreign_token = foreign.add_dsl_with_token(CAVEAT_SCHEMA)?;
+    let request = |id: &str, user: &str| {
+        CheckRequest::new(
+            Object::new("doc", id),
+            viewer(),
+            User::user_id(user),
+            Consistency::Latest,
+        )
+    };
+
+    let results = engine.check_bulk(vec![
+        request("readme", "alice"),
+        request("bad id", "alice"),
+        request("guide", "alice"),
+        request("readme", "bob"),
+        request("readme", "bob").with_context(CaveatContext::new().with("amount", 5)),
+        CheckRequest::new(
+            doc_object(),
+            viewer(),
+            User::user_id("alice"),
+            Consistency::Exact(foreign_token),
+        ),
+        request("readme", "carol"),
+    ]);
+
+    let permissionships = results
+        .iter()
+        .map(|result| result.as_ref().map(|response| response.permissionship))
+        .collect::<Vec<_>>();
+    assert_eq!(permissionships.len(), 7);
+    assert_eq!(permissionships[0], Ok(Permissionship::HasPermission));
+    assert!(matches!(permissionships[1], Err(EngineError::Domain(_))));
+    assert_eq!(permissionships[2], Ok(Permissionship::HasPermission));
+    assert_eq!(
+        permissionships[3],
+        Ok(Permissionship::ConditionalPermission)
+    );
+    assert_eq!(permissionships[4], Ok(Permissionship::HasPermission));
+    assert!(matches!(
+        permissionships[5],
+        Err(EngineError::Consistency(ConsistencyError::WrongDatastore))
+    ));
+    assert_eq!(permissionships[6], Ok(Permissionship::NoPermission));
+    assert_eq!(
+        results[3]
+            .as_ref()
+            .map(|response| response.missing_context.clone()),
+        Ok(vec!["amount".to_owned()])
+    );
+    assert_eq!(
+        results[6]
+            .as_ref()
+            .map(|response| response.missing_context.clone()),
+        Ok(Vec::new())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_should_check_mixed_context_bulk_at_one_snapshot_during_writes()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(CAVEAT_SCHEMA)?;
+    engine.touch_relationship(r#"doc:readme#viewer@user:bob[under_limit:{"limit":10}]"#)?;
+    let requests = (0..32)
+        .map(|amount| {
+            CheckRequest::new(
+                doc_object(),
+                viewer(),
+                User::user_id("bob"),
+                Consistency::Latest,
+            )
+            .with_context(CaveatContext::new().with("amount", amount))
+        })
+        .collect::<Vec<_>>();
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|scope| -> Result<(), Box<dyn std::error::Error>> {
+        let writer = scope.spawn(|| {
+            let mut index = 0_u64;
+            while !stop.load(Ordering::Relaxed) {
+                let _ = engine.touch_relationship(&format!("doc:d{index}#viewer@user:alice"));
+                index += 1;
+            }
+        });
+        let mut outcome = Ok(());
+        for _ in 0..50 {
+            let tokens = engine
+                .check_bulk(requests.clone())
+                .into_iter()
+                .map(|result| result.map(|response| response.token.map(|token| token.to_string())))
+                .collect::<Result<HashSet<_>, _>>()?;
+            if tokens.len() != 1 {
+                outcome = Err(format!("one batch read {} snapshots", tokens.len()));
+                break;
+            }
+        }
+        stop.store(true, Ordering::Relaxed);
+        writer.join().map_err(|_| "writer thread panicked")?;
+        Ok(outcome?)
+    })
+}
+
+#[test]
+fn test_should_apply_caveats_on_userset_edges() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(CAVEAT_SCHEMA)?;
+    engine.touch_relationship("doc:readme#viewer@group:eng#member[on_network]")?;
+    engine.touch_relationship("group:eng#member@user:alice")?;
+
+    let check = |context: CaveatContext| {
+        engine.check(
+            CheckRequest::new(
+                doc_object(),
+                viewer(),
+                User::user_id("alice"),
+                Consistency::Latest,
+            )
+            .with_context(context),
+        )
+    };
+
+    let inside = check(CaveatContext::new().with("user_ip", "10.1.2.3"))?;
+    assert_eq!(inside.permissionship, Permissionship::HasPermission);
+    let outside = check(CaveatContext::new().with("user_ip", "192.168.0.1"))?;
+    assert_eq!(outside.permissionship, Permissionship::NoPermission);
+    assert!(!engine.check_relation(&doc_object(), &viewer(), &User::user_id("alice"))?);
+
+    let bob = engine.check(
+        CheckRequest::new(
+            doc_object(),
+            viewer(),
+            User::user_id("bob"),
+            Consistency::Latest,
+        )
+        .with_context(CaveatContext::new().with("user_ip", "10.1.2.3")),
+    )?;
+    assert_eq!(bob.permissionship, Permissionship::NoPermission);
+    Ok(())
+}
+
+const PUBLIC_SCHEMA: &str = r#"
+    namespace doc {
+        relation viewer {
+            wildcard(subject_type: "user")
+        }
+        relation banned {}
+        relation can_view {
+            rewrite exclusion(
+                computed_userset(relation: "viewer"),
+                computed_userset(relation: "banned")
+            )
+        }
+    }
+"#;
+
+#[test]
+fn test_should_grant_wildcard_subjects_to_every_user() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(PUBLIC_SCHEMA)?;
+    engine.touch_relationship("doc:readme#viewer@user:*")?;
+    engine.touch_relationship("doc:readme#banned@user:mallory")?;
+    engine.touch_relationship("doc:private#viewer@user:bob")?;
+    let can_view = Relation("can_view".to_string());
+
+    assert!(engine.check_relation(&doc_object(), &viewer(), &User::user_id("alice"))?);
+    assert!(engine.check_relation(&doc_object(), &can_view, &User::user_id("alice"))?);
+    assert!(!engine.check_relation(&doc_object(), &can_view, &User::user_id("mallory"))?);
+
+    let resources = engine.lookup_resources(LookupResourcesRequest::new(
+        User::user_id("alice"),
+        can_view.clone(),
+        "doc",
+    ))?;
+    assert_eq!(resources.resources, vec![doc_object()]);
+    let mallory_resources = engine.lookup_resources(LookupResourcesRequest::new(
+        User::user_id("mallory"),
+        can_view.clone(),
+        "doc",
+    ))?;
+    assert!(mallory_resources.resources.is_empty());
+
+    let subjects =
+        engine.lookup_subjects(LookupSubjectsRequest::new(doc_object(), can_view, "user"))?;
+    assert_eq!(subjects.subjects, vec![User::Wildcard]);
+    Ok(())
+}
+
+#[test]
+fn test_should_reject_wildcard_subject_on_relation_without_wildcard()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(PUBLIC_SCHEMA)?;
+
+    let error = engine.touch_relationship("doc:readme#banned@user:*").err();
+    assert!(matches!(
+        error,
+        Some(EngineError::Schema(SchemaError::WildcardNotAllowed { relation, subject_type, .. }))
+            if relation == "banned" && subject_type == "user"
+    ));
+    Ok(())
+}
+
+const TYPED_SCHEMA: &str = r#"
+    namespace group {
+        relation member: user | group#member
+    }
+    namespace folder {
+        relation viewer: user
+    }
+    namespace doc {
+        relation parent: folder
+        relation viewer: user | user:* | group#member
+        relation can_view {
+            rewrite union(
+                computed_userset(relation: "viewer"),
+                tuple_to_userset(tupleset: "parent", computed_userset: "viewer")
+            )
+        }
+    }
+"#;
+
+#[test]
+fn test_should_enforce_typed_relation_subjects() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(TYPED_SCHEMA)?;
+    engine.touch_relationship("doc:readme#parent@folder:root")?;
+    engine.touch_relationship("folder:root#viewer@user:alice")?;
+    engine.touch_relationship("doc:readme#viewer@group:eng#member")?;
+    engine.touch_relationship("group:eng#member@user:bob")?;
+    engine.touch_relationship("doc:public#viewer@user:*")?;
+    let can_view = Relation("can_view".to_string());
+
+    assert!(engine.check_relation(&doc_object(), &can_view, &User::user_id("alice"))?);
+    assert!(engine.check_relation(&doc_object(), &can_view, &User::user_id("bob"))?);
+    let resources = engine.lookup_resources(LookupResourcesRequest::new(
+        User::user_id("alice"),
+        can_view.clone(),
+        "doc",
+    ))?;
+    assert_eq!(
+        resources.resources,
+        vec![Object::new("doc", "public"), doc_object()]
+    );
+    let subjects = engine.lookup_subjects(LookupSubjectsRequest::new(
+        doc_object(),
+        can_view.clone(),
+        "user",
+    ))?;
+    assert!(subjects.subjects.contains(&User::user_id("alice")));
+    for (relationship, expected_subject) in [
+        ("doc:readme#parent@user:bob", "user"),
+        ("doc:readme#parent@folder:root#viewer", "folder#viewer"),
+        ("doc:readme#viewer@folder:root", "folder"),
+        ("doc:readme#viewer@folder:root#viewer", "folder#viewer"),
+    ] {
+        let error = engine.touch_relationship(relationship).err();
+        assert!(
+            matches!(
+                &error,
+                Some(EngineError::Schema(SchemaError::SubjectNotAllowed { subject, .. }))
+                    if subject == expected_subject
+            ),
+            "{relationship}: {error:?}"
+        );
+    }
+    let error = engine
+        .touch_relationship("doc:readme#parent@user:bob")
+        .err()
+        .map(|error| error.to_string());
+    assert_eq!(
+        error.as_deref(),
+        Some("relation 'doc.parent' does not allow subject 'user'; expected folder")
+    );
+    assert!(matches!(
+        engine.touch_relationship("folder:root#viewer@user:*").err(),
+        Some(EngineError::Schema(SchemaError::WildcardNotAllowed { .. }))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_should_not_follow_plain_object_arrows_in_untyped_tuplesets()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(
+        r#"
+        namespace folder {
+            relation viewer {}
+        }
+        namespace doc {
+            relation parent {}
+            relation viewer {
+                rewrite union(
+                    this,
+                    tuple_to_userset(tupleset: "parent", computed_userset: "viewer")
+                )
+            }
+        }
+        "#,
+    )?;
+    engine.touch_relationship("doc:1#parent@folder:f")?;
+    engine.touch_relationship("folder:f#viewer@user:a")?;
+    let viewer = Relation("viewer".to_string());
+    let doc = Object::new("doc", "1");
+
+    assert!(!engine.check_relation(&doc, &viewer, &User::user_id("a"))?);
+    let resources = engine.lookup_resources(LookupResourcesRequest::new(
+        User::user_id("a"),
+        viewer.clone(),
+        "doc",
+    ))?;
+    assert!(resources.resources.is_empty());
+    let subjects = engine.lookup_subjects(LookupSubjectsRequest::new(doc, viewer, "user"))?;
+    assert!(subjects.subjects.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_should_export_typed_relation_declarations() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(TYPED_SCHEMA)?;
+    engine.touch_relationship("doc:readme#viewer@group:eng#member")?;
+
+    let exported = engine.export_policy_text()?;
+    assert!(
+        exported
+            .schema
+            .contains("    relation viewer: group#member | user | user:*\n")
+    );
+    assert!(exported.schema.contains("    relation parent: folder\n"));
+    let imported = ZanzibarEngine::from_policy_text(&exported)?;
+    assert_eq!(imported.export_policy_text()?, exported);
+    assert!(
+        imported
+            .touch_relationship("doc:readme#parent@user:bob")
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_should_stream_relationship_and_schema_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    let mut live = engine.watch(Consistency::Latest)?;
+    let schema_token = engine.apply_schema(SchemaSource {
+        name: Some("doc-schema"),
+        text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
+    })?;
+    let touch_token = engine.touch_relationship("doc:readme#viewer@user:alice")?;
+    let delete_token = engine.delete_relationship("doc:readme#viewer@user:alice")?;
+
+    let schema_event = live
+        .next_timeout(WATCH_TIMEOUT)
+        .ok_or("missing schema event")?;
+    assert_eq!(schema_event.token, schema_token);
+    assert!(schema_event.updates.is_empty());
+    let schema_change = schema_event.schema_change.ok_or("missing schema change")?;
+    assert_eq!(schema_change.previous, None);
+    assert_eq!(schema_change.current, schema_token.schema_hash());
+
+    let alice: Relationship = "doc:readme#viewer@user:alice".parse()?;
+    let touch_event = live
+        .next_timeout(WATCH_TIMEOUT)
+        .ok_or("missing touch event")?;
+    assert_eq!(touch_event.token, touch_token);
+    assert_eq!(
+        touch_event.updates,
+        vec![RelationshipUpdate::Touch(alice.clone())]
+    );
+    assert_eq!(touch_event.schema_change, None);
+    let delete_event = live
+        .next_timeout(WATCH_TIMEOUT)
+        .ok_or("missing delete event")?;
+    assert_eq!(delete_event.revision(), delete_token.revision());
+    assert_eq!(
+        delete_event.updates,
+        vec![RelationshipUpdate::Delete(alice)]
+    );
+    assert!(live.try_next().is_none());
+
+    let mut resumed = engine.watch(Consistency::Exact(schema_token))?;
+    let replayed = [resumed.try_next(), resumed.try_next(), resumed.try_next()];
+    assert_eq!(
+        replayed,
+        [Some(touch_event), Some(delete_event), None],
+        "exact watch replays retained revisions newer than the token"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_should_reject_watch_from_expired_revision() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder()
+        .retained_snapshots(std::num::NonZeroUsize::MIN)
+        .build();
+    let expired = engine.apply_schema(SchemaSource {
+        name: Some("doc-schema"),
+        text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
+    })?;
+    engine.touch_relationship("doc:readme#viewer@user:alice")?;
+
+    assert!(matches!(
+        engine.watch(Consistency::Exact(expired)),
+        Err(EngineError::Consistency(
+            ConsistencyError::RevisionExpired { .. }
+        ))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_should_stream_policy_replacement_as_deletes_then_touches()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.apply_schema(SchemaSource {
+        name: Some("doc-schema"),
+        text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
+    })?;
+    let before = engine.touch_relationship("doc:readme#viewer@user:alice")?;
+    let mut stream = engine.watch(Consistency::Exact(before))?;
+
+    let replaced = engine.apply_policy_text(&PolicyText::new(
+        DOC_SCHEMA.to_string(),
+        vec![PolicyTextFile {
+            path: "relationships/doc.rel".to_string(),
+            contents: "doc:readme#viewer@user:bob\n".to_string(),
+        }],
+    ))?;
+
+    let mut updates = Vec::new();
+    while let Some(event) = stream.try_next() {
+        assert!(event.schema_change.is_none());
+        updates.extend(event.updates);
+        if event.token == replaced {
+            break;
+        }
+    }
+    assert_eq!(
+        updates,
+        vec![
+            RelationshipUpdate::Delete("doc:readme#viewer@user:alice".parse()?),
+            RelationshipUpdate::Touch("doc:readme#viewer@user:bob".parse()?),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_should_hide_and_sweep_expired_relationships() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.apply_schema(SchemaSource {
+        name: Some("doc-schema"),
+        text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
+    })?;
+    let expired: Relationship =
+        "doc:readme#viewer@user:alice[expiration:2000-01-01T00:00:00Z]".parse()?;
+    engine.write_relationships([
+        RelationshipMutation::Touch(expired.clone()),
+        RelationshipMutation::touch("doc:readme#viewer@user:bob[expiration:9999-12-31T23:59:59Z]")?,
+    ])?;
+
+    assert!(!engine.check_relation(&doc_object(), &viewer(), &User::user_id("alice"))?);
+    assert!(engine.check_relation(&doc_object(), &viewer(), &User::user_id("bob"))?);
+    let subjects = engine
+        .lookup_subjects(&LookupSubjectsRequest {
+            resource: doc_object(),
+            permission: viewer(),
+            subject_type: "user".to_string(),
+            cursor: None,
+        })?
+        .subjects;
+    assert_eq!(subjects, vec![User::user_id("bob")]);
+    let resources = engine
+        .lookup_resources(&LookupResourcesRequest {
+            subject: User::user_id("alice"),
+            permission: viewer(),
+            resource_type: "doc".to_string(),
+            cursor: None,
+        })?
+        .resources;
+    assert!(resources.is_empty());
+
+    let mut stream = engine.watch(Consistency::Latest)?;
+    let swept = engine
+        .sweep_expired_relationships()?
+        .ok_or("expired relationship was not swept")?;
+    let event = stream
+        .next_timeout(WATCH_TIMEOUT)
+        .ok_or("missing sweep event")?;
+    assert_eq!(event.token, swept);
+    assert_eq!(event.updates, vec![RelationshipUpdate::Delete(expired)]);
+    assert_eq!(engine.sweep_expired_relationships()?, None);
+    Ok(())
+}
+
+#[test]
+fn test_should_reject_older_bracketed_subject_ids_that_read_as_caveats()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.apply_schema(SchemaSource {
+        name: Some("doc-schema"),
+        text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
+    })?;
+
+    assert!(
+        engine
+            .touch_relationship("doc:readme#viewer@user:a[b]")
+            .is_err()
+    );
+    engine.touch_relationship(r"doc:readme#viewer@user:a\[b\]")?;
+    assert!(engine.check_relation(&doc_object(), &viewer(), &User::user_id("a[b]"))?);
+    Ok(())
+}
+
+#[test]
+fn test_should_regrant_an_expired_relationship_before_the_sweep()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.apply_schema(SchemaSource {
+        name: Some("doc-schema"),
+        text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
+    })?;
+    engine.write_relationships([RelationshipMutation::create(
+        "doc:readme#viewer@user:alice[expiration:2000-01-01T00:00:00Z]",
+    )?])?;
+    assert!(!engine.check_relation(&doc_object(), &viewer(), &User::user_id("alice"))?);
+
+    engine.write_relationships([RelationshipMutation::create(
+        "doc:readme#viewer@user:alice",
+    )?])?;
+    assert!(engine.check_relation(&doc_object(), &viewer(), &User::user_id("alice"))?);
+    assert_eq!(engine.sweep_expired_relationships()?, None);
+    Ok(())
+}
+
+const PAGED_SCHEMA: &str = r"
+    namespace doc {
+        relation editor {}
+        relation viewer {}
+    }
+";
+
+#[test]
+fn test_should_page_lookups_at_cursor_revision() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = paged_engine()?;
+    for index in 0..5 {
+        engine.touch_relationship(&format!("doc:d{index}#viewer@user:alice"))?;
+        engine.touch_relationship(&format!("doc:readme#viewer@user:u{index}"))?;
+    }
+
+    let request = LookupResourcesRequest::new(User::user_id("alice"), viewer(), "doc");
+    let mut page = engine.lookup_resources(&request)?;
+    let mut resources = page.resources.clone();
+    let first_token = page
+        .token
+        .clone()
+        .ok_or("lookup did not report its token")?;
+    engine.touch_relationship("doc:late#viewer@user:alice")?;
+    while let Some(cursor) = page.cursor {
+        assert_eq!(page.resources.len(), 2);
+        page = engine.lookup_resources(request.clone().with_cursor(cursor))?;
+        assert_eq!(page.token.as_ref(), Some(&first_token));
+        resources.extend(page.resources.iter().cloned());
+    }
+    assert_eq!(resources.len(), 5);
+    assert!(resources.iter().all(|resource| resource.id != "late"));
+
+    let request = LookupSubjectsRequest::new(doc_object(), viewer(), "user");
+    let mut page = engine.lookup_subjects(&request)?;
+    let mut subjects = page.subjects.clone();
+    while let Some(cursor) = page.cursor {
+        page = engine.lookup_subjects(request.clone().with_cursor(cursor))?;
+        subjects.extend(page.subjects.iter().cloned());
+    }
+    let mut expected = (0..5)
+        .map(|index| User::user_id(format!("u{index}")))
+        .collect::<Vec<_>>();
+    subjects.sort_by_key(|subject| format!("{subject:?}"));
+    expected.sort_by_key(|subject| format!("{subject:?}"));
+    assert_eq!(subjects, expected);
+    Ok(())
+}
+
+#[test]
+fn test_should_page_with_the_expiration_cutoff_of_the_first_page()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = paged_engine()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let expires = RelationshipExpiration::from_unix_seconds(now + 2)?;
+    for index in 0..6 {
+        let relationship: Relationship = format!("doc:readme#viewer@user:u{index}").parse()?;
+        let relationship = if index % 2 == 0 {
+            relationship.with_expiration(expires)
+        } else {
+            relationship
+        };
+        engine.write_relationships([RelationshipMutation::Touch(relationship)])?;
+    }
+    let readme_viewers = RelationshipFilter::new(
+        "doc".try_into()?,
+        Some("readme".try_into()?),
+        Some("viewer".try_into()?),
+        None,
+        QueryLimit::default(),
+    );
+    let page_size = NonZeroU32::new(2).ok_or("page size must be non-zero")?;
+    let mut read =
+        engine.read_relationships(&readme_viewers, Consistency::Latest, None, page_size)?;
+    let mut lookup = engine.lookup_subjects(LookupSubjectsRequest::new(
+        Object::new("doc", "readme"),
+        viewer(),
+        "user",
+    ))?;
+    let mut relationships = read.relationships.clone();
+    let mut subjects = lookup.subjects.clone();
+    assert!(read.cursor.is_some() && lookup.cursor.is_some());
+
+    while SystemTime::now() < expires.to_system_time() {
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert!(!engine.check_relation(&doc_object(), &viewer(), &User::user_id("u0"))?);
+    while let Some(cursor) = read.cursor.take() {
+        read = engine.read_relationships(
+            &readme_viewers,
+            Consistency::Latest,
+            Some(&cursor),
+            page_size,
+        )?;
+        relationships.extend(read.relationships.iter().cloned());
+    }
+    while let Some(cursor) = lookup.cursor.take() {
+        lookup = engine.lookup_subjects(
+            LookupSubjectsRequest::new(Object::new("doc", "readme"), viewer(), "user")
+                .with_cursor(cursor),
+        )?;
+        subjects.extend(lookup.subjects.iter().cloned());
+    }
+    assert_eq!(relationships.len(), 6);
+    assert_eq!(
+        subjects.into_iter().collect::<HashSet<_>>(),
+        (0..6)
+            .map(|index| User::user_id(format!("u{index}")))
+            .collect::<HashSet<_>>()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_should_reject_cursors_with_a_rewritten_expiration_cutoff()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = paged_engine()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let expires = RelationshipExpiration::from_unix_seconds(now + 1)?;
+    for index in 1..=5 {
+        let relationship: Relationship = format!("doc:{index}#viewer@user:alice").parse()?;
+        let relationship = if index == 3 {
+            relationship.with_expiration(expires)
+        } else {
+            relationship
+        };
+        engine.write_relationships([RelationshipMutation::Touch(relationship)])?;
+    }
+    while SystemTime::now() < expires.to_system_time() {
+        thread::sleep(Duration::from_millis(50));
+    }
+    let request = LookupResourcesRequest::new(User::user_id("alice"), viewer(), "doc");
+    let cursor = engine
+        .lookup_resources(&request)?
+        .cursor
+        .ok_or("missing continuation cursor")?;
+
+    let mut fields = cursor
+        .to_string()
+        .splitn(6, ':')
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    fields[3] = "0".to_owned();
+    let tampered = fields.join(":").parse::<LookupCursor>()?;
+    let error = engine
+        .lookup_resources(request.clone().with_cursor(tampered))
+        .err()
+        .ok_or("cursor with a rewritten expiration cutoff was accepted")?;
+    assert!(matches!(
+        error,
+        EngineError::Consistency(ConsistencyError::CursorMismatch)
+    ));
+
+    let other = paged_engine()?;
+    assert!(matches!(
+        other.lookup_resources(request.clone().with_cursor(cursor.clone())),
+        Err(EngineError::Consistency(ConsistencyError::CursorMismatch))
+    ));
+    let rest = engine.lookup_resources(request.with_cursor(cursor))?;
+    assert!(!rest.resources.contains(&Object::new("doc", "3")));
+    Ok(())
+}
+
+#[test]
+fn test_should_page_object_permissions_across_groups() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = paged_engine()?;
+    engine.write_relationships([
+        RelationshipMutation::touch("doc:readme#editor@user:alice")?,
+        RelationshipMutation::touch("doc:readme#viewer@user:bob")?,
+        RelationshipMutation::touch("doc:readme#viewer@user:carol")?,
+    ])?;
+    let request = LookupObjectPermissionsRequest::new(doc_object(), "user", Consistency::Latest);
+
+    let first = engine.lookup_object_permissions(&request)?;
+    let groups = first
+        .permissions
+        .iter()
+        .map(|group| (group.permission.0.as_str(), group.subjects.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(groups, vec![("editor", 1), ("viewer", 1)]);
+    let cursor = first.cursor.ok_or("missing continuation cursor")?;
+    assert_eq!(cursor.to_string().parse::<LookupCursor>()?, cursor);
+
+    let second = engine.lookup_object_permissions(request.with_cursor(cursor.clone()))?;
+    assert_eq!(second.permissions.len(), 1);
+    assert_eq!(second.permissions[0].permission, viewer());
+    assert_eq!(second.permissions[0].subjects.len(), 1);
+    assert_eq!(second.cursor, None);
+
+    let other = LookupObjectPermissionsRequest::new(doc_object(), "group", Consistency::Latest);
+    let error = engine
+        .lookup_object_permissions(other.with_cursor(cursor))
+        .err()
+        .ok_or("cursor from another request was accepted")?;
+    assert!(matches!(
+        error,
+        EngineError::Consistency(ConsistencyError::CursorMismatch)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_should_read_relationships_by_filter_in_pages() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = paged_engine()?;
+    engine.write_relationships([
+        RelationshipMutation::touch("doc:readme#viewer@user:alice")?,
+        RelationshipMutation::touch("doc:readme#viewer@user:bob")?,
+        RelationshipMutation::touch("doc:readme#viewer@user:carol")?,
+        RelationshipMutation::touch("doc:readme#editor@user:alice")?,
+        RelationshipMutation::touch("doc:guide#viewer@user:alice")?,
+    ])?;
+    let page_size = NonZeroU32::new(2).ok_or("page size must be non-zero")?;
+
+    let readme_viewers = RelationshipFilter::new(
+        "doc".try_into()?,
+        Some("readme".try_into()?),
+        Some("viewer".try_into()?),
+        None,
+        QueryLimit::default(),
+    );
+    let mut page =
+        engine.read_relationships(&readme_viewers, Consistency::Latest, None, page_size)?;
+    let mut relationships = page.relationships.clone();
+    engine.touch_relationship("doc:readme#viewer@user:dave")?;
+    while let Some(cursor) = page.cursor.take() {
+        assert_eq!(page.relationships.len(), 2);
+        page = engine.read_relationships(
+            &readme_viewers,
+            Consistency::Latest,
+            Some(&cursor),
+            page_size,
+        )?;
+        relationships.extend(page.relationships.iter().cloned());
+    }
+    let mut relationships = relationships
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    relationships.sort();
+    assert_eq!(
+        relationships,
+        vec![
+            "doc:readme#viewer@user:alice",
+            "doc:readme#viewer@user:bob",
+            "doc:readme#viewer@user:carol",
+        ]
+    );
+
+    let alice_on_docs = RelationshipFilter::new(
+        "doc".try_into()?,
+        None,
+        None,
+        Some(SubjectFilter::exact(
+            "user".try_into()?,
+            "alice".try_into()?,
+            None,
+        )),
+        QueryLimit::default(),
+    );
+    let page =
+        engine.read_relationships(&alice_on_docs, Consistency::Latest, None, NonZeroU32::MAX)?;
+    assert_eq!(page.relationships.len(), 3);
+    assert_eq!(page.cursor, None);
+
+    let cursor = engine
+        .read_relationships(&readme_viewers, Consistency::Latest, None, page_size)?
+        .cursor
+        .ok_or("missing continuation cursor")?;
+    let error = engine
+        .read_relationships(
+            &alice_on_docs,
+            Consistency::Latest,
+            Some(&cursor),
+            page_size,
+        )
+        .err()
+        .ok_or("cursor from another filter was accepted")?;
+    assert!(matches!(
+        error,
+        EngineError::Consistency(ConsistencyError::CursorMismatch)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_should_read_relationships_by_subject_without_resource_type()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.apply_schema(SchemaSource {
+        name: Some("subject-read-schema"),
+        text: r"
+            namespace doc {
+                relation viewer {}
+            }
+            namespace folder {
+                relation owner {}
+            }
+        ",
+        syntax: SchemaSyntax::Legacy,
+    })?;
+    engine.write_relationships([
+        RelationshipMutation::touch("doc:readme#viewer@user:alice")?,
+        RelationshipMutation::touch("doc:guide#viewer@user:alice")?,
+        RelationshipMutation::touch("doc:guide#viewer@user:bob")?,
+        RelationshipMutation::touch("folder:root#owner@user:alice")?,
+    ])?;
+    let page_size = NonZeroU32::new(2).ok_or("page size must be non-zero")?;
+
+    let alice = RelationshipFilter::any_resource_type(
+        None,
+        Some(SubjectFilter::exact(
+            "user".try_into()?,
+            "alice".try_into()?,
+            None,
+        )),
+        QueryLimit::default(),
+    );
+    let mut page = engine.read_relationships(&alice, Consistency::Latest, None, page_size)?;
+    let mut relationships = page.relationships.clone();
+    engine.touch_relationship("folder:archive#owner@user:alice")?;
+    while let Some(cursor) = page.cursor.take() {
+        page = engine.read_relationships(&alice, Consistency::Latest, Some(&cursor), page_size)?;
+        relationships.extend(page.relationships.iter().cloned());
+    }
+    let mut relationships = relationships
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    relationships.sort();
+    assert_eq!(
+        relationships,
+        vec![
+            "doc:guide#viewer@user:alice",
+            "doc:readme#viewer@user:alice",
+            "folder:root#owner@user:alice",
+        ]
+    );
+
+    let everything = RelationshipFilter::any_resource_type(None, None, QueryLimit::default());
+    let page =
+        engine.read_relationships(&everything, Consistency::Latest, None, NonZeroU32::MAX)?;
+    assert_eq!(page.relationships.len(), 5);
+    Ok(())
+}
+
+#[test]
+fn test_should_delete_relationships_by_filter() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = paged_engine()?;
+    engine.write_relationships([
+        RelationshipMutation::touch("doc:readme#viewer@user:alice")?,
+        RelationshipMutation::touch("doc:readme#viewer@user:bob")?,
+        RelationshipMutation::touch("doc:readme#editor@user:carol")?,
+        RelationshipMutation::touch("doc:guide#viewer@user:alice")?,
+    ])?;
+    let readme = RelationshipFilter::new(
+        "doc".try_into()?,
+        Some("readme".try_into()?),
+        None,
+        None,
+        QueryLimit::default(),
+    );
+    let guarded = Precondition::MustMatch(RelationshipFilter::for_exact_subject(
+        &"doc:readme".parse()?,
+        "editor".try_into()?,
+        SubjectFilter::exact("user".try_into()?, "alice".try_into()?, None),
+    ));
+    let error = engine
+        .delete_relationships(readme.clone(), [guarded], NonZeroU32::MAX)
+        .err()
+        .ok_or("failed precondition was ignored")?;
+    assert!(matches!(
+        error,
+        EngineError::Store(StoreError::PreconditionFailed { .. })
+    ));
+
+    let first =
+        engine.delete_relationships(readme.clone(), [], NonZeroU32::new(2).ok_or("zero")?)?;
+    assert_eq!(first.deleted, 2);
+    assert!(first.truncated);
+    let second = engine.delete_relationships(readme.clone(), [], NonZeroU32::MAX)?;
+    assert_eq!(second.deleted, 1);
+    assert!(!second.truncated);
+    assert!(second.token.revision() > first.token.revision());
+
+    let remaining =
+        engine.read_relationships(&readme.clone(), Consistency::Latest, None, NonZeroU32::MAX)?;
+    assert!(remaining.relationships.is_empty());
+    let before = engine.read_relationships(
+        &readme,
+        Consistency::Exact(first.token),
+        None,
+        NonZeroU32::MAX,
+    )?;
+    assert_eq!(before.relationships.len(), 1);
+    assert!(engine.check_relation(
+        &Object::new("doc", "guide"),
+        &viewer(),
+        &User::user_id("alice")
+    )?);
+    Ok(())
+}
+
+#[test]
+fn test_should_fork_engine_for_speculative_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let engine = paged_engine()?;
+    let forked_at = engine
+        .write_relationships([RelationshipMutation::touch("doc:readme#viewer@user:alice")?])?;
+    engine.touch_relationship("doc:readme#viewer@user:bob")?;
+
+    let fork = engine.fork(Consistency::Exact(forked_at.clone()))?;
+    let readme = Object::new("doc", "readme");
+    assert!(fork.check_relation(&readme, &viewer(), &User::user_id("alice"))?);
+    assert!(!fork.check_relation(&readme, &viewer(), &User::user_id("bob"))?);
+
+    fork.write_relationships([
+        RelationshipMutation::touch("doc:readme#viewer@user:carol")?,
+        RelationshipMutation::delete("doc:readme#viewer@user:alice")?,
+    ])?;
+    fork.add_dsl("namespace folder { relation viewer {} }")?;
+    fork.touch_relationship("folder:shared#viewer@user:carol")?;
+    let resources = fork.lookup_resources(&LookupResourcesRequest {
+        subject: User::user_id("carol"),
+        permission: viewer(),
+        resource_type: "doc".to_string(),
+        cursor: None,
+    })?;
+    assert_eq!(resources.resources, vec![readme.clone()]);
+    assert!(!fork.check_relation(&readme, &viewer(), &User::user_id("alice"))?);
+
+    assert!(engine.check_relation(&readme, &viewer(), &User::user_id("alice"))?);
+    assert!(!engine.check_relation(&readme, &viewer(), &User::user_id("carol"))?);
+    assert!(
+        engine
+            .touch_relationship("folder:shared#viewer@user:carol")
+            .is_err()
+    );
+    assert!(matches!(
+        fork.check_with_consistency(
+            &readme,
+            &viewer(),
+            &User::user_id("alice"),
+            Consistency::Exact(forked_at),
+        ),
+        Err(EngineError::Consistency(ConsistencyError::WrongDatastore))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_should_report_access_impact_of_pending_changes() -> Result<(), Box<dyn std::error::Error>> {
+    const SCHEMA: &str = r"
+        definition user {}
+        definition group {
+            relation member: user
+        }
+        definition folder {
+            relation viewer: user | group#member
+            relation editor: user
+            permission view = viewer + editor
+        }
+    ";
+    let engine = ZanzibarEngine::builder().build();
+    engine.apply_schema(SchemaSource::spicedb(SCHEMA))?;
+    engine.write_relationships([
+        RelationshipMutation::touch("group:eng#member@user:alice")?,
+        RelationshipMutation::touch("group:eng#member@user:bob")?,
+        RelationshipMutation::touch("folder:root#viewer@group:eng#member")?,
+        RelationshipMutation::touch("folder:root#editor@user:bob")?,
+    ])?;
+    let view = AccessImpactScope::new("folder", Relation("view".to_string()), "user");
+    let root = Object::new("folder", "root");
+
+    let impact = engine.analyze_access_impact(
+        &AccessImpactRequest::new([view.clone()]).with_mutations([
+            RelationshipMutation::delete("folder:root#viewer@group:eng#member")?,
+            RelationshipMutation::touch("folder:root#viewer@user:carol")?,
+        ]),
+    )?;
+    let grant = |subject: &str| AccessGrant {
+        resource: root.clone(),
+        permission: Relation("view".to_string()),
+        subject: User::user_id(subject),
+    };
+    assert_eq!(impact.gained, vec![grant("carol")]);
+    assert_eq!(impact.lost, vec![grant("alice")]);
+    assert!(engine.check_relation(
+        &root,
+        &Relation("view".to_string()),
+        &User::user_id("alice")
+    )?);
+
+    let schema_impact =
+        engine.analyze_access_impact(&AccessImpactRequest::new([view]).with_schema(
+            SchemaSource::spicedb(&SCHEMA.replace("viewer + editor", "editor")),
+        ))?;
+    assert!(schema_impact.gained.is_empty());
+    assert_eq!(schema_impact.lost, vec![grant("alice")]);
+    Ok(())
+}
+
+#[test]
+fn test_should_count_relationships_invalidated_by_schema_diff()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.apply_schema(SchemaSource::spicedb(
+        r"
+        definition user {}
+        definition group {
+            relation member: user
+        }
+        definition doc {
+            relation owner: user
+            relation viewer: user | user:* | group#member
+        }
+        ",
+    ))?;
+    let token = engine.write_relationships([
+        RelationshipMutation::touch("group:eng#member@user:carol")?,
+        RelationshipMutation::touch("doc:readme#owner@user:alice")?,
+        RelationshipMutation::touch("doc:readme#viewer@user:bob")?,
+        RelationshipMutation::touch("doc:readme#viewer@user:*")?,
+        RelationshipMutation::touch("doc:readme#viewer@group:eng#member")?,
+    ])?;
+    let candidate = SchemaSource::spicedb(
+        r"
+        definition user {}
+        definition doc {
+            relation viewer: user
+            relation editor: user
+        }
+        ",
+    );
+
+    let report = engine.diff_schema(candidate)?;
+    let counts = report
+        .changes
+        .iter()
+        .map(|impact| (impact.change.severity(), impact.invalidated_relationships))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        counts,
+        [
+            (SchemaChangeSeverity::Breaking, 1),
+            (SchemaChangeSeverity::Breaking, 1),
+            (SchemaChangeSeverity::Breaking, 1),
+            (SchemaChangeSeverity::Safe, 0),
+            (SchemaChangeSeverity::Breaking, 2),
+        ]
+    );
+    assert!(matches!(
+        report.changes.get(3).map(|impact| &impact.change),
+        Some(SchemaChange::RelationAdded { relation, .. }) if relation.as_str() == "editor"
+    ));
+    assert_eq!(report.invalidated_relationships(), 5);
+    assert_eq!(report.token, token);
+    assert!(engine.replace_schema(candidate).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_should_migrate_schema_and_relationships_in_one_revision()
+-> Result<(), Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(
+        r"
+        namespace group {
+            relation member {}
+            relation reader {}
+        }
+        namespace doc {
+            relation reader {}
+            relation legacy {}
+            relation banned {}
+        }
+        ",
+    )?;
+    engine.write_relationships([
+        RelationshipMutation::touch("group:eng#member@user:alice")?,
+        RelationshipMutation::touch("doc:readme#reader@user:bob")?,
+        RelationshipMutation::touch("doc:readme#reader@group:eng#member")?,
+        RelationshipMutation::touch("group:eng#reader@user:dave")?,
+        RelationshipMutation::touch("doc:guide#legacy@group:eng#reader")?,
+        RelationshipMutation::touch("doc:guide#banned@user:carol")?,
+    ])?;
+    let mut events = engine.watch(Consistency::Latest)?;
+    let target = r"
+        namespace group {
+            relation member {}
+            relation viewer {}
+        }
+        namespace doc {
+            relation viewer {}
+        }
+        namespace folder {
+            relation viewer {}
+        }
+    ";
+    let rename = |namespace: &str| -> Result<MigrationStep, Box<dyn std::error::Error>> {
+        Ok(MigrationStep::RenameRelation {
+            namespace: namespace.try_into()?,
+            from: "reader".try_into()?,
+            to: "viewer".try_into()?,
+        })
+    };
+
+    let incomplete = engine.migrate_schema(
+        SchemaMigration::new(SchemaSource::legacy(target)).with_step(rename("doc")?),
+    );
+    assert!(incomplete.is_err());
+    assert!(engine.check_relation(
+        &Object::new("doc", "readme"),
+        &Relation("reader".to_string()),
+        &User::user_id("alice"),
+    )?);
+
+    let token = engine.migrate_schema(
+        SchemaMigration::new(SchemaSource::legacy(target))
+            .with_step(rename("group")?)
+            .with_step(rename("doc")?)
+            .with_step(MigrationStep::MoveRelationships {
+                from_namespace: "doc".try_into()?,
+                from_relation: "legacy".try_into()?,
+                to_namespace: "folder".try_into()?,
+                to_relation: "viewer".try_into()?,
+            })
+            .with_step(MigrationStep::DropRelation {
+                namespace: "doc".try_into()?,
+                relation: "banned".try_into()?,
+            }),
+    )?;
+
+    for (object, subject) in [
+        (Object::new("doc", "readme"), "alice"),
+        (Object::new("doc", "readme"), "bob"),
+        (Object::new("folder", "guide"), "dave"),
+    ] {
+        assert!(engine.check_relation_with_consistency(
+            &object,
+            &viewer(),
+            &User::user_id(subject),
+            Consistency::Exact(token.clone()),
+        )?);
+    }
+    let event = events
+        .next_timeout(WATCH_TIMEOUT)
+        .ok_or("migration event")?;
+    assert_eq!(event.token, token);
+    assert!(event.schema_change.is_some());
+    assert!(event.updates.iter().any(|update| matches!(
+        update,
+        RelationshipUpdate::Delete(relationship)
+            if relationship.to_string() == "doc:guide#banned@user:carol"
+    )));
+    Ok(())
+}
+
+fn paged_engine() -> Result<ZanzibarEngine, Box<dyn std::error::Error>> {
+    let engine = ZanzibarEngine::builder()
+        .evaluation_limits(EvaluationLimits {
+            max_lookup_results: NonZeroU32::new(2).ok_or("page size must be non-zero")?,
+            ..EvaluationLimits::default()
+        })
+        .build();
+    engine.apply_schema(SchemaSource {
+        name: Some("paged-schema"),
+        text: PAGED_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
+    })?;
+    Ok(engine)
+}
@@ -7,9 +7,11 @@ use simple_zanzibar::{
     error::ZanzibarError,
     eval::{EvaluationError, EvaluationLimits, Membership},
     model::{
-        ExpandedUserset, NamespaceConfig, Object, Relation, RelationConfig, RelationTuple, User,
-        UsersetExpression,
+        CheckRequest, ExpandedUserset, NamespaceConfig, Object, Permissionship, Relation,
+        RelationConfig, RelationTuple, User, UsersetExpression,
     },
+    revision::Consistency,
+    trace::{CheckTraceKind, CheckTraceNote},
 };
 
 /// Creates a `ZanzibarEngine` pre-populated with a common test configuration.
@@ -24,6 +26,8 @@ fn create_test_service() -> Result<ZanzibarEngine, ZanzibarError> {
                 RelationConfig {
                     name: Relation("parent".to_string()),
                     userset_rewrite: None,
+                    allowed_subject_types: None,
+                    wildcard_subject_types: Vec::new(),
                 },
             ),
             (
@@ -31,6 +35,8 @@ fn create_test_service() -> Result<ZanzibarEngine, ZanzibarError> {
                 RelationConfig {
                     name: Relation("owner".to_string()),
                     userset_rewrite: None,
+                    allowed_subject_types: None,
+                    wildcard_subject_types: Vec::new(),
                 },
             ),
             (
@@ -50,6 +56,8 @@ fn create_test_service() -> Result<ZanzibarEngine, ZanzibarError> {
                             computed_userset_relation: Relation("viewer".to_string()),
                         },
                     ])),
+                    allowed_subject_types: None,
+                    wildcard_subject_types: Vec::new(),
                 },
             ),
         ]),
@@ -64,6 +72,8 @@ fn create_test_service() -> Result<ZanzibarEngine, ZanzibarError> {
                 RelationConfig {
                     name: Relation("owner".to_string()),
                     userset_rewrite: None,
+                    allowed_subject_types: None,
+                    wildcard_subject_types: Vec::new(),
                 },
             ),
             (
@@ -73,6 +83,8 @@ fn create_test_service() -> Result<ZanzibarEngine, ZanzibarError> {
                     userset_rewrite: Some(UsersetExpression::ComputedUserset {
                         relation: Relation("owner".to_string()),
                     }),
+                    allowed_subject_types: None,
+                    wildcard_subject_types: Vec::new(),
                 },
             ),
         ]),
@@ -216,6 +228,8 @@ fn test_should_evaluate_intersection_and_exclusion() -> Result<(), ZanzibarError
                             relation: editor.clone(),
                         },
                     ])),
+                    allowed_subject_types: None,
+                    wildcard_subject_types: Vec::new(),
                 },
             ),
             (
@@ -230,6 +244,8 @@ fn test_should_evaluate_intersection_and_exclusion() -> Result<(), ZanzibarError
                             relation: banned.clone(),
                         }),
                     }),
+                    allowed_subject_types: None,
+                    wildcard_subject_types: Vec::new(),
                 },
             ),
         ]),
@@ -283,6 +299,8 @@ fn test_should_short_circuit_plain_exclusion_before_recursive_base() -> Result<(
                             relation: banned.clone(),
                         }),
                     }),
+                    allowed_subject_types: None,
+                    wildcard_subject_types: Vec::new(),
                 },
             ),
         ]),
@@ -326,6 +344,8 @@ fn test_should_return_depth_exceeded_distinct_from_denied() -> Result<(), Zanzib
                     userset_rewrite: Some(UsersetExpression::ComputedUserset {
                         relation: parent.clone(),
                     }),
+                    allowed_subject_types: None,
+                    wildcard_subject_types: Vec::new(),
                 },
             ),
         ]),
@@ -507,6 +527,8 @@ fn test_should_deny_recursion_cycle_without_depth_error() -> Result<(), Zanzibar
                 userset_rewrite: Some(UsersetExpression::ComputedUserset {
                     relation: viewer.clone(),
                 }),
+                allowed_subject_types: None,
+                wildcard_subject_types: Vec::new(),
             },
         )]),
     })?;
@@ -539,6 +561,8 @@ fn test_should_bound_expand_recursion_depth() -> Result<(), ZanzibarError> {
                 RelationConfig {
                     name: viewer.clone(),
                     userset_rewrite: Some(UsersetExpression::ComputedUserset { relation: parent }),
+                    allowed_subject_types: None,
+                    wildcard_subject_types: Vec::new(),
                 },
             ),
         ]),
@@ -580,6 +604,8 @@ fn test_should_bound_expand_cycle_without_depth_error() -> Result<(), ZanzibarEr
                 userset_rewrite: Some(UsersetExpression::ComputedUserset {
                     relation: viewer.clone(),
                 }),
+                allowed_subject_types: None,
+                wildcard_subject_types: Vec::new(),
             },
         )]),
     })?;
@@ -700,12 +726,112 @@ fn test_should_apply_membership_algebra_to_conditional_shape() {
     );
 }
 
+const TRACE_SCHEMA: &str = r#"
+    namespace folder {
+        relation viewer {}
+    }
+
+    namespace doc {
+        relation parent {}
+        relation viewer {
+            rewrite union(this, tuple_to_userset(tupleset: "parent", computed_userset: "viewer"))
+        }
+    }
+"#;
+
+#[test]
+fn test_should_trace_granting_path() -> Result<(), ZanzibarError> {
+    let service = ZanzibarEngine::builder().build();
+    service.add_dsl(TRACE_SCHEMA)?;
+    service.touch_relationship("doc:readme#parent@folder:root#viewer")?;
+    service.touch_relationship("folder:root#viewer@user:alice")?;
+    let doc = Object::new("doc", "readme");
+    let viewer = Relation("viewer".to_string());
+
+    let trace = service.check_with_trace(CheckRequest::new(
+        doc.clone(),
+        viewer.clone(),
+        User::user_id("alice"),
+        Consistency::Latest,
+    ))?;
+
+    assert_eq!(
+        trace.response.map(|response| response.permissionship),
+        Some(Permissionship::HasPermission)
+    );
+    assert_eq!(
+        trace.root.kind,
+        CheckTraceKind::Check {
+            object: doc,
+            relation: viewer.clone(),
+            subject: User::user_id("alice"),
+        }
+    );
+    let [union] = trace.root.children.as_slice() else {
+        panic!("expected one rewrite node, got {:?}", trace.root.children);
+    };
+    assert_eq!(union.kind, CheckTraceKind::Union);
+    let [this, arrow] = union.children.as_slice() else {
+        panic!("expected two union branches, got {:?}", union.children);
+    };
+    assert_eq!(this.kind, CheckTraceKind::This);
+    assert_eq!(this.result, Some(Permissionship::NoPermission));
+    assert_eq!(arrow.result, Some(Permissionship::HasPermission));
+    assert_eq!(
+        arrow.relationships,
+        vec!["doc:readme#parent@folder:root#viewer".parse()?]
+    );
+    let [folder_check] = arrow.children.as_slice() else {
+        panic!("expected one folder check, got {:?}", arrow.children);
+    };
+    assert_eq!(
+        folder_check.relationships,
+        vec!["folder:root#viewer@user:alice".parse()?]
+    );
+    assert_eq!(trace.stats.checks, 2);
+    assert_eq!(trace.stats.relationships_read, 2);
+    Ok(())
+}
+
+#[test]
+fn test_should_trace_depth_limit_instead_of_failing() -> Result<(), ZanzibarError> {
+    let service = ZanzibarEngine::builder()
+        .evaluation_limits(EvaluationLimits {
+            max_depth: NonZeroU32::MIN,
+            max_fanout_per_step: non_zero(100),
+            max_lookup_results: non_zero(100),
+        })
+        .build();
+    service.add_dsl(TRACE_SCHEMA)?;
+    service.touch_relationship("doc:readme#parent@folder:root#viewer")?;
+
+    let trace = service.check_with_trace(CheckRequest::new(
+        Object::new("doc", "readme"),
+        Relation("viewer".to_string()),
+        User::user_id("alice"),
+        Consistency::Latest,
+    ))?;
+
+    assert_eq!(trace.response, None);
+    assert_eq!(trace.root.result, None);
+    let mut nodes = vec![&trace.root];
+    let mut notes = Vec::new();
+    while let Some(node) = nodes.pop() {
+        notes.extend(node.note);
+        nodes.extend(node.children.iter());
+    }
+    assert_eq!(notes, vec![CheckTraceNote::DepthExceeded]);
+    Ok(())
+}
+
 fn plain_relation(relation: Relation) -> (Relation, RelationConfig) {
     (
         relation.clone(),
         RelationConfig {
             name: relation,
             userset_rewrite: None,
+            allowed_subject_types: None,
+            wildcard_subject_types: Vec::new(),
         },
     )
 }
@@ -455,6 +455,7 @@ fn test_lookup_resources_should_reuse_check_semantics_and_deduplicate()
         subject: alice,
         permission: viewer,
         resource_type: "doc".to_string(),
+        cursor: None,
     })?;
 
     assert_eq!(result.resources, vec![doc1, doc2]);
@@ -487,6 +488,7 @@ fn test_lookup_resources_should_enforce_result_limit() -> Result<(), Box<dyn std
         subject: alice,
         permission: viewer,
         resource_type: "doc".to_string(),
+        cursor: None,
     })?;
 
     assert_eq!(result.resources.len(), 1);
@@ -523,6 +525,7 @@ fn test_lookup_subjects_should_reuse_check_semantics() -> Result<(), Box<dyn std
         resource: doc,
         permission: viewer,
         subject_type: "user".to_string(),
+        cursor: None,
     })?;
 
     assert_eq!(result.subjects, vec![alice]);
@@ -557,6 +560,7 @@ fn test_lookup_subjects_should_return_userset_subjects() -> Result<(), Box<dyn s
         resource: doc,
         permission: viewer,
         subject_type: "group".to_string(),
+        cursor: None,
     })?;
 
     assert_eq!(result.subjects, vec![User::Userset(group, member)]);
@@ -579,6 +583,7 @@ fn test_lookup_subjects_should_reject_unknown_subject_namespace()
         resource: object("doc", "one"),
         permission: Relation("viewer".to_string()),
         subject_type: "group".to_string(),
+        cursor: None,
     });
 
     assert!(result.is_err());
@@ -608,6 +613,7 @@ fn test_lookup_resources_should_honor_exact_consistency() -> Result<(), Box<dyn
             subject: alice.clone(),
             permission: viewer.clone(),
             resource_type: "doc".to_string(),
+            cursor: None,
         },
         Consistency::Exact(schema_token),
     )?;
@@ -616,6 +622,7 @@ fn test_lookup_resources_should_honor_exact_consistency() -> Result<(), Box<dyn
             subject: alice,
             permission: viewer,
             resource_type: "doc".to_string(),
+            cursor: None,
         },
         Consistency::Exact(write_token),
     )?;
@@ -661,6 +668,11 @@ fn exact_filter(value: &str) -> Result<RelationshipFilter, DomainError> {
             object.object_id().as_str().try_into()?,
             Some(relation.clone()),
         ),
+        simple_zanzibar::domain::SubjectRef::Wildcard(object_type) => SubjectFilter::exact(
+            object_type.as_str().try_into()?,
+            simple_zanzibar::domain::SubjectId::wildcard(),
+            None,
+        ),
     };
 
     Ok(RelationshipFilter::for_exact_subject(
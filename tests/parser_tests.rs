@@ -92,3 +92,160 @@ fn test_parse_full_dsl() -> Result<(), ZanzibarError> {
 
     Ok(())
 }
+
+const SPICEDB_SCHEMA: &str = r#"
+    /** Users are never written as resources. */
+    definition user {}
+
+    definition group {
+        relation member: user | group#member
+    }
+
+    definition folder {
+        relation viewer: user
+    }
+
+    definition doc {
+        relation parent: folder;
+        relation owner: user;
+        relation editor: user | group#member;
+        relation viewer: user | user:* | group#member;
+        relation banned: user;
+        // `-` binds loosest, then `&`, then `+`.
+        permission edit = owner + editor;
+        permission view = viewer + edit + parent->viewer - banned;
+        permission audit = (owner & editor) - banned - viewer;
+    }
+
+    caveat ip_allowlist(user_ip string) {
+        user_ip == "10.0.0.1"
+    }
+"#;
+
+#[test]
+fn test_should_compile_spicedb_schema_into_schema_ir() -> Result<(), ZanzibarError> {
+    use simple_zanzibar::schema::{self, SchemaSource, UsersetExpression};
+
+    let compiled = schema::compile_source(SchemaSource::spicedb(SPICEDB_SCHEMA))?;
+    let doc = "doc".try_into()?;
+    let computed = |relation: &str| -> Result<UsersetExpression, ZanzibarError> {
+        Ok(UsersetExpression::ComputedUserset {
+            relation: relation.try_into()?,
+        })
+    };
+
+    let view = compiled.resolver().relation(&doc, &"view".try_into()?)?;
+    assert_eq!(
+        view.userset_rewrite(),
+        Some(&UsersetExpression::Exclusion {
+            base: Box::new(UsersetExpression::Union(vec![
+                computed("viewer")?,
+                computed("edit")?,
+                UsersetExpression::TupleToUserset {
+                    tupleset_relation: "parent".try_into()?,
+                    computed_userset_relation: "viewer".try_into()?,
+                },
+            ])),
+            exclude: Box::new(computed("banned")?),
+        })
+    );
+    let audit = compiled.resolver().relation(&doc, &"audit".try_into()?)?;
+    assert_eq!(
+        audit.userset_rewrite(),
+        Some(&UsersetExpression::Exclusion {
+            base: Box::new(UsersetExpression::Exclusion {
+                base: Box::new(UsersetExpression::Intersection(vec![
+                    computed("owner")?,
+                    computed("editor")?,
+                ])),
+                exclude: Box::new(computed("banned")?),
+            }),
+            exclude: Box::new(computed("viewer")?),
+        })
+    );
+    let viewer = compiled.resolver().relation(&doc, &"viewer".try_into()?)?;
+    assert!(viewer.userset_rewrite().is_none());
+    assert_eq!(viewer.wildcard_subject_types(), ["user".try_into()?]);
+    assert_eq!(compiled.caveats().len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_should_reject_spicedb_subject_caveats_until_enforced() {
+    use simple_zanzibar::{
+        diagnostic::DiagnosticError,
+        schema::{self, SchemaError, SchemaSource},
+    };
+
+    let source = "definition user {}\ndefinition doc {\n    relation viewer: user | user with \
+                  ip_allowlist and expiration\n}\n";
+    let error = schema::compile_source(SchemaSource::spicedb(source).with_name("doc.zed")).err();
+    let Some(ZanzibarError::Diagnostics(diagnostics)) = error else {
+        panic!("expected diagnostics, got {error:?}");
+    };
+    assert!(matches!(
+        diagnostics.first().map(|diagnostic| &diagnostic.error),
+        Some(DiagnosticError::Schema(SchemaError::UnsupportedSubjectCaveat {
+            relation,
+            subject,
+            caveat,
+            ..
+        })) if relation == "viewer" && subject == "user" && caveat == "ip_allowlist and expiration"
+    ));
+}
+
+#[test]
+fn test_should_apply_spicedb_schema_through_engine() -> Result<(), Box<dyn std::error::Error>> {
+    use simple_zanzibar::{model::User, schema::SchemaSource};
+
+    let engine = ZanzibarEngine::builder().build();
+    engine.apply_schema(SchemaSource::spicedb(SPICEDB_SCHEMA).with_name("schema.zed"))?;
+    engine.touch_relationship("doc:readme#editor@group:eng#member")?;
+    engine.touch_relationship("group:eng#member@user:alice")?;
+    engine.touch_relationship("doc:readme#banned@user:mallory")?;
+    engine.touch_relationship("doc:readme#viewer@user:*")?;
+    assert!(
+        engine
+            .touch_relationship("doc:readme#owner@group:eng#member")
+            .is_err()
+    );
+    let error = engine
+        .touch_relationship("doc:readme#view@user:alice")
+        .err()
+        .map(|error| error.to_string());
+    assert_eq!(
+        error.as_deref(),
+        Some("relation 'doc.view' does not allow subject 'user'; expected no subjects")
+    );
+
+    let readme = simple_zanzibar::model::Object::new("doc", "readme");
+    let view = Relation::new("view");
+    assert!(engine.check_relation(&readme, &Relation::new("edit"), &User::user_id("alice"))?);
+    assert!(engine.check_relation(&readme, &view, &User::user_id("bob"))?);
+    assert!(!engine.check_relation(&readme, &view, &User::user_id("mallory"))?);
+
+    let exported = engine.export_policy_text()?;
+    assert!(exported.schema.contains("    relation view: {\n"));
+    let reimported = ZanzibarEngine::from_policy_text(&exported)?;
+    assert_eq!(reimported.export_policy_text()?, exported);
+    assert!(
+        reimported
+            .touch_relationship("doc:readme#view@user:alice")
+            .is_err()
+    );
+
+    let error = engine
+        .apply_schema(SchemaSource::spicedb(
+            "definition doc { permission view = viewer + }",
+        ))
+        .err();
+    assert!(matches!(
+        error,
+        Some(simple_zanzibar::EngineError::Diagnostics(diagnostics))
+            if matches!(
+                diagnostics.first().map(|diagnostic| &diagnostic.error),
+                Some(simple_zanzibar::diagnostic::DiagnosticError::Syntax(_))
+            )
+    ));
+    Ok(())
+}
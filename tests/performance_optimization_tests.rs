@@ -1190,6 +1190,7 @@ fn trusted_external_options() -> SnapshotLoadOptions {
         max_file_bytes: non_zero_u64(16 * 1024 * 1024),
         required_index_profile: IndexProfile::Full,
         compression: simple_zanzibar::SnapshotCompression::None,
+        identity: simple_zanzibar::SnapshotIdentityMode::Restore,
     }
 }
 
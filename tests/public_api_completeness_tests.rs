@@ -7,8 +7,10 @@ use std::{
 };
 
 use simple_zanzibar::{
-    EngineError, PolicyText, SnapshotLoadOptions, SnapshotSaveOptions, ZanzibarEngine,
-    domain::Relationship,
+    EngineError, PolicyText, PolicyTextFile, SnapshotLoadOptions, SnapshotSaveOptions,
+    ZanzibarEngine,
+    diagnostic::DiagnosticError,
+    domain::{DomainError, Relationship},
     eval::EvaluationLimits,
     model::{
         CheckRequest, LookupObjectPermissionsRequest, LookupPermissionsRequest,
@@ -16,7 +18,7 @@ use simple_zanzibar::{
     },
     relationship::RelationshipMutation,
     revision::Consistency,
-    schema::SchemaSource,
+    schema::{SchemaSource, SchemaSyntax},
 };
 
 static NEXT_TEST_PATH: AtomicU64 = AtomicU64::new(1);
@@ -44,6 +46,7 @@ fn test_should_lookup_subject_permissions_and_object_permission_subjects()
             resource: direct_doc,
             subject_type: "user".to_string(),
             consistency: Consistency::Latest,
+            cursor: None,
         })?;
 
     assert_eq!(
@@ -86,6 +89,7 @@ fn test_should_offer_checked_ergonomic_engine_helpers() -> Result<(), Box<dyn st
     let engine = ZanzibarEngine::builder().build();
     engine.apply_schema(SchemaSource {
         name: Some("docs"),
+        syntax: SchemaSyntax::Legacy,
         text: r"
         namespace doc {
             relation viewer {}
@@ -242,6 +246,80 @@ fn test_should_reject_duplicate_policy_text_relationships_atomically()
     Ok(())
 }
 
+#[test]
+fn test_should_report_bad_policy_relationships_with_file_and_line()
+-> Result<(), Box<dyn std::error::Error>> {
+    let service = populated_service()?;
+    let policy = PolicyText::new(
+        schema().to_string(),
+        vec![
+            PolicyTextFile {
+                path: "relationships/group.zedtuples".to_string(),
+                contents: "group:eng#member@user:alice\n\n  group:eng#member\n".to_string(),
+            },
+            PolicyTextFile {
+                path: "relationships/doc.zedtuples".to_string(),
+                contents: "# reviewed\ndoc:readme#viewer@@user:bob\n".to_string(),
+            },
+        ],
+    );
+
+    let Err(EngineError::Diagnostics(diagnostics)) = service.apply_policy_text(&policy) else {
+        panic!("expected relationship diagnostics");
+    };
+    let located = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.file.as_deref(),
+                diagnostic.line,
+                diagnostic.column,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        located,
+        [
+            (Some("relationships/group.zedtuples"), 3, 3),
+            (Some("relationships/doc.zedtuples"), 2, 1),
+        ]
+    );
+    assert!(diagnostics.iter().all(|diagnostic| matches!(
+        diagnostic.error,
+        DiagnosticError::Domain(DomainError::MalformedRelationship { .. })
+    )));
+    Ok(())
+}
+
+#[test]
+fn test_should_report_every_policy_relationship_the_schema_rejects()
+-> Result<(), Box<dyn std::error::Error>> {
+    let service = populated_service()?;
+    let before = service.export_policy_text()?;
+    let policy = PolicyText::from_single_relationship_file(
+        schema().to_string(),
+        "doc:readme#viewer@user:alice\ndoc:readme#editor@user:bob\nwidget:one#viewer@user:carol\n"
+            .to_string(),
+    );
+
+    let Err(EngineError::Diagnostics(diagnostics)) = service.apply_policy_text(&policy) else {
+        panic!("expected relationship diagnostics");
+    };
+    let located = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.column))
+        .collect::<Vec<_>>();
+    assert_eq!(located, [(2, 1), (3, 1)]);
+    assert!(
+        diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.file.is_some()
+                && matches!(diagnostic.error, DiagnosticError::Schema(_)))
+    );
+    assert_eq!(service.export_policy_text()?, before);
+    Ok(())
+}
+
 #[test]
 fn test_should_replace_and_delete_schema_policy_with_atomic_revalidation()
 -> Result<(), Box<dyn std::error::Error>> {
@@ -285,6 +363,7 @@ fn test_should_expose_schema_replacement_and_policy_export_through_engine()
     let engine = ZanzibarEngine::builder().build();
     engine.apply_schema(SchemaSource {
         name: Some("docs"),
+        syntax: SchemaSyntax::Legacy,
         text: r"
         namespace doc {
             relation viewer {}
@@ -296,6 +375,7 @@ fn test_should_expose_schema_replacement_and_policy_export_through_engine()
 
     let replacement = engine.replace_schema(SchemaSource {
         name: Some("broken"),
+        syntax: SchemaSyntax::Legacy,
         text: r"
         namespace doc {
             relation editor {}
@@ -323,22 +403,27 @@ fn assert_equivalent_public_queries(
         subject: alice.clone(),
         permission: relation("can_view"),
         resource_type: "doc".to_string(),
+        cursor: None,
     };
     assert_eq!(
-        original.lookup_resources(&request)?,
-        loaded.lookup_resources(&request)?
+        original.lookup_resources(&request)?.resources,
+        loaded.lookup_resources(&request)?.resources
     );
     assert_eq!(
-        original.lookup_permissions(&LookupPermissionsRequest {
-            subject: alice,
-            resource: doc("direct_doc"),
-            consistency: Consistency::Latest,
-        })?,
-        loaded.lookup_permissions(&LookupPermissionsRequest {
-            subject: User::UserId("alice".to_string()),
-            resource: doc("direct_doc"),
-            consistency: Consistency::Latest,
-        })?,
+        original
+            .lookup_permissions(&LookupPermissionsRequest {
+                subject: alice,
+                resource: doc("direct_doc"),
+                consistency: Consistency::Latest,
+            })?
+            .permissions,
+        loaded
+            .lookup_permissions(&LookupPermissionsRequest {
+                subject: User::UserId("alice".to_string()),
+                resource: doc("direct_doc"),
+                consistency: Consistency::Latest,
+            })?
+            .permissions,
     );
     assert_eq!(
         original.check_relation(
@@ -26,6 +26,11 @@ fn exact_filter(value: &str) -> Result<RelationshipFilter, DomainError> {
             object.object_id().as_str().try_into()?,
             Some(relation.clone()),
         ),
+        simple_zanzibar::domain::SubjectRef::Wildcard(object_type) => SubjectFilter::exact(
+            object_type.as_str().try_into()?,
+            simple_zanzibar::domain::SubjectId::wildcard(),
+            None,
+        ),
     };
 
     Ok(RelationshipFilter::for_exact_subject(
@@ -59,6 +64,22 @@ fn test_should_create_touch_and_delete_relationships() -> Result<(), Box<dyn std
     Ok(())
 }
 
+#[test]
+fn test_should_create_over_an_expired_relationship() -> Result<(), Box<dyn std::error::Error>> {
+    let expired = relationship("doc:3#viewer@user:a[expiration:2000-01-01T00:00:00Z]")?;
+    let regranted = relationship("doc:3#viewer@user:a")?;
+    let mut store = IndexedRelationshipStore::default();
+    store.apply_mutations([RelationshipMutation::Create(expired)], [])?;
+
+    store.apply_mutations([RelationshipMutation::Create(regranted.clone())], [])?;
+    assert_eq!(store.rows().len(), 1);
+    assert!(matches!(
+        store.apply_mutations([RelationshipMutation::Create(regranted)], []),
+        Err(StoreError::RelationshipAlreadyExists { .. })
+    ));
+    Ok(())
+}
+
 #[test]
 fn test_should_enforce_preconditions() -> Result<(), Box<dyn std::error::Error>> {
     let mut store = IndexedRelationshipStore::default();
@@ -509,6 +530,21 @@ fn resource_filters() -> Result<Vec<RelationshipFilter>, DomainError> {
             None,
             QueryLimit::default(),
         ),
+        RelationshipFilter::any_resource_type(None, None, QueryLimit::default()),
+        RelationshipFilter::any_resource_type(
+            Some("viewer".try_into()?),
+            None,
+            QueryLimit::default(),
+        ),
+        RelationshipFilter::any_resource_type(
+            None,
+            Some(SubjectFilter::exact(
+                "user".try_into()?,
+                "alice".try_into()?,
+                None,
+            )),
+            QueryLimit::default(),
+        ),
     ])
 }
 
@@ -559,7 +595,9 @@ fn subject_filters() -> Result<Vec<SubjectFilter>, DomainError> {
 }
 
 fn resource_filter_matches(relationship: &Relationship, filter: &RelationshipFilter) -> bool {
-    relationship.resource().object_type() == filter.resource_type()
+    filter
+        .optional_resource_type()
+        .is_none_or(|object_type| relationship.resource().object_type() == object_type)
         && filter
             .optional_resource_id()
             .is_none_or(|object_id| relationship.resource().object_id() == object_id)
@@ -589,5 +627,12 @@ fn subject_filter_matches(relationship: &Relationship, filter: &SubjectFilter) -
                     .optional_relation()
                     .is_none_or(|expected_relation| relation == expected_relation)
         }
+        simple_zanzibar::domain::SubjectRef::Wildcard(object_type) => {
+            filter.optional_relation().is_none()
+                && object_type.as_str() == filter.subject_type().as_str()
+                && filter
+                    .optional_subject_id()
+                    .is_none_or(simple_zanzibar::domain::SubjectId::is_wildcard)
+        }
     }
 }
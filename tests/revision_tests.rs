@@ -2,7 +2,7 @@ use std::{num::NonZeroUsize, str::FromStr};
 
 use simple_zanzibar::{
     EngineError, ZanzibarEngine,
-    model::{NamespaceConfig, Object, Relation, RelationConfig, RelationTuple, User},
+    model::{CheckRequest, NamespaceConfig, Object, Relation, RelationConfig, RelationTuple, User},
     revision::{Consistency, ConsistencyError, ConsistencyToken},
 };
 
@@ -230,6 +230,54 @@ fn test_should_reject_schema_hash_mismatch() -> Result<(), Box<dyn std::error::E
     Ok(())
 }
 
+#[test]
+fn test_should_serve_at_least_as_fresh_after_token_expires()
+-> Result<(), Box<dyn std::error::Error>> {
+    let service = ZanzibarEngine::builder()
+        .retained_snapshots(NonZeroUsize::new(1).ok_or("invalid retention")?)
+        .build();
+    service.add_dsl(DOC_SCHEMA)?;
+    let written = service.write_tuple_with_token(&tuple("readme", "alice"))?;
+    let latest = service.write_tuple_with_token(&tuple("guide", "bob"))?;
+    let request = |consistency| {
+        CheckRequest::new(
+            doc_object(),
+            Relation("viewer".to_string()),
+            User::UserId("alice".to_string()),
+            consistency,
+        )
+    };
+
+    assert!(matches!(
+        service.check(request(Consistency::Exact(written.clone()))),
+        Err(EngineError::Consistency(
+            ConsistencyError::RevisionExpired { .. }
+        ))
+    ));
+    let response = service.check(request(Consistency::AtLeastAsFresh(written.clone())))?;
+    assert!(response.allowed);
+    assert_eq!(response.token, Some(latest.clone()));
+
+    let future = ConsistencyToken::new(
+        latest.revision().next()?,
+        latest.schema_hash(),
+        latest.datastore_id(),
+    );
+    assert!(matches!(
+        service.check(request(Consistency::AtLeastAsFresh(future))),
+        Err(EngineError::Consistency(
+            ConsistencyError::RevisionUnavailable { .. }
+        ))
+    ));
+    let other = ZanzibarEngine::builder().build();
+    other.add_dsl(DOC_SCHEMA)?;
+    assert!(matches!(
+        other.check(request(Consistency::AtLeastAsFresh(written))),
+        Err(EngineError::Consistency(ConsistencyError::WrongDatastore))
+    ));
+    Ok(())
+}
+
 #[test]
 fn test_should_reject_oversized_token_string() {
     let oversized = "x".repeat(123);
@@ -266,6 +314,8 @@ fn namespace(name: &str, relation: &str) -> NamespaceConfig {
             RelationConfig {
                 name: relation,
                 userset_rewrite: None,
+                allowed_subject_types: None,
+                wildcard_subject_types: Vec::new(),
             },
         )]
         .into_iter()
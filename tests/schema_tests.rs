@@ -2,14 +2,27 @@ use std::sync::Arc;
 
 use simple_zanzibar::{
     EngineError, ZanzibarEngine,
-    domain::ObjectType,
+    caveat::{CaveatError, CaveatParameterType},
+    diagnostic::DiagnosticError,
+    domain::{DomainError, ObjectType},
     error::ZanzibarError,
     schema::{
-        self, CompiledSchema, NamespaceDefinition, RelationDefinition, SchemaError,
-        UsersetExpression,
+        self, AllowedSubjectTypes, CompiledSchema, NamespaceDefinition, RelationDefinition,
+        SchemaChange, SchemaChangeSeverity, SchemaError, SchemaLintKind, SchemaLintSeverity,
+        SchemaSource, UsersetExpression,
     },
 };
 
+fn first_schema_error(error: Option<ZanzibarError>) -> Option<SchemaError> {
+    let Some(ZanzibarError::Diagnostics(diagnostics)) = error else {
+        return None;
+    };
+    match &diagnostics.first()?.error {
+        DiagnosticError::Schema(error) => Some(error.clone()),
+        _ => None,
+    }
+}
+
 const VALID_SCHEMA: &str = r#"
     namespace doc {
         relation owner {}
@@ -41,6 +54,94 @@ fn test_should_compile_legacy_dsl_to_schema_ir() -> Result<(), ZanzibarError> {
     Ok(())
 }
 
+#[test]
+fn test_should_compile_caveat_definitions() -> Result<(), ZanzibarError> {
+    let dsl = r"
+        caveat under_limit(amount int, limit int) {
+            amount <= limit && !(amount < 0)
+        }
+
+        namespace doc {
+            relation viewer {}
+        }
+    ";
+
+    let compiled = schema::compile_legacy_dsl(dsl)?;
+    let caveat = compiled
+        .caveat(&"under_limit".try_into()?)
+        .ok_or(SchemaError::UnknownCaveat {
+            caveat: "under_limit".to_owned(),
+        })?;
+    assert_eq!(
+        caveat.parameter_type("amount"),
+        Some(&CaveatParameterType::Int)
+    );
+    assert_eq!(
+        caveat.to_string(),
+        "caveat under_limit(amount int, limit int) {\n    ((amount <= limit) && !(amount < 0))\n}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_should_reject_reserved_caveat_name() {
+    let dsl = r"
+        caveat expiration(days int) {
+            days > 0
+        }
+    ";
+
+    let error = schema::compile_legacy_dsl(dsl).err();
+    assert!(matches!(
+        error,
+        Some(ZanzibarError::Diagnostics(diagnostics))
+            if matches!(diagnostics.first(), Some(diagnostic)
+                if diagnostic.line == 2
+                    && matches!(diagnostic.error, DiagnosticError::Domain(
+                        DomainError::ReservedIdentifier { name: "expiration", .. }
+                    )))
+    ));
+}
+
+#[test]
+fn test_should_reject_ill_typed_caveat_expression() {
+    let dsl = r#"
+        caveat bad(amount int) {
+            amount == "ten"
+        }
+    "#;
+
+    let error = schema::compile_legacy_dsl(dsl).err();
+    assert!(matches!(
+        first_schema_error(error),
+        Some(SchemaError::Caveat(
+            CaveatError::TypeMismatch { caveat, .. }
+        )) if caveat == "bad"
+    ));
+}
+
+#[test]
+fn test_should_reject_relationship_with_unknown_caveat() -> Result<(), EngineError> {
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(
+        r"
+        namespace doc {
+            relation viewer {}
+        }
+    ",
+    )?;
+
+    let error = engine
+        .touch_relationship("doc:readme#viewer@user:alice[missing]")
+        .err();
+    assert!(matches!(
+        error,
+        Some(EngineError::Schema(SchemaError::UnknownCaveat { caveat })) if caveat == "missing"
+    ));
+    Ok(())
+}
+
 #[test]
 fn test_should_reject_duplicate_namespace_definitions() {
     let dsl = r"
@@ -55,8 +156,8 @@ fn test_should_reject_duplicate_namespace_definitions() {
 
     let error = schema::compile_legacy_dsl(dsl).err();
     assert!(matches!(
-        error,
-        Some(ZanzibarError::Schema(SchemaError::DuplicateNamespace { namespace }))
+        first_schema_error(error),
+        Some(SchemaError::DuplicateNamespace { namespace })
             if namespace == "doc"
     ));
 }
@@ -72,8 +173,8 @@ fn test_should_reject_duplicate_relation_definitions() {
 
     let error = schema::compile_legacy_dsl(dsl).err();
     assert!(matches!(
-        error,
-        Some(ZanzibarError::Schema(SchemaError::DuplicateRelation { namespace, relation }))
+        first_schema_error(error),
+        Some(SchemaError::DuplicateRelation { namespace, relation })
             if namespace == "doc" && relation == "viewer"
     ));
 }
@@ -90,10 +191,10 @@ fn test_should_reject_missing_computed_userset_relation() {
 
     let error = schema::compile_legacy_dsl(dsl).err();
     assert!(matches!(
-        error,
-        Some(ZanzibarError::Schema(
+        first_schema_error(error),
+        Some(
             SchemaError::MissingRelationReference { missing, .. }
-        )) if missing == "owner"
+        ) if missing == "owner"
     ));
 }
 
@@ -109,10 +210,10 @@ fn test_should_reject_missing_tuple_to_userset_left_relation() {
 
     let error = schema::compile_legacy_dsl(dsl).err();
     assert!(matches!(
-        error,
-        Some(ZanzibarError::Schema(
+        first_schema_error(error),
+        Some(
             SchemaError::MissingRelationReference { missing, .. }
-        )) if missing == "parent"
+        ) if missing == "parent"
     ));
 }
 
@@ -129,10 +230,10 @@ fn test_should_reject_missing_tuple_to_userset_target_relation() {
 
     let error = schema::compile_legacy_dsl(dsl).err();
     assert!(matches!(
-        error,
-        Some(ZanzibarError::Schema(
+        first_schema_error(error),
+        Some(
             SchemaError::MissingTupleToUsersetTarget { missing, .. }
-        )) if missing == "missing"
+        ) if missing == "missing"
     ));
 }
 
@@ -272,11 +373,11 @@ fn test_should_reject_empty_union() {
 
     let error = schema::compile_legacy_dsl(dsl).err();
     assert!(matches!(
-        error,
-        Some(ZanzibarError::Schema(SchemaError::EmptyExpression {
+        first_schema_error(error),
+        Some(SchemaError::EmptyExpression {
             operator: "union",
             ..
-        }))
+        })
     ));
 }
 
@@ -307,3 +408,234 @@ fn test_service_should_reject_invalid_schema_without_mutating_previous_schema()
 
     Ok(())
 }
+
+#[test]
+fn test_should_compile_typed_relation_declarations() -> Result<(), ZanzibarError> {
+    let compiled = schema::compile_legacy_dsl(
+        r#"
+        namespace group {
+            relation member: user | group#member {}
+        }
+        namespace doc {
+            relation viewer: group#member | user | user:* {
+                rewrite this
+            }
+            relation owner {}
+        }
+        "#,
+    )?;
+    let doc_type = "doc".try_into()?;
+    let viewer = compiled
+        .resolver()
+        .relation(&doc_type, &"viewer".try_into()?)?;
+    let subjects = match viewer.allowed_subject_types() {
+        AllowedSubjectTypes::Explicit(subjects) => {
+            subjects.iter().map(ToString::to_string).collect::<Vec<_>>()
+        }
+        AllowedSubjectTypes::Unspecified => Vec::new(),
+    };
+    assert_eq!(subjects, ["group#member", "user"]);
+    assert_eq!(
+        viewer.wildcard_subject_types(),
+        [ObjectType::try_from("user")?]
+    );
+    let owner = compiled
+        .resolver()
+        .relation(&doc_type, &"owner".try_into()?)?;
+    assert_eq!(
+        owner.allowed_subject_types(),
+        &AllowedSubjectTypes::Unspecified
+    );
+
+    let error = schema::compile_legacy_dsl(
+        "namespace doc { relation viewer: group#member } namespace group { relation admin: user }",
+    )
+    .err();
+    assert!(matches!(
+        first_schema_error(error),
+        Some(SchemaError::MissingRelationReference { missing, .. })
+            if missing == "group#member"
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_should_diff_schemas_and_classify_breaking_changes() -> Result<(), Box<dyn std::error::Error>>
+{
+    let old = schema::compile_source(SchemaSource::spicedb(
+        r"
+        definition user {}
+        definition group {
+            relation member: user
+        }
+        definition doc {
+            relation owner: user
+            relation viewer: user | user:* | group#member
+            permission view = viewer + owner
+        }
+        ",
+    ))?;
+    let new = schema::compile_source(SchemaSource::spicedb(
+        r"
+        definition user {}
+        definition team {
+            relation member: user
+        }
+        definition doc {
+            relation viewer: user | team#member
+            permission view = viewer
+        }
+        ",
+    ))?;
+
+    assert!(schema::diff(&old, &old).is_empty());
+    let diff = schema::diff(&old, &new);
+    assert!(matches!(
+        diff.changes.as_slice(),
+        [
+            SchemaChange::RelationRemoved { relation, .. },
+            SchemaChange::RewriteChanged { current: Some(UsersetExpression::ComputedUserset { .. }), .. },
+            SchemaChange::SubjectDisallowed { subject: removed, .. },
+            SchemaChange::SubjectAllowed { subject: added, .. },
+            SchemaChange::WildcardDisallowed { .. },
+            SchemaChange::NamespaceRemoved { namespace: group },
+            SchemaChange::NamespaceAdded { namespace: team },
+        ] if relation.as_str() == "owner"
+            && removed.to_string() == "group#member"
+            && added.to_string() == "team#member"
+            && group.as_str() == "group"
+            && team.as_str() == "team"
+    ));
+    assert!(diff.is_breaking());
+    assert_eq!(
+        diff.changes
+            .iter()
+            .map(SchemaChange::severity)
+            .filter(|severity| *severity == SchemaChangeSeverity::Breaking)
+            .count(),
+        4
+    );
+    assert!(!schema::diff(&new, &old).breaking_changes().any(|change| {
+        matches!(
+            change,
+            SchemaChange::SubjectAllowed { .. } | SchemaChange::NamespaceAdded { .. }
+        )
+    }));
+    Ok(())
+}
+
+#[test]
+fn test_should_lint_rewrites_that_can_never_grant() -> Result<(), Box<dyn std::error::Error>> {
+    assert!(schema::lint(&schema::compile_legacy_dsl(VALID_SCHEMA)?).is_empty());
+
+    let compiled = schema::compile_source(SchemaSource::spicedb(
+        r"
+        definition user {}
+        definition folder {
+            relation viewer: user
+        }
+        definition doc {
+            relation parent: folder
+            relation viewer: user
+            relation legacy: user
+            permission browse = parent->viewer
+            permission manage = browse->viewer
+            permission cycle_a = cycle_b
+            permission cycle_b = cycle_a
+            permission edit = viewer & cycle_a
+            permission view = viewer - cycle_b
+        }
+        ",
+    ))?;
+    let lints = schema::lint(&compiled);
+    let found = lints
+        .iter()
+        .map(|lint| (lint.relation.as_str(), &lint.kind, lint.severity()))
+        .collect::<Vec<_>>();
+    assert!(matches!(
+        found.as_slice(),
+        [
+            ("cycle_a", SchemaLintKind::DenyOnlyCycle { cycle }, SchemaLintSeverity::Error),
+            ("edit", SchemaLintKind::EmptyIntersectionOperand { operand: UsersetExpression::ComputedUserset { relation: operand } }, SchemaLintSeverity::Error),
+            ("legacy", SchemaLintKind::UnusedRelation, SchemaLintSeverity::Warning),
+            ("manage", SchemaLintKind::UnreachableTupleToUserset { tupleset_relation, computed_userset_relation }, SchemaLintSeverity::Error),
+            ("view", SchemaLintKind::EmptyExclusionSubtrahend { .. }, SchemaLintSeverity::Warning),
+        ] if cycle.iter().map(|relation| relation.as_str()).eq(["cycle_a", "cycle_b"])
+            && operand.as_str() == "cycle_a"
+            && tupleset_relation.as_str() == "browse"
+            && computed_userset_relation.as_str() == "viewer"
+    ));
+    assert!(lints.iter().all(|lint| lint.namespace.as_str() == "doc"));
+    Ok(())
+}
+
+#[test]
+fn test_should_report_every_schema_error_with_its_location() {
+    let source = "namespace doc {\n    relation viewer {}\n    relation viewer {}\n    relation \
+                  editor {\n        rewrite computed_userset(relation: \"owner\")\n    }\n}\n";
+
+    let error = schema::compile_source(SchemaSource::legacy(source).with_name("doc.zed")).err();
+    let Some(ZanzibarError::Diagnostics(diagnostics)) = error else {
+        panic!("expected diagnostics, got {error:?}");
+    };
+    let located = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.column, &diagnostic.error))
+        .collect::<Vec<_>>();
+    assert!(matches!(
+        located.as_slice(),
+        [
+            (3, 14, DiagnosticError::Schema(SchemaError::DuplicateRelation { .. })),
+            (4, 14, DiagnosticError::Schema(SchemaError::MissingRelationReference { missing, .. })),
+        ] if missing == "owner"
+    ));
+    assert!(
+        diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.file.as_deref() == Some("doc.zed"))
+    );
+    assert!(
+        diagnostics
+            .to_string()
+            .starts_with("doc.zed:3:14: duplicate relation 'viewer'")
+    );
+
+    let syntax = schema::compile_source(SchemaSource::spicedb(
+        "definition doc {\n  permission view = \n}",
+    ))
+    .err();
+    assert!(matches!(
+        syntax,
+        Some(ZanzibarError::Diagnostics(diagnostics))
+            if diagnostics.len() == 1
+                && matches!(diagnostics.first(), Some(diagnostic)
+                    if diagnostic.line == 3 && matches!(diagnostic.error, DiagnosticError::Syntax(_)))
+    ));
+}
+
+#[test]
+fn test_should_locate_engine_schema_errors_in_named_sources()
+-> Result<(), Box<dyn std::error::Error>> {
+    use simple_zanzibar::schema::SchemaMigration;
+
+    let engine = ZanzibarEngine::builder().build();
+    engine.add_dsl(VALID_SCHEMA)?;
+    let source = "namespace doc {\n    relation viewer {}\n    relation viewer {}\n}\n";
+    let named = || SchemaSource::legacy(source).with_name("doc.zed");
+
+    for error in [
+        engine.apply_schema(named()).err(),
+        engine.replace_schema(named()).err(),
+        engine.migrate_schema(SchemaMigration::new(named())).err(),
+    ] {
+        let Some(EngineError::Diagnostics(diagnostics)) = error else {
+            panic!("expected diagnostics, got {error:?}");
+        };
+        assert!(
+            diagnostics
+                .to_string()
+                .starts_with("doc.zed:3:14: duplicate relation 'viewer'")
+        );
+    }
+    Ok(())
+}
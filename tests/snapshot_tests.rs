@@ -321,7 +321,7 @@ fn collect_users(expanded: &ExpandedUserset) -> Vec<String> {
 fn collect_users_inner(expanded: &ExpandedUserset, out: &mut Vec<String>) {
     match expanded {
         ExpandedUserset::User(id) => out.push(id.clone()),
-        ExpandedUserset::Userset(_, _) => {}
+        ExpandedUserset::Userset(_, _) | ExpandedUserset::Wildcard => {}
         ExpandedUserset::Union(children) | ExpandedUserset::Intersection(children) => {
             for child in children {
                 collect_users_inner(child, out);
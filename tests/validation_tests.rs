@@ -0,0 +1,127 @@
+use simple_zanzibar::{
+    EngineError,
+    validation::{ValidationCheck, ValidationFile, ValidationOutcome},
+};
+
+const VALIDATION_FILE: &str = r#"# Reviewed with every policy change.
+schemaSyntax: spicedb
+schema: |-
+  definition user {}
+
+  definition group {
+    relation member: user
+  }
+
+  definition doc {
+    relation viewer: user | group#member | user:*
+    relation banned: user
+    permission view = viewer - banned
+  }
+relationships: |-
+  group:eng#member@user:alice
+  doc:readme#viewer@group:eng#member
+  doc:readme#banned@user:mallory
+  doc:public#viewer@user:*
+assertions:
+  assertTrue:
+    - doc:readme#view@user:alice
+    - "doc:public#view@user:bob"
+  assertFalse:
+    - doc:readme#view@user:mallory
+    - doc:readme#view@user:bob
+validation:
+  doc:readme#view:
+    - "[user:alice] is <group:eng#member>"
+  doc:public#view:
+    - "[user:*]"
+"#;
+
+#[test]
+fn test_should_run_validation_file_and_report_failures_with_diffs()
+-> Result<(), Box<dyn std::error::Error>> {
+    let file = ValidationFile::parse(Some("policy.validation.yaml"), VALIDATION_FILE)?;
+    assert_eq!(file.assert_true.len(), 2);
+    assert_eq!(file.assert_false.len(), 2);
+    assert_eq!(file.expected_relations[0].line, 28);
+
+    let report = file.run()?;
+    assert!(!report.is_success());
+    let failures = report
+        .failures()
+        .map(|result| (result.check, result.line, &result.outcome))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        [(
+            ValidationCheck::ExpectedRelations,
+            28,
+            &ValidationOutcome::RelationsDiffer {
+                missing: vec!["user:alice".to_string()],
+                unexpected: vec!["group:eng#member".to_string()],
+            },
+        )]
+    );
+    assert_eq!(
+        report.to_string(),
+        "policy.validation.yaml:28: validation doc:readme#view differs:\n  - user:alice\n  + \
+         group:eng#member"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_should_apply_exclusions_to_expected_relations() -> Result<(), Box<dyn std::error::Error>> {
+    let validate = |operator: &str| -> Result<Vec<ValidationOutcome>, Box<dyn std::error::Error>> {
+        let text = format!(
+            "schemaSyntax: spicedb\nschema: |-\n  definition user {{}}\n  definition doc {{\n    \
+             relation viewer: user\n    relation banned: user\n    permission view = viewer \
+             {operator} banned\n  }}\nrelationships: |-\n  doc:readme#viewer@user:alice\n  \
+             doc:readme#viewer@user:mallory\n  doc:readme#banned@user:mallory\nvalidation:\n  \
+             doc:readme#view:\n    - \"[user:alice]\"\n"
+        );
+        let report = ValidationFile::parse(None, &text)?.run()?;
+        Ok(report
+            .failures()
+            .map(|result| result.outcome.clone())
+            .collect())
+    };
+
+    assert_eq!(validate("-")?, []);
+    assert_eq!(
+        validate("+")?,
+        [ValidationOutcome::RelationsDiffer {
+            missing: Vec::new(),
+            unexpected: vec!["user:mallory".to_string()],
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_should_locate_validation_file_errors_in_the_file() {
+    let Err(diagnostics) = ValidationFile::parse(
+        Some("bad.yaml"),
+        "schema: |\n  namespace doc {}\nassertions:\n  assertTrue:\n    - doc:readme#view\n  \
+         assertMaybe: []\nextra: 1\n",
+    ) else {
+        panic!("expected parse diagnostics");
+    };
+    let lines = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.column))
+        .collect::<Vec<_>>();
+    assert_eq!(lines, [(5, 7), (6, 3), (7, 1)]);
+
+    let file = ValidationFile::parse(
+        Some("bad.yaml"),
+        "schema: |\n  namespace doc {\n    relation viewer {}\n  }\nrelationships: |\n    \
+         doc:readme#viewer@user:alice\n    doc:readme#viewer\n",
+    )
+    .expect("validation file parses");
+    let Err(EngineError::Diagnostics(diagnostics)) = file.run() else {
+        panic!("expected relationship diagnostics");
+    };
+    let diagnostic = diagnostics.first().expect("one diagnostic");
+    assert_eq!(diagnostic.file.as_deref(), Some("bad.yaml"));
+    assert_eq!((diagnostic.line, diagnostic.column), (7, 5));
+}
@@ -0,0 +1,207 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use simple_zanzibar::{
+    PolicyText, PolicyTextFile, WalError, ZanzibarEngine,
+    model::{Object, Relation, User},
+    relationship::RelationshipMutation,
+    revision::Consistency,
+    schema::{MigrationStep, SchemaMigration, SchemaSource, SchemaSyntax},
+};
+
+static NEXT_TEST_DIRECTORY: AtomicUsize = AtomicUsize::new(0);
+
+const DOC_SCHEMA: &str = r"
+    namespace doc {
+        relation viewer {}
+    }
+";
+
+#[test]
+fn test_should_replay_write_ahead_log_after_reopen() -> Result<(), Box<dyn std::error::Error>> {
+    let directory = temp_directory("replay");
+    let (schema_token, write_token) = {
+        let engine = ZanzibarEngine::open(&directory)?;
+        let schema_token = engine.apply_schema(doc_schema())?;
+        let write_token = engine.write_relationships([
+            RelationshipMutation::touch("doc:readme#viewer@user:alice")?,
+            RelationshipMutation::touch("doc:readme#viewer@user:bob")?,
+        ])?;
+        engine.delete_relationship("doc:readme#viewer@user:bob")?;
+        (schema_token, write_token)
+    };
+
+    let engine = ZanzibarEngine::open(&directory)?;
+    assert!(engine.check_relation(&doc("readme"), &viewer(), &User::user_id("alice"))?);
+    assert!(!engine.check_relation(&doc("readme"), &viewer(), &User::user_id("bob"))?);
+    let write_revision = write_token.revision().get();
+    assert!(engine.check_relation_with_consistency(
+        &doc("readme"),
+        &viewer(),
+        &User::user_id("bob"),
+        Consistency::Exact(write_token),
+    )?);
+    let next = engine.touch_relationship("doc:guide#viewer@user:carol")?;
+    assert_eq!(next.datastore_id(), schema_token.datastore_id());
+    assert_eq!(next.revision().get(), write_revision + 2);
+
+    remove_directory(&directory);
+    Ok(())
+}
+
+#[test]
+fn test_should_reopen_from_checkpoint_and_log_tail() -> Result<(), Box<dyn std::error::Error>> {
+    let directory = temp_directory("checkpoint");
+    let (checkpoint, policy_token) = {
+        let engine = ZanzibarEngine::open(&directory)?;
+        engine.apply_schema(doc_schema())?;
+        engine.touch_relationship("doc:readme#viewer@user:alice")?;
+        let checkpoint = engine.checkpoint()?;
+        let policy_token = engine.apply_policy_text(&PolicyText::new(
+            DOC_SCHEMA.to_string(),
+            vec![PolicyTextFile {
+                path: "doc.zr".to_string(),
+                contents: "doc:guide#viewer@user:bob\n".to_string(),
+            }],
+        ))?;
+        (checkpoint, policy_token)
+    };
+    let snapshots = fs::read_dir(&directory)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "szsnap"))
+        .count();
+    assert_eq!(snapshots, 1);
+
+    let engine = ZanzibarEngine::open(&directory)?;
+    assert!(!engine.check_relation(&doc("readme"), &viewer(), &User::user_id("alice"))?);
+    assert!(engine.check_relation(&doc("guide"), &viewer(), &User::user_id("bob"))?);
+    let next = engine.touch_relationship("doc:readme#viewer@user:carol")?;
+    assert_eq!(next.datastore_id(), checkpoint.datastore_id());
+    assert_eq!(next.revision().get(), policy_token.revision().get() + 1);
+
+    remove_directory(&directory);
+    Ok(())
+}
+
+#[test]
+fn test_should_truncate_torn_write_ahead_log_tail() -> Result<(), Box<dyn std::error::Error>> {
+    let directory = temp_directory("torn_tail");
+    let token = {
+        let engine = ZanzibarEngine::open(&directory)?;
+        engine.apply_schema(doc_schema())?;
+        engine.touch_relationship("doc:readme#viewer@user:alice")?
+    };
+    let mut log = OpenOptions::new()
+        .append(true)
+        .open(directory.join("engine.wal"))?;
+    log.write_all(&[0x40, 0, 0, 0, 1, 2, 3])?;
+    drop(log);
+
+    let engine = ZanzibarEngine::open(&directory)?;
+    assert!(engine.check_relation(&doc("readme"), &viewer(), &User::user_id("alice"))?);
+    let next = engine.touch_relationship("doc:readme#viewer@user:bob")?;
+    assert_eq!(next.revision().get(), token.revision().get() + 1);
+    drop(engine);
+    let engine = ZanzibarEngine::open(&directory)?;
+    assert!(engine.check_relation(&doc("readme"), &viewer(), &User::user_id("bob"))?);
+
+    remove_directory(&directory);
+    Ok(())
+}
+
+#[test]
+fn test_should_reject_damaged_record_length_before_the_log_tail()
+-> Result<(), Box<dyn std::error::Error>> {
+    let directory = temp_directory("damaged_length");
+    {
+        let engine = ZanzibarEngine::open(&directory)?;
+        engine.apply_schema(doc_schema())?;
+        engine.touch_relationship("doc:readme#viewer@user:alice")?;
+        engine.touch_relationship("doc:readme#viewer@user:bob")?;
+    }
+    let path = directory.join("engine.wal");
+    let mut bytes = fs::read(&path)?;
+    let first_record = 24;
+    bytes[first_record..first_record + 4].copy_from_slice(&0x00ff_ffff_u32.to_le_bytes());
+    fs::write(&path, &bytes)?;
+
+    assert!(matches!(
+        ZanzibarEngine::open(&directory),
+        Err(WalError::Format { .. })
+    ));
+    assert_eq!(fs::read(&path)?, bytes);
+
+    remove_directory(&directory);
+    Ok(())
+}
+
+#[test]
+fn test_should_replay_schema_migration_after_reopen() -> Result<(), Box<dyn std::error::Error>> {
+    let directory = temp_directory("migration");
+    let token = {
+        let engine = ZanzibarEngine::open(&directory)?;
+        engine.add_dsl("namespace doc { relation reader {} }")?;
+        engine.touch_relationship("doc:readme#reader@user:alice")?;
+        engine.migrate_schema(SchemaMigration::new(doc_schema()).with_step(
+            MigrationStep::RenameRelation {
+                namespace: "doc".try_into()?,
+                from: "reader".try_into()?,
+                to: "viewer".try_into()?,
+            },
+        ))?
+    };
+
+    let engine = ZanzibarEngine::open(&directory)?;
+    assert!(engine.check_relation_with_consistency(
+        &doc("readme"),
+        &viewer(),
+        &User::user_id("alice"),
+        Consistency::Exact(token),
+    )?);
+
+    remove_directory(&directory);
+    Ok(())
+}
+
+#[test]
+fn test_should_reject_checkpoint_for_in_memory_engine() {
+    let engine = ZanzibarEngine::builder().build();
+    assert!(matches!(engine.checkpoint(), Err(WalError::NotDurable)));
+}
+
+fn doc_schema() -> SchemaSource<'static> {
+    SchemaSource {
+        name: Some("doc-schema"),
+        text: DOC_SCHEMA,
+        syntax: SchemaSyntax::Legacy,
+    }
+}
+
+fn doc(id: &str) -> Object {
+    Object {
+        namespace: "doc".to_string(),
+        id: id.to_string(),
+    }
+}
+
+fn viewer() -> Relation {
+    Relation::new("viewer")
+}
+
+fn temp_directory(name: &str) -> PathBuf {
+    let counter = NEXT_TEST_DIRECTORY.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!(
+        "simple_zanzibar_wal_{name}_{}_{}",
+        process::id(),
+        counter,
+    ))
+}
+
+fn remove_directory(path: &Path) {
+    let _ = fs::remove_dir_all(path);
+}